@@ -95,8 +95,11 @@ async fn main() -> Result<()> {
         println!("\n[DRY RUN] No changes made.");
     } else {
         println!("\nPerforming compaction...");
-        wal.compact(checkpoint_seq).await?;
-        println!("Compaction completed successfully.");
+        let removed = wal.compact(checkpoint_seq).await?;
+        println!(
+            "Compaction completed successfully ({} segments removed).",
+            removed
+        );
 
         // Verify
         let remaining = wal.read_all().await?;
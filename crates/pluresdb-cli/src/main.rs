@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -9,16 +9,24 @@ use axum::{
     extract::State,
     http::StatusCode,
     response::Json,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use clap::{Parser, Subcommand};
-use pluresdb_core::{CoreErrorCode, CrdtStore, StoreError};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pluresdb_core::backup::{restore_backup, write_backup};
+use pluresdb_core::schema::{type_definition, type_definition_id, TypeRegistry};
+use pluresdb_core::{
+    ActorId, ActorIdExt, CoreErrorCode, CrdtStore, JsonFilter, NodeRecord, StoreError,
+    ACTOR_ID_FILE,
+};
 use pluresdb_storage::{
-    MemoryStorage, SledStorage, StorageEngine, StorageErrorCode, StoredNode, WalError,
-    WriteAheadLog,
+    MemoryStorage, RecoveryPolicy, SledStorage, StorageEngine, StorageErrorCode, StoredNode,
+    WalError, WalValidation, WriteAheadLog,
 };
-use pluresdb_sync::{GunRelayServer, SyncBroadcaster};
+use pluresdb_sync::{GunRelayServer, PeerManager, PeerStatus, SyncBroadcaster};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tokio::runtime::Runtime;
@@ -30,7 +38,7 @@ use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, EnvFilter};
 
 #[cfg(feature = "sqlite-compat")]
-use pluresdb_core::{Database, DatabaseError, DatabaseOptions, SqlValue};
+use pluresdb_core::{CheckpointMode, Database, DatabaseError, DatabaseOptions, SqlValue};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const STATUS_OK: &str = "ok";
@@ -114,9 +122,9 @@ enum Commands {
         /// JSON data (use @file to read from file)
         data: String,
 
-        /// Actor identifier for CRDT merge
-        #[arg(long, default_value = "cli-actor")]
-        actor: String,
+        /// Actor identifier for CRDT merge; defaults to the data directory's own
+        #[arg(long)]
+        actor: Option<String>,
 
         /// Node type
         #[arg(long, short = 't')]
@@ -131,6 +139,24 @@ enum Commands {
         embedding: Option<String>,
     },
 
+    /// Put every record of an NDJSON or CSV file as a node
+    Import {
+        /// File to import
+        file: PathBuf,
+
+        /// Input format (ndjson, csv); a CSV header row names the JSON keys
+        #[arg(long, short = 'f', default_value = "ndjson")]
+        format: String,
+
+        /// Field or column holding each node's id; random UUIDs if omitted
+        #[arg(long)]
+        id_field: Option<String>,
+
+        /// Actor identifier for CRDT merge; defaults to the data directory's own
+        #[arg(long)]
+        actor: Option<String>,
+    },
+
     /// Retrieve a node by identifier
     Get {
         /// Node identifier
@@ -165,6 +191,11 @@ enum Commands {
         #[arg(long)]
         tag: Option<String>,
 
+        /// Filter by field values, as a JSON filter such as
+        /// '{"eq": {"path": "address.zip", "value": "02139"}}'
+        #[arg(long)]
+        filter: Option<String>,
+
         /// Limit number of results
         #[arg(long, short = 'l', default_value = "100")]
         limit: usize,
@@ -179,7 +210,7 @@ enum Commands {
         /// SQL query
         query: String,
 
-        /// Output format (json, table, csv)
+        /// Output format (json, table, csv, jsonl); csv and jsonl stream rows
         #[arg(long, short = 'f', default_value = "table")]
         format: String,
 
@@ -188,7 +219,7 @@ enum Commands {
         params: Option<String>,
     },
 
-    /// Full-text search
+    /// Full-text search over node data, ranked by TF-IDF
     Search {
         /// Search query
         query: String,
@@ -216,9 +247,15 @@ enum Commands {
     #[command(subcommand)]
     Type(TypeCommands),
 
-    /// Network commands
-    #[command(subcommand)]
-    Network(NetworkCommands),
+    /// Peer sync commands, sent to a running `serve`
+    Network {
+        /// Base URL of the server to control
+        #[arg(long, global = true, default_value = "http://127.0.0.1:34569")]
+        server: String,
+
+        #[command(subcommand)]
+        command: NetworkCommands,
+    },
 
     /// Configuration commands
     #[command(subcommand)]
@@ -228,6 +265,10 @@ enum Commands {
     #[command(subcommand)]
     Maintenance(MaintenanceCommands),
 
+    /// Write-ahead log integrity commands
+    #[command(subcommand)]
+    Wal(WalCommands),
+
     /// Migrate data from a legacy SQLite database to sled storage
     ///
     /// Reads CRDT nodes from an existing SQLite `crdt_nodes` table and writes
@@ -297,9 +338,9 @@ enum TypeCommands {
 
 #[derive(Subcommand, Debug)]
 enum NetworkCommands {
-    /// Connect to a peer
+    /// Connect to a peer and keep syncing with it
     Connect {
-        /// Peer URL (e.g., ws://localhost:34569)
+        /// Peer sync URL (e.g., ws://localhost:34569/sync)
         url: String,
     },
 
@@ -316,7 +357,7 @@ enum NetworkCommands {
         detailed: bool,
     },
 
-    /// Force synchronization
+    /// Run an anti-entropy round, pulling what this server is missing
     Sync {
         /// Specific peer ID (optional)
         peer_id: Option<String>,
@@ -374,6 +415,9 @@ enum MaintenanceCommands {
     },
 
     /// Optimize database (vacuum)
+    ///
+    /// Compacts sled and deletes WAL segments that only hold entries before
+    /// the last checkpoint.  A no-op without `--data-dir`.
     Vacuum {
         /// Show size before and after
         #[arg(long)]
@@ -394,6 +438,27 @@ enum MaintenanceCommands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum WalCommands {
+    /// Check every entry of a WAL, exiting with status 2 if any is corrupt
+    Verify {
+        /// WAL directory
+        #[arg(long)]
+        dir: PathBuf,
+    },
+
+    /// Cut a torn entry, as a crash mid-append leaves it, off the end of a
+    /// WAL, then check it as `verify` does
+    ///
+    /// Complete entries are never removed, so other corruption is reported
+    /// but left in place.
+    Repair {
+        /// WAL directory
+        #[arg(long)]
+        dir: PathBuf,
+    },
+}
+
 #[derive(Clone)]
 struct AppState {
     storage: Arc<dyn StorageEngine>,
@@ -401,6 +466,7 @@ struct AppState {
     #[cfg(feature = "sqlite-compat")]
     db: Option<Arc<Database>>,
     broadcaster: Arc<SyncBroadcaster>,
+    peers: Arc<PeerManager>,
 }
 
 #[derive(Debug, Serialize)]
@@ -496,6 +562,50 @@ fn create_storage(data_dir: Option<&PathBuf>) -> Result<Arc<dyn StorageEngine>>
     }
 }
 
+/// The actor this process writes as: the one kept in `data_dir`, created on
+/// first use, or a fresh one for an in-memory database.
+fn instance_actor(data_dir: Option<&PathBuf>) -> Result<ActorId> {
+    match data_dir {
+        Some(dir) => ActorId::load_or_create(dir.join(ACTOR_ID_FILE)),
+        None => Ok(ActorId::new_random()),
+    }
+}
+
+/// Build the peer manager for this process.  Changes merged from peers are
+/// written through to `storage` in the order they arrive.
+fn create_peer_manager(
+    storage: Arc<dyn StorageEngine>,
+    store: Arc<CrdtStore>,
+    broadcaster: Arc<SyncBroadcaster>,
+) -> Arc<PeerManager> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(String, Option<Value>)>();
+    tokio::spawn(async move {
+        while let Some((id, data)) = rx.recv().await {
+            let result = match data {
+                Some(payload) => {
+                    storage
+                        .put(StoredNode {
+                            id: id.clone(),
+                            payload,
+                            meta: None,
+                        })
+                        .await
+                }
+                None => storage.delete(&id).await,
+            };
+            if let Err(err) = result {
+                warn!("Failed to persist peer change to '{}': {}", id, err);
+            }
+        }
+    });
+    let peer_id = uuid::Uuid::new_v4().to_string();
+    Arc::new(
+        PeerManager::new(peer_id, store, broadcaster).with_merge_hook(move |id, record| {
+            let _ = tx.send((id.to_string(), record.map(|record| record.data.clone())));
+        }),
+    )
+}
+
 #[cfg(feature = "sqlite-compat")]
 fn create_database(data_dir: Option<&PathBuf>) -> Result<Option<Arc<Database>>> {
     if let Some(dir) = data_dir {
@@ -540,7 +650,17 @@ async fn handle_put(
         }
     }
 
+    // Reject typed data that does not match its registered schema
+    if let Err(errors) = load_type_registry(&storage).await?.validate_node(&payload) {
+        let err = StoreError::Validation {
+            id: id.clone(),
+            errors,
+        };
+        return Err(err.into());
+    }
+
     // Parse and index embedding if provided.
+    let mut meta = None;
     if let Some(emb_json) = embedding {
         let emb_values: Vec<f64> = serde_json::from_str(&emb_json).with_context(|| {
             format!(
@@ -549,7 +669,9 @@ async fn handle_put(
             )
         })?;
         let emb_f32: Vec<f32> = emb_values.iter().map(|&v| v as f32).collect();
-        store.put_with_embedding(id.clone(), actor.clone(), payload.clone(), emb_f32);
+        // Kept in the node's meta so later `vsearch` runs can index it
+        meta = Some(json!({ "embedding": emb_f32 }));
+        store.try_put_with_embedding(id.clone(), actor.clone(), payload.clone(), emb_f32)?;
     } else {
         store.put(id.clone(), actor.clone(), payload.clone());
     }
@@ -558,6 +680,7 @@ async fn handle_put(
         .put(StoredNode {
             id: id.clone(),
             payload,
+            meta,
         })
         .await?;
 
@@ -567,6 +690,216 @@ async fn handle_put(
     Ok(())
 }
 
+/// Nodes handed to `put_batch` at a time by `import`.
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/// Stream `file` a record at a time, putting each as a node in batches.
+/// Records that cannot be parsed, lack the id field, or fail their type's
+/// schema are skipped with a warning rather than aborting the import.
+async fn handle_import(
+    storage: Arc<dyn StorageEngine>,
+    store: Arc<CrdtStore>,
+    broadcaster: Arc<SyncBroadcaster>,
+    file: PathBuf,
+    format: String,
+    id_field: Option<String>,
+    actor: String,
+) -> Result<()> {
+    let input = fs::File::open(&file)
+        .with_context(|| format!("failed to read file: {}", file.display()))?;
+    let mut reader = ImportReader::new(BufReader::new(input), &format)?;
+    let registry = load_type_registry(&storage).await?;
+
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut imported = 0;
+    let mut skipped = 0;
+    while let Some((line, record)) = reader.next_record()? {
+        let node = record.and_then(|payload| {
+            let id = import_id(&payload, id_field.as_deref())?;
+            if let Err(errors) = registry.validate_node(&payload) {
+                return Err(StoreError::Validation { id, errors }.to_string());
+            }
+            Ok(StoredNode {
+                id,
+                payload,
+                meta: None,
+            })
+        });
+        match node {
+            Ok(node) => {
+                store.put(node.id.clone(), actor.clone(), node.payload.clone());
+                batch.push(node);
+            }
+            Err(reason) => {
+                warn!("Skipping line {}: {}", line, reason);
+                skipped += 1;
+            }
+        }
+        if batch.len() == IMPORT_BATCH_SIZE {
+            imported += put_import_batch(&storage, &broadcaster, &mut batch).await?;
+        }
+    }
+    imported += put_import_batch(&storage, &broadcaster, &mut batch).await?;
+
+    println!(
+        "{}",
+        json!({ "success": true, "imported": imported, "skipped": skipped })
+    );
+    Ok(())
+}
+
+async fn put_import_batch(
+    storage: &Arc<dyn StorageEngine>,
+    broadcaster: &SyncBroadcaster,
+    batch: &mut Vec<StoredNode>,
+) -> Result<usize> {
+    let nodes = std::mem::take(batch);
+    let ids: Vec<String> = nodes.iter().map(|node| node.id.clone()).collect();
+    storage.put_batch(nodes).await?;
+    for id in &ids {
+        broadcaster.publish(pluresdb_sync::SyncEvent::NodeUpsert { id: id.clone() })?;
+    }
+    Ok(ids.len())
+}
+
+/// The id of an imported record: the string or number in `id_field`, or a
+/// fresh UUID when no field is named.
+fn import_id(record: &Value, id_field: Option<&str>) -> Result<String, String> {
+    let Some(field) = id_field else {
+        return Ok(uuid::Uuid::new_v4().to_string());
+    };
+    match record.get(field) {
+        Some(Value::String(id)) if !id.is_empty() => Ok(id.clone()),
+        Some(Value::Number(id)) => Ok(id.to_string()),
+        _ => Err(format!("missing id field '{}'", field)),
+    }
+}
+
+/// The shape of each line read by [`ImportReader`].
+enum ImportFormat {
+    Ndjson,
+    /// CSV, keyed by the column names of its header row.
+    Csv(Vec<String>),
+}
+
+/// Reads `import` records one at a time, so a file of any size is streamed.
+struct ImportReader<R> {
+    lines: io::Lines<R>,
+    line: usize,
+    format: ImportFormat,
+}
+
+impl<R: BufRead> ImportReader<R> {
+    fn new(input: R, format: &str) -> Result<Self> {
+        let mut reader = Self {
+            lines: input.lines(),
+            line: 0,
+            format: ImportFormat::Ndjson,
+        };
+        match format {
+            "ndjson" | "jsonl" => {}
+            "csv" => {
+                let header = match reader.next_line()? {
+                    Some((_, header)) => parse_csv_record(&header)
+                        .map_err(|reason| anyhow::anyhow!("invalid CSV header: {}", reason))?,
+                    None => Vec::new(),
+                };
+                reader.format = ImportFormat::Csv(header);
+            }
+            other => anyhow::bail!(
+                "unsupported import format '{}' (expected ndjson or csv)",
+                other
+            ),
+        }
+        Ok(reader)
+    }
+
+    /// The next non-blank line and its 1-based number.  For CSV, a line
+    /// that ends inside a quoted field is joined with the lines after it.
+    fn next_line(&mut self) -> Result<Option<(usize, String)>> {
+        loop {
+            let Some(line) = self.lines.next() else {
+                return Ok(None);
+            };
+            let mut line = line?;
+            self.line += 1;
+            let start = self.line;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if matches!(self.format, ImportFormat::Csv(_)) {
+                while line.matches('"').count() % 2 == 1 {
+                    let Some(next) = self.lines.next() else {
+                        break;
+                    };
+                    line.push('\n');
+                    line.push_str(&next?);
+                    self.line += 1;
+                }
+            }
+            return Ok(Some((start, line)));
+        }
+    }
+
+    /// The next record as a JSON object, or why its line was malformed.
+    /// I/O errors end the import; malformed lines do not.
+    fn next_record(&mut self) -> Result<Option<(usize, Result<Value, String>)>> {
+        let Some((line, text)) = self.next_line()? else {
+            return Ok(None);
+        };
+        let record = match &self.format {
+            ImportFormat::Ndjson => match serde_json::from_str(&text) {
+                Ok(value @ Value::Object(_)) => Ok(value),
+                Ok(_) => Err("not a JSON object".to_string()),
+                Err(e) => Err(format!("invalid JSON: {}", e)),
+            },
+            ImportFormat::Csv(header) => parse_csv_record(&text).and_then(|fields| {
+                if fields.len() != header.len() {
+                    return Err(format!(
+                        "expected {} fields, found {}",
+                        header.len(),
+                        fields.len()
+                    ));
+                }
+                Ok(Value::Object(
+                    header
+                        .iter()
+                        .cloned()
+                        .zip(fields.into_iter().map(Value::String))
+                        .collect(),
+                ))
+            }),
+        };
+        Ok(Some((line, record)))
+    }
+}
+
+/// Split one CSV record into its fields, unquoting `"..."` fields and their
+/// doubled `""` quotes.
+fn parse_csv_record(text: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
 async fn handle_get(
     storage: Arc<dyn StorageEngine>,
     store: Arc<CrdtStore>,
@@ -618,9 +951,16 @@ async fn handle_list(
     storage: Arc<dyn StorageEngine>,
     node_type: Option<String>,
     tag: Option<String>,
+    filter: Option<String>,
     limit: usize,
     format: String,
 ) -> Result<()> {
+    let filter = filter
+        .map(|filter| {
+            serde_json::from_str::<JsonFilter>(&filter)
+                .with_context(|| format!("invalid --filter: {}", filter))
+        })
+        .transpose()?;
     let mut nodes = storage.list().await?;
 
     // Filter by type
@@ -645,6 +985,10 @@ async fn handle_list(
         });
     }
 
+    if let Some(filter) = filter {
+        nodes.retain(|n| filter.matches(&n.payload));
+    }
+
     // Limit results
     nodes.truncate(limit);
 
@@ -686,34 +1030,36 @@ async fn handle_query(
     format: String,
     params: Option<String>,
 ) -> Result<()> {
-    let db = db.context("SQL queries require a persistent database (use --data-dir)")?;
+    let db = match db {
+        Some(db) => db,
+        None => {
+            eprintln!("No --data-dir given; running against an empty in-memory database");
+            Arc::new(Database::open(DatabaseOptions::in_memory())?)
+        }
+    };
 
     let sql_params = if let Some(p) = params {
         let json_params: Vec<Value> = serde_json::from_str(&p)?;
         json_params
             .into_iter()
-            .map(|v| -> Result<SqlValue, serde_json::Error> {
-                Ok(match v {
-                    Value::Null => SqlValue::Null,
-                    Value::Number(n) => {
-                        if n.is_i64() {
-                            SqlValue::Integer(n.as_i64().unwrap())
-                        } else {
-                            SqlValue::Real(n.as_f64().unwrap())
-                        }
-                    }
-                    Value::String(s) => SqlValue::Text(s),
-                    Value::Bool(b) => SqlValue::Integer(if b { 1 } else { 0 }),
-                    Value::Array(_) | Value::Object(_) => {
-                        SqlValue::Text(serde_json::to_string(&v)?)
-                    }
-                })
-            })
+            .map(SqlValue::from_json)
             .collect::<Result<Vec<_>, _>>()?
     } else {
         vec![]
     };
 
+    if matches!(format.as_str(), "csv" | "jsonl") {
+        let stdout = io::stdout();
+        export_rows(
+            &db,
+            &query,
+            &sql_params,
+            &format,
+            io::BufWriter::new(stdout.lock()),
+        )?;
+        return Ok(());
+    }
+
     let result = db.query(&query, &sql_params)?;
 
     match format.as_str() {
@@ -726,24 +1072,6 @@ async fn handle_query(
             });
             println!("{}", serde_json::to_string_pretty(&output)?);
         }
-        "csv" => {
-            // Print CSV header
-            println!("{}", result.columns.join(","));
-            // Print CSV rows
-            for row in &result.rows {
-                let csv_row: Vec<String> = row
-                    .iter()
-                    .map(|v| match v {
-                        SqlValue::Null => "".to_string(),
-                        SqlValue::Integer(i) => i.to_string(),
-                        SqlValue::Real(r) => r.to_string(),
-                        SqlValue::Text(t) => format!("\"{}\"", t.replace('"', "\"\"")),
-                        SqlValue::Blob(b) => format!("{:?}", b),
-                    })
-                    .collect();
-                println!("{}", csv_row.join(","));
-            }
-        }
         "table" | _ => {
             // Print table header
             for col in &result.columns {
@@ -759,8 +1087,8 @@ async fn handle_query(
                         SqlValue::Integer(i) => i.to_string(),
                         SqlValue::Real(r) => format!("{:.2}", r),
                         SqlValue::Text(t) => {
-                            if t.len() > 18 {
-                                format!("{}...", &t[..18])
+                            if t.chars().count() > 18 {
+                                format!("{}...", t.chars().take(18).collect::<String>())
                             } else {
                                 t.clone()
                             }
@@ -780,30 +1108,85 @@ async fn handle_query(
     Ok(())
 }
 
-async fn handle_search(storage: Arc<dyn StorageEngine>, query: String, limit: usize) -> Result<()> {
-    let nodes = storage.list().await?;
-    let query_lower = query.to_lowercase();
-
-    let mut matches: Vec<(&StoredNode, usize)> = nodes
-        .iter()
-        .filter_map(|node| {
-            let json_str = serde_json::to_string(&node.payload).ok()?;
-            let count = json_str.to_lowercase().matches(&query_lower).count();
-            if count > 0 {
-                Some((node, count))
-            } else {
-                None
-            }
-        })
-        .collect();
+/// Rows written between flushes when streaming `csv`/`jsonl` output.
+#[cfg(feature = "sqlite-compat")]
+const EXPORT_FLUSH_ROWS: u64 = 10_000;
 
-    matches.sort_by_key(|b| std::cmp::Reverse(b.1));
-    matches.truncate(limit);
+/// Stream the rows of `query` to `out` as CSV or JSON Lines, one row at a
+/// time, so exporting a table of any size uses constant memory.  Returns the
+/// number of rows written.
+#[cfg(feature = "sqlite-compat")]
+fn export_rows(
+    db: &Database,
+    query: &str,
+    params: &[SqlValue],
+    format: &str,
+    mut out: impl Write,
+) -> Result<u64> {
+    let stmt = db.prepare(query)?;
+    let columns = stmt.columns()?;
+    if format == "csv" {
+        let header: Vec<String> = columns.iter().map(|name| csv_header(name)).collect();
+        writeln!(out, "{}", header.join(","))?;
+    }
+
+    let mut written = 0u64;
+    let rows = stmt.stream(params, |row| -> Result<()> {
+        if format == "csv" {
+            let fields: Vec<String> = row.iter().map(csv_field).collect();
+            writeln!(out, "{}", fields.join(","))?;
+        } else {
+            let object: serde_json::Map<String, Value> = columns
+                .iter()
+                .cloned()
+                .zip(row.iter().map(SqlValue::to_json))
+                .collect();
+            serde_json::to_writer(&mut out, &object)?;
+            out.write_all(b"\n")?;
+        }
+        written += 1;
+        if written % EXPORT_FLUSH_ROWS == 0 {
+            out.flush()?;
+        }
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(rows)
+}
+
+#[cfg(feature = "sqlite-compat")]
+fn csv_field(value: &SqlValue) -> String {
+    match value {
+        SqlValue::Null => "".to_string(),
+        SqlValue::Integer(i) => i.to_string(),
+        SqlValue::Real(r) => r.to_string(),
+        SqlValue::Text(t) => format!("\"{}\"", t.replace('"', "\"\"")),
+        SqlValue::Blob(b) => b.iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+/// A column name, quoted only when it would otherwise break the CSV row.
+#[cfg(feature = "sqlite-compat")]
+fn csv_header(name: &str) -> String {
+    if name.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+async fn handle_search(storage: Arc<dyn StorageEngine>, query: String, limit: usize) -> Result<()> {
+    // The command's CrdtStore starts empty, so index what is on disk.
+    let store = CrdtStore::default();
+    for node in storage.list().await? {
+        store.put(node.id, "cli", node.payload);
+    }
+    let results = store.search(&query, limit);
 
-    println!("Found {} matches:", matches.len());
-    for (node, score) in matches {
-        println!("  {} (matches: {})", node.id, score);
-        let preview = serde_json::to_string_pretty(&node.payload)?;
+    println!("Found {} matches:", results.len());
+    for (record, score) in results {
+        println!("  {} (score: {:.4})", record.id, score);
+        let preview = serde_json::to_string_pretty(&record.data)?;
         let preview_lines: Vec<&str> = preview.lines().take(5).collect();
         println!("    {}", preview_lines.join("\n    "));
         if preview.lines().count() > 5 {
@@ -814,21 +1197,35 @@ async fn handle_search(storage: Arc<dyn StorageEngine>, query: String, limit: us
     Ok(())
 }
 
+/// The embedding `put --embedding` stored in the node's meta.
+fn stored_embedding(node: &StoredNode) -> Option<Vec<f32>> {
+    let embedding = node.meta.as_ref()?.get("embedding")?;
+    serde_json::from_value(embedding.clone()).ok()
+}
+
 async fn handle_vsearch(
-    store: Arc<CrdtStore>,
+    storage: Arc<dyn StorageEngine>,
     embedding: Vec<f32>,
     limit: usize,
-    min_score: f32,
+    threshold: f32,
 ) -> Result<()> {
-    let results = store.vector_search(&embedding, limit, min_score);
+    // The command's CrdtStore starts empty, so index the embeddings on disk.
+    let store = CrdtStore::default();
+    for node in storage.list().await? {
+        let Some(stored) = stored_embedding(&node) else {
+            continue;
+        };
+        let id = node.id.clone();
+        if let Err(e) = store.try_put_with_embedding(node.id, "cli", node.payload, stored) {
+            eprintln!("Skipping {}: {}", id, e);
+        }
+    }
+    let results = store.nearest_neighbors(&embedding, limit, threshold)?;
 
     println!("Found {} matches:", results.len());
-    for r in results {
-        println!(
-            "  {} (score: {:.4}, similarity: {:.4})",
-            r.record.id, r.score, r.similarity
-        );
-        let preview = serde_json::to_string_pretty(&r.record.data)?;
+    for (record, similarity) in results {
+        println!("  {} (similarity: {:.4})", record.id, similarity);
+        let preview = serde_json::to_string_pretty(&record.data)?;
         let preview_lines: Vec<&str> = preview.lines().take(5).collect();
         // is_truncated if we hit the take(5) limit and there are more lines.
         let is_truncated = preview_lines.len() == 5 && preview.lines().nth(5).is_some();
@@ -841,29 +1238,34 @@ async fn handle_vsearch(
     Ok(())
 }
 
+/// Registry of the type definitions held in `storage`.
+async fn load_type_registry(storage: &Arc<dyn StorageEngine>) -> Result<TypeRegistry> {
+    let registry = TypeRegistry::default();
+    for node in storage.list().await? {
+        registry.register_definition(&node.payload);
+    }
+    Ok(registry)
+}
+
 async fn handle_type_define(
     storage: Arc<dyn StorageEngine>,
     name: String,
     schema: Option<String>,
 ) -> Result<()> {
     let schema_value = if let Some(s) = schema {
-        serde_json::from_str(&s).context("invalid JSON schema")?
+        load_payload(&s).context("invalid JSON schema")?
     } else {
         json!({})
     };
+    TypeRegistry::default()
+        .define(name.as_str(), schema_value.clone())
+        .map_err(|e| anyhow::anyhow!("invalid schema for type '{}': {}", name, e))?;
 
-    let type_node = json!({
-        "type": "__type_definition__",
-        "name": name,
-        "schema": schema_value,
-        "created_at": chrono::Utc::now()
-    });
-
-    let id = format!("type:{}", name);
     storage
         .put(StoredNode {
-            id: id.clone(),
-            payload: type_node,
+            id: type_definition_id(&name),
+            payload: type_definition(&name, schema_value),
+            meta: None,
         })
         .await?;
 
@@ -872,17 +1274,7 @@ async fn handle_type_define(
 }
 
 async fn handle_type_list(storage: Arc<dyn StorageEngine>) -> Result<()> {
-    let nodes = storage.list().await?;
-    let types: Vec<_> = nodes
-        .iter()
-        .filter_map(|n| {
-            if n.payload.get("type") == Some(&Value::String("__type_definition__".to_string())) {
-                n.payload.get("name").and_then(|v| v.as_str())
-            } else {
-                None
-            }
-        })
-        .collect();
+    let types = load_type_registry(&storage).await?.names();
 
     if types.is_empty() {
         println!("No types defined");
@@ -901,37 +1293,22 @@ async fn handle_type_instances(
     name: String,
     limit: usize,
 ) -> Result<()> {
-    let nodes = storage.list().await?;
-    let instances: Vec<_> = nodes
-        .iter()
-        .filter(|n| {
-            n.payload
-                .get("type")
-                .and_then(|v| v.as_str())
-                .map(|s| s == name)
-                .unwrap_or(false)
-        })
-        .take(limit)
-        .collect();
+    let store = load_store(&storage).await?;
+    let instances = store.list_by_type(&name);
 
     println!("Instances of type '{}':", name);
-    for node in instances {
-        println!("  - {}", node.id);
+    for record in instances.iter().take(limit) {
+        println!("  - {}", record.id);
     }
 
     Ok(())
 }
 
 async fn handle_type_schema(storage: Arc<dyn StorageEngine>, name: String) -> Result<()> {
-    let id = format!("type:{}", name);
-    match storage.get(&id).await? {
-        Some(node) => {
-            if let Some(schema) = node.payload.get("schema") {
-                println!("Schema for type '{}':", name);
-                println!("{}", serde_json::to_string_pretty(schema)?);
-            } else {
-                println!("No schema defined for type '{}'", name);
-            }
+    match load_type_registry(&storage).await?.get(&name) {
+        Some(schema) => {
+            println!("Schema for type '{}':", name);
+            println!("{}", serde_json::to_string_pretty(&schema)?);
         }
         None => {
             error!("Type '{}' not found", name);
@@ -942,40 +1319,137 @@ async fn handle_type_schema(storage: Arc<dyn StorageEngine>, name: String) -> Re
     Ok(())
 }
 
-async fn handle_network_connect(url: String) -> Result<()> {
+/// Send a request to the peer API of the server at `server`, returning its
+/// JSON body or the error it reported.
+async fn network_api(server: &str, request: reqwest::RequestBuilder) -> Result<Value> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("failed to reach {} (is `pluresdb serve` running?)", server))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .with_context(|| format!("invalid response from {}", server))?;
+    if !status.is_success() {
+        let message = body["error"].as_str().unwrap_or("request failed");
+        anyhow::bail!("{} ({})", message, status);
+    }
+    Ok(body)
+}
+
+async fn handle_network_connect(server: &str, url: String) -> Result<()> {
     info!("Connecting to peer: {}", url);
-    warn!("Network connection not yet fully implemented");
-    println!("Would connect to: {}", url);
+    let request = reqwest::Client::new()
+        .post(format!("{}/api/peers", server))
+        .json(&json!({ "url": url }));
+    let body = network_api(server, request).await?;
+    println!(
+        "Connected to peer {}",
+        body["peer_id"].as_str().unwrap_or("?")
+    );
     Ok(())
 }
 
-async fn handle_network_disconnect(peer_id: String) -> Result<()> {
+async fn handle_network_disconnect(server: &str, peer_id: String) -> Result<()> {
     info!("Disconnecting from peer: {}", peer_id);
-    warn!("Network disconnection not yet fully implemented");
-    println!("Would disconnect from: {}", peer_id);
+    let request = reqwest::Client::new().delete(format!("{}/api/peers/{}", server, peer_id));
+    network_api(server, request).await?;
+    println!("Disconnected from peer {}", peer_id);
     Ok(())
 }
 
-async fn handle_network_peers(detailed: bool) -> Result<()> {
-    warn!("Network peer listing not yet fully implemented");
-    println!("Connected peers: 0");
-    if detailed {
-        println!("  (No peers connected)");
+async fn handle_network_peers(server: &str, detailed: bool) -> Result<()> {
+    let body = network_api(
+        server,
+        reqwest::Client::new().get(format!("{}/api/peers", server)),
+    )
+    .await?;
+    let peers: Vec<PeerStatus> = serde_json::from_value(body["data"].clone())?;
+    println!("Connected peers: {}", peers.len());
+    for peer in peers {
+        if detailed {
+            let direction = if peer.outbound { "outbound" } else { "inbound" };
+            let since = chrono::DateTime::from_timestamp_millis(peer.connected_at_ms as i64)
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_default();
+            println!(
+                "  {} ({}, {}, since {})",
+                peer.peer_id, peer.address, direction, since
+            );
+        } else {
+            println!("  {}", peer.peer_id);
+        }
     }
     Ok(())
 }
 
-async fn handle_network_sync(peer_id: Option<String>) -> Result<()> {
+async fn handle_network_sync(server: &str, peer_id: Option<String>) -> Result<()> {
     info!("Synchronizing with peer: {:?}", peer_id);
-    warn!("Network synchronization not yet fully implemented");
-    if let Some(p) = peer_id {
-        println!("Would sync with peer: {}", p);
-    } else {
-        println!("Would sync with all peers");
+    let request = reqwest::Client::new()
+        .post(format!("{}/api/peers/sync", server))
+        .json(&json!({ "peer_id": peer_id }));
+    let body = network_api(server, request).await?;
+    println!("Sync requested from {} peer(s)", body["peers"]);
+    Ok(())
+}
+
+/// Fail unless `dir` is an existing directory, so checking a mistyped path
+/// does not create an empty, healthy-looking WAL there.
+fn existing_wal_dir(dir: &std::path::Path) -> Result<()> {
+    if !dir.is_dir() {
+        anyhow::bail!("WAL directory not found: {}", dir.display());
     }
     Ok(())
 }
 
+fn print_wal_validation(dir: &std::path::Path, validation: &WalValidation) {
+    println!("WAL: {}", dir.display());
+    println!(
+        "  Status: {}",
+        if validation.is_healthy() {
+            "healthy"
+        } else {
+            "corrupted"
+        }
+    );
+    println!("  Segments: {}", validation.total_segments);
+    println!("  Entries: {}", validation.total_entries);
+    println!("  Valid entries: {}", validation.valid_entries);
+    println!("  Corrupted entries: {}", validation.corrupted_entries);
+    println!("  Corrupted segments: {}", validation.corrupted_segments);
+    println!(
+        "  Corruption rate: {:.2}%",
+        validation.corruption_rate() * 100.0
+    );
+}
+
+/// Print the validation of the WAL in `dir` and return whether it is healthy.
+async fn handle_wal_verify(dir: &std::path::Path) -> Result<bool> {
+    existing_wal_dir(dir)?;
+    let validation = WriteAheadLog::open(dir)?.validate().await?;
+    print_wal_validation(dir, &validation);
+    Ok(validation.is_healthy())
+}
+
+/// Cut a torn tail off the WAL in `dir`, print what was reclaimed and the
+/// validation afterwards, and return whether it is now healthy.
+async fn handle_wal_repair(dir: &std::path::Path) -> Result<bool> {
+    existing_wal_dir(dir)?;
+    let (wal, before) = WriteAheadLog::open_with_recovery(dir, RecoveryPolicy::RepairTornTail)?;
+    if before.discarded_bytes > 0 {
+        println!(
+            "Reclaimed {} bytes from a torn entry at the end of the WAL",
+            before.discarded_bytes
+        );
+    } else {
+        println!("No torn entry found; nothing reclaimed");
+    }
+    let after = wal.validate().await?;
+    print_wal_validation(dir, &after);
+    Ok(after.is_healthy())
+}
+
 fn directory_size_bytes(path: &std::path::Path) -> Result<u64> {
     let mut total = 0u64;
     for entry in fs::read_dir(path)? {
@@ -1371,28 +1845,45 @@ async fn handle_config_reset(data_dir: Option<&PathBuf>, force: bool) -> Result<
     Ok(())
 }
 
+/// Load the nodes in `storage` into a fresh [`CrdtStore`].
+///
+/// Payloads that are themselves serialized [`NodeRecord`]s keep their clocks
+/// and timestamps; plain payloads are written as new local versions.
+async fn load_store(storage: &Arc<dyn StorageEngine>) -> Result<CrdtStore> {
+    let store = CrdtStore::default();
+    for node in storage.list().await? {
+        match serde_json::from_value::<NodeRecord>(node.payload.clone()) {
+            Ok(record) if record.id == node.id => {
+                store.merge_record(record);
+            }
+            _ => {
+                store.put(node.id, "cli", node.payload);
+            }
+        }
+    }
+    Ok(store)
+}
+
 async fn handle_backup(
     storage: Arc<dyn StorageEngine>,
     path: PathBuf,
     compress: bool,
 ) -> Result<()> {
-    let nodes = storage.list().await?;
-    let backup_data = json!({
-        "version": VERSION,
-        "timestamp": chrono::Utc::now(),
-        "nodes": nodes
-    });
+    let store = load_store(&storage).await?;
+    let file = BufWriter::new(
+        fs::File::create(&path).with_context(|| format!("Failed to create {:?}", path))?,
+    );
 
-    let content = serde_json::to_string_pretty(&backup_data)?;
-    if compress {
-        // Simple compression - in production, use proper compression
-        fs::write(&path, content)?;
-        info!("Backup saved to: {:?}", path);
+    let records = if compress {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        let records = write_backup(&store, &mut encoder)?;
+        encoder.finish()?.flush()?;
+        records
     } else {
-        fs::write(&path, content)?;
-    }
+        write_backup(&store, file)?
+    };
 
-    println!("Backup saved to: {:?}", path);
+    println!("Backed up {} records to: {:?}", records, path);
     Ok(())
 }
 
@@ -1408,95 +1899,195 @@ async fn handle_restore(storage: Arc<dyn StorageEngine>, path: PathBuf, force: b
         }
     }
 
-    let content = fs::read_to_string(&path)?;
-    let backup: Value = serde_json::from_str(&content)?;
+    let mut file = BufReader::new(
+        fs::File::open(&path).with_context(|| format!("Failed to open {:?}", path))?,
+    );
+    // Gzip streams start with 0x1f 0x8b, which never begins a JSON header line
+    let gzipped = file.fill_buf()?.starts_with(&[0x1f, 0x8b]);
 
-    if let Some(nodes_array) = backup.get("nodes").and_then(|v| v.as_array()) {
-        for node_value in nodes_array {
-            if let Ok(node) = serde_json::from_value::<StoredNode>(node_value.clone()) {
-                storage.put(node).await?;
-            }
-        }
-        println!("Restored {} nodes from backup", nodes_array.len());
+    let store = CrdtStore::default();
+    let (header, stats) = if gzipped {
+        restore_backup(&store, BufReader::new(GzDecoder::new(file)))
     } else {
-        error!("Invalid backup format");
-        std::process::exit(1);
+        restore_backup(&store, file)
+    }
+    .with_context(|| format!("Failed to restore {:?}", path))?;
+
+    let mut live = 0;
+    for record in store.list_including_tombstones() {
+        if record.is_tombstone() {
+            storage.delete(&record.id).await?;
+        } else {
+            storage
+                .put(StoredNode {
+                    id: record.id,
+                    payload: record.data,
+                    meta: None,
+                })
+                .await?;
+            live += 1;
+        }
     }
 
+    println!(
+        "Restored {} nodes ({} records, format version {}, created {})",
+        live, stats.records, header.version, header.created_at
+    );
+    Ok(())
+}
+
+/// Compact sled and drop WAL segments made obsolete by the last checkpoint,
+/// then vacuum the SQLite database when the `sqlite-compat` feature is on.
+async fn handle_vacuum(
+    storage: Arc<dyn StorageEngine>,
+    data_dir: Option<&PathBuf>,
+    #[cfg(feature = "sqlite-compat")] db: Option<Arc<Database>>,
+    stats: bool,
+) -> Result<()> {
+    let Some(dir) = data_dir else {
+        println!("Nothing to vacuum: the in-memory backend keeps no data on disk (use --data-dir)");
+        return Ok(());
+    };
+
+    let size_before = directory_size_bytes(dir)?;
+    storage.compact().await?;
+
+    let mut segments_removed = 0;
+    if let Some(wal_dir) = detect_wal_directory(dir) {
+        let wal = WriteAheadLog::open(&wal_dir)?;
+        match wal.last_checkpoint().await? {
+            Some(base_seq) => segments_removed = wal.compact(base_seq).await?,
+            None => warn!(
+                "WAL in {:?} has no checkpoint; keeping every segment",
+                wal_dir
+            ),
+        }
+    }
+
+    #[cfg(feature = "sqlite-compat")]
+    if let Some(db) = db {
+        vacuum_sqlite(&db, stats)?;
+    }
+
+    if stats {
+        let size_after = directory_size_bytes(dir)?;
+        println!("On-disk size:");
+        println!("  Before: {} bytes", size_before);
+        println!("  After: {} bytes", size_after);
+        println!(
+            "  Reclaimed: {} bytes",
+            size_before.saturating_sub(size_after)
+        );
+        println!("WAL segments removed: {}", segments_removed);
+    }
+
+    println!("Vacuum complete");
     Ok(())
 }
 
 /// Vacuum the SQLite database (requires `sqlite-compat` feature).
 #[cfg(feature = "sqlite-compat")]
-async fn handle_vacuum(db: Option<Arc<Database>>, stats: bool) -> Result<()> {
-    let db = db.context("Vacuum requires a persistent database (use --data-dir)")?;
-
+fn vacuum_sqlite(db: &Database, stats: bool) -> Result<()> {
     if stats {
-        let before = db.pragma("page_count")?;
-        let size_before = db.pragma("page_size")?;
         println!("Before vacuum:");
-        println!("  Pages: {:?}", before.rows_as_json());
-        println!("  Page size: {:?}", size_before.rows_as_json());
+        print_sqlite_size(db)?;
     }
 
     db.exec("VACUUM")?;
 
+    // VACUUM in WAL mode rewrites the database through the log; fold it back
+    // into the main file and reclaim the `-wal` file's disk space.
+    let checkpoint = db.wal_checkpoint(CheckpointMode::Truncate)?;
+    if checkpoint.busy {
+        warn!("WAL checkpoint could not complete; another connection is using the database");
+    }
+
     if stats {
-        let after = db.pragma("page_count")?;
-        let size_after = db.pragma("page_size")?;
         println!("After vacuum:");
-        println!("  Pages: {:?}", after.rows_as_json());
-        println!("  Page size: {:?}", size_after.rows_as_json());
+        print_sqlite_size(db)?;
+        println!("  WAL truncated: {}", !checkpoint.busy);
     }
 
-    println!("Database vacuumed successfully");
+    println!("SQLite database vacuumed");
+    Ok(())
+}
+
+/// Print the page layout and size of the SQLite database file.
+#[cfg(feature = "sqlite-compat")]
+fn print_sqlite_size(db: &Database) -> Result<()> {
+    println!("  Pages: {}", db.page_count()?);
+    println!("  Free pages: {}", db.freelist_count()?);
+    println!("  Page size: {} bytes", db.page_size()?);
+    println!("  Size: {} bytes", db.database_size_bytes()?);
     Ok(())
 }
 
-/// Run schema migrations via SQLite (requires `sqlite-compat` feature).
+/// Migrate the `--data-dir` SQLite database to `version`, or to the latest
+/// schema (requires `sqlite-compat` feature).
 #[cfg(feature = "sqlite-compat")]
 async fn handle_migrate(db: Option<Arc<Database>>, version: Option<u32>) -> Result<()> {
     let db = db.context("Migrations require a persistent database (use --data-dir)")?;
 
-    // Create migrations table if it doesn't exist
-    db.exec(
-        "CREATE TABLE IF NOT EXISTS schema_migrations (
-            version INTEGER PRIMARY KEY,
-            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
-    )?;
-
     if let Some(target_version) = version {
         info!("Migrating to version: {}", target_version);
-        // In a real implementation, apply migrations up to target_version
-        println!("Migration to version {} completed", target_version);
+    }
+    let report = pluresdb_core::run_migrations(&db, version)?;
+    if report.changed() {
+        println!(
+            "Migrated schema from version {} to {}",
+            report.from, report.to
+        );
     } else {
-        // Apply all pending migrations
-        println!("All migrations are up to date");
+        println!("Schema is up to date at version {}", report.to);
     }
 
     Ok(())
 }
 
-async fn handle_stats(storage: Arc<dyn StorageEngine>, detailed: bool) -> Result<()> {
-    let nodes = storage.list().await?;
+async fn handle_stats(
+    storage: Arc<dyn StorageEngine>,
+    #[cfg(feature = "sqlite-compat")] db: Option<Arc<Database>>,
+    detailed: bool,
+) -> Result<()> {
     println!("Database Statistics:");
-    println!("  Total nodes: {}", nodes.len());
+    println!("  Total nodes: {}", storage.count().await?);
+    #[cfg(feature = "sqlite-compat")]
+    if let Some(db) = &db {
+        println!("  SQLite size: {} bytes", db.database_size_bytes()?);
+    }
 
     if detailed {
         // Count by type
         let mut type_counts: HashMap<String, usize> = HashMap::new();
-        for node in &nodes {
-            if let Some(t) = node.payload.get("type").and_then(|v| v.as_str()) {
-                *type_counts.entry(t.to_string()).or_insert(0) += 1;
-            }
-        }
+        storage
+            .for_each(&mut |node: StoredNode| {
+                if let Some(t) = node.payload.get("type").and_then(|v| v.as_str()) {
+                    *type_counts.entry(t.to_string()).or_insert(0) += 1;
+                }
+                true
+            })
+            .await?;
         if !type_counts.is_empty() {
             println!("\nNodes by type:");
             for (t, count) in type_counts {
                 println!("  {}: {}", t, count);
             }
         }
+
+        #[cfg(feature = "sqlite-compat")]
+        if let Some(db) = &db {
+            println!("\nSQLite database:");
+            print_sqlite_size(db)?;
+        }
+
+        // Counters cover this process only, since they start at zero on open
+        if let Some(metrics) = storage.metrics() {
+            println!("\nStorage operations (this session):");
+            println!("  Puts: {}", metrics.puts);
+            println!("  Get hits: {}", metrics.get_hits);
+            println!("  Get misses: {}", metrics.get_misses);
+            println!("  Deletes: {}", metrics.deletes);
+        }
     }
 
     Ok(())
@@ -1611,6 +2202,7 @@ async fn handle_migrate_from_sqlite(source: PathBuf, target: PathBuf) -> Result<
             id: id.clone(),
             payload: serde_json::to_value(&record)
                 .with_context(|| format!("Failed to serialize node '{}'", id))?,
+            meta: None,
         };
         storage
             .put(stored)
@@ -1630,6 +2222,7 @@ async fn handle_migrate_from_sqlite(source: PathBuf, target: PathBuf) -> Result<
 }
 
 async fn create_api_server(state: AppState, bind: String, port: u16) -> Result<()> {
+    let peers = state.peers.clone();
     let app = Router::new()
         .route("/health", get(health_handler))
         .route(
@@ -1642,13 +2235,20 @@ async fn create_api_server(state: AppState, bind: String, port: u16) -> Result<(
         )
         .route("/api/nodes/{id}/embedding", post(node_embedding_handler))
         .route("/api/vector-search", post(vector_search_handler))
+        .route(
+            "/api/peers",
+            get(list_peers_handler).post(connect_peer_handler),
+        )
+        .route("/api/peers/sync", post(sync_peers_handler))
+        .route("/api/peers/{id}", delete(disconnect_peer_handler))
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
                 .layer(CorsLayer::permissive())
                 .into_inner(),
         )
-        .with_state(state);
+        .with_state(state)
+        .merge(peers.router());
 
     let addr = format!("{}:{}", bind, port);
     let listener = tokio::net::TcpListener::bind(&addr).await?;
@@ -1764,6 +2364,7 @@ async fn create_node_handler(
     let node = StoredNode {
         id: id.to_string(),
         payload: payload.clone(),
+        meta: None,
     };
 
     match state.storage.put(node).await {
@@ -1786,6 +2387,7 @@ async fn delete_node_handler(
 ) -> Result<Json<Value>, StatusCode> {
     match state.storage.delete(&id).await {
         Ok(_) => {
+            let _ = state.store.delete(&id);
             let _ = state
                 .broadcaster
                 .publish(pluresdb_sync::SyncEvent::NodeDelete { id: id.clone() });
@@ -1798,6 +2400,87 @@ async fn delete_node_handler(
     }
 }
 
+type PeerApiResult = Result<Json<Value>, (StatusCode, Json<Value>)>;
+
+fn peer_api_error(status: StatusCode, err: impl std::fmt::Display) -> (StatusCode, Json<Value>) {
+    (
+        status,
+        Json(json!({
+            "success": false,
+            "error": err.to_string()
+        })),
+    )
+}
+
+async fn list_peers_handler(State(state): State<AppState>) -> Json<Value> {
+    Json(json!({
+        "success": true,
+        "peer_id": state.peers.peer_id(),
+        "data": state.peers.peers()
+    }))
+}
+
+/// Request body for `POST /api/peers`.
+#[derive(Debug, Deserialize)]
+struct ConnectPeerRequest {
+    /// WebSocket URL of the peer's `/sync` endpoint.
+    url: String,
+}
+
+async fn connect_peer_handler(
+    State(state): State<AppState>,
+    Json(request): Json<ConnectPeerRequest>,
+) -> PeerApiResult {
+    match state.peers.connect(&request.url).await {
+        Ok(peer_id) => Ok(Json(json!({
+            "success": true,
+            "peer_id": peer_id
+        }))),
+        Err(err) => Err(peer_api_error(
+            StatusCode::BAD_GATEWAY,
+            format!("{:#}", err),
+        )),
+    }
+}
+
+/// Request body for `POST /api/peers/sync`.
+#[derive(Debug, Default, Deserialize)]
+struct SyncPeersRequest {
+    /// Only sync with this peer; every connected peer when absent.
+    #[serde(default)]
+    peer_id: Option<String>,
+}
+
+async fn sync_peers_handler(
+    State(state): State<AppState>,
+    Json(request): Json<SyncPeersRequest>,
+) -> PeerApiResult {
+    match state.peers.sync(request.peer_id.as_deref()) {
+        Ok(peers) => Ok(Json(json!({
+            "success": true,
+            "peers": peers
+        }))),
+        Err(err) => Err(peer_api_error(StatusCode::NOT_FOUND, err)),
+    }
+}
+
+async fn disconnect_peer_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> PeerApiResult {
+    if state.peers.disconnect(&id) {
+        Ok(Json(json!({
+            "success": true,
+            "peer_id": id
+        })))
+    } else {
+        Err(peer_api_error(
+            StatusCode::NOT_FOUND,
+            format!("not connected to peer '{}'", id),
+        ))
+    }
+}
+
 /// Request body for `POST /api/nodes/:id/embedding`.
 #[derive(Debug, Deserialize)]
 struct NodeEmbeddingRequest {
@@ -1886,13 +2569,36 @@ async fn vector_search_handler(
 
 fn classify_error_diagnostic(err: &anyhow::Error) -> (&str, &[&str]) {
     if let Some(store_err) = err.downcast_ref::<StoreError>() {
-        return (
-            store_err.code().as_str(),
-            &[
+        let next_steps: &[&str] = match store_err {
+            StoreError::NotFound(_) => &[
                 "Verify the node ID exists with: pluresdb list",
                 "Insert the node first with: pluresdb put <id> '{\"key\":\"value\"}'",
             ],
-        );
+            StoreError::Validation { .. } => &[
+                "Show the expected shape with: pluresdb type schema <type>",
+                "Fix the listed fields, or redefine the type with: pluresdb type define",
+            ],
+            StoreError::Vector(_) => &[
+                "Use embeddings with as many dimensions as the stored ones",
+                "Embeddings must be non-empty, finite, and not all zero",
+            ],
+            StoreError::ClockMismatch { .. } => &[
+                "Re-read the node with: pluresdb get <id>",
+                "Retry the update against the current version",
+            ],
+            StoreError::NodeTooLarge { .. } => &[
+                "Shrink the payload, e.g. by splitting it across several nodes",
+                "Or raise the limit set with CrdtStore::with_max_node_size",
+            ],
+            StoreError::TooDeep { .. } => &[
+                "Flatten the payload so objects and arrays nest less deeply",
+                "Or raise the limit set with CrdtStore::with_max_node_depth",
+            ],
+            StoreError::InvalidArgument(_) => {
+                &["Check the value named in the error against the documented range"]
+            }
+        };
+        return (store_err.code().as_str(), next_steps);
     }
 
     if let Some(wal_err) = err.downcast_ref::<WalError>() {
@@ -1978,6 +2684,38 @@ fn run() -> Result<()> {
 
     info!("PluresDB CLI v{}", VERSION);
 
+    // The server owns peer connections (and the data directory), so these
+    // only talk to it over HTTP.
+    if let Commands::Network { server, command } = cli.command {
+        let rt = init_runtime();
+        return rt.block_on(async move {
+            match command {
+                NetworkCommands::Connect { url } => handle_network_connect(&server, url).await,
+                NetworkCommands::Disconnect { peer_id } => {
+                    handle_network_disconnect(&server, peer_id).await
+                }
+                NetworkCommands::Peers { detailed } => {
+                    handle_network_peers(&server, detailed).await
+                }
+                NetworkCommands::Sync { peer_id } => handle_network_sync(&server, peer_id).await,
+            }
+        });
+    }
+
+    if let Commands::Wal(command) = &cli.command {
+        let rt = init_runtime();
+        return rt.block_on(async move {
+            let healthy = match command {
+                WalCommands::Verify { dir } => handle_wal_verify(dir).await?,
+                WalCommands::Repair { dir } => handle_wal_repair(dir).await?,
+            };
+            if !healthy {
+                std::process::exit(2);
+            }
+            Ok(())
+        });
+    }
+
     if let Commands::Doctor { json } = &cli.command {
         let json_output = *json;
         let rt = init_runtime();
@@ -2002,6 +2740,7 @@ fn run() -> Result<()> {
         #[cfg(feature = "sqlite-compat")]
         let db = create_database(cli.data_dir.as_ref())?;
         let broadcaster = Arc::new(SyncBroadcaster::default());
+        let peers = create_peer_manager(storage.clone(), store.clone(), broadcaster.clone());
 
         let state = AppState {
             storage: storage.clone(),
@@ -2009,6 +2748,7 @@ fn run() -> Result<()> {
             #[cfg(feature = "sqlite-compat")]
             db: db.clone(),
             broadcaster: broadcaster.clone(),
+            peers,
         };
 
         match cli.command {
@@ -2025,6 +2765,10 @@ fn run() -> Result<()> {
 
             Commands::Serve { port, bind, websocket: _ } => {
                 info!("Starting PluresDB server on {}:{}", bind, port);
+                // Peers diff against the CRDT store, so give it what is on disk
+                for node in storage.list().await? {
+                    store.put(node.id, "cli", node.payload);
+                }
                 println!("Server starting on http://{}:{}", bind, port);
                 println!("Press Ctrl+C to stop");
                 create_api_server(state, bind, port).await
@@ -2041,8 +2785,11 @@ fn run() -> Result<()> {
                         "In-memory"
                     };
                     println!("Storage: {}", storage_type);
-                    let nodes = storage.list().await?;
-                    println!("Nodes: {}", nodes.len());
+                    println!("Nodes: {}", storage.count().await?);
+                    #[cfg(feature = "sqlite-compat")]
+                    if let Some(db) = &db {
+                        println!("SQLite size: {} bytes", db.database_size_bytes()?);
+                    }
                 }
                 Ok(())
             }
@@ -2057,9 +2804,20 @@ fn run() -> Result<()> {
                 tags,
                 embedding,
             } => {
+                let actor = actor.map_or_else(|| instance_actor(cli.data_dir.as_ref()), Ok)?;
                 handle_put(storage, store, broadcaster, id, data, actor, node_type, tags, embedding).await
             }
 
+            Commands::Import {
+                file,
+                format,
+                id_field,
+                actor,
+            } => {
+                let actor = actor.map_or_else(|| instance_actor(cli.data_dir.as_ref()), Ok)?;
+                handle_import(storage, store, broadcaster, file, format, id_field, actor).await
+            }
+
             Commands::Get { id, format, metadata } => {
                 handle_get(storage, store, id, format, metadata).await
             }
@@ -2086,9 +2844,10 @@ fn run() -> Result<()> {
             Commands::List {
                 node_type,
                 tag,
+                filter,
                 limit,
                 format,
-            } => handle_list(storage, node_type, tag, limit, format).await,
+            } => handle_list(storage, node_type, tag, filter, limit, format).await,
 
             Commands::Query { query, format, params } => {
                 #[cfg(feature = "sqlite-compat")]
@@ -2115,7 +2874,7 @@ fn run() -> Result<()> {
                         embedding
                     ))?;
                 let emb_f32: Vec<f32> = emb_values.iter().map(|&v| v as f32).collect();
-                handle_vsearch(store, emb_f32, limit, threshold as f32).await
+                handle_vsearch(storage, emb_f32, limit, threshold as f32).await
             }
 
             Commands::Type(cmd) => match cmd {
@@ -2129,14 +2888,11 @@ fn run() -> Result<()> {
                 TypeCommands::Schema { name } => handle_type_schema(storage, name).await,
             },
 
-            Commands::Network(cmd) => match cmd {
-                NetworkCommands::Connect { url } => handle_network_connect(url).await,
-                NetworkCommands::Disconnect { peer_id } => {
-                    handle_network_disconnect(peer_id).await
-                }
-                NetworkCommands::Peers { detailed } => handle_network_peers(detailed).await,
-                NetworkCommands::Sync { peer_id } => handle_network_sync(peer_id).await,
-            },
+            Commands::Network { .. } => {
+                unreachable!("network commands are handled before storage initialization")
+            }
+
+            Commands::Wal(_) => unreachable!("wal commands are handled before storage initialization"),
 
             Commands::Config(cmd) => match cmd {
                 ConfigCommands::List => handle_config_list(cli.data_dir.as_ref()).await,
@@ -2159,15 +2915,14 @@ fn run() -> Result<()> {
                     handle_restore(storage, path, force).await
                 }
                 MaintenanceCommands::Vacuum { stats } => {
-                    #[cfg(feature = "sqlite-compat")]
-                    return handle_vacuum(db, stats).await;
-
-                    #[cfg(not(feature = "sqlite-compat"))]
-                    {
-                        let _ = stats;
-                        eprintln!("Vacuum requires the 'sqlite-compat' feature. Recompile with --features sqlite-compat");
-                        std::process::exit(1);
-                    }
+                    handle_vacuum(
+                        storage,
+                        cli.data_dir.as_ref(),
+                        #[cfg(feature = "sqlite-compat")]
+                        db,
+                        stats,
+                    )
+                    .await
                 }
                 MaintenanceCommands::Migrate { version } => {
                     #[cfg(feature = "sqlite-compat")]
@@ -2181,7 +2936,13 @@ fn run() -> Result<()> {
                     }
                 }
                 MaintenanceCommands::Stats { detailed } => {
-                    handle_stats(storage, detailed).await
+                    handle_stats(
+                        storage,
+                        #[cfg(feature = "sqlite-compat")]
+                        db,
+                        detailed,
+                    )
+                    .await
                 }
             },
 
@@ -2233,6 +2994,36 @@ mod tests {
         assert_eq!(code, CoreErrorCode::NodeNotFound.as_str());
     }
 
+    #[test]
+    fn classifies_store_validation_error_code() {
+        let err = anyhow::Error::from(StoreError::Validation {
+            id: "p1".to_string(),
+            errors: Vec::new(),
+        });
+        let (code, next_steps) = classify_error_diagnostic(&err);
+        assert_eq!(code, CoreErrorCode::InvalidInput.as_str());
+        assert!(next_steps[0].contains("type schema"));
+    }
+
+    #[test]
+    fn classifies_node_too_large_error_code() {
+        let err = anyhow::Error::from(StoreError::NodeTooLarge { size: 20, max: 10 });
+        let (code, next_steps) = classify_error_diagnostic(&err);
+        assert_eq!(code, CoreErrorCode::NodeTooLarge.as_str());
+        assert!(next_steps[1].contains("with_max_node_size"));
+    }
+
+    #[test]
+    fn classifies_too_deep_error_code() {
+        let err = anyhow::Error::from(StoreError::TooDeep {
+            depth: 200,
+            max: 100,
+        });
+        let (code, next_steps) = classify_error_diagnostic(&err);
+        assert_eq!(code, CoreErrorCode::NodeTooDeep.as_str());
+        assert!(next_steps[1].contains("with_max_node_depth"));
+    }
+
     #[test]
     fn classifies_wal_corruption_error_code() {
         let err = anyhow::Error::from(WalError::TruncatedEntry {
@@ -2244,6 +3035,99 @@ mod tests {
         assert_eq!(code, StorageErrorCode::WalTruncatedEntry.as_str());
     }
 
+    /// Counts lines without keeping any output, so the export is the only
+    /// thing that could hold rows in memory.
+    #[cfg(feature = "sqlite-compat")]
+    #[derive(Default)]
+    struct LineCounter {
+        lines: u64,
+        bytes: u64,
+    }
+
+    #[cfg(feature = "sqlite-compat")]
+    impl Write for LineCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.lines += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+            self.bytes += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "sqlite-compat")]
+    #[test]
+    fn exports_large_table_as_jsonl() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        db.exec("CREATE TABLE big (id INTEGER PRIMARY KEY, label TEXT)")
+            .unwrap();
+        db.exec(
+            "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 200000) \
+             INSERT INTO big SELECT n, 'row-' || n FROM seq",
+        )
+        .unwrap();
+
+        let mut counter = LineCounter::default();
+        let rows = export_rows(&db, "SELECT * FROM big", &[], "jsonl", &mut counter).unwrap();
+
+        assert_eq!(rows, 200_000);
+        assert_eq!(counter.lines, 200_000);
+        assert!(counter.bytes > 200_000 * 20);
+
+        let mut csv = Vec::new();
+        export_rows(
+            &db,
+            "SELECT * FROM big WHERE id <= ?1",
+            &[SqlValue::Integer(2)],
+            "csv",
+            &mut csv,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,label\n1,\"row-1\"\n2,\"row-2\"\n"
+        );
+    }
+
+    #[cfg(feature = "sqlite-compat")]
+    #[test]
+    fn csv_escapes_quotes_commas_and_blobs() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        let mut csv = Vec::new();
+        export_rows(
+            &db,
+            "SELECT 'say \"hi\", bob' AS \"a,b\", NULL AS n, X'00ff' AS bytes",
+            &[],
+            "csv",
+            &mut csv,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "\"a,b\",n,bytes\n\"say \"\"hi\"\", bob\",,00ff\n"
+        );
+    }
+
+    #[test]
+    fn csv_import_unquotes_fields_and_joins_multiline_records() {
+        let input = "id,note\r\n1,\"say \"\"hi\"\", bob\"\n\n2,\"two\nlines\"\n3,\"open\n";
+        let mut reader = ImportReader::new(input.as_bytes(), "csv").unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = reader.next_record().unwrap() {
+            records.push(record);
+        }
+        assert_eq!(
+            records,
+            [
+                (2, Ok(json!({ "id": "1", "note": "say \"hi\", bob" }))),
+                (4, Ok(json!({ "id": "2", "note": "two\nlines" }))),
+                (6, Err("unterminated quoted field".to_string())),
+            ]
+        );
+    }
+
     #[test]
     fn parses_sync_mode_from_config() {
         let mut config = HashMap::new();
@@ -0,0 +1,82 @@
+//! Runs `pluresdb maintenance backup` and `restore` end to end.
+
+use std::fs;
+
+mod common;
+
+use common::{data_dir, pluresdb, run};
+
+#[test]
+fn backup_and_restore_round_trip_with_and_without_compression() {
+    let source = data_dir("backup-source");
+    run(&source, &["put", "user-1", r#"{"name":"Alice"}"#]);
+    run(
+        &source,
+        &["put", "user-2", r#"{"name":"Bob","tags":["a","b"]}"#],
+    );
+
+    let files = data_dir("backup-files");
+    fs::create_dir_all(&files).unwrap();
+    let plain = files.join("plain.ndjson");
+    let gzipped = files.join("compressed.ndjson.gz");
+    run(&source, &["maintenance", "backup", plain.to_str().unwrap()]);
+    run(
+        &source,
+        &[
+            "maintenance",
+            "backup",
+            gzipped.to_str().unwrap(),
+            "--compress",
+        ],
+    );
+
+    let text = fs::read_to_string(&plain).unwrap();
+    let header: serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+    assert_eq!(header["format"], "pluresdb-backup");
+    assert_eq!(header["records"], 2);
+    assert_eq!(text.lines().count(), 3);
+    assert_eq!(&fs::read(&gzipped).unwrap()[..2], &[0x1f, 0x8b]);
+
+    for (name, backup) in [("backup-plain", &plain), ("backup-gzip", &gzipped)] {
+        let target = data_dir(name);
+        run(
+            &target,
+            &[
+                "maintenance",
+                "restore",
+                backup.to_str().unwrap(),
+                "--force",
+            ],
+        );
+        assert_eq!(
+            run(&target, &["get", "user-1", "--format", "json"]).trim(),
+            r#"{"name":"Alice"}"#
+        );
+        assert_eq!(
+            run(&target, &["get", "user-2", "--format", "json"]).trim(),
+            r#"{"name":"Bob","tags":["a","b"]}"#
+        );
+        fs::remove_dir_all(&target).unwrap();
+    }
+
+    // A truncated backup is rejected rather than partially restored
+    let truncated = files.join("truncated.ndjson");
+    let first_two: Vec<&str> = text.lines().take(2).collect();
+    fs::write(&truncated, first_two.join("\n")).unwrap();
+    let target = data_dir("backup-truncated");
+    let output = pluresdb(
+        &target,
+        &[
+            "maintenance",
+            "restore",
+            truncated.to_str().unwrap(),
+            "--force",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(!pluresdb(&target, &["get", "user-1"]).status.success());
+
+    for dir in [&source, &files, &target] {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
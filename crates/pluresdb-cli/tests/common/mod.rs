@@ -0,0 +1,49 @@
+//! Helpers shared by the CLI integration tests.
+
+// Each test binary compiles this module and uses only some of it
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A path under the temp directory for test `name`, removed if a previous
+/// run left it behind.
+pub fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pluresdb-cli-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    dir
+}
+
+/// Run `pluresdb <args>`.
+pub fn pluresdb_without_data_dir(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pluresdb"))
+        .args(args)
+        .output()
+        .expect("failed to run pluresdb")
+}
+
+/// Run `pluresdb --data-dir <dir> <args>`.
+pub fn pluresdb(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pluresdb"))
+        .arg("--data-dir")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("failed to run pluresdb")
+}
+
+/// Run `pluresdb --data-dir <dir> <args>`, assert it succeeded, and return
+/// its stdout.
+pub fn run(dir: &Path, args: &[&str]) -> String {
+    let output = pluresdb(dir, args);
+    assert!(
+        output.status.success(),
+        "pluresdb {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
@@ -0,0 +1,108 @@
+//! Runs `pluresdb import` end to end on small NDJSON and CSV files.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+mod common;
+
+use common::{data_dir, pluresdb, run};
+
+/// Write `contents` to `name` beside the data directory and return its path.
+fn fixture(dir: &Path, name: &str, contents: &str) -> String {
+    let path = dir.with_extension(name);
+    fs::write(&path, contents).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn ids(dir: &Path) -> Vec<String> {
+    let listed: Vec<Value> =
+        serde_json::from_str(&run(dir, &["list", "--format", "json"])).unwrap();
+    let mut ids: Vec<String> = listed
+        .iter()
+        .map(|node| node["id"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn imports_ndjson_by_id_field_and_skips_malformed_lines() {
+    let dir = data_dir("import-ndjson");
+    let file = fixture(
+        &dir,
+        "ndjson",
+        concat!(
+            "{\"id\":\"p1\",\"type\":\"Person\",\"name\":\"Alice\",\"age\":30}\n",
+            "{\"id\":\"p2\",\"type\":\"Person\",\"name\":\"Bob\"}\n",
+            "\n",
+            "{not json\n",
+            "{\"name\":\"no id\"}\n",
+            "{\"id\":3,\"type\":\"Note\"}\n",
+        ),
+    );
+
+    let output = pluresdb(&dir, &["import", &file, "--id-field", "id"]);
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report,
+        json!({ "success": true, "imported": 3, "skipped": 2 })
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Skipping line 4"), "{stderr}");
+    assert!(stderr.contains("Skipping line 5"), "{stderr}");
+
+    assert_eq!(ids(&dir), ["3", "p1", "p2"]);
+    let alice: Value =
+        serde_json::from_str(&run(&dir, &["get", "p1", "--format", "json"])).unwrap();
+    assert_eq!(alice["name"], "Alice");
+    assert_eq!(alice["age"], 30);
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn imports_csv_keyed_by_header_or_generated_ids() {
+    let dir = data_dir("import-csv");
+    let file = fixture(
+        &dir,
+        "csv",
+        concat!(
+            "sku,name,note\n",
+            "a1,Widget,\"small, blue\"\n",
+            "a2,Gadget,\"says \"\"hi\"\"\"\n",
+            "a3,too,many,fields\n",
+        ),
+    );
+
+    let report: Value = serde_json::from_str(&run(
+        &dir,
+        &["import", &file, "-f", "csv", "--id-field", "sku"],
+    ))
+    .unwrap();
+    assert_eq!(
+        report,
+        json!({ "success": true, "imported": 2, "skipped": 1 })
+    );
+    assert_eq!(ids(&dir), ["a1", "a2"]);
+    let widget: Value =
+        serde_json::from_str(&run(&dir, &["get", "a1", "--format", "json"])).unwrap();
+    assert_eq!(
+        widget,
+        json!({ "sku": "a1", "name": "Widget", "note": "small, blue" })
+    );
+
+    // Without --id-field every record gets a fresh UUID
+    let other = data_dir("import-csv-uuid");
+    run(&other, &["import", &file, "--format", "csv"]);
+    let generated = ids(&other);
+    assert_eq!(generated.len(), 2);
+    assert!(generated.iter().all(|id| id.len() == 36), "{generated:?}");
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&other).unwrap();
+    fs::remove_file(&file).unwrap();
+}
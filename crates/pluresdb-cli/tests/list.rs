@@ -0,0 +1,44 @@
+//! Runs `pluresdb list` with field filters against a data directory.
+
+use std::fs;
+
+use serde_json::Value;
+
+mod common;
+
+use common::{data_dir, pluresdb, run};
+
+#[test]
+fn list_filter_matches_nested_fields() {
+    let dir = data_dir("list-filter");
+    run(
+        &dir,
+        &["put", "a", r#"{"age":36,"address":{"zip":"02139"}}"#],
+    );
+    run(
+        &dir,
+        &["put", "b", r#"{"age":41,"address":{"zip":"02139"}}"#],
+    );
+    run(
+        &dir,
+        &["put", "c", r#"{"age":85,"address":{"zip":"10001"}}"#],
+    );
+
+    let filter = r#"{"and": [
+        {"eq": {"path": "address.zip", "value": "02139"}},
+        {"gt": {"path": "age", "value": 40}}
+    ]}"#;
+    let listed: Vec<Value> = serde_json::from_str(&run(
+        &dir,
+        &["list", "--filter", filter, "--format", "json"],
+    ))
+    .unwrap();
+    let ids: Vec<&str> = listed.iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["b"]);
+
+    assert!(!pluresdb(&dir, &["list", "--filter", "zip=02139"])
+        .status
+        .success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,34 @@
+//! Runs `pluresdb maintenance migrate` against a data directory.
+
+#![cfg(feature = "sqlite-compat")]
+
+use std::fs;
+
+mod common;
+
+use common::{data_dir, pluresdb, run};
+
+#[test]
+fn migrate_applies_pending_migrations_once() {
+    let dir = data_dir("migrate");
+    let first = run(&dir, &["maintenance", "migrate"]);
+    assert!(first.contains("from version 0 to 1"), "{}", first);
+    let again = run(&dir, &["maintenance", "migrate"]);
+    assert!(again.contains("up to date at version 1"), "{}", again);
+
+    let version = run(&dir, &["query", "SELECT version FROM schema_version"]);
+    assert!(
+        version.lines().any(|line| line.trim() == "1"),
+        "{}",
+        version
+    );
+
+    // The first migration has no down SQL, so downgrading is refused
+    assert!(!pluresdb(&dir, &["maintenance", "migrate", "0"])
+        .status
+        .success());
+    let unknown = pluresdb(&dir, &["maintenance", "migrate", "99"]);
+    assert!(!unknown.status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
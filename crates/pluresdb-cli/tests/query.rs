@@ -0,0 +1,53 @@
+//! Runs `pluresdb query` end to end against a data directory.
+
+#![cfg(feature = "sqlite-compat")]
+
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+use common::{data_dir, run};
+
+/// Run `pluresdb --data-dir <dir> query <args>` and return its stdout.
+fn query(dir: &Path, args: &[&str]) -> String {
+    run(dir, &[&["query"][..], args].concat())
+}
+
+#[test]
+fn create_insert_select_renders_a_table() {
+    let dir = data_dir("query-table");
+    query(
+        &dir,
+        &["CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, photo BLOB)"],
+    );
+    query(
+        &dir,
+        &["INSERT INTO users (name, photo) VALUES ('Alice', X'0102'), (NULL, NULL)"],
+    );
+
+    let output = query(&dir, &["SELECT id, name, photo FROM users ORDER BY id"]);
+    let lines: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    assert_eq!(lines[0], ["id", "name", "photo"]);
+    assert!(lines[1][0].chars().all(|c| c == '-'));
+    assert_eq!(lines[2], ["1", "Alice", "<2", "bytes>"]);
+    assert_eq!(lines[3], ["2", "NULL", "NULL"]);
+    assert_eq!(lines.len(), 4);
+
+    let output = query(
+        &dir,
+        &[
+            "SELECT name FROM users WHERE id = ?",
+            "--params",
+            "[1]",
+            "--format",
+            "csv",
+        ],
+    );
+    assert_eq!(output, "name\n\"Alice\"\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
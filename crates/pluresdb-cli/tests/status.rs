@@ -0,0 +1,26 @@
+//! Runs `pluresdb status --detailed` and `pluresdb maintenance stats` end to
+//! end.
+
+use std::fs;
+
+mod common;
+
+use common::{data_dir, run};
+
+#[test]
+fn status_and_stats_report_the_node_count() {
+    let dir = data_dir("status");
+    run(&dir, &["put", "p1", r#"{"type":"Person","name":"Alice"}"#]);
+    run(&dir, &["put", "p2", r#"{"type":"Person","name":"Bob"}"#]);
+    run(&dir, &["put", "n1", r#"{"type":"Note"}"#]);
+
+    let status = run(&dir, &["status", "--detailed"]);
+    assert!(status.contains("Nodes: 3"), "{status}");
+
+    let stats = run(&dir, &["maintenance", "stats", "--detailed"]);
+    assert!(stats.contains("Total nodes: 3"), "{stats}");
+    assert!(stats.contains("Person: 2"), "{stats}");
+    assert!(stats.contains("Note: 1"), "{stats}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
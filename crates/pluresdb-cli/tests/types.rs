@@ -0,0 +1,45 @@
+//! Runs the `pluresdb type` commands and typed `put`s end to end.
+
+use std::fs;
+
+mod common;
+
+use common::{data_dir, pluresdb, run};
+
+#[test]
+fn typed_puts_are_checked_against_the_defined_schema() {
+    let dir = data_dir("types");
+    run(
+        &dir,
+        &[
+            "type",
+            "define",
+            "Person",
+            r#"{"required":["name"],"properties":{"name":{"type":"string"}}}"#,
+        ],
+    );
+    assert!(run(&dir, &["type", "list"]).contains("- Person"));
+    assert!(run(&dir, &["type", "schema", "Person"]).contains("\"required\""));
+
+    run(&dir, &["put", "p1", r#"{"type":"Person","name":"Alice"}"#]);
+    run(
+        &dir,
+        &["put", "p2", r#"{"name":"Bob"}"#, "--node-type", "Person"],
+    );
+    run(&dir, &["put", "n1", r#"{"type":"Note"}"#]);
+
+    let rejected = pluresdb(&dir, &["put", "p3", r#"{"type":"Person","name":7}"#]);
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("$.name"));
+    let rejected = pluresdb(&dir, &["put", "p4", r#"{"type":"Person"}"#]);
+    assert!(!rejected.status.success());
+
+    let instances = run(&dir, &["type", "instances", "Person"]);
+    let ids: Vec<&str> = instances
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("- "))
+        .collect();
+    assert_eq!(ids, ["p1", "p2"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,34 @@
+//! Runs `pluresdb put --embedding` and `vsearch` end to end.
+
+use std::fs;
+
+mod common;
+
+use common::{data_dir, pluresdb, run};
+
+#[test]
+fn vsearch_finds_the_nearest_stored_embeddings() {
+    let dir = data_dir("vsearch");
+    for (id, embedding) in [
+        ("east", "[1,0,0]"),
+        ("north", "[0,1,0]"),
+        ("north-east", "[0.7,0.7,0]"),
+    ] {
+        run(&dir, &["put", id, "{}", "--embedding", embedding]);
+    }
+    run(&dir, &["put", "plain", r#"{"name":"no embedding"}"#]);
+
+    let output = run(&dir, &["vsearch", "[0.9,0.1,0]", "--threshold", "0.5"]);
+    let ids: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.strip_prefix("  ")?.split(' ').next())
+        .filter(|word| !word.is_empty())
+        .collect();
+    assert_eq!(ids, ["east", "north-east"]);
+
+    let rejected = pluresdb(&dir, &["vsearch", "[1,0]"]);
+    assert!(!rejected.status.success());
+    assert!(String::from_utf8_lossy(&rejected.stderr).contains("CORE_DIMENSION_MISMATCH"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
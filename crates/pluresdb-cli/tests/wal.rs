@@ -0,0 +1,100 @@
+//! Runs `pluresdb wal verify` and `pluresdb wal repair` against WALs
+//! damaged on disk.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use pluresdb_storage::{WalOperation, WriteAheadLog};
+
+mod common;
+
+use common::{data_dir, pluresdb_without_data_dir as pluresdb};
+
+/// Write `count` entries to a new WAL in `dir` and return its segment.
+async fn write_entries(dir: &Path, count: u32) -> PathBuf {
+    let wal = WriteAheadLog::open(dir).unwrap();
+    for i in 0..count {
+        wal.append(
+            "actor-1".to_string(),
+            WalOperation::Put {
+                id: format!("node-{}", i),
+                data: serde_json::json!({ "index": i }),
+            },
+        )
+        .await
+        .unwrap();
+    }
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().and_then(|ext| ext.to_str()) == Some("wal"))
+        .expect("expected a .wal segment")
+}
+
+#[tokio::test]
+async fn verify_reports_a_corrupted_entry_and_exits_non_zero() {
+    let dir = data_dir("wal-verify");
+    let segment = write_entries(&dir, 4).await;
+
+    let healthy = pluresdb(&["wal", "verify", "--dir", dir.to_str().unwrap()]);
+    assert!(healthy.status.success());
+    let out = String::from_utf8(healthy.stdout).unwrap();
+    assert!(out.contains("Status: healthy"), "{}", out);
+    assert!(out.contains("Valid entries: 4"), "{}", out);
+
+    // Flip bytes in the middle of the segment to break an entry
+    let mut bytes = fs::read(&segment).unwrap();
+    let mid = bytes.len() / 2;
+    bytes[mid] ^= 0xFF;
+    bytes[mid + 1] ^= 0xFF;
+    fs::write(&segment, &bytes).unwrap();
+
+    let damaged = pluresdb(&["wal", "verify", "--dir", dir.to_str().unwrap()]);
+    assert_eq!(damaged.status.code(), Some(2));
+    let out = String::from_utf8(damaged.stdout).unwrap();
+    assert!(out.contains("Status: corrupted"), "{}", out);
+    assert!(
+        !(out.contains("Corrupted entries: 0") && out.contains("Corrupted segments: 0")),
+        "{}",
+        out
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn repair_cuts_a_torn_tail_and_reports_bytes_reclaimed() {
+    let dir = data_dir("wal-repair");
+    let segment = write_entries(&dir, 2).await;
+    // Half a length prefix, as a crash mid-append leaves it
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&segment)
+        .unwrap()
+        .write_all(&[0xAB, 0xCD])
+        .unwrap();
+    let dir_arg = dir.to_str().unwrap();
+    assert_eq!(
+        pluresdb(&["wal", "verify", "--dir", dir_arg]).status.code(),
+        Some(2)
+    );
+
+    let repaired = pluresdb(&["wal", "repair", "--dir", dir_arg]);
+    assert!(repaired.status.success());
+    let out = String::from_utf8(repaired.stdout).unwrap();
+    assert!(out.contains("Reclaimed 2 bytes"), "{}", out);
+    assert!(out.contains("Status: healthy"), "{}", out);
+    assert!(out.contains("Valid entries: 2"), "{}", out);
+
+    assert!(pluresdb(&["wal", "verify", "--dir", dir_arg])
+        .status
+        .success());
+    assert!(
+        !pluresdb(&["wal", "verify", "--dir", "/nonexistent/pluresdb-wal"])
+            .status
+            .success()
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
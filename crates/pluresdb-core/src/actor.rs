@@ -0,0 +1,109 @@
+//! Unique, stable actor ids for writers.
+//!
+//! Vector clocks count writes per [`ActorId`], so two processes writing
+//! under one id make concurrent writes look causally ordered and one of
+//! them is silently dropped on merge.  Each instance should therefore write
+//! under its own id: [`ActorIdExt::new_random`] for one that lives as long
+//! as the instance, or [`ActorIdExt::load_or_create`] to keep one in the
+//! instance's data directory across restarts.
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use uuid::Uuid;
+
+use crate::ActorId;
+
+/// Name of the file holding an instance's actor id in its data directory.
+pub const ACTOR_ID_FILE: &str = "actor_id";
+
+/// Constructors for [`ActorId`], which is a plain `String`.
+pub trait ActorIdExt: Sized {
+    /// A fresh id, unique to the caller.
+    fn new_random() -> Self;
+
+    /// The id stored at `path`, or a fresh one written there if the file
+    /// does not exist yet.  Processes racing to create the file all get the
+    /// id of the one that wins.
+    fn load_or_create(path: impl AsRef<Path>) -> Result<Self>;
+}
+
+impl ActorIdExt for ActorId {
+    fn new_random() -> Self {
+        Uuid::new_v4().to_string()
+    }
+
+    fn load_or_create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match read_actor_id(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            result => return checked(path, result),
+        }
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create directory: {}", dir.display()))?;
+        }
+        // Write the id in full under a unique name, then link it into place,
+        // which fails rather than overwrites if another process got there
+        // first, so no reader ever sees a partial file
+        let actor = Self::new_random();
+        let tmp = path.with_extension(format!("{}.tmp", actor));
+        let mut file = fs::File::create(&tmp)
+            .with_context(|| format!("failed to write actor id: {}", tmp.display()))?;
+        file.write_all(actor.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        let linked = fs::hard_link(&tmp, path);
+        let _ = fs::remove_file(&tmp);
+        match linked {
+            Ok(()) => Ok(actor),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => checked(path, read_actor_id(path)),
+            Err(e) => {
+                Err(e).with_context(|| format!("failed to write actor id: {}", path.display()))
+            }
+        }
+    }
+}
+
+fn read_actor_id(path: &Path) -> std::io::Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+fn checked(path: &Path, result: std::io::Result<String>) -> Result<ActorId> {
+    let actor = result.with_context(|| format!("failed to read actor id: {}", path.display()))?;
+    if actor.is_empty() {
+        bail!("actor id file is empty: {}", path.display());
+    }
+    Ok(actor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn random_actor_ids_are_unique() {
+        let ids: HashSet<ActorId> = (0..1000).map(|_| ActorId::new_random()).collect();
+        assert_eq!(ids.len(), 1000);
+    }
+
+    #[test]
+    fn load_or_create_is_stable_across_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(ACTOR_ID_FILE);
+        let first = ActorId::load_or_create(&path).unwrap();
+        assert_eq!(ActorId::load_or_create(&path).unwrap(), first);
+        assert_eq!(fs::read_to_string(&path).unwrap(), first);
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+
+        let other = dir.path().join("other");
+        assert_ne!(ActorId::load_or_create(&other).unwrap(), first);
+
+        fs::write(&other, "  \n").unwrap();
+        assert!(ActorId::load_or_create(&other).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! An append-only trail of local writes to a [`CrdtStore`](crate::CrdtStore).
+//!
+//! A store built [`with_audit`](crate::CrdtStore::with_audit) hands its
+//! [`AuditSink`] one [`AuditEvent`] per put and delete, synchronously,
+//! before the write returns, so an acknowledged write is never missing
+//! from the trail.  Records merged from peers are not audited; they are in
+//! the audit trail of the replica that wrote them.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{ActorId, NodeId, VectorClock};
+
+/// The kind of write an [`AuditEvent`] records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOp {
+    Put,
+    Delete,
+}
+
+/// One write to a node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: NodeId,
+    /// Who wrote.  Deletes are only attributed when made with
+    /// [`CrdtStore::delete_by`](crate::CrdtStore::delete_by); expiry sweeps never are.
+    pub actor: Option<ActorId>,
+    pub op: AuditOp,
+    /// The node's clock before the write, or `None` if it did not exist.
+    pub before_clock: Option<VectorClock>,
+    pub after_clock: VectorClock,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Destination for a store's [`AuditEvent`]s.
+pub trait AuditSink: Send + Sync + fmt::Debug {
+    /// Record `event`.  Called within the write, so this should be quick,
+    /// and must not write to the store itself.
+    fn record(&self, event: AuditEvent);
+}
+
+/// Keeps events in memory, for tests and short-lived stores.
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    events: Mutex<Vec<AuditEvent>>,
+}
+
+impl MemoryAuditSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every event recorded so far, oldest first.
+    pub fn events(&self) -> Vec<AuditEvent> {
+        self.events.lock().clone()
+    }
+
+    /// The events recorded for node `id`, oldest first.
+    pub fn events_for(&self, id: &str) -> Vec<AuditEvent> {
+        self.events
+            .lock()
+            .iter()
+            .filter(|event| event.id == id)
+            .cloned()
+            .collect()
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, event: AuditEvent) {
+        self.events.lock().push(event);
+    }
+}
+
+/// Appends events to a file as newline-delimited JSON.
+#[derive(Debug)]
+pub struct FileAuditSink {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Append to the log at `path`, creating it if missing.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open audit log: {}", path.display()))?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read back every event in the log at `path`.
+    pub fn read(path: impl AsRef<Path>) -> Result<Vec<AuditEvent>> {
+        let file = File::open(path.as_ref())?;
+        let mut events = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line)
+                .with_context(|| format!("malformed audit event on line {}", index + 1))?;
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, event: AuditEvent) {
+        let mut line = match serde_json::to_vec(&event) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!(id = %event.id, "failed to encode audit event: {}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        // One write per event, so concurrent appenders never interleave lines
+        if let Err(e) = self.file.lock().write_all(&line) {
+            tracing::error!(
+                id = %event.id,
+                path = %self.path.display(),
+                "failed to append audit event: {}",
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CrdtStore;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn puts_and_deletes_each_record_one_event() {
+        let sink = Arc::new(MemoryAuditSink::new());
+        let store = CrdtStore::default().with_audit(sink.clone());
+
+        store.put("n1", "alice", json!({ "v": 1 }));
+        store.put("n1", "bob", json!({ "v": 2 }));
+        store.delete_by("n1", "carol").unwrap();
+        store.put("n2", "alice", json!({}));
+        store.delete("n2").unwrap();
+
+        let events = sink.events_for("n1");
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].op, AuditOp::Put);
+        assert_eq!(events[0].actor.as_deref(), Some("alice"));
+        assert_eq!(events[0].before_clock, None);
+        assert_eq!(
+            events[0].after_clock,
+            VectorClock::from([("alice".into(), 1)])
+        );
+        assert_eq!(events[1].actor.as_deref(), Some("bob"));
+        assert_eq!(events[1].before_clock, Some(events[0].after_clock.clone()));
+        assert_eq!(
+            events[1].after_clock,
+            VectorClock::from([("alice".into(), 1), ("bob".into(), 1)])
+        );
+        assert_eq!(events[2].op, AuditOp::Delete);
+        assert_eq!(events[2].actor.as_deref(), Some("carol"));
+        assert_eq!(events[2].before_clock, Some(events[1].after_clock.clone()));
+        assert!(events[1].timestamp <= events[2].timestamp);
+
+        let n2 = sink.events_for("n2");
+        assert_eq!(n2.len(), 2);
+        assert_eq!((n2[1].op, n2[1].actor.as_ref()), (AuditOp::Delete, None));
+
+        // A failed delete writes nothing, so it is not audited
+        assert!(store.delete("n2").is_err());
+        assert_eq!(sink.events().len(), 5);
+    }
+
+    #[test]
+    fn file_sink_appends_across_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.ndjson");
+        for actor in ["alice", "bob"] {
+            let sink = Arc::new(FileAuditSink::open(&path).unwrap());
+            let store = CrdtStore::default().with_audit(sink);
+            store.put("n1", actor, json!({ "by": actor }));
+        }
+
+        let events = FileAuditSink::read(&path).unwrap();
+        let actors: Vec<_> = events.iter().map(|e| e.actor.as_deref()).collect();
+        assert_eq!(actors, [Some("alice"), Some("bob")]);
+        assert!(events.iter().all(|e| e.op == AuditOp::Put));
+    }
+}
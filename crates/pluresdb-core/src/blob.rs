@@ -0,0 +1,193 @@
+//! Incremental I/O on single blobs, for values too large to bind or read
+//! whole as [`SqlValue::Blob`](crate::SqlValue::Blob).
+//!
+//! A [`BlobHandle`] names one cell by table, column and rowid, and reads or
+//! overwrites byte ranges of it in place.  It cannot change the blob's size,
+//! so reserve the full size up front with `zeroblob(n)` and stream the bytes
+//! in afterwards; the rowid comes from the insert's
+//! [`ExecutionResult::last_insert_rowid`](crate::ExecutionResult), and any
+//! other metadata can live in neighbouring columns written through
+//! [`Statement`](crate::Statement) as usual.
+//!
+//! # Transactions and lifetime
+//!
+//! A handle does not hold the connection.  Each call locks the writer,
+//! opens the blob, does its I/O and closes it again, so:
+//!
+//! - other calls on the [`Database`] may run between two calls on a handle,
+//!   and each [`write_at`](BlobHandle::write_at) commits on its own.  To
+//!   write a blob atomically, open it with rusqlite's `blob_open` on the
+//!   connection [`Database::transaction`] hands out instead;
+//! - a handle is not tied to the row's current value.  If the row is
+//!   updated, later calls see the new value; if it is deleted, they fail;
+//! - a handle may be kept, cloned and sent between threads freely, and a
+//!   write on it is retried while the database is busy like any other call.
+
+use rusqlite::blob::Blob;
+use rusqlite::MAIN_DB;
+
+use crate::{Database, DatabaseError, DbResult};
+
+/// One blob in a [`Database`], opened with [`Database::open_blob`].
+#[derive(Debug, Clone)]
+pub struct BlobHandle {
+    database: Database,
+    table: String,
+    column: String,
+    rowid: i64,
+    read_only: bool,
+}
+
+impl Database {
+    /// Open the value in `column` of the row of `table` with `rowid` for
+    /// incremental I/O; see the [`blob` module docs](crate::blob) for how a
+    /// handle relates to transactions.  Fails if there is no such row or the
+    /// value is neither a blob nor text.
+    pub fn open_blob(
+        &self,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        read_only: bool,
+    ) -> DbResult<BlobHandle> {
+        let handle = BlobHandle {
+            database: self.clone(),
+            table: table.to_owned(),
+            column: column.to_owned(),
+            rowid,
+            read_only,
+        };
+        handle.with_blob(|_| Ok(()))?;
+        Ok(handle)
+    }
+}
+
+impl BlobHandle {
+    pub fn rowid(&self) -> i64 {
+        self.rowid
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Size of the blob in bytes.
+    pub fn len(&self) -> DbResult<usize> {
+        self.with_blob(|blob| Ok(blob.len()))
+    }
+
+    pub fn is_empty(&self) -> DbResult<bool> {
+        self.with_blob(|blob| Ok(blob.is_empty()))
+    }
+
+    /// Read into `buf` from `offset`, returning how many bytes were read:
+    /// fewer than `buf.len()` only at the end of the blob.
+    pub fn read_at(&self, buf: &mut [u8], offset: usize) -> DbResult<usize> {
+        self.with_blob(|blob| Ok(blob.read_at(buf, offset)?))
+    }
+
+    /// Overwrite the bytes from `offset` with `data`.  Fails, writing
+    /// nothing, if `data` would run past the end of the blob or the handle
+    /// is read-only.
+    pub fn write_at(&self, data: &[u8], offset: usize) -> DbResult<()> {
+        if self.read_only {
+            return Err(DatabaseError::InvalidParameter(format!(
+                "blob {}.{} of row {} was opened read-only",
+                self.table, self.column, self.rowid
+            )));
+        }
+        self.database.invalidate_query_cache();
+        self.with_blob(|blob| Ok(blob.write_at(data, offset)?))
+    }
+
+    fn with_blob<T>(&self, mut f: impl FnMut(&mut Blob<'_>) -> DbResult<T>) -> DbResult<T> {
+        self.database.with_connection(|conn| {
+            let mut blob = conn.blob_open(
+                MAIN_DB,
+                self.table.as_str(),
+                self.column.as_str(),
+                self.rowid,
+                self.read_only,
+            )?;
+            f(&mut blob)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatabaseOptions, SqlValue};
+
+    const SIZE: usize = 4 * 1024 * 1024;
+
+    #[test]
+    fn large_blob_round_trips_in_chunks() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        db.exec("CREATE TABLE files (id INTEGER PRIMARY KEY, name TEXT, data BLOB)")
+            .unwrap();
+        let rowid = db
+            .prepare("INSERT INTO files (name, data) VALUES (?1, zeroblob(?2))")
+            .unwrap()
+            .run(&[
+                SqlValue::Text("big.bin".into()),
+                SqlValue::Integer(SIZE as i64),
+            ])
+            .unwrap()
+            .last_insert_rowid;
+        let expected: Vec<u8> = (0..SIZE).map(|i| (i % 251) as u8).collect();
+
+        let writer = db.open_blob("files", "data", rowid, false).unwrap();
+        assert_eq!(writer.len().unwrap(), SIZE);
+        for (i, chunk) in expected.chunks(64 * 1024).enumerate() {
+            writer.write_at(chunk, i * 64 * 1024).unwrap();
+        }
+
+        // An odd chunk size, so the last read comes up short
+        let reader = db.open_blob("files", "data", rowid, true).unwrap();
+        let mut buf = vec![0; 100_000];
+        let mut offset = 0;
+        loop {
+            let n = reader.read_at(&mut buf, offset).unwrap();
+            assert!(
+                buf[..n] == expected[offset..offset + n],
+                "bytes differ at {offset}"
+            );
+            offset += n;
+            if n < buf.len() {
+                break;
+            }
+        }
+        assert_eq!(offset, SIZE);
+
+        let length = db
+            .query("SELECT length(data) FROM files WHERE name = 'big.bin'", &[])
+            .unwrap();
+        assert_eq!(length.rows, vec![vec![SqlValue::Integer(SIZE as i64)]]);
+    }
+
+    #[test]
+    fn blob_handles_cannot_resize_or_write_read_only() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        db.exec("CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB)")
+            .unwrap();
+        db.exec("INSERT INTO files (id, data) VALUES (1, zeroblob(4))")
+            .unwrap();
+
+        let writer = db.open_blob("files", "data", 1, false).unwrap();
+        assert!(writer.write_at(b"abcde", 0).is_err());
+        writer.write_at(b"cd", 2).unwrap();
+        let reader = db.open_blob("files", "data", 1, true).unwrap();
+        assert!(matches!(
+            reader.write_at(b"ab", 0),
+            Err(DatabaseError::InvalidParameter(_))
+        ));
+        let mut buf = [0xff; 4];
+        assert_eq!(reader.read_at(&mut buf, 0).unwrap(), 4);
+        assert_eq!(&buf, b"\0\0cd");
+
+        assert!(db.open_blob("files", "data", 2, true).is_err());
+        db.exec("DELETE FROM files").unwrap();
+        assert!(reader.len().is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Comparison and merging of [`VectorClock`]s.
+//!
+//! An actor missing from a clock counts as zero, so `{a: 0}` and `{}` describe
+//! the same history.
+
+use crate::VectorClock;
+
+/// Causal order of two vector clocks, from the first clock's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockOrdering {
+    /// Both clocks have seen exactly the same writes.
+    Equal,
+    /// The first clock has seen every write the second has, and more.
+    Dominates,
+    /// The second clock has seen every write the first has, and more.
+    DominatedBy,
+    /// Each clock has seen writes the other has not.
+    Concurrent,
+}
+
+/// Compare `a` against `b`.
+pub fn compare(a: &VectorClock, b: &VectorClock) -> ClockOrdering {
+    let mut a_ahead = false;
+    let mut b_ahead = false;
+    for actor in a.keys().chain(b.keys()) {
+        let x = a.get(actor).copied().unwrap_or(0);
+        let y = b.get(actor).copied().unwrap_or(0);
+        a_ahead |= x > y;
+        b_ahead |= y > x;
+    }
+    match (a_ahead, b_ahead) {
+        (false, false) => ClockOrdering::Equal,
+        (true, false) => ClockOrdering::Dominates,
+        (false, true) => ClockOrdering::DominatedBy,
+        (true, true) => ClockOrdering::Concurrent,
+    }
+}
+
+/// The element-wise maximum of `a` and `b`: a clock that has seen every
+/// write either of them has.
+pub fn merge(a: &VectorClock, b: &VectorClock) -> VectorClock {
+    let mut merged = a.clone();
+    for (actor, &counter) in b {
+        let entry = merged.entry(actor.clone()).or_insert(0);
+        *entry = (*entry).max(counter);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(entries: &[(&str, u64)]) -> VectorClock {
+        entries
+            .iter()
+            .map(|(actor, counter)| (actor.to_string(), *counter))
+            .collect()
+    }
+
+    #[test]
+    fn compare_equal_clocks() {
+        assert_eq!(compare(&clock(&[]), &clock(&[])), ClockOrdering::Equal);
+        assert_eq!(
+            compare(&clock(&[("a", 2), ("b", 1)]), &clock(&[("b", 1), ("a", 2)])),
+            ClockOrdering::Equal
+        );
+    }
+
+    #[test]
+    fn compare_dominating_clocks() {
+        let older = clock(&[("a", 1)]);
+        let newer = clock(&[("a", 2), ("b", 1)]);
+        assert_eq!(compare(&newer, &older), ClockOrdering::Dominates);
+        assert_eq!(compare(&older, &newer), ClockOrdering::DominatedBy);
+    }
+
+    #[test]
+    fn compare_concurrent_clocks() {
+        assert_eq!(
+            compare(&clock(&[("a", 2), ("b", 1)]), &clock(&[("a", 1), ("b", 2)])),
+            ClockOrdering::Concurrent
+        );
+        // Disjoint actor sets: each side has writes the other never saw.
+        assert_eq!(
+            compare(&clock(&[("a", 1)]), &clock(&[("b", 1)])),
+            ClockOrdering::Concurrent
+        );
+    }
+
+    #[test]
+    fn empty_clock_is_dominated_by_any_non_empty_clock() {
+        let empty = clock(&[]);
+        let written = clock(&[("a", 1)]);
+        assert_eq!(compare(&empty, &written), ClockOrdering::DominatedBy);
+        assert_eq!(compare(&written, &empty), ClockOrdering::Dominates);
+    }
+
+    #[test]
+    fn zero_entries_count_as_absent() {
+        assert_eq!(
+            compare(&clock(&[("a", 0)]), &clock(&[])),
+            ClockOrdering::Equal
+        );
+        assert_eq!(
+            compare(&clock(&[("a", 1), ("b", 0)]), &clock(&[("a", 1), ("c", 0)])),
+            ClockOrdering::Equal
+        );
+    }
+
+    #[test]
+    fn merge_takes_element_wise_max() {
+        let a = clock(&[("a", 3), ("b", 1)]);
+        let b = clock(&[("b", 2), ("c", 1)]);
+        let merged = merge(&a, &b);
+        assert_eq!(merged, clock(&[("a", 3), ("b", 2), ("c", 1)]));
+        assert_eq!(merged, merge(&b, &a));
+        assert_eq!(compare(&merged, &a), ClockOrdering::Dominates);
+        assert_eq!(compare(&merged, &b), ClockOrdering::Dominates);
+        assert_eq!(merge(&clock(&[]), &clock(&[])), clock(&[]));
+    }
+}
@@ -0,0 +1,210 @@
+//! How concurrent writes to one node are reconciled.
+//!
+//! When [`CrdtStore::merge_record`] meets two writes neither of which has
+//! seen the other, it first picks a winner last-writer-wins, exactly as
+//! before, then asks the node's [`ConflictStrategy`] for the merged data.
+//! Strategies receive the winner and the loser in that order on every
+//! replica, so a deterministic strategy converges everywhere.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::Value as JsonValue;
+
+use crate::{CrdtStore, CrdtValue, NodeData, NodeRecord};
+
+/// Data for a conflict from `(winner, loser)`; see [`ConflictStrategy::Custom`].
+pub type ConflictResolver = dyn Fn(&NodeRecord, &NodeRecord) -> NodeData + Send + Sync;
+
+/// What a concurrent write does to the data of the write it conflicts with.
+#[derive(Default)]
+pub enum ConflictStrategy {
+    /// Keep the winner's data and drop the loser's.
+    #[default]
+    LastWriteWins,
+    /// Deep-merge the two payloads when both are JSON objects: keys from
+    /// either side are kept, nested objects are merged the same way, and
+    /// the winner's value is kept where both set a key to something else.
+    /// Other payloads fall back to last-writer-wins.
+    MergeJsonObjects,
+    /// Compute the data with a function of `(winner, loser)`.  It must be
+    /// deterministic for replicas to converge.
+    Custom(Box<ConflictResolver>),
+}
+
+impl fmt::Debug for ConflictStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LastWriteWins => f.write_str("LastWriteWins"),
+            Self::MergeJsonObjects => f.write_str("MergeJsonObjects"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl ConflictStrategy {
+    /// The data to store after `winner` beat the concurrent `loser`, or
+    /// `None` to keep the winner's.  Deletions always win or lose whole,
+    /// and [`CrdtValue`] payloads of one kind are always merged.
+    pub(crate) fn resolve(&self, winner: &NodeRecord, loser: &NodeRecord) -> Option<NodeData> {
+        if winner.is_tombstone() || loser.is_tombstone() {
+            return None;
+        }
+        if let Some(merged) = CrdtValue::merge_data(&winner.data, &loser.data) {
+            return Some(merged);
+        }
+        match self {
+            Self::LastWriteWins => None,
+            Self::MergeJsonObjects => match (&winner.data, &loser.data) {
+                (JsonValue::Object(_), JsonValue::Object(_)) => {
+                    Some(deep_merge(&winner.data, &loser.data))
+                }
+                _ => None,
+            },
+            Self::Custom(resolve) => Some(resolve(winner, loser)),
+        }
+    }
+}
+
+/// `preferred` with every key of `other` it lacks, recursing where both
+/// hold an object.
+fn deep_merge(preferred: &JsonValue, other: &JsonValue) -> JsonValue {
+    match (preferred, other) {
+        (JsonValue::Object(preferred), JsonValue::Object(other)) => {
+            let mut merged = preferred.clone();
+            for (key, value) in other {
+                let entry = merged.entry(key.clone()).or_insert_with(|| value.clone());
+                *entry = deep_merge(entry, value);
+            }
+            JsonValue::Object(merged)
+        }
+        (preferred, _) => preferred.clone(),
+    }
+}
+
+/// Strategies of a store, by node type.
+#[derive(Debug, Default)]
+pub(crate) struct ConflictStrategies {
+    pub(crate) default: ConflictStrategy,
+    pub(crate) by_type: HashMap<String, ConflictStrategy>,
+}
+
+impl ConflictStrategies {
+    /// The strategy for a conflict between `a` and `b`: their type's, if
+    /// both share a `type` with a strategy, and the default otherwise.
+    /// Either order gives the same answer.
+    pub(crate) fn for_records(&self, a: &NodeRecord, b: &NodeRecord) -> &ConflictStrategy {
+        let type_of = |record: &NodeRecord| record.data.get("type").and_then(JsonValue::as_str);
+        match (type_of(a), type_of(b)) {
+            (Some(a), Some(b)) if a == b => self.by_type.get(a).unwrap_or(&self.default),
+            _ => &self.default,
+        }
+    }
+}
+
+impl CrdtStore {
+    /// Resolve concurrent writes with `strategy` instead of
+    /// last-writer-wins, except for types given their own strategy.
+    pub fn with_conflict_strategy(mut self, strategy: ConflictStrategy) -> Self {
+        self.conflict_strategies.default = strategy;
+        self
+    }
+
+    /// Resolve concurrent writes to nodes whose `type` is `type_name` with
+    /// `strategy`.  Both writes must have that type for it to apply.
+    pub fn with_type_conflict_strategy(
+        mut self,
+        type_name: impl Into<String>,
+        strategy: ConflictStrategy,
+    ) -> Self {
+        self.conflict_strategies
+            .by_type
+            .insert(type_name.into(), strategy);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MergeOutcome;
+    use serde_json::json;
+
+    /// Write `a` on one replica and `b` concurrently on another, exchange
+    /// the records both ways, and return what each replica then holds.
+    fn converge(
+        make_store: impl Fn() -> CrdtStore,
+        a: JsonValue,
+        b: JsonValue,
+    ) -> (JsonValue, JsonValue) {
+        let (left, right) = (make_store(), make_store());
+        left.put("doc", "alice", a);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        right.put("doc", "bob", b);
+        let from_left = left.get("doc").unwrap();
+        let from_right = right.get("doc").unwrap();
+        assert_eq!(left.merge_record(from_right), MergeOutcome::Conflict);
+        assert_eq!(right.merge_record(from_left), MergeOutcome::Conflict);
+        let (left, right) = (left.get("doc").unwrap(), right.get("doc").unwrap());
+        assert_eq!(left.clock, right.clock);
+        (left.data, right.data)
+    }
+
+    #[test]
+    fn last_write_wins_keeps_the_later_payload() {
+        let (left, right) = converge(
+            CrdtStore::default,
+            json!({ "title": "A", "tags": ["x"] }),
+            json!({ "title": "B" }),
+        );
+        assert_eq!(left, json!({ "title": "B" }));
+        assert_eq!(right, left);
+    }
+
+    #[test]
+    fn merge_json_objects_keeps_disjoint_keys_at_every_depth() {
+        let (left, right) = converge(
+            || CrdtStore::default().with_conflict_strategy(ConflictStrategy::MergeJsonObjects),
+            json!({ "title": "A", "meta": { "author": "alice", "rev": 1 } }),
+            json!({ "body": "text", "meta": { "rev": 2, "lang": "en" } }),
+        );
+        assert_eq!(
+            left,
+            json!({
+                "title": "A",
+                "body": "text",
+                "meta": { "author": "alice", "rev": 2, "lang": "en" },
+            })
+        );
+        assert_eq!(right, left);
+    }
+
+    #[test]
+    fn custom_and_per_type_strategies_apply_to_their_types() {
+        let make_store = || {
+            CrdtStore::default().with_type_conflict_strategy(
+                "counter",
+                ConflictStrategy::Custom(Box::new(|winner: &NodeRecord, loser: &NodeRecord| {
+                    let count = |r: &NodeRecord| r.data["count"].as_i64().unwrap_or(0);
+                    json!({ "type": "counter", "count": count(winner) + count(loser) })
+                })),
+            )
+        };
+
+        let (left, right) = converge(
+            make_store,
+            json!({ "type": "counter", "count": 2 }),
+            json!({ "type": "counter", "count": 3 }),
+        );
+        assert_eq!(left, json!({ "type": "counter", "count": 5 }));
+        assert_eq!(right, left);
+
+        // Nodes of other types keep last-writer-wins
+        let (left, _) = converge(
+            make_store,
+            json!({ "type": "note", "count": 2 }),
+            json!({ "type": "note", "count": 3 }),
+        );
+        assert_eq!(left, json!({ "type": "note", "count": 3 }));
+    }
+}
@@ -0,0 +1,321 @@
+//! Counter and set CRDTs that merge field by field instead of overwriting.
+//!
+//! A node whose data is a [`CrdtValue`], marked by its `crdt_type` field,
+//! is merged structurally by [`CrdtStore::merge_record`](crate::CrdtStore::merge_record)
+//! when two writes are concurrent, whatever the store's
+//! [`ConflictStrategy`](crate::ConflictStrategy), so no increment or add is
+//! lost.  Local updates read the value with [`CrdtValue::from_data`],
+//! change it as the writing actor, and put back [`CrdtValue::to_data`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::{ActorId, NodeData};
+
+/// Field of a node's data naming its [`CrdtValue`] type.
+pub const CRDT_TYPE_FIELD: &str = "crdt_type";
+
+/// A counter that only goes up: one tally per actor, merged by maximum.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GCounter {
+    counts: BTreeMap<ActorId, u64>,
+}
+
+impl GCounter {
+    /// Add `by` to `actor`'s tally.
+    pub fn increment(&mut self, actor: impl Into<ActorId>, by: u64) {
+        let count = self.counts.entry(actor.into()).or_default();
+        *count = count.saturating_add(by);
+    }
+
+    pub fn value(&self) -> u64 {
+        self.counts
+            .values()
+            .fold(0, |sum, n| sum.saturating_add(*n))
+    }
+
+    pub fn merge(&mut self, other: &GCounter) {
+        for (actor, &count) in &other.counts {
+            let mine = self.counts.entry(actor.clone()).or_default();
+            *mine = (*mine).max(count);
+        }
+    }
+}
+
+/// A counter that goes up and down: a [`GCounter`] of increments and one
+/// of decrements.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PNCounter {
+    increments: GCounter,
+    decrements: GCounter,
+}
+
+impl PNCounter {
+    pub fn increment(&mut self, actor: impl Into<ActorId>, by: u64) {
+        self.increments.increment(actor, by);
+    }
+
+    pub fn decrement(&mut self, actor: impl Into<ActorId>, by: u64) {
+        self.decrements.increment(actor, by);
+    }
+
+    pub fn value(&self) -> i64 {
+        self.increments.value() as i64 - self.decrements.value() as i64
+    }
+
+    pub fn merge(&mut self, other: &PNCounter) {
+        self.increments.merge(&other.increments);
+        self.decrements.merge(&other.decrements);
+    }
+}
+
+/// Identifies one add to an [`OrSet`]: the adding actor and its count of
+/// adds so far.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Dot {
+    pub actor: ActorId,
+    pub counter: u64,
+}
+
+/// An observed-remove set: a remove only cancels the adds it has seen, so
+/// an add concurrent with a remove of the same element wins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Ord + Serialize",
+    deserialize = "T: Ord + Deserialize<'de>"
+))]
+pub struct OrSet<T> {
+    /// Adds not yet removed, each with the dot that made it.
+    adds: BTreeSet<(T, Dot)>,
+    /// Dots of removed adds, kept so merging cannot bring them back.
+    removed: BTreeSet<Dot>,
+    /// Adds made by each actor, to mint new dots.
+    counters: BTreeMap<ActorId, u64>,
+}
+
+impl<T: Ord> Default for OrSet<T> {
+    fn default() -> Self {
+        Self {
+            adds: BTreeSet::new(),
+            removed: BTreeSet::new(),
+            counters: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> OrSet<T> {
+    pub fn add(&mut self, actor: impl Into<ActorId>, element: T) {
+        let actor = actor.into();
+        let counter = self.counters.entry(actor.clone()).or_default();
+        *counter += 1;
+        let dot = Dot {
+            actor,
+            counter: *counter,
+        };
+        self.adds.insert((element, dot));
+    }
+
+    /// Remove `element` as far as this replica has seen it added.
+    pub fn remove(&mut self, element: &T) {
+        let observed: Vec<(T, Dot)> = self
+            .adds
+            .iter()
+            .filter(|(e, _)| e == element)
+            .cloned()
+            .collect();
+        for add in observed {
+            self.adds.remove(&add);
+            self.removed.insert(add.1);
+        }
+    }
+
+    pub fn contains(&self, element: &T) -> bool {
+        self.adds.iter().any(|(e, _)| e == element)
+    }
+
+    /// The elements, in order, each once.
+    pub fn elements(&self) -> Vec<T> {
+        let mut elements: Vec<T> = self.adds.iter().map(|(e, _)| e.clone()).collect();
+        elements.dedup();
+        elements
+    }
+
+    pub fn merge(&mut self, other: &OrSet<T>) {
+        for (actor, &counter) in &other.counters {
+            let mine = self.counters.entry(actor.clone()).or_default();
+            *mine = (*mine).max(counter);
+        }
+        self.removed.extend(other.removed.iter().cloned());
+        self.adds.extend(other.adds.iter().cloned());
+        let removed = &self.removed;
+        self.adds.retain(|(_, dot)| !removed.contains(dot));
+    }
+}
+
+/// A CRDT stored as a node's data, tagged by [`CRDT_TYPE_FIELD`].
+///
+/// Sets stored in nodes hold strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "crdt_type", rename_all = "snake_case")]
+pub enum CrdtValue {
+    GCounter(GCounter),
+    PnCounter(PNCounter),
+    OrSet(OrSet<String>),
+}
+
+impl CrdtValue {
+    /// The value held by `data`, or `None` if it is not a CRDT payload.
+    pub fn from_data(data: &NodeData) -> Option<Self> {
+        data.get(CRDT_TYPE_FIELD)?;
+        serde_json::from_value(data.clone()).ok()
+    }
+
+    pub fn to_data(&self) -> NodeData {
+        serde_json::to_value(self).unwrap_or(JsonValue::Null)
+    }
+
+    /// Merge `other` into this value.  Returns `false`, changing nothing,
+    /// if the two are different kinds of CRDT.
+    pub fn merge(&mut self, other: &CrdtValue) -> bool {
+        match (self, other) {
+            (Self::GCounter(a), Self::GCounter(b)) => a.merge(b),
+            (Self::PnCounter(a), Self::PnCounter(b)) => a.merge(b),
+            (Self::OrSet(a), Self::OrSet(b)) => a.merge(b),
+            _ => return false,
+        }
+        true
+    }
+
+    /// The merge of two node payloads, if both are CRDTs of one kind.
+    pub(crate) fn merge_data(a: &NodeData, b: &NodeData) -> Option<NodeData> {
+        let mut merged = Self::from_data(a)?;
+        merged.merge(&Self::from_data(b)?).then(|| merged.to_data())
+    }
+}
+
+impl From<GCounter> for CrdtValue {
+    fn from(counter: GCounter) -> Self {
+        Self::GCounter(counter)
+    }
+}
+
+impl From<PNCounter> for CrdtValue {
+    fn from(counter: PNCounter) -> Self {
+        Self::PnCounter(counter)
+    }
+}
+
+impl From<OrSet<String>> for CrdtValue {
+    fn from(set: OrSet<String>) -> Self {
+        Self::OrSet(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CrdtStore, MergeOutcome};
+
+    /// Two replicas of `id`, both holding `initial`.
+    fn replicas(id: &str, initial: CrdtValue) -> (CrdtStore, CrdtStore) {
+        let (left, right) = (CrdtStore::default(), CrdtStore::default());
+        left.put(id, "origin", initial.to_data());
+        right.merge_record(left.get(id).unwrap());
+        (left, right)
+    }
+
+    fn update(store: &CrdtStore, id: &str, actor: &str, change: impl FnOnce(&mut CrdtValue)) {
+        let mut value = CrdtValue::from_data(&store.get(id).unwrap().data).unwrap();
+        change(&mut value);
+        store.put(id, actor, value.to_data());
+    }
+
+    /// Exchange `id` between the replicas and return the value both hold.
+    fn sync(left: &CrdtStore, right: &CrdtStore, id: &str) -> CrdtValue {
+        let (from_left, from_right) = (left.get(id).unwrap(), right.get(id).unwrap());
+        assert_eq!(left.merge_record(from_right), MergeOutcome::Conflict);
+        assert_eq!(right.merge_record(from_left), MergeOutcome::Conflict);
+        let value = CrdtValue::from_data(&left.get(id).unwrap().data).unwrap();
+        assert_eq!(
+            CrdtValue::from_data(&right.get(id).unwrap().data),
+            Some(value.clone())
+        );
+        value
+    }
+
+    #[test]
+    fn concurrent_pn_counter_updates_converge_to_their_sum() {
+        let (left, right) = replicas("votes", PNCounter::default().into());
+        update(&left, "votes", "alice", |value| {
+            let CrdtValue::PnCounter(counter) = value else {
+                unreachable!()
+            };
+            counter.increment("alice", 5);
+        });
+        update(&right, "votes", "bob", |value| {
+            let CrdtValue::PnCounter(counter) = value else {
+                unreachable!()
+            };
+            counter.increment("bob", 3);
+            counter.decrement("bob", 1);
+        });
+
+        let CrdtValue::PnCounter(counter) = sync(&left, &right, "votes") else {
+            panic!("not a counter");
+        };
+        assert_eq!(counter.value(), 7);
+    }
+
+    #[test]
+    fn or_set_keeps_concurrent_adds_over_removes() {
+        let mut initial = OrSet::default();
+        initial.add("origin", "rust".to_string());
+        initial.add("origin", "go".to_string());
+        let (left, right) = replicas("tags", initial.into());
+
+        update(&left, "tags", "alice", |value| {
+            let CrdtValue::OrSet(set) = value else {
+                unreachable!()
+            };
+            set.remove(&"rust".to_string());
+            set.remove(&"go".to_string());
+        });
+        update(&right, "tags", "bob", |value| {
+            let CrdtValue::OrSet(set) = value else {
+                unreachable!()
+            };
+            set.add("bob", "rust".to_string());
+            set.add("bob", "zig".to_string());
+        });
+
+        let CrdtValue::OrSet(set) = sync(&left, &right, "tags") else {
+            panic!("not a set");
+        };
+        // bob's re-add of "rust" was unseen by alice's remove, so it wins;
+        // "go" was only ever added before the remove
+        assert_eq!(set.elements(), ["rust", "zig"]);
+        assert!(!set.contains(&"go".to_string()));
+    }
+
+    #[test]
+    fn g_counter_merge_is_idempotent_and_commutative() {
+        let (mut a, mut b) = (GCounter::default(), GCounter::default());
+        a.increment("a", 2);
+        b.increment("b", 3);
+        b.increment("a", 1);
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b.clone();
+        ba.merge(&a);
+        assert_eq!(ab, ba);
+        assert_eq!(ab.value(), 5);
+        ab.merge(&b);
+        assert_eq!(ab.value(), 5);
+
+        let mut value = CrdtValue::from(a);
+        assert!(!value.merge(&PNCounter::default().into()));
+        assert_eq!(CrdtValue::from_data(&serde_json::json!({ "n": 1 })), None);
+    }
+}
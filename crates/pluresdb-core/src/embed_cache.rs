@@ -0,0 +1,228 @@
+//! A disk cache in front of any [`EmbedText`] backend.
+//!
+//! [`CachedEmbedder`] writes every embedding it computes to its own file in a
+//! cache directory, named by a BLAKE3 hash of the model id and the text, so
+//! a restarted process reuses earlier results instead of recomputing them.
+//! Files are read lazily, one per lookup, and only the texts that miss are
+//! passed on to the backend, still as a single batch.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::EmbedText;
+
+/// Lookups answered by a [`CachedEmbedder`] since it was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbeddingCacheStats {
+    /// Texts whose embedding was read from the cache.
+    pub hits: u64,
+    /// Texts that had to be embedded by the backend.
+    pub misses: u64,
+}
+
+/// Persistent embedding cache wrapping another [`EmbedText`].
+pub struct CachedEmbedder {
+    inner: Arc<dyn EmbedText>,
+    dir: PathBuf,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CachedEmbedder {
+    /// Cache embeddings from `inner` in `cache_dir`, creating it if missing.
+    pub fn new(inner: Arc<dyn EmbedText>, cache_dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = cache_dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create embedding cache: {}", dir.display()))?;
+        Ok(Self {
+            inner,
+            dir,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// File holding the embedding of `text`.  The model id is part of the
+    /// key, so backends sharing a directory never read each other's vectors.
+    fn entry_path(&self, text: &str) -> PathBuf {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.inner.model_id().unwrap_or_default().as_bytes());
+        hasher.update(&[0]);
+        hasher.update(text.as_bytes());
+        self.dir.join(format!("{}.f32", hasher.finalize().to_hex()))
+    }
+
+    /// The cached embedding at `path`, if there is a readable one of the
+    /// backend's dimension.
+    fn load(&self, path: &Path) -> Option<Vec<f32>> {
+        let bytes = fs::read(path).ok()?;
+        if bytes.len() != self.inner.dimension() * 4 {
+            warn!(?path, "ignoring embedding cache entry of the wrong size");
+            return None;
+        }
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().expect("4-byte chunk")))
+                .collect(),
+        )
+    }
+
+    /// Write `embedding` to `path` via a temporary file, so a crash never
+    /// leaves a partial entry behind.  The temporary file is named uniquely,
+    /// so processes caching the same text at once cannot write into each
+    /// other's.
+    fn store(&self, path: &Path, embedding: &[f32]) -> Result<()> {
+        let bytes: Vec<u8> = embedding.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let tmp = path.with_extension(format!("{}.tmp", Uuid::new_v4()));
+        let written = fs::write(&tmp, bytes).and_then(|()| fs::rename(&tmp, path));
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        Ok(written?)
+    }
+}
+
+impl std::fmt::Debug for CachedEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedEmbedder")
+            .field("inner", &self.inner)
+            .field("dir", &self.dir)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl EmbedText for CachedEmbedder {
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let paths: Vec<PathBuf> = texts.iter().map(|text| self.entry_path(text)).collect();
+        let mut embeddings: Vec<Option<Vec<f32>>> =
+            paths.iter().map(|path| self.load(path)).collect();
+        let missing: Vec<usize> = (0..texts.len())
+            .filter(|&i| embeddings[i].is_none())
+            .collect();
+        self.hits
+            .fetch_add((texts.len() - missing.len()) as u64, Ordering::Relaxed);
+        self.misses
+            .fetch_add(missing.len() as u64, Ordering::Relaxed);
+
+        if !missing.is_empty() {
+            let batch: Vec<&str> = missing.iter().map(|&i| texts[i]).collect();
+            let computed = self.inner.embed(&batch)?;
+            if computed.len() != batch.len() {
+                bail!(
+                    "embedding backend returned {} vectors for {} texts",
+                    computed.len(),
+                    batch.len()
+                );
+            }
+            for (i, embedding) in missing.into_iter().zip(computed) {
+                if let Err(e) = self.store(&paths[i], &embedding) {
+                    warn!(path = ?paths[i], "failed to write embedding cache entry: {}", e);
+                }
+                embeddings[i] = Some(embedding);
+            }
+        }
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn model_id(&self) -> Option<&str> {
+        self.inner.model_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    /// Embeds each text as `[len, 1]`, recording every batch it is given.
+    #[derive(Debug, Default)]
+    struct RecordingEmbedder {
+        batches: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl EmbedText for RecordingEmbedder {
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            self.batches
+                .lock()
+                .push(texts.iter().map(|t| t.to_string()).collect());
+            Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn model_id(&self) -> Option<&str> {
+            Some("recording")
+        }
+    }
+
+    #[test]
+    fn a_new_embedder_reuses_what_an_earlier_one_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = Arc::new(RecordingEmbedder::default());
+        let cached = CachedEmbedder::new(first.clone(), dir.path()).unwrap();
+        assert_eq!(cached.embed(&["hello"]).unwrap(), [vec![5.0, 1.0]]);
+        assert_eq!(cached.stats(), EmbeddingCacheStats { hits: 0, misses: 1 });
+        drop(cached);
+
+        let second = Arc::new(RecordingEmbedder::default());
+        let cached = CachedEmbedder::new(second.clone(), dir.path()).unwrap();
+        let embeddings = cached.embed(&["hello", "world!", "hello"]).unwrap();
+        assert_eq!(embeddings, [vec![5.0, 1.0], vec![6.0, 1.0], vec![5.0, 1.0]]);
+        assert_eq!(cached.stats(), EmbeddingCacheStats { hits: 2, misses: 1 });
+        // Only the miss reached the backend, in one batch
+        assert_eq!(*second.batches.lock(), [vec!["world!".to_string()]]);
+        assert_eq!(first.batches.lock().len(), 1);
+    }
+
+    #[test]
+    fn concurrent_writers_of_one_entry_leave_only_that_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let backend = Arc::new(RecordingEmbedder::default());
+                    let cached = CachedEmbedder::new(backend, dir.path()).unwrap();
+                    for _ in 0..20 {
+                        let path = cached.entry_path("shared");
+                        cached.store(&path, &[6.0, 1.0]).unwrap();
+                    }
+                });
+            }
+        });
+
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names.len(), 1, "{names:?}");
+        let cached =
+            CachedEmbedder::new(Arc::new(RecordingEmbedder::default()), dir.path()).unwrap();
+        assert_eq!(cached.embed(&["shared"]).unwrap(), [vec![6.0, 1.0]]);
+        assert_eq!(cached.stats().hits, 1);
+    }
+}
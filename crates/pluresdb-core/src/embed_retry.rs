@@ -0,0 +1,336 @@
+//! Retry with exponential backoff in front of any [`EmbedText`] backend.
+//!
+//! Backends that talk to a remote service report rate limits, server errors
+//! and dropped connections by returning a [`TransientEmbedError`], with or
+//! without added `anyhow` context.  [`RetryingEmbedder`] retries those, waiting an
+//! exponentially growing, jittered delay or the server's `Retry-After` hint,
+//! and fails fast on every other error, such as rejected credentials.
+//! [`TransientEmbedError::from_http_status`] sorts HTTP responses for a
+//! backend, and [`EmbeddingConfig`] holds the retry settings.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use tracing::warn;
+
+use crate::EmbedText;
+
+/// An embedding failure worth retrying, e.g. HTTP 429, a 5xx response or a
+/// connection error.
+#[derive(Debug, thiserror::Error)]
+#[error("transient embedding error: {message}")]
+pub struct TransientEmbedError {
+    pub message: String,
+    /// How long the service asked callers to wait, from `Retry-After`.
+    pub retry_after: Option<Duration>,
+}
+
+impl TransientEmbedError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    /// The error for an HTTP response with `status`, if it is worth
+    /// retrying: 429 and 5xx are, other statuses such as 400 or 401 are
+    /// not.  `retry_after` is the response's `Retry-After` header; only the
+    /// delay-seconds form is understood.
+    pub fn from_http_status(status: u16, retry_after: Option<&str>) -> Option<Self> {
+        if status != 429 && !(500..600).contains(&status) {
+            return None;
+        }
+        let err = Self::new(format!("HTTP {status}"));
+        match retry_after.and_then(|value| value.trim().parse::<u64>().ok()) {
+            Some(seconds) => Some(err.with_retry_after(Duration::from_secs(seconds))),
+            None => Some(err),
+        }
+    }
+}
+
+/// Retry settings for a [`RetryingEmbedder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingConfig {
+    /// Attempts made after the first one fails.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub retry_base_delay: Duration,
+}
+
+impl Default for EmbeddingConfig {
+    /// 3 retries, starting 500 ms apart.
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            retry_base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Retries transient failures of another [`EmbedText`].
+#[derive(Debug)]
+pub struct RetryingEmbedder {
+    inner: Arc<dyn EmbedText>,
+    config: EmbeddingConfig,
+}
+
+impl RetryingEmbedder {
+    /// Doublings after which the backoff stops growing.
+    const MAX_BACKOFF_SHIFT: u32 = 10;
+
+    /// Retry `inner` with the default [`EmbeddingConfig`].
+    pub fn new(inner: Arc<dyn EmbedText>) -> Self {
+        Self::with_config(inner, EmbeddingConfig::default())
+    }
+
+    pub fn with_config(inner: Arc<dyn EmbedText>, config: EmbeddingConfig) -> Self {
+        Self { inner, config }
+    }
+
+    pub fn config(&self) -> EmbeddingConfig {
+        self.config
+    }
+
+    /// Attempts made after the first one fails (default 3).
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.config.max_retries = max_retries;
+        self
+    }
+
+    /// Delay before the first retry, doubled for each one after it
+    /// (default 500 ms).
+    pub fn with_retry_base_delay(mut self, delay: Duration) -> Self {
+        self.config.retry_base_delay = delay;
+        self
+    }
+
+    /// Wait before retry number `retry` (1-based): the backoff for that
+    /// retry, with its upper half randomised so that concurrent callers
+    /// spread out.
+    fn backoff(&self, retry: u32) -> Duration {
+        let shift = (retry - 1).min(Self::MAX_BACKOFF_SHIFT);
+        let delay = self.config.retry_base_delay.saturating_mul(1 << shift);
+        let half = delay.as_millis() as u64 / 2;
+        let mut hasher = DefaultHasher::new();
+        retry.hash(&mut hasher);
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos()
+            .hash(&mut hasher);
+        Duration::from_millis(half + hasher.finish() % (half + 1))
+    }
+}
+
+impl EmbedText for RetryingEmbedder {
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut retry = 0;
+        loop {
+            let err = match self.inner.embed(texts) {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(err) => err,
+            };
+            let Some(transient) = err.downcast_ref::<TransientEmbedError>() else {
+                return Err(err);
+            };
+            if retry == self.config.max_retries {
+                return Err(err.context(format!("embedding failed after {} retries", retry)));
+            }
+            retry += 1;
+            let delay = transient.retry_after.unwrap_or_else(|| self.backoff(retry));
+            warn!(retry, ?delay, "retrying embedding request: {}", transient);
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn model_id(&self) -> Option<&str> {
+        self.inner.model_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails the first `failures` calls, transiently or not, then succeeds.
+    #[derive(Debug)]
+    struct FlakyEmbedder {
+        failures: u32,
+        transient: bool,
+        attempts: AtomicU32,
+    }
+
+    impl EmbedText for FlakyEmbedder {
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+            if attempt < self.failures {
+                if self.transient {
+                    let err = TransientEmbedError::new("HTTP 429 Too Many Requests")
+                        .with_retry_after(Duration::from_millis(1));
+                    return Err(err.into());
+                }
+                anyhow::bail!("HTTP 401 Unauthorized");
+            }
+            Ok(texts.iter().map(|_| vec![1.0, 0.0]).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+    }
+
+    fn flaky(failures: u32, transient: bool) -> Arc<FlakyEmbedder> {
+        Arc::new(FlakyEmbedder {
+            failures,
+            transient,
+            attempts: AtomicU32::new(0),
+        })
+    }
+
+    #[test]
+    fn transient_errors_are_retried_and_others_fail_fast() {
+        let rate_limited = flaky(2, true);
+        let embedder = RetryingEmbedder::new(rate_limited.clone())
+            .with_retry_base_delay(Duration::from_millis(1));
+        assert_eq!(embedder.embed(&["hi"]).unwrap(), [vec![1.0, 0.0]]);
+        assert_eq!(rate_limited.attempts.load(Ordering::SeqCst), 3);
+
+        let exhausted = flaky(2, true);
+        let embedder = RetryingEmbedder::new(exhausted.clone()).with_max_retries(1);
+        assert!(embedder.embed(&["hi"]).is_err());
+        assert_eq!(exhausted.attempts.load(Ordering::SeqCst), 2);
+
+        let unauthorized = flaky(1, false);
+        let embedder = RetryingEmbedder::new(unauthorized.clone());
+        assert!(embedder.embed(&["hi"]).is_err());
+        assert_eq!(unauthorized.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn http_statuses_are_sorted_into_transient_and_fatal() {
+        let limited = TransientEmbedError::from_http_status(429, Some(" 2 ")).unwrap();
+        assert_eq!(limited.retry_after, Some(Duration::from_secs(2)));
+        let unavailable = TransientEmbedError::from_http_status(503, Some("soon")).unwrap();
+        assert_eq!(unavailable.retry_after, None);
+        for status in [200, 400, 401, 404] {
+            assert!(TransientEmbedError::from_http_status(status, None).is_none());
+        }
+    }
+
+    /// Minimal HTTP embedding client, as a remote backend would be written.
+    #[derive(Debug)]
+    struct HttpEmbedder {
+        addr: String,
+    }
+
+    impl EmbedText for HttpEmbedder {
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            let mut stream = TcpStream::connect(&self.addr)
+                .map_err(|e| TransientEmbedError::new(format!("connection failed: {e}")))?;
+            let body = serde_json::json!({ "input": texts }).to_string();
+            write!(
+                stream,
+                "POST /embeddings HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+                self.addr,
+                body.len(),
+                body
+            )?;
+            stream.shutdown(Shutdown::Write)?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+
+            let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+            let status: u16 = head
+                .split_whitespace()
+                .nth(1)
+                .and_then(|status| status.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("malformed response"))?;
+            if status != 200 {
+                let retry_after = head.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("retry-after").then_some(value)
+                });
+                match TransientEmbedError::from_http_status(status, retry_after) {
+                    Some(err) => return Err(err.into()),
+                    None => anyhow::bail!("HTTP {status}"),
+                }
+            }
+            let parsed: serde_json::Value = serde_json::from_str(body)?;
+            Ok(parsed["data"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|item| {
+                    item["embedding"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|v| v.as_f64().map(|v| v as f32))
+                        .collect()
+                })
+                .collect())
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+    }
+
+    /// Serve `responses` in order, one per connection, and count the
+    /// requests received.
+    fn mock_server(responses: Vec<&'static str>) -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for (stream, response) in listener.incoming().zip(responses) {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                stream.read_to_end(&mut request).unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (addr, requests)
+    }
+
+    #[test]
+    fn a_rate_limited_server_is_retried_until_it_answers() {
+        const RATE_LIMITED: &str =
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n";
+        const UNAUTHORIZED: &str = "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n";
+        const OK: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n\
+                          {\"data\":[{\"embedding\":[0.6,0.8]}]}";
+        let config = EmbeddingConfig {
+            max_retries: 3,
+            retry_base_delay: Duration::from_millis(1),
+        };
+
+        let (addr, requests) = mock_server(vec![RATE_LIMITED, RATE_LIMITED, OK]);
+        let embedder = RetryingEmbedder::with_config(Arc::new(HttpEmbedder { addr }), config);
+        assert_eq!(embedder.embed(&["hi"]).unwrap(), [vec![0.6, 0.8]]);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let (addr, requests) = mock_server(vec![UNAUTHORIZED, OK]);
+        let embedder = RetryingEmbedder::with_config(Arc::new(HttpEmbedder { addr }), config);
+        assert!(embedder.embed(&["hi"]).is_err());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
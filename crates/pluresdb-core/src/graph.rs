@@ -0,0 +1,687 @@
+//! Graph traversal over edge nodes held in a [`CrdtStore`].
+//!
+//! Edges are ordinary nodes whose data carries `_edge: true`, `from`, `to`,
+//! and an optional `label` — the same shape the procedure engine's graph
+//! operators read — so they replicate and merge like any other node.
+//! [`CrdtStore::add_edge`] keys each edge by its endpoints and label, so one
+//! pair of nodes can be linked by several relationship types.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::ControlFlow;
+
+use crate::{ActorId, CrdtStore, NodeData, NodeId, NodeRecord, StoreError};
+
+/// Which edges to follow from a node during traversal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    /// Follow edges from `from` to `to`.
+    #[default]
+    Outgoing,
+    /// Follow edges backwards, from `to` to `from`.
+    Incoming,
+    /// Follow edges in both directions.
+    Both,
+}
+
+/// Options for [`CrdtStore::traverse`].
+#[derive(Debug, Clone)]
+pub struct TraverseOpts {
+    pub direction: Direction,
+    /// Only follow edges whose `label` is in this set.  `None` follows all edges.
+    pub labels: Option<HashSet<String>>,
+    /// Maximum number of hops from the start node.  Defaults to unbounded.
+    pub max_depth: usize,
+}
+
+impl Default for TraverseOpts {
+    fn default() -> Self {
+        Self {
+            direction: Direction::Outgoing,
+            labels: None,
+            max_depth: usize::MAX,
+        }
+    }
+}
+
+/// Endpoints and label of an edge node, or `None` for non-edge nodes and
+/// edges missing a non-empty `from` or `to`.
+fn edge_parts(record: &NodeRecord) -> Option<(&str, &str, &str)> {
+    if !record
+        .data
+        .get("_edge")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    let from = record.data.get("from").and_then(|v| v.as_str())?;
+    let to = record.data.get("to").and_then(|v| v.as_str())?;
+    if from.is_empty() || to.is_empty() {
+        return None;
+    }
+    let label = record
+        .data
+        .get("label")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    Some((from, to, label))
+}
+
+/// Node id of the `rel_type` edge from `from` to `to`.
+fn edge_id(from: &str, rel_type: &str, to: &str) -> NodeId {
+    format!("edge:{from}:{rel_type}:{to}")
+}
+
+/// Weighted undirected graph: `adjacency[i][j]` is the weight between `i`
+/// and `j`, stored in both rows, with a self-loop counted twice.
+type WeightedGraph = Vec<BTreeMap<usize, f64>>;
+
+/// Upper bound on local-moving passes in one Louvain level.
+const MAX_LOUVAIN_PASSES: usize = 100;
+
+/// One Louvain level: move each node into the neighbouring community that
+/// most increases modularity until no move helps.  Returns each node's
+/// community, or `None` if no node moved.
+///
+/// Candidate communities are tried in id order and a move must strictly
+/// improve on staying put, so the result is deterministic.
+fn louvain_level(graph: &WeightedGraph) -> Option<Vec<usize>> {
+    let strength: Vec<f64> = graph.iter().map(|row| row.values().sum()).collect();
+    let total_weight = strength.iter().sum::<f64>() / 2.0;
+    if total_weight == 0.0 {
+        return None;
+    }
+    let mut community: Vec<usize> = (0..graph.len()).collect();
+    // Summed strength of each community's members
+    let mut totals = strength.clone();
+    let mut moved_any = false;
+
+    for _ in 0..MAX_LOUVAIN_PASSES {
+        let mut moved = false;
+        for (i, row) in graph.iter().enumerate() {
+            let current = community[i];
+            totals[current] -= strength[i];
+            let mut links: BTreeMap<usize, f64> = BTreeMap::new();
+            for (&j, &weight) in row {
+                if j != i {
+                    *links.entry(community[j]).or_default() += weight;
+                }
+            }
+            // Modularity gain of joining `c`, scaled by the total weight
+            let gain = |c: usize, weight_to_c: f64| {
+                weight_to_c - totals[c] * strength[i] / (2.0 * total_weight)
+            };
+            let mut best = current;
+            let mut best_gain = gain(current, links.get(&current).copied().unwrap_or(0.0));
+            for (&c, &weight_to_c) in &links {
+                let g = gain(c, weight_to_c);
+                if g > best_gain + 1e-12 {
+                    best = c;
+                    best_gain = g;
+                }
+            }
+            totals[best] += strength[i];
+            if best != current {
+                community[i] = best;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+        moved_any = true;
+    }
+    moved_any.then_some(community)
+}
+
+impl CrdtStore {
+    /// Link `from` to `to` with a `rel_type` edge, replacing any earlier edge
+    /// of that type between them, and return the edge's node id.
+    ///
+    /// Fields of `data` are stored on the edge next to its endpoints and
+    /// label; a non-object `data` is kept under a `data` field instead.
+    pub fn add_edge(
+        &self,
+        from: &str,
+        to: &str,
+        rel_type: &str,
+        actor: impl Into<ActorId>,
+        data: NodeData,
+    ) -> NodeId {
+        let mut fields = match data {
+            serde_json::Value::Object(fields) => fields,
+            serde_json::Value::Null => serde_json::Map::new(),
+            other => serde_json::Map::from_iter([("data".to_string(), other)]),
+        };
+        fields.insert("_edge".to_string(), true.into());
+        fields.insert("from".to_string(), from.into());
+        fields.insert("to".to_string(), to.into());
+        fields.insert("label".to_string(), rel_type.into());
+        self.put(edge_id(from, rel_type, to), actor, fields.into())
+    }
+
+    /// Delete the `rel_type` edge from `from` to `to`.
+    pub fn remove_edge(&self, from: &str, to: &str, rel_type: &str) -> Result<(), StoreError> {
+        self.delete(edge_id(from, rel_type, to))
+    }
+
+    /// Edge nodes touching `id` in `direction`, ordered by edge id.
+    ///
+    /// This covers every edge node, including ones written directly or by
+    /// the procedure engine rather than through [`add_edge`](Self::add_edge).
+    pub fn edges_of(&self, id: &str, direction: Direction) -> Vec<NodeRecord> {
+        let mut edges: Vec<NodeRecord> = self
+            .list()
+            .into_iter()
+            .filter(|record| {
+                edge_parts(record).is_some_and(|(from, to, _)| match direction {
+                    Direction::Outgoing => from == id,
+                    Direction::Incoming => to == id,
+                    Direction::Both => from == id || to == id,
+                })
+            })
+            .collect();
+        edges.sort_by(|a, b| a.id.cmp(&b.id));
+        edges
+    }
+
+    /// Neighbour lists of every node with an edge matching `opts`'
+    /// direction and labels.
+    fn adjacency(&self, opts: &TraverseOpts) -> HashMap<NodeId, Vec<NodeId>> {
+        let mut adjacency: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for record in self.list() {
+            let Some((from, to, label)) = edge_parts(&record) else {
+                continue;
+            };
+            if let Some(labels) = &opts.labels {
+                if !labels.contains(label) {
+                    continue;
+                }
+            }
+            if matches!(opts.direction, Direction::Outgoing | Direction::Both) {
+                adjacency
+                    .entry(from.to_string())
+                    .or_default()
+                    .push(to.to_string());
+            }
+            if matches!(opts.direction, Direction::Incoming | Direction::Both) {
+                adjacency
+                    .entry(to.to_string())
+                    .or_default()
+                    .push(from.to_string());
+            }
+        }
+        adjacency
+    }
+
+    /// Shortest path from `from` to `to` along outgoing edges, as the node
+    /// ids on it from `from` through `to`, or `None` if `to` is more than
+    /// `max_hops` edges away or unreachable.
+    pub fn find_path(&self, from: &str, to: &str, max_hops: usize) -> Option<Vec<NodeId>> {
+        let adjacency = self.adjacency(&TraverseOpts::default());
+        // Each reached node's predecessor on its shortest path
+        let mut previous: HashMap<NodeId, NodeId> = HashMap::new();
+        let mut queue: VecDeque<(NodeId, usize)> = VecDeque::new();
+        queue.push_back((from.to_string(), 0));
+        let mut reached = from == to;
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if reached || depth >= max_hops {
+                break;
+            }
+            for next in adjacency.get(&current).into_iter().flatten() {
+                if next == from || previous.contains_key(next) {
+                    continue;
+                }
+                previous.insert(next.clone(), current.clone());
+                if next == to {
+                    reached = true;
+                    break;
+                }
+                queue.push_back((next.clone(), depth + 1));
+            }
+        }
+        if !reached {
+            return None;
+        }
+
+        let mut path = vec![to.to_string()];
+        while let Some(prev) = previous.get(path.last()?) {
+            path.push(prev.clone());
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Nodes reachable from `id` along outgoing edges within `max_depth`
+    /// hops, nearest first, not counting `id` itself.
+    pub fn neighbors(&self, id: &str, max_depth: usize) -> Vec<NodeId> {
+        let opts = TraverseOpts {
+            max_depth,
+            ..Default::default()
+        };
+        let mut reachable = Vec::new();
+        self.traverse(id, opts, |node, depth| {
+            if depth > 0 {
+                reachable.push(node.clone());
+            }
+            ControlFlow::Continue(())
+        });
+        reachable
+    }
+
+    /// Ids of every non-edge node plus every edge endpoint, sorted, and the
+    /// `(from, to)` index pairs of all edges between them.
+    fn graph_snapshot(&self) -> (Vec<NodeId>, Vec<(usize, usize)>) {
+        let records = self.list();
+        let mut ids: HashSet<&str> = HashSet::new();
+        let mut edges = Vec::new();
+        for record in &records {
+            match edge_parts(record) {
+                Some((from, to, _)) => {
+                    ids.insert(from);
+                    ids.insert(to);
+                    edges.push((from, to));
+                }
+                None => {
+                    ids.insert(&record.id);
+                }
+            }
+        }
+        let mut nodes: Vec<NodeId> = ids.into_iter().map(str::to_string).collect();
+        nodes.sort();
+        let index: HashMap<&str, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let edges = edges
+            .into_iter()
+            .map(|(from, to)| (index[from], index[to]))
+            .collect();
+        (nodes, edges)
+    }
+
+    /// PageRank of every node, following edges from `from` to `to`.
+    ///
+    /// Runs the power method until the scores change by less than `1e-10`
+    /// in total or after `iterations` rounds.  The rank of nodes without
+    /// outgoing edges is spread evenly over all nodes, so the scores always
+    /// sum to 1.
+    ///
+    /// Fails with [`StoreError::InvalidArgument`] if `damping` is not
+    /// strictly between 0 and 1.
+    pub fn pagerank(
+        &self,
+        damping: f64,
+        iterations: usize,
+    ) -> Result<HashMap<NodeId, f64>, StoreError> {
+        const TOLERANCE: f64 = 1e-10;
+        if damping.is_nan() || damping <= 0.0 || damping >= 1.0 {
+            return Err(StoreError::InvalidArgument(format!(
+                "damping must be in (0, 1), got {damping}"
+            )));
+        }
+        let (nodes, edges) = self.graph_snapshot();
+        let n = nodes.len();
+        if n == 0 {
+            return Ok(HashMap::new());
+        }
+        let mut out_degree = vec![0usize; n];
+        for &(from, _) in &edges {
+            out_degree[from] += 1;
+        }
+
+        let mut scores = vec![1.0 / n as f64; n];
+        for _ in 0..iterations {
+            let dangling: f64 = (0..n)
+                .filter(|&i| out_degree[i] == 0)
+                .map(|i| scores[i])
+                .sum();
+            let base = (1.0 - damping) / n as f64 + damping * dangling / n as f64;
+            let mut next = vec![base; n];
+            for &(from, to) in &edges {
+                next[to] += damping * scores[from] / out_degree[from] as f64;
+            }
+            let delta: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+            scores = next;
+            if delta < TOLERANCE {
+                break;
+            }
+        }
+        Ok(nodes.into_iter().zip(scores).collect())
+    }
+
+    /// Degree centrality of every node: its incoming plus outgoing edges,
+    /// divided by the number of other nodes.
+    pub fn degree_centrality(&self) -> HashMap<NodeId, f64> {
+        let (nodes, edges) = self.graph_snapshot();
+        let mut degree = vec![0usize; nodes.len()];
+        for (from, to) in edges {
+            degree[from] += 1;
+            degree[to] += 1;
+        }
+        let others = nodes.len().saturating_sub(1).max(1) as f64;
+        nodes
+            .into_iter()
+            .zip(degree)
+            .map(|(id, degree)| (id, degree as f64 / others))
+            .collect()
+    }
+
+    /// Communities of at least `min_size` nodes, found by Louvain modularity
+    /// optimisation over the edges with their direction ignored.
+    ///
+    /// Each community lists its node ids in order; communities come largest
+    /// first, ties broken by their first id.  The result only depends on the
+    /// store's contents.  Nodes without edges form communities of one.
+    pub fn detect_communities(&self, min_size: usize) -> Vec<Vec<NodeId>> {
+        let (nodes, edges) = self.graph_snapshot();
+        let mut graph: WeightedGraph = vec![BTreeMap::new(); nodes.len()];
+        for (from, to) in edges {
+            *graph[from].entry(to).or_default() += 1.0;
+            *graph[to].entry(from).or_default() += 1.0;
+        }
+
+        // Community of each node in `nodes`, refined level by level while
+        // `graph` is collapsed to one vertex per community
+        let mut membership: Vec<usize> = (0..nodes.len()).collect();
+        while let Some(community) = louvain_level(&graph) {
+            let mut renumbered: HashMap<usize, usize> = HashMap::new();
+            let community: Vec<usize> = community
+                .into_iter()
+                .map(|c| {
+                    let next = renumbered.len();
+                    *renumbered.entry(c).or_insert(next)
+                })
+                .collect();
+            if renumbered.len() == graph.len() {
+                break;
+            }
+            for c in &mut membership {
+                *c = community[*c];
+            }
+            let mut collapsed: WeightedGraph = vec![BTreeMap::new(); renumbered.len()];
+            for (i, row) in graph.iter().enumerate() {
+                for (&j, &weight) in row {
+                    *collapsed[community[i]].entry(community[j]).or_default() += weight;
+                }
+            }
+            graph = collapsed;
+        }
+
+        let mut groups: BTreeMap<usize, Vec<NodeId>> = BTreeMap::new();
+        for (id, c) in nodes.into_iter().zip(membership) {
+            groups.entry(c).or_default().push(id);
+        }
+        let mut communities: Vec<Vec<NodeId>> = groups
+            .into_values()
+            .filter(|members| members.len() >= min_size)
+            .collect();
+        communities.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+        communities
+    }
+
+    /// Breadth-first walk from `start`, calling `visit(id, depth)` once per
+    /// reachable node (the start node at depth 0).
+    ///
+    /// Only edges matching `opts.direction` and `opts.labels` are followed, up
+    /// to `opts.max_depth` hops.  Returning [`ControlFlow::Break`] from `visit`
+    /// stops the walk immediately.  Cycles are handled: each node is visited
+    /// at most once, at its shortest distance from `start`.
+    pub fn traverse(
+        &self,
+        start: &str,
+        opts: TraverseOpts,
+        mut visit: impl FnMut(&NodeId, usize) -> ControlFlow<()>,
+    ) {
+        let adjacency = self.adjacency(&opts);
+        let mut visited: HashSet<NodeId> = HashSet::new();
+        let mut queue: VecDeque<(NodeId, usize)> = VecDeque::new();
+        visited.insert(start.to_string());
+        queue.push_back((start.to_string(), 0));
+
+        while let Some((current, depth)) = queue.pop_front() {
+            if visit(&current, depth).is_break() {
+                return;
+            }
+            if depth >= opts.max_depth {
+                continue;
+            }
+            for next in adjacency.get(&current).into_iter().flatten() {
+                if visited.insert(next.clone()) {
+                    queue.push_back((next.clone(), depth + 1));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put_edge(store: &CrdtStore, from: &str, to: &str, label: &str) {
+        store.put(
+            format!("{from}:{label}:{to}"),
+            "actor",
+            serde_json::json!({ "_edge": true, "from": from, "to": to, "label": label }),
+        );
+    }
+
+    fn family_graph() -> CrdtStore {
+        let store = CrdtStore::default();
+        put_edge(&store, "child", "parent", "parent");
+        put_edge(&store, "parent", "grandparent", "parent");
+        put_edge(&store, "child", "friend", "knows");
+        put_edge(&store, "friend", "friend-parent", "parent");
+        store
+    }
+
+    fn collect(store: &CrdtStore, start: &str, opts: TraverseOpts) -> Vec<(NodeId, usize)> {
+        let mut seen = Vec::new();
+        store.traverse(start, opts, |id, depth| {
+            seen.push((id.clone(), depth));
+            ControlFlow::Continue(())
+        });
+        seen
+    }
+
+    #[test]
+    fn traverse_follows_only_matching_labels() {
+        let store = family_graph();
+        let opts = TraverseOpts {
+            labels: Some(HashSet::from(["parent".to_string()])),
+            ..Default::default()
+        };
+
+        let seen = collect(&store, "child", opts);
+
+        assert_eq!(
+            seen,
+            vec![
+                ("child".to_string(), 0),
+                ("parent".to_string(), 1),
+                ("grandparent".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn traverse_respects_direction_and_max_depth() {
+        let store = family_graph();
+
+        let up = collect(
+            &store,
+            "grandparent",
+            TraverseOpts {
+                direction: Direction::Incoming,
+                max_depth: 1,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            up,
+            vec![("grandparent".to_string(), 0), ("parent".to_string(), 1)]
+        );
+
+        let all = collect(&store, "child", TraverseOpts::default());
+        assert_eq!(all.len(), 5);
+    }
+
+    #[test]
+    fn traverse_stops_when_visitor_breaks() {
+        let store = family_graph();
+        let mut visited = 0;
+        store.traverse("child", TraverseOpts::default(), |_, _| {
+            visited += 1;
+            if visited == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(visited, 2);
+    }
+
+    #[test]
+    fn edges_of_lists_outgoing_and_incoming_edges() {
+        let store = CrdtStore::default();
+        store.add_edge(
+            "alice",
+            "bob",
+            "knows",
+            "actor",
+            serde_json::json!({ "since": 2020 }),
+        );
+        store.add_edge(
+            "alice",
+            "bob",
+            "works_with",
+            "actor",
+            serde_json::Value::Null,
+        );
+        store.add_edge(
+            "bob",
+            "carol",
+            "knows",
+            "actor",
+            serde_json::json!("met at work"),
+        );
+        let ids = |edges: Vec<NodeRecord>| edges.into_iter().map(|e| e.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(store.edges_of("alice", Direction::Outgoing)),
+            ["edge:alice:knows:bob", "edge:alice:works_with:bob"]
+        );
+        assert!(store.edges_of("alice", Direction::Incoming).is_empty());
+        assert_eq!(ids(store.edges_of("bob", Direction::Incoming)).len(), 2);
+        assert_eq!(ids(store.edges_of("bob", Direction::Both)).len(), 3);
+
+        let knows = store.get("edge:alice:knows:bob").unwrap();
+        assert_eq!(knows.data["since"], 2020);
+        assert_eq!(knows.data["label"], "knows");
+        let carol = store.edges_of("carol", Direction::Incoming);
+        assert_eq!(carol[0].data["data"], "met at work");
+
+        // Edges drive traversal like any other edge node
+        let seen = collect(&store, "alice", TraverseOpts::default());
+        assert_eq!(seen.last(), Some(&("carol".to_string(), 2)));
+
+        store.remove_edge("alice", "bob", "works_with").unwrap();
+        assert_eq!(
+            ids(store.edges_of("alice", Direction::Outgoing)),
+            ["edge:alice:knows:bob"]
+        );
+        assert!(store.remove_edge("alice", "bob", "works_with").is_err());
+    }
+
+    #[test]
+    fn find_path_returns_the_shortest_path_within_max_hops() {
+        let store = CrdtStore::default();
+        for (from, to) in [
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "d"),
+            ("a", "x"),
+            ("x", "d"),
+            ("d", "a"),
+        ] {
+            store.add_edge(from, to, "next", "actor", serde_json::Value::Null);
+        }
+        store.put("island", "actor", serde_json::json!({}));
+
+        assert_eq!(
+            store.find_path("a", "d", 5),
+            Some(vec!["a".to_string(), "x".to_string(), "d".to_string()])
+        );
+        assert_eq!(store.find_path("a", "d", 1), None);
+        assert_eq!(store.find_path("a", "a", 0), Some(vec!["a".to_string()]));
+        assert_eq!(store.find_path("a", "island", 10), None);
+        // Edges are directed, but the cycle through d leads back to a
+        assert_eq!(store.find_path("c", "b", 10).map(|p| p.len()), Some(4));
+
+        let mut near = store.neighbors("a", 1);
+        near.sort();
+        assert_eq!(near, ["b", "x"]);
+        assert_eq!(store.neighbors("a", usize::MAX).len(), 4);
+    }
+
+    #[test]
+    fn pagerank_and_degree_centrality_rank_the_hub_first() {
+        let store = CrdtStore::default();
+        for (from, to) in [("a", "b"), ("b", "c"), ("c", "d"), ("d", "a")] {
+            store.add_edge(from, to, "next", "actor", serde_json::Value::Null);
+            store.add_edge(from, "hub", "uses", "actor", serde_json::Value::Null);
+        }
+        store.put("lonely", "actor", serde_json::json!({}));
+
+        let ranks = store.pagerank(0.85, 100).unwrap();
+        assert_eq!(ranks.len(), 6);
+        assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        let top = ranks
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(id, _)| id.as_str());
+        assert_eq!(top, Some("hub"));
+        assert!((ranks["a"] - ranks["c"]).abs() < 1e-9);
+        assert!(ranks["lonely"] < ranks["a"]);
+
+        let degrees = store.degree_centrality();
+        assert_eq!(degrees["hub"], 4.0 / 5.0);
+        assert_eq!(degrees["a"], 3.0 / 5.0);
+        assert_eq!(degrees["lonely"], 0.0);
+    }
+
+    #[test]
+    fn pagerank_rejects_damping_outside_the_unit_interval() {
+        let store = CrdtStore::default();
+        store.add_edge("a", "b", "next", "actor", serde_json::Value::Null);
+        for damping in [0.0, 1.0, -0.5, 1.5, f64::NAN] {
+            let err = store.pagerank(damping, 10).unwrap_err();
+            assert!(matches!(err, StoreError::InvalidArgument(_)), "{err}");
+            assert_eq!(err.code(), crate::CoreErrorCode::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn detect_communities_splits_two_bridged_clusters() {
+        let store = CrdtStore::default();
+        for cluster in [["a1", "a2", "a3", "a4"], ["b1", "b2", "b3", "b4"]] {
+            for (i, from) in cluster.iter().enumerate() {
+                for to in &cluster[i + 1..] {
+                    store.add_edge(from, to, "related", "actor", serde_json::Value::Null);
+                }
+            }
+        }
+        store.add_edge("a1", "b1", "bridge", "actor", serde_json::Value::Null);
+        store.put("solo", "actor", serde_json::json!({}));
+
+        assert_eq!(
+            store.detect_communities(2),
+            [["a1", "a2", "a3", "a4"], ["b1", "b2", "b3", "b4"]]
+        );
+        assert_eq!(store.detect_communities(1).len(), 3);
+        assert!(store.detect_communities(5).is_empty());
+    }
+}
@@ -0,0 +1,249 @@
+//! Secondary indexes over fields of node data.
+//!
+//! [`CrdtStore::create_index`] maps every value found at a field path to the
+//! ids of the live nodes holding it, so [`CrdtStore::query_index`] answers an
+//! equality lookup without scanning the store.  Paths are dotted, e.g.
+//! `"address.city"`, and values compare by their canonical JSON, so `1` and
+//! `"1"` are different values.  Indexes live in memory only and are kept in
+//! step with every local write, merge, and delete.
+//!
+//! The index lock is always taken before any node entry, never while one is
+//! held, so index maintenance cannot deadlock with writers.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde_json::Value;
+
+use crate::{canonical_json, CrdtStore, NodeData, NodeId, NodeRecord};
+
+/// The value at dotted `path` in `data`, if every step is an object field.
+fn field_value<'a>(data: &'a NodeData, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(data, |value, field| value.as_object()?.get(field))
+}
+
+/// One index: field value (as canonical JSON) to node ids, plus the reverse
+/// map so a node's old entry can be dropped when it changes.
+#[derive(Debug, Default)]
+pub(crate) struct FieldIndex {
+    by_value: HashMap<String, BTreeSet<NodeId>>,
+    by_node: HashMap<NodeId, String>,
+}
+
+impl FieldIndex {
+    /// Re-file `id` under its value at `path` in `record`, or drop it if the
+    /// record is gone, deleted, or lacks the field.
+    fn update(&mut self, path: &str, id: &str, record: Option<&NodeRecord>) {
+        if let Some(old) = self.by_node.remove(id) {
+            if let Some(ids) = self.by_value.get_mut(&old) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.by_value.remove(&old);
+                }
+            }
+        }
+        let value = record
+            .filter(|record| !record.is_tombstone())
+            .and_then(|record| field_value(&record.data, path));
+        if let Some(value) = value {
+            let key = canonical_json(value);
+            self.by_value
+                .entry(key.clone())
+                .or_default()
+                .insert(id.to_owned());
+            self.by_node.insert(id.to_owned(), key);
+        }
+    }
+}
+
+impl CrdtStore {
+    /// Index the live nodes by their value at dotted `field_path` and keep
+    /// the index up to date from now on.  Returns how many nodes have the
+    /// field; creating an index that exists already only counts them.
+    pub fn create_index(&self, field_path: &str) -> usize {
+        let mut indexes = self.field_indexes.write();
+        if let Some(index) = indexes.get(field_path) {
+            return index.by_node.len();
+        }
+        let mut index = FieldIndex::default();
+        self.for_each_sync(&mut |record: &NodeRecord| {
+            index.update(field_path, &record.id, Some(record));
+            true
+        });
+        let count = index.by_node.len();
+        indexes.insert(field_path.to_owned(), index);
+        count
+    }
+
+    /// Drop the index on `field_path`, freeing its memory.  Returns `false`
+    /// if there was none.
+    pub fn drop_index(&self, field_path: &str) -> bool {
+        self.field_indexes.write().remove(field_path).is_some()
+    }
+
+    /// Whether [`create_index`](Self::create_index) was called for
+    /// `field_path` and the index has not been dropped since.
+    pub fn has_index(&self, field_path: &str) -> bool {
+        self.field_indexes.read().contains_key(field_path)
+    }
+
+    /// Live nodes whose value at dotted `field_path` equals `value`, ordered
+    /// by id.
+    ///
+    /// Without an index on `field_path` this scans every node instead.
+    pub fn query_index(&self, field_path: &str, value: &Value) -> Vec<NodeRecord> {
+        let ids: Vec<NodeId> = {
+            let indexes = self.field_indexes.read();
+            let Some(index) = indexes.get(field_path) else {
+                drop(indexes);
+                return self.scan_field(field_path, value);
+            };
+            index
+                .by_value
+                .get(&canonical_json(value))
+                .map(|ids| ids.iter().cloned().collect())
+                .unwrap_or_default()
+        };
+        ids.iter()
+            .filter_map(|id| self.get_including_tombstones(id))
+            .filter(|record| record.is_visible())
+            .collect()
+    }
+
+    fn scan_field(&self, field_path: &str, value: &Value) -> Vec<NodeRecord> {
+        let mut records = Vec::new();
+        self.for_each_sync(&mut |record: &NodeRecord| {
+            if field_value(&record.data, field_path) == Some(value) {
+                records.push(record.clone());
+            }
+            true
+        });
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        records
+    }
+
+    /// Bring every index up to date with the current state of node `id`.
+    /// Call after the write, with no node entry held.
+    pub(crate) fn reindex_node(&self, id: &str) {
+        if self.field_indexes.read().is_empty() {
+            return;
+        }
+        // Read the node under the index lock, so a concurrent writer that
+        // reindexes first cannot be overwritten with an older state
+        let mut indexes = self.field_indexes.write();
+        let record = self.get_including_tombstones(id);
+        for (path, index) in indexes.iter_mut() {
+            index.update(path, id, record.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn ids(records: Vec<NodeRecord>) -> Vec<NodeId> {
+        records.into_iter().map(|record| record.id).collect()
+    }
+
+    fn seed_people(store: &CrdtStore) {
+        store.put(
+            "alice",
+            "a",
+            json!({ "type": "Person", "address": { "city": "Oslo" } }),
+        );
+        store.put(
+            "bob",
+            "a",
+            json!({ "type": "Person", "address": { "city": "Bergen" } }),
+        );
+        store.put("note", "a", json!({ "type": "Note" }));
+    }
+
+    #[test]
+    fn create_index_covers_existing_nodes_and_nested_fields() {
+        let store = CrdtStore::default();
+        seed_people(&store);
+
+        assert_eq!(store.create_index("address.city"), 2);
+        assert_eq!(store.create_index("type"), 3);
+        assert_eq!(store.create_index("type"), 3);
+        assert!(store.has_index("address.city"));
+        assert!(!store.has_index("address"));
+
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Oslo"))),
+            ["alice"]
+        );
+        assert_eq!(
+            ids(store.query_index("type", &json!("Person"))),
+            ["alice", "bob"]
+        );
+        assert!(store.query_index("type", &json!("Post")).is_empty());
+    }
+
+    #[test]
+    fn indexes_follow_puts_merges_and_deletes() {
+        let store = CrdtStore::default();
+        seed_people(&store);
+        store.create_index("address.city");
+
+        store.put(
+            "alice",
+            "a",
+            json!({ "type": "Person", "address": { "city": "Bergen" } }),
+        );
+        store.put("carol", "a", json!({ "address": { "city": "Oslo" } }));
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Bergen"))),
+            ["alice", "bob"]
+        );
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Oslo"))),
+            ["carol"]
+        );
+
+        store.delete("bob").unwrap();
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Bergen"))),
+            ["alice"]
+        );
+
+        store.merge_record(NodeRecord::new(
+            "dave".to_owned(),
+            "peer",
+            json!({ "address": { "city": "Oslo" } }),
+        ));
+        store
+            .update_with("carol", "a", |_| json!({ "address": {} }))
+            .unwrap();
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Oslo"))),
+            ["dave"]
+        );
+    }
+
+    #[test]
+    fn query_without_an_index_scans_and_drop_index_forgets_it() {
+        let store = CrdtStore::default();
+        seed_people(&store);
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Bergen"))),
+            ["bob"]
+        );
+
+        store.create_index("address.city");
+        assert!(store.drop_index("address.city"));
+        assert!(!store.drop_index("address.city"));
+        assert!(!store.has_index("address.city"));
+
+        // Writes after the drop are still found, by scanning
+        store.put("erin", "a", json!({ "address": { "city": "Bergen" } }));
+        assert_eq!(
+            ids(store.query_index("address.city", &json!("Bergen"))),
+            ["bob", "erin"]
+        );
+    }
+}
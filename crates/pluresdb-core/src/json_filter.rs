@@ -0,0 +1,276 @@
+//! Predicates over the fields of JSON node data.
+//!
+//! A [`JsonFilter`] tests values at dotted paths such as `"address.zip"`,
+//! where each segment names an object field, or indexes an array if it is a
+//! number (`"tags.0"`).  Filters combine with [`JsonFilter::and`] and
+//! [`JsonFilter::or`], and serialize as JSON, e.g.
+//! `{"and": [{"eq": {"path": "type", "value": "person"}},
+//! {"gt": {"path": "age", "value": 30}}]}`, so they can be passed in from the
+//! CLI or an API as they are.
+//!
+//! Comparisons are type-aware: numbers compare by value whatever their
+//! representation (`1` equals `1.0`), strings compare lexicographically, and
+//! a number never equals, or orders against, a string, so `"10"` matches
+//! neither `eq 10` nor `gt 9`.  A path that is missing fails every
+//! predicate.
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+
+/// A predicate over JSON data; see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonFilter {
+    /// The value at `path` equals `value`.
+    Eq { path: String, value: Value },
+    /// `path` is present, even if its value is `null`.
+    Exists { path: String },
+    /// The value at `path` is a number greater than the number `value`, or
+    /// a string after the string `value`.
+    Gt { path: String, value: Value },
+    /// The value at `path` is a number less than the number `value`, or a
+    /// string before the string `value`.
+    Lt { path: String, value: Value },
+    /// The value at `path` is an array with an element equal to `value`, or
+    /// a string containing the string `value`.
+    Contains { path: String, value: Value },
+    /// Every filter matches; true if there are none.
+    And(Vec<JsonFilter>),
+    /// Some filter matches; false if there are none.
+    Or(Vec<JsonFilter>),
+}
+
+impl JsonFilter {
+    pub fn eq(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Eq {
+            path: path.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn exists(path: impl Into<String>) -> Self {
+        Self::Exists { path: path.into() }
+    }
+
+    pub fn gt(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Gt {
+            path: path.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn lt(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Lt {
+            path: path.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn contains(path: impl Into<String>, value: impl Into<Value>) -> Self {
+        Self::Contains {
+            path: path.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Match only where both this filter and `other` do.
+    pub fn and(self, other: JsonFilter) -> Self {
+        match self {
+            Self::And(mut filters) => {
+                filters.push(other);
+                Self::And(filters)
+            }
+            first => Self::And(vec![first, other]),
+        }
+    }
+
+    /// Match wherever this filter or `other` does.
+    pub fn or(self, other: JsonFilter) -> Self {
+        match self {
+            Self::Or(mut filters) => {
+                filters.push(other);
+                Self::Or(filters)
+            }
+            first => Self::Or(vec![first, other]),
+        }
+    }
+
+    /// Whether `data` satisfies the filter.
+    pub fn matches(&self, data: &Value) -> bool {
+        match self {
+            Self::Eq { path, value } => {
+                resolve(data, path).is_some_and(|found| equal(found, value))
+            }
+            Self::Exists { path } => resolve(data, path).is_some(),
+            Self::Gt { path, value } => resolve(data, path)
+                .is_some_and(|found| compare(found, value) == Some(Ordering::Greater)),
+            Self::Lt { path, value } => resolve(data, path)
+                .is_some_and(|found| compare(found, value) == Some(Ordering::Less)),
+            Self::Contains { path, value } => match (resolve(data, path), value) {
+                (Some(Value::Array(items)), _) => items.iter().any(|item| equal(item, value)),
+                (Some(Value::String(text)), Value::String(part)) => text.contains(part.as_str()),
+                _ => false,
+            },
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(data)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(data)),
+        }
+    }
+}
+
+/// The value at the dotted `path` in `data`; the empty path is `data`.
+fn resolve<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return Some(data);
+    }
+    path.split('.')
+        .try_fold(data, |value, segment| match value {
+            Value::Object(fields) => fields.get(segment),
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) => compare(a, b) == Some(Ordering::Equal),
+        _ => a == b,
+    }
+}
+
+/// How `a` orders against `b` if both are numbers or both are strings.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => compare_numbers(a, b),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Compares integers exactly, and anything involving a float as `f64`.
+fn compare_numbers(a: &Number, b: &Number) -> Option<Ordering> {
+    fn integer(n: &Number) -> Option<i128> {
+        n.as_i64()
+            .map(i128::from)
+            .or_else(|| n.as_u64().map(i128::from))
+    }
+    match (integer(a), integer(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CrdtStore;
+    use serde_json::json;
+
+    fn seeded() -> CrdtStore {
+        let store = CrdtStore::default();
+        let people = [
+            (
+                "ada",
+                json!({
+                    "name": "Ada",
+                    "age": 36,
+                    "address": { "zip": "02139", "city": "Cambridge" },
+                    "tags": ["math", "engines"],
+                }),
+            ),
+            (
+                "alan",
+                json!({
+                    "name": "Alan",
+                    "age": 41.0,
+                    "address": { "zip": "02139" },
+                    "tags": ["math"],
+                }),
+            ),
+            (
+                "grace",
+                json!({
+                    "name": "Grace",
+                    "age": 85,
+                    "address": { "zip": "10001", "city": null },
+                }),
+            ),
+            (
+                "linus",
+                json!({ "name": "Linus", "age": "54", "tags": ["kernels"] }),
+            ),
+        ];
+        for (id, data) in people {
+            store.put(id, "seed", data);
+        }
+        store
+    }
+
+    fn ids(store: &CrdtStore, filter: JsonFilter) -> Vec<String> {
+        let mut ids: Vec<String> = store
+            .query_json(filter)
+            .into_iter()
+            .map(|record| record.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn nested_fields_match_by_equality_and_existence() {
+        let store = seeded();
+        assert_eq!(
+            ids(&store, JsonFilter::eq("address.zip", "02139")),
+            ["ada", "alan"]
+        );
+        // A null field exists; a missing one does not
+        assert_eq!(
+            ids(&store, JsonFilter::exists("address.city")),
+            ["ada", "grace"]
+        );
+        assert_eq!(
+            ids(&store, JsonFilter::eq("tags.0", "math")),
+            ["ada", "alan"]
+        );
+        assert!(ids(&store, JsonFilter::eq("address.zip.plus4", "1")).is_empty());
+    }
+
+    #[test]
+    fn ranges_compare_numbers_by_value_and_never_against_strings() {
+        let store = seeded();
+        // 41.0 is in range; Linus's age is the string "54", so it is not
+        assert_eq!(
+            ids(
+                &store,
+                JsonFilter::gt("age", 40).and(JsonFilter::lt("age", 90))
+            ),
+            ["alan", "grace"]
+        );
+        assert_eq!(ids(&store, JsonFilter::eq("age", 41)), ["alan"]);
+        assert_eq!(ids(&store, JsonFilter::eq("age", "54")), ["linus"]);
+        assert_eq!(ids(&store, JsonFilter::gt("name", "G")), ["grace", "linus"]);
+    }
+
+    #[test]
+    fn combined_predicates_and_contains() {
+        let store = seeded();
+        let filter = JsonFilter::contains("tags", "math")
+            .and(JsonFilter::gt("age", 40))
+            .or(JsonFilter::contains("name", "inu"));
+        assert_eq!(ids(&store, filter.clone()), ["alan", "linus"]);
+
+        // Filters survive a round trip through their JSON form
+        let parsed: JsonFilter =
+            serde_json::from_value(serde_json::to_value(&filter).unwrap()).unwrap();
+        assert_eq!(parsed, filter);
+        let from_text: JsonFilter = serde_json::from_str(
+            r#"{"or": [{"eq": {"path": "name", "value": "Ada"}}, {"exists": {"path": "address.city"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(ids(&store, from_text), ["ada", "grace"]);
+
+        assert_eq!(ids(&store, JsonFilter::And(vec![])).len(), 4);
+        assert!(ids(&store, JsonFilter::Or(vec![])).is_empty());
+    }
+}
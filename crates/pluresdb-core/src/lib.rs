@@ -4,21 +4,104 @@
 //! foundation that can be reused across the native CLI, the Node addon, and
 //! any future host integrations.
 
+pub mod actor;
+pub use actor::{ActorIdExt, ACTOR_ID_FILE};
+pub mod audit;
+pub use audit::{AuditEvent, AuditOp, AuditSink, FileAuditSink, MemoryAuditSink};
+pub mod backup;
+pub mod conflict;
+pub use conflict::{ConflictResolver, ConflictStrategy};
+pub mod crdt;
+pub use crdt::{CrdtValue, GCounter, OrSet, PNCounter};
+pub mod clock;
+pub use clock::ClockOrdering;
+
+mod graph;
+pub use graph::{Direction, TraverseOpts};
+
+mod index;
+
+pub mod json_filter;
+pub use json_filter::JsonFilter;
+
+mod text_search;
+mod transaction;
+
+pub mod schema;
+pub use schema::{TypeRegistry, ValidationError};
+
+pub mod store_snapshot;
+pub use store_snapshot::StoreSnapshot;
+
+pub mod vector;
+use vector::ActiveVectorIndex;
+#[cfg(not(feature = "native"))]
+pub use vector::BruteForceVectorIndex;
+#[cfg(feature = "native")]
+pub use vector::VectorIndex;
+pub use vector::{DistanceMetric, VectorError};
+
 pub mod plugin;
 pub use plugin::{NoOpPlugin, PluresLmPlugin};
 
+pub mod embed_cache;
+pub use embed_cache::{CachedEmbedder, EmbeddingCacheStats};
+pub mod embed_retry;
+pub use embed_retry::{EmbeddingConfig, RetryingEmbedder, TransientEmbedError};
+
 /// Higher-level document, training, and AI-agent procedures built on top of
 /// the core CRDT store.  See [`procedures::document`], [`procedures::training`],
 /// and [`procedures::ai_procedures`] for the individual sub-modules.
 pub mod procedures;
 
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "native")]
+pub mod maintenance;
+#[cfg(feature = "native")]
+pub mod replay;
+#[cfg(feature = "native")]
+pub mod snapshot;
+#[cfg(feature = "native")]
+pub use maintenance::{MaintenanceReport, MaintenanceScheduler};
+#[cfg(feature = "native")]
+mod watch;
+#[cfg(feature = "native")]
+pub use watch::NodeWatch;
+
+#[cfg(feature = "sqlite-compat")]
+mod query_cache;
+#[cfg(feature = "sqlite-compat")]
+use query_cache::QueryCache;
+#[cfg(feature = "sqlite-compat")]
+pub use query_cache::QueryCacheStats;
+
+#[cfg(feature = "sqlite-compat")]
+pub mod blob;
+#[cfg(feature = "sqlite-compat")]
+pub use blob::BlobHandle;
+
+#[cfg(feature = "sqlite-compat")]
+pub mod migrate;
+#[cfg(feature = "sqlite-compat")]
+pub use migrate::{run_migrations, Migration, MigrationReport, MIGRATIONS};
+
+#[cfg(feature = "sqlite-compat")]
+mod pool;
+#[cfg(feature = "sqlite-compat")]
+pub use pool::{DatabasePool, PooledDatabase, DEFAULT_POOL_TIMEOUT};
+
+#[cfg(feature = "sqlite-compat")]
+mod sqlite_storage;
+#[cfg(feature = "sqlite-compat")]
+pub use sqlite_storage::SqliteStorage;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
-#[cfg(feature = "native")]
+#[cfg(feature = "sqlite-compat")]
 use parking_lot::Mutex;
 #[cfg(feature = "native")]
 use pluresdb_storage::StorageEngine;
@@ -30,14 +113,12 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value as JsonValue;
 use thiserror::Error;
-#[cfg(feature = "native")]
+#[cfg(feature = "sqlite-compat")]
 use tracing::debug;
 use uuid::Uuid;
 
 #[cfg(feature = "native")]
 use futures::executor::block_on;
-#[cfg(feature = "native")]
-use hnsw_rs::prelude::*;
 
 #[cfg(feature = "sqlite-compat")]
 use rusqlite::types::{Value as SqliteValue, ValueRef};
@@ -45,7 +126,6 @@ use rusqlite::types::{Value as SqliteValue, ValueRef};
 use rusqlite::{params_from_iter, Connection, OpenFlags, Transaction};
 #[cfg(feature = "sqlite-compat")]
 use std::path::PathBuf;
-#[cfg(feature = "sqlite-compat")]
 use std::time::Duration;
 
 /// Unique identifier for a stored node.
@@ -98,6 +178,36 @@ pub struct EmbeddingWorkerStats {
     pub dropped_tasks: usize,
 }
 
+/// Cumulative operation counts for a [`CrdtStore`], from
+/// [`CrdtStore::metrics`].
+///
+/// Counts start at zero when the store is created and only cover calls made
+/// through it, not writes merged in from peers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreMetrics {
+    /// Local writes: `put` and every method built on it, plus
+    /// `put_with_embedding`.
+    pub puts: u64,
+    /// `get` calls that returned a live node.
+    pub get_hits: u64,
+    /// `get` calls for a node that is missing, deleted, or expired.
+    pub get_misses: u64,
+    /// Successful `delete` calls.
+    pub deletes: u64,
+    /// Live nodes at the time of the snapshot, as [`CrdtStore::len`].
+    pub nodes: usize,
+}
+
+/// The atomics behind [`StoreMetrics`], bumped with relaxed ordering since
+/// they are only ever read as a snapshot.
+#[derive(Debug, Default)]
+struct StoreCounters {
+    puts: AtomicU64,
+    get_hits: AtomicU64,
+    get_misses: AtomicU64,
+    deletes: AtomicU64,
+}
+
 /// A search result from vector similarity search.
 ///
 /// # Score semantics (read this before comparing to a raw cosine threshold)
@@ -141,6 +251,15 @@ pub struct NodeRecord {
     pub embedding: Option<Vec<f32>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub quality_score: Option<f32>,
+    /// When the node was deleted.  A tombstone keeps the clock it was deleted
+    /// at, so writes it has already seen cannot resurrect the node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// When the node stops being visible, set by
+    /// [`CrdtStore::put_with_ttl`].  It is an absolute instant carried with
+    /// the write, so every replica expires the node at the same moment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 impl NodeRecord {
@@ -155,202 +274,165 @@ impl NodeRecord {
             timestamp: Utc::now(),
             embedding: None,
             quality_score: None,
+            deleted_at: None,
+            expires_at: None,
         }
     }
 
+    pub fn is_tombstone(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Whether the node's time to live has run out.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    /// Neither deleted nor expired, so reads should return it.
+    fn is_visible(&self) -> bool {
+        !self.is_tombstone() && !self.is_expired()
+    }
+
+    /// Turn this record into a tombstone, keeping its id and clock.
+    fn mark_deleted(&mut self) {
+        let now = Utc::now();
+        self.data = JsonValue::Null;
+        self.embedding = None;
+        self.quality_score = None;
+        self.timestamp = now;
+        self.deleted_at = Some(now);
+        self.expires_at = None;
+    }
+
+    /// Apply a local write by `actor`.
+    ///
+    /// The writer has seen the stored clock, so bumping its counter yields a
+    /// clock that dominates the stored one and the new data always wins.
+    /// Records written elsewhere must go through
+    /// [`merge_record`](Self::merge_record) instead.
     pub fn merge_update(&mut self, actor: impl Into<ActorId>, data: NodeData) {
         let actor = actor.into();
         let counter = self.clock.entry(actor).or_insert(0);
         *counter += 1;
         self.timestamp = Utc::now();
         self.data = data;
+        self.deleted_at = None;
+        self.expires_at = None;
     }
-}
-
-// ---------------------------------------------------------------------------
-// Vector Index — HNSW (native) or BruteForce (WASM)
-// ---------------------------------------------------------------------------
-
-#[cfg(feature = "native")]
-pub struct VectorIndex {
-    hnsw: Hnsw<'static, f32, DistCosine>,
-    id_to_idx: DashMap<NodeId, usize>,
-    idx_to_id: DashMap<usize, NodeId>,
-    next_idx: Mutex<usize>,
-    max_elements: usize,
-}
-
-#[cfg(feature = "native")]
-const _: fn() = || {
-    fn assert_send_sync<T: Send + Sync>() {}
-    assert_send_sync::<VectorIndex>();
-};
-
-#[cfg(feature = "native")]
-impl std::fmt::Debug for VectorIndex {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("VectorIndex")
-            .field("indexed_nodes", &self.id_to_idx.len())
-            .finish()
-    }
-}
 
-#[cfg(feature = "native")]
-impl VectorIndex {
-    pub fn new(max_elements: usize) -> Self {
-        Self {
-            hnsw: Hnsw::new(16, max_elements, 16, 200, DistCosine),
-            id_to_idx: DashMap::new(),
-            idx_to_id: DashMap::new(),
-            next_idx: Mutex::new(0),
-            max_elements,
-        }
-    }
-
-    pub fn insert(&self, id: &str, embedding: &[f32]) {
-        let idx = {
-            let mut n = self.next_idx.lock();
-            let current = *n;
-            if current >= self.max_elements {
-                debug!(
-                    "VectorIndex at capacity ({} slots); insert for '{}' dropped",
-                    self.max_elements, id
-                );
-                return;
+    /// Merge a record for the same node written by another replica.
+    ///
+    /// If `incoming`'s clock dominates, it replaces this record; if this
+    /// record's clock already covers it, nothing changes.  Concurrent writes
+    /// are resolved last-writer-wins on `timestamp`, with ties broken by the
+    /// greatest actor that wrote on one side but not the other, so every
+    /// replica picks the same winner.  Either way the clocks are merged.
+    ///
+    /// A tombstone with the same clock as a live record was deleted after
+    /// seeing exactly that write, so it replaces the live record.
+    pub fn merge_record(&mut self, incoming: NodeRecord) -> MergeOutcome {
+        self.merge_record_with(incoming, &ConflictStrategy::LastWriteWins)
+    }
+
+    /// [`merge_record`](Self::merge_record), but let `strategy` compute the
+    /// data when the writes are concurrent.  The clocks and the winner's
+    /// timestamp are kept as with last-writer-wins.
+    pub fn merge_record_with(
+        &mut self,
+        incoming: NodeRecord,
+        strategy: &ConflictStrategy,
+    ) -> MergeOutcome {
+        match clock::compare(&self.clock, &incoming.clock) {
+            ClockOrdering::Equal if incoming.is_tombstone() && !self.is_tombstone() => {
+                *self = incoming;
+                return MergeOutcome::Applied;
             }
-            if current + 1 >= (self.max_elements as f64 * 0.9) as usize {
-                debug!(
-                    "VectorIndex nearing capacity: {} / {} slots used",
-                    current + 1,
-                    self.max_elements
-                );
+            ClockOrdering::Equal | ClockOrdering::Dominates => return MergeOutcome::Ignored,
+            ClockOrdering::DominatedBy => {
+                *self = incoming;
+                return MergeOutcome::Applied;
             }
-            *n += 1;
-            current
-        };
-        self.id_to_idx.insert(id.to_string(), idx);
-        self.idx_to_id.insert(idx, id.to_string());
-        let emb_owned: Vec<f32> = embedding.to_vec();
-        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            self.hnsw.insert((&emb_owned, idx));
-        }))
-        .is_err()
-        {
-            eprintln!("[VectorIndex] HNSW insert panicked for '{}'; skipping", id);
-            self.id_to_idx.remove(id);
-            self.idx_to_id.remove(&idx);
-        }
-    }
-
-    pub fn search(&self, query: &[f32], limit: usize) -> Vec<(NodeId, f32)> {
-        if self.id_to_idx.is_empty() {
-            return Vec::new();
+            ClockOrdering::Concurrent => {}
         }
-        let neighbours = self.hnsw.search(query, limit, 16);
-        neighbours
-            .into_iter()
-            .filter_map(|n| {
-                let node_id = self.idx_to_id.get(&n.d_id)?.clone();
-                let current_idx = self.id_to_idx.get(&*node_id)?;
-                if *current_idx != n.d_id {
-                    return None;
-                }
-                let score = (1.0_f32 - n.distance).max(0.0);
-                Some((node_id.clone(), score))
-            })
-            .collect()
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.id_to_idx.is_empty()
-    }
-}
-
-#[cfg(feature = "native")]
-impl Default for VectorIndex {
-    fn default() -> Self {
-        Self::new(1_024)
-    }
-}
-
-// ---------------------------------------------------------------------------
-// BruteForceVectorIndex — WASM-safe fallback
-// ---------------------------------------------------------------------------
 
-#[cfg(not(feature = "native"))]
-#[derive(Debug)]
-pub struct BruteForceVectorIndex {
-    embeddings: DashMap<NodeId, Vec<f32>>,
-}
-
-#[cfg(not(feature = "native"))]
-impl BruteForceVectorIndex {
-    pub fn new(_max_elements: usize) -> Self {
-        Self {
-            embeddings: DashMap::new(),
+        let incoming_wins = (
+            incoming.timestamp,
+            greatest_unseen_actor(&incoming.clock, &self.clock),
+        ) > (
+            self.timestamp,
+            greatest_unseen_actor(&self.clock, &incoming.clock),
+        );
+        let merged = clock::merge(&self.clock, &incoming.clock);
+        let resolved = if incoming_wins {
+            strategy.resolve(&incoming, self)
+        } else {
+            strategy.resolve(self, &incoming)
+        };
+        if incoming_wins {
+            *self = incoming;
         }
-    }
-
-    pub fn insert(&self, id: &str, embedding: &[f32]) {
-        self.embeddings.insert(id.to_string(), embedding.to_vec());
-    }
-
-    pub fn search(&self, query: &[f32], limit: usize) -> Vec<(NodeId, f32)> {
-        let query_norm = vec_norm(query);
-        if query_norm == 0.0 {
-            return Vec::new();
+        if let Some(data) = resolved {
+            self.data = data;
         }
-        let mut results: Vec<(NodeId, f32)> = self
-            .embeddings
-            .iter()
-            .filter_map(|entry| {
-                let emb = entry.value();
-                let emb_norm = vec_norm(emb);
-                if emb_norm == 0.0 {
-                    return None;
-                }
-                let dot: f32 = query.iter().zip(emb.iter()).map(|(a, b)| a * b).sum();
-                let score = (dot / (query_norm * emb_norm)).max(0.0);
-                Some((entry.key().clone(), score))
-            })
-            .collect();
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        results.truncate(limit);
-        results
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.embeddings.is_empty()
+        self.clock = merged;
+        MergeOutcome::Conflict
     }
 }
 
-#[cfg(not(feature = "native"))]
-impl Default for BruteForceVectorIndex {
-    fn default() -> Self {
-        Self::new(1_024)
-    }
+/// Result of [`NodeRecord::merge_record`] and [`CrdtStore::merge_record`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The incoming record's clock dominates; it replaced the local one.
+    Applied,
+    /// The local record had already seen every write in the incoming one.
+    Ignored,
+    /// The writes were concurrent; the last writer won, or the node's
+    /// [`ConflictStrategy`] merged them, and clocks were merged.
+    Conflict,
 }
 
-#[cfg(not(feature = "native"))]
-fn vec_norm(v: &[f32]) -> f32 {
-    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+/// The greatest actor with writes in `a` that `b` has not seen.
+fn greatest_unseen_actor<'a>(a: &'a VectorClock, b: &VectorClock) -> Option<&'a ActorId> {
+    a.iter()
+        .filter(|&(actor, &counter)| counter > b.get(actor).copied().unwrap_or(0))
+        .map(|(actor, _)| actor)
+        .max()
 }
 
-// ---------------------------------------------------------------------------
-// Unified type alias for the vector index
-// ---------------------------------------------------------------------------
-
-#[cfg(feature = "native")]
-type ActiveVectorIndex = VectorIndex;
-
-#[cfg(not(feature = "native"))]
-type ActiveVectorIndex = BruteForceVectorIndex;
-
 /// Errors that can be produced by the CRDT store.
 #[derive(Debug, Error)]
 pub enum StoreError {
     #[error("node not found: {0}")]
     NotFound(NodeId),
+    #[error(
+        "node {id} does not match its type schema: {}",
+        errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    Validation {
+        id: NodeId,
+        errors: Vec<ValidationError>,
+    },
+    /// A [`CrdtStore::compare_and_swap`] found the node written since its
+    /// expected clock was read.
+    #[error("node {id} was modified concurrently: expected clock {expected:?}, found {actual:?}")]
+    ClockMismatch {
+        id: NodeId,
+        expected: VectorClock,
+        actual: VectorClock,
+    },
+    /// A checked write's data serializes to more than the store's
+    /// [`max_node_size`](CrdtStore::max_node_size).
+    #[error("node data is {size} bytes, over the {max}-byte limit")]
+    NodeTooLarge { size: usize, max: usize },
+    /// A checked write's data nests deeper than the store's
+    /// [`max_node_depth`](CrdtStore::max_node_depth).
+    #[error("node data is nested {depth} levels deep, over the limit of {max}")]
+    TooDeep { depth: usize, max: usize },
+    /// An argument to a store method was outside its documented range.
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error(transparent)]
+    Vector(#[from] VectorError),
 }
 
 /// Stable, documented error codes emitted by `pluresdb-core`.
@@ -361,6 +443,13 @@ pub enum CoreErrorCode {
     InvalidInput,
     SerializationError,
     FeatureDisabled,
+    PoolTimeout,
+    ClockMismatch,
+    DimensionMismatch,
+    CapacityExceeded,
+    DatabaseBusy,
+    NodeTooLarge,
+    NodeTooDeep,
 }
 
 impl CoreErrorCode {
@@ -371,6 +460,13 @@ impl CoreErrorCode {
             Self::InvalidInput => "CORE_INVALID_INPUT",
             Self::SerializationError => "CORE_SERIALIZATION_ERROR",
             Self::FeatureDisabled => "CORE_FEATURE_DISABLED",
+            Self::PoolTimeout => "CORE_POOL_TIMEOUT",
+            Self::ClockMismatch => "CORE_CLOCK_MISMATCH",
+            Self::DimensionMismatch => "CORE_DIMENSION_MISMATCH",
+            Self::CapacityExceeded => "CORE_CAPACITY_EXCEEDED",
+            Self::DatabaseBusy => "CORE_DATABASE_BUSY",
+            Self::NodeTooLarge => "CORE_NODE_TOO_LARGE",
+            Self::NodeTooDeep => "CORE_NODE_TOO_DEEP",
         }
     }
 }
@@ -381,12 +477,83 @@ impl std::fmt::Display for CoreErrorCode {
     }
 }
 
+impl CoreErrorCode {
+    pub const fn kind(self) -> ErrorKind {
+        match self {
+            Self::NodeNotFound => ErrorKind::NotFound,
+            Self::SqliteError => ErrorKind::Internal,
+            Self::InvalidInput => ErrorKind::InvalidInput,
+            Self::SerializationError => ErrorKind::Internal,
+            Self::FeatureDisabled => ErrorKind::Unsupported,
+            Self::PoolTimeout => ErrorKind::Busy,
+            Self::ClockMismatch => ErrorKind::Constraint,
+            Self::DimensionMismatch => ErrorKind::InvalidInput,
+            Self::CapacityExceeded => ErrorKind::Constraint,
+            Self::DatabaseBusy => ErrorKind::Busy,
+            Self::NodeTooLarge => ErrorKind::InvalidInput,
+            Self::NodeTooDeep => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+/// Coarse error category that host bindings surface to callers as `err.code`.
+///
+/// [`CoreErrorCode`] pinpoints where an error came from; `ErrorKind` says what
+/// the caller can do about it, so JavaScript can branch on "not found" versus
+/// "constraint violated" without parsing messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorKind {
+    NotFound,
+    AccessDenied,
+    Constraint,
+    Busy,
+    InvalidInput,
+    Unsupported,
+    Internal,
+}
+
+impl ErrorKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NotFound => "NOT_FOUND",
+            Self::AccessDenied => "ACCESS_DENIED",
+            Self::Constraint => "CONSTRAINT",
+            Self::Busy => "BUSY",
+            Self::InvalidInput => "INVALID_INPUT",
+            Self::Unsupported => "UNSUPPORTED",
+            Self::Internal => "INTERNAL",
+        }
+    }
+}
+
+impl AsRef<str> for ErrorKind {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl StoreError {
     pub const fn code(&self) -> CoreErrorCode {
         match self {
             Self::NotFound(_) => CoreErrorCode::NodeNotFound,
+            Self::Validation { .. } => CoreErrorCode::InvalidInput,
+            Self::ClockMismatch { .. } => CoreErrorCode::ClockMismatch,
+            Self::NodeTooLarge { .. } => CoreErrorCode::NodeTooLarge,
+            Self::TooDeep { .. } => CoreErrorCode::NodeTooDeep,
+            Self::InvalidArgument(_) => CoreErrorCode::InvalidInput,
+            Self::Vector(err) => err.code(),
         }
     }
+
+    pub const fn kind(&self) -> ErrorKind {
+        self.code().kind()
+    }
 }
 
 /// CRDT operations that clients may apply to the store.
@@ -400,14 +567,252 @@ pub enum CrdtOperation {
     Delete {
         id: NodeId,
     },
+    /// Several puts by one actor, shipped and applied as a single operation.
+    Batch {
+        actor: ActorId,
+        items: Vec<(NodeId, NodeData)>,
+    },
+}
+
+impl CrdtOperation {
+    /// The single-node operations this stands for: a batch expands into one
+    /// put per item, anything else is returned as is.
+    fn expand(&self) -> Vec<CrdtOperation> {
+        match self {
+            CrdtOperation::Batch { actor, items } => items
+                .iter()
+                .map(|(id, data)| CrdtOperation::Put {
+                    id: id.clone(),
+                    actor: actor.clone(),
+                    data: data.clone(),
+                })
+                .collect(),
+            op => vec![op.clone()],
+        }
+    }
+}
+
+/// What applying a remote operation does to the local store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictOutcome {
+    /// The remote operation supersedes (or creates) the local node.
+    Applied,
+    /// The local node already reflects everything the operation has seen.
+    Ignored,
+    /// Local and remote writes are concurrent and get merged.
+    Concurrent,
+}
+
+/// Per-operation result of [`CrdtStore::preview_remote_batch`] and
+/// [`CrdtStore::apply_remote_batch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictPreview {
+    pub id: NodeId,
+    pub outcome: ConflictOutcome,
+}
+
+/// Flatten batches in `ops` into single-node operations sharing the batch's
+/// clock and timestamp.
+fn expand_ops(
+    ops: &[(CrdtOperation, VectorClock, DateTime<Utc>)],
+) -> Vec<(CrdtOperation, &VectorClock, DateTime<Utc>)> {
+    ops.iter()
+        .flat_map(|(op, clock, timestamp)| {
+            op.expand()
+                .into_iter()
+                .map(move |op| (op, clock, *timestamp))
+        })
+        .collect()
+}
+
+fn op_id(op: &CrdtOperation) -> &NodeId {
+    match op {
+        CrdtOperation::Put { id, .. } | CrdtOperation::Delete { id } => id,
+        CrdtOperation::Batch { .. } => unreachable!("batches are expanded before lookup"),
+    }
+}
+
+fn classify_remote_op(
+    local: Option<&VectorClock>,
+    op: &CrdtOperation,
+    remote: &VectorClock,
+) -> ConflictOutcome {
+    match (op, local) {
+        (CrdtOperation::Put { .. } | CrdtOperation::Batch { .. }, None) => ConflictOutcome::Applied,
+        (CrdtOperation::Delete { .. }, None) => ConflictOutcome::Ignored,
+        (_, Some(local)) => match clock::compare(local, remote) {
+            ClockOrdering::Equal | ClockOrdering::Dominates => ConflictOutcome::Ignored,
+            ClockOrdering::DominatedBy => ConflictOutcome::Applied,
+            ClockOrdering::Concurrent => ConflictOutcome::Concurrent,
+        },
+    }
+}
+
+/// A JSON Merge Patch ([RFC 7386]) applied to a node's data by
+/// [`CrdtStore::patch`].
+///
+/// Object members in the patch replace the corresponding members of the
+/// target, `null` members remove them, and nested objects are merged
+/// recursively. Any non-object patch replaces the target wholesale.
+///
+/// [RFC 7386]: https://www.rfc-editor.org/rfc/rfc7386
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct JsonPatch(pub JsonValue);
+
+impl JsonPatch {
+    pub fn merge(patch: JsonValue) -> Self {
+        Self(patch)
+    }
+
+    /// Apply this patch to `target` in place.
+    pub fn apply_to(&self, target: &mut JsonValue) {
+        merge_patch(target, &self.0);
+    }
+}
+
+fn merge_patch(target: &mut JsonValue, patch: &JsonValue) {
+    let JsonValue::Object(patch_map) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = JsonValue::Object(serde_json::Map::new());
+    }
+    if let JsonValue::Object(target_map) = target {
+        for (key, value) in patch_map {
+            if value.is_null() {
+                target_map.remove(key);
+            } else {
+                merge_patch(
+                    target_map.entry(key.clone()).or_insert(JsonValue::Null),
+                    value,
+                );
+            }
+        }
+    }
+}
+
+/// How a node id is derived when the caller does not supply one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// A random UUIDv4, as used by [`CrdtStore::operation_for`].
+    #[default]
+    Random,
+    /// The hex BLAKE3 hash of the data's canonical JSON (object keys sorted,
+    /// no whitespace), so identical content always maps to the same id.
+    ContentHash,
+}
+
+impl IdStrategy {
+    pub fn derive_id(self, data: &NodeData) -> NodeId {
+        match self {
+            Self::Random => Uuid::new_v4().to_string(),
+            Self::ContentHash => blake3::hash(canonical_json(data).as_bytes())
+                .to_hex()
+                .to_string(),
+        }
+    }
+}
+
+/// Serialize `value` with object keys sorted recursively and no whitespace.
+///
+/// Semantically equal values always produce the same string, regardless of
+/// key insertion order or whether `serde_json` was built with
+/// `preserve_order`, so the output is suitable for hashing and comparison.
+pub fn canonical_json(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_canonical_json(value, &mut out);
+    out
+}
+
+fn write_canonical_json(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&JsonValue::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical_json(value, out);
+            }
+            out.push('}');
+        }
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// How deeply `value` nests arrays and objects: `0` for a scalar, `1` for
+/// `{}` or `[1, 2]`, `2` for `{"a": []}`, and so on.
+///
+/// Walks the value with an explicit stack rather than recursion, so even
+/// values nested far deeper than any limit are measured without risk to
+/// the call stack.
+pub fn json_depth(value: &JsonValue) -> usize {
+    let mut deepest = 0;
+    let mut pending = vec![(value, 0)];
+    while let Some((value, depth)) = pending.pop() {
+        let children: Box<dyn Iterator<Item = &JsonValue>> = match value {
+            JsonValue::Object(fields) => Box::new(fields.values()),
+            JsonValue::Array(items) => Box::new(items.iter()),
+            _ => continue,
+        };
+        deepest = deepest.max(depth + 1);
+        pending.extend(children.map(|child| (child, depth + 1)));
+    }
+    deepest
+}
+
+/// Length of `value` serialized as compact JSON, counted without building
+/// the string.
+fn serialized_len(value: &JsonValue) -> usize {
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, value).expect("JSON values always serialize");
+    counter.0
 }
 
+/// Bucket [`CrdtStore::type_histogram`] counts nodes without a string `type` under.
+pub const UNTYPED_BUCKET: &str = "untyped";
+
 /// A simple conflict-free replicated data store backed by a concurrent map.
 pub struct CrdtStore {
     nodes: DashMap<NodeId, NodeRecord>,
     vector_index: parking_lot::RwLock<Arc<ActiveVectorIndex>>,
     embedder: Option<Arc<dyn EmbedText>>,
     lm_plugin: Option<Arc<dyn PluresLmPlugin>>,
+    type_registry: Option<Arc<TypeRegistry>>,
+    audit: Option<Arc<dyn AuditSink>>,
+    max_node_size: Option<usize>,
+    max_node_depth: Option<usize>,
+    conflict_strategies: conflict::ConflictStrategies,
+    field_indexes: parking_lot::RwLock<HashMap<String, index::FieldIndex>>,
     #[cfg(feature = "native")]
     persistence: Option<Arc<dyn StorageEngine>>,
     #[cfg(not(feature = "native"))]
@@ -420,6 +825,9 @@ pub struct CrdtStore {
     embedding_queue_depth: AtomicUsize,
     embedding_last_processed: parking_lot::Mutex<Option<DateTime<Utc>>>,
     embedding_dropped: AtomicUsize,
+    counters: StoreCounters,
+    #[cfg(feature = "native")]
+    watchers: watch::Watchers,
 }
 
 impl std::fmt::Debug for CrdtStore {
@@ -429,6 +837,11 @@ impl std::fmt::Debug for CrdtStore {
             .field("vector_index", &*self.vector_index.read())
             .field("embedder", &self.embedder.is_some())
             .field("lm_plugin", &self.lm_plugin.as_ref().map(|p| p.plugin_id()))
+            .field("type_registry", &self.type_registry)
+            .field("audit", &self.audit)
+            .field("max_node_size", &self.max_node_size)
+            .field("max_node_depth", &self.max_node_depth)
+            .field("field_indexes", &self.field_indexes.read().len())
             .finish()
     }
 }
@@ -440,6 +853,12 @@ impl Default for CrdtStore {
             vector_index: parking_lot::RwLock::new(Arc::new(ActiveVectorIndex::default())),
             embedder: None,
             lm_plugin: None,
+            type_registry: None,
+            audit: None,
+            max_node_size: None,
+            max_node_depth: None,
+            conflict_strategies: conflict::ConflictStrategies::default(),
+            field_indexes: parking_lot::RwLock::new(HashMap::new()),
             persistence: None,
             vector_index_ready: AtomicBool::new(true),
             #[cfg(feature = "native")]
@@ -449,6 +868,9 @@ impl Default for CrdtStore {
             embedding_queue_depth: AtomicUsize::new(0),
             embedding_last_processed: parking_lot::Mutex::new(None),
             embedding_dropped: AtomicUsize::new(0),
+            counters: StoreCounters::default(),
+            #[cfg(feature = "native")]
+            watchers: watch::Watchers::default(),
         }
     }
 }
@@ -579,26 +1001,163 @@ impl CrdtStore {
         self
     }
 
-    pub fn lm_plugin_id(&self) -> Option<&str> {
-        self.lm_plugin.as_ref().map(|p| p.plugin_id())
+    /// Check typed writes made through [`try_put`](Self::try_put) against
+    /// `registry`, and keep it in step with the type definitions this store
+    /// writes, merges, and deletes.
+    pub fn with_type_registry(mut self, registry: Arc<TypeRegistry>) -> Self {
+        self.type_registry = Some(registry);
+        self
     }
 
-    pub fn build_vector_index(&self) -> usize {
-        let expected_dim = self.embedder.as_ref().map(|e| e.dimension());
-        let mut indexed = 0usize;
-        for entry in self.nodes.iter() {
-            if let Some(emb) = &entry.value().embedding {
-                if let Some(dim) = expected_dim {
-                    if emb.len() != dim {
-                        continue;
-                    }
-                }
-                if emb.is_empty()
-                    || !emb.iter().all(|v| v.is_finite())
-                    || !emb.iter().any(|v| *v != 0.0)
-                {
-                    continue;
-                }
+    pub fn type_registry(&self) -> Option<&TypeRegistry> {
+        self.type_registry.as_deref()
+    }
+
+    /// Report every local put and delete to `sink` as it happens.
+    pub fn with_audit(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = Some(sink);
+        self
+    }
+
+    /// Reject checked writes whose data serializes to more than `bytes` of
+    /// JSON with [`StoreError::NodeTooLarge`].
+    ///
+    /// [`try_put`](Self::try_put), [`try_put_with_embedding`](Self::try_put_with_embedding),
+    /// [`apply`](Self::apply) and transactions are checked; [`put`](Self::put)
+    /// and merges from peers are not, so replicas with different limits
+    /// still converge.  Without a limit, which is the default, any size is
+    /// accepted.
+    pub fn with_max_node_size(mut self, bytes: usize) -> Self {
+        self.max_node_size = Some(bytes);
+        self
+    }
+
+    pub fn max_node_size(&self) -> Option<usize> {
+        self.max_node_size
+    }
+
+    /// Reject checked writes whose data nests arrays and objects more than
+    /// `depth` levels deep, as measured by [`json_depth`], with
+    /// [`StoreError::TooDeep`].
+    ///
+    /// The same writes are checked as for
+    /// [`with_max_node_size`](Self::with_max_node_size), and the depth is
+    /// checked first, so an overly deep value is never serialized.  Without
+    /// a limit, which is the default, any depth is accepted.
+    pub fn with_max_node_depth(mut self, depth: usize) -> Self {
+        self.max_node_depth = Some(depth);
+        self
+    }
+
+    pub fn max_node_depth(&self) -> Option<usize> {
+        self.max_node_depth
+    }
+
+    /// Check `data` against the depth and size limits, if there are any.
+    pub(crate) fn check_limits(&self, data: &NodeData) -> Result<(), StoreError> {
+        if let Some(max) = self.max_node_depth {
+            let depth = json_depth(data);
+            if depth > max {
+                return Err(StoreError::TooDeep { depth, max });
+            }
+        }
+        if let Some(max) = self.max_node_size {
+            let size = serialized_len(data);
+            if size > max {
+                return Err(StoreError::NodeTooLarge { size, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand `sink` an event for a write to `id` that moved its clock from
+    /// `before_clock` to `after_clock`.
+    fn record_audit(
+        &self,
+        op: AuditOp,
+        id: &NodeId,
+        actor: Option<ActorId>,
+        before_clock: Option<VectorClock>,
+        after_clock: VectorClock,
+    ) {
+        if let Some(sink) = &self.audit {
+            sink.record(AuditEvent {
+                id: id.clone(),
+                actor,
+                op,
+                before_clock,
+                after_clock,
+                timestamp: Utc::now(),
+            });
+        }
+    }
+
+    /// Without the `native` feature there is no [`watch`](Self::watch).
+    #[cfg(not(feature = "native"))]
+    fn notify_watchers(&self, _id: &str) {}
+
+    /// Index only embeddings of `dimension`; without this the first
+    /// embedding indexed fixes the dimension.
+    pub fn with_vector_dimension(self, dimension: usize) -> Self {
+        let index = self.empty_vector_index(1_024).with_dimension(dimension);
+        *self.vector_index.write() = Arc::new(index);
+        self
+    }
+
+    /// Compare embeddings by `metric` when indexing and searching, scaling
+    /// every embedding and query to unit length first if `auto_normalize` is
+    /// set.  Scores and thresholds then follow that metric (see
+    /// [`DistanceMetric`]); the default is unnormalized cosine similarity.
+    pub fn with_vector_metric(self, metric: DistanceMetric, auto_normalize: bool) -> Self {
+        let mut index = ActiveVectorIndex::default()
+            .with_metric(metric)
+            .with_auto_normalize(auto_normalize);
+        if let Some(dimension) = self.vector_dimension() {
+            index = index.with_dimension(dimension);
+        }
+        *self.vector_index.write() = Arc::new(index);
+        self
+    }
+
+    /// Length of the embeddings the vector index holds, once known.
+    pub fn vector_dimension(&self) -> Option<usize> {
+        self.vector_index.read().dimension()
+    }
+
+    /// The metric the vector index compares embeddings by.
+    pub fn vector_metric(&self) -> DistanceMetric {
+        self.vector_index.read().metric()
+    }
+
+    /// An empty vector index of `capacity` that compares embeddings the way
+    /// the current one does.
+    fn empty_vector_index(&self, capacity: usize) -> ActiveVectorIndex {
+        let current = self.vector_index.read();
+        ActiveVectorIndex::new(capacity)
+            .with_metric(current.metric())
+            .with_auto_normalize(current.auto_normalize())
+    }
+
+    pub fn lm_plugin_id(&self) -> Option<&str> {
+        self.lm_plugin.as_ref().map(|p| p.plugin_id())
+    }
+
+    pub fn build_vector_index(&self) -> usize {
+        let expected_dim = self.embedder.as_ref().map(|e| e.dimension());
+        let mut indexed = 0usize;
+        for entry in self.nodes.iter() {
+            if let Some(emb) = &entry.value().embedding {
+                if let Some(dim) = expected_dim {
+                    if emb.len() != dim {
+                        continue;
+                    }
+                }
+                if emb.is_empty()
+                    || !emb.iter().all(|v| v.is_finite())
+                    || !emb.iter().any(|v| *v != 0.0)
+                {
+                    continue;
+                }
                 self.vector_index.read().insert(entry.key(), emb);
                 indexed += 1;
             }
@@ -654,7 +1213,11 @@ impl CrdtStore {
 
         // Right-size: 2x actual count, minimum 1024.
         let capacity = (embedding_count * 2).max(1024);
-        let new_index = Arc::new(ActiveVectorIndex::new(capacity));
+        let mut new_index = self.empty_vector_index(capacity);
+        if let Some(dimension) = self.vector_index.read().dimension() {
+            new_index = new_index.with_dimension(dimension);
+        }
+        let new_index = Arc::new(new_index);
         tracing::info!(
             "[CrdtStore] Building vector index: {} embeddings, capacity {}",
             embedding_count,
@@ -778,13 +1341,38 @@ impl CrdtStore {
         storage.for_each(f)
     }
 
+    #[cfg(feature = "native")]
+    fn storage_for_each_by_prefix(
+        storage: &dyn StorageEngine,
+        prefix: &str,
+        f: &mut (dyn FnMut(StoredNode) -> bool + Send),
+    ) -> anyhow::Result<()> {
+        block_on(storage.for_each_by_prefix(prefix, f))
+    }
+
+    #[cfg(not(feature = "native"))]
+    fn storage_for_each_by_prefix(
+        storage: &dyn SyncStorageEngine,
+        prefix: &str,
+        f: &mut (dyn FnMut(StoredNode) -> bool + Send),
+    ) -> anyhow::Result<()> {
+        storage.for_each_by_prefix(prefix, f)
+    }
+
     fn persist_node(&self, record: &NodeRecord, embedding_override: Option<Vec<f32>>) {
         if let Some(storage) = &self.persistence {
+            // Storage-level metadata is written independently of the record, so
+            // carry over whatever is already attached to the node.
+            let previous = Self::storage_get(storage.as_ref(), &record.id)
+                .ok()
+                .flatten();
             let mut record_for_persistence = record.clone();
             if let Some(embedding) = embedding_override {
                 record_for_persistence.embedding = Some(embedding);
-            } else if record_for_persistence.embedding.is_none() {
-                if let Some(stored) = self.get_from_persistence(&record_for_persistence.id) {
+            } else if record_for_persistence.embedding.is_none() && !record.is_tombstone() {
+                if let Some(stored) = previous.as_ref().and_then(|node| {
+                    serde_json::from_value::<NodeRecord>(node.payload.clone()).ok()
+                }) {
                     record_for_persistence.embedding = stored.embedding;
                 }
             }
@@ -802,6 +1390,7 @@ impl CrdtStore {
             let stored = StoredNode {
                 id: record.id.clone(),
                 payload,
+                meta: previous.and_then(|node| node.meta),
             };
             if let Err(e) = Self::storage_put(storage.as_ref(), stored) {
                 tracing::error!("[CrdtStore] persist failed for {}: {}", record.id, e);
@@ -835,19 +1424,71 @@ impl CrdtStore {
     }
 
     pub fn put(&self, id: impl Into<NodeId>, actor: impl Into<ActorId>, data: NodeData) -> NodeId {
-        let id = id.into();
-        let actor = actor.into();
-        self.nodes
-            .entry(id.clone())
-            .and_modify(|record| record.merge_update(actor.clone(), data.clone()))
-            .or_insert_with(|| NodeRecord::new(id.clone(), actor, data.clone()));
+        self.put_expiring(id.into(), actor.into(), data, None)
+    }
+
+    /// Write `data` like [`put`](Self::put), but hide the node from reads
+    /// once `ttl` has passed since this write.
+    ///
+    /// The expiry instant is stored in [`NodeRecord::expires_at`] and
+    /// replicates with the write.  A later plain `put` makes the node
+    /// permanent again.  Expired nodes stay in storage until
+    /// [`sweep_expired`](Self::sweep_expired) removes them.
+    pub fn put_with_ttl(
+        &self,
+        id: impl Into<NodeId>,
+        actor: impl Into<ActorId>,
+        data: NodeData,
+        ttl: Duration,
+    ) -> NodeId {
+        self.put_expiring(id.into(), actor.into(), data, Some(ttl))
+    }
+
+    fn put_expiring(
+        &self,
+        id: NodeId,
+        actor: ActorId,
+        data: NodeData,
+        ttl: Option<Duration>,
+    ) -> NodeId {
+        self.counters.puts.fetch_add(1, Ordering::Relaxed);
+        let mut before_clock = None;
+        let after_clock = {
+            let mut record = self
+                .nodes
+                .entry(id.clone())
+                .and_modify(|record| {
+                    before_clock = Some(record.clock.clone());
+                    record.merge_update(actor.clone(), data.clone());
+                })
+                .or_insert_with(|| NodeRecord::new(id.clone(), actor.clone(), data.clone()));
+            if let Some(ttl) = ttl {
+                let expires_at = chrono::Duration::from_std(ttl)
+                    .ok()
+                    .and_then(|ttl| record.timestamp.checked_add_signed(ttl))
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC);
+                record.expires_at = Some(expires_at);
+            }
+            record.clock.clone()
+        };
         if let Some(entry) = self.nodes.get(&id) {
             self.persist_node(entry.value(), None);
+            self.track_type_definition(entry.value());
         }
+        self.reindex_node(&id);
+        self.notify_watchers(&id);
+        self.record_audit(AuditOp::Put, &id, Some(actor), before_clock, after_clock);
+        self.after_local_write(&id, &data);
+        id
+    }
+
+    /// Queue an embedding for, and notify the plugin of, a node written
+    /// locally with `data`.
+    fn after_local_write(&self, id: &NodeId, data: &NodeData) {
         // Enqueue embedding task (native only).
         #[cfg(feature = "native")]
         if let Some(tx) = &self.embedding_tx {
-            if let Some(text) = extract_text_from_data(&data) {
+            if let Some(text) = extract_text_from_data(data) {
                 let model_id = self
                     .embedder
                     .as_ref()
@@ -880,9 +1521,60 @@ impl CrdtStore {
             }
         }
         if let Some(plugin) = &self.lm_plugin {
-            plugin.on_node_written(&id, &data);
+            plugin.on_node_written(id, data);
         }
-        id
+    }
+
+    /// Write every item as `actor` and return the ids in order.
+    ///
+    /// Each item goes through [`put`](Self::put), so only the map shard
+    /// holding that node is locked while it is written; concurrent readers
+    /// may observe a partially applied batch.  To ship the writes to peers as
+    /// one unit, send them as a [`CrdtOperation::Batch`].
+    pub fn put_batch(
+        &self,
+        actor: impl Into<ActorId>,
+        items: Vec<(NodeId, NodeData)>,
+    ) -> Vec<NodeId> {
+        let actor = actor.into();
+        items
+            .into_iter()
+            .map(|(id, data)| self.put(id, actor.clone(), data))
+            .collect()
+    }
+
+    /// Apply a merge patch to the node's current data and write the result.
+    ///
+    /// A missing node is treated as an empty object, so patching an unknown id
+    /// creates it. The patch is applied through [`update_with`](Self::update_with)
+    /// while the node's entry is locked, so concurrent patches to different
+    /// fields all land, and it fails the same way if the result is invalid.
+    pub fn patch(
+        &self,
+        id: impl Into<NodeId>,
+        actor: impl Into<ActorId>,
+        patch: JsonPatch,
+    ) -> Result<NodeId, StoreError> {
+        let record = self.update_with(id, actor, |current| {
+            let mut data = current
+                .cloned()
+                .unwrap_or_else(|| JsonValue::Object(serde_json::Map::new()));
+            patch.apply_to(&mut data);
+            data
+        })?;
+        Ok(record.id)
+    }
+
+    /// Store `data` under its [`IdStrategy::ContentHash`] id and return the id.
+    ///
+    /// If a node with that id already exists nothing is written, so storing
+    /// the same content twice leaves a single node with an unchanged clock.
+    pub fn put_content_addressed(&self, actor: impl Into<ActorId>, data: NodeData) -> NodeId {
+        let id = IdStrategy::ContentHash.derive_id(&data);
+        if self.get(&id).is_some() {
+            return id;
+        }
+        self.put(id, actor, data)
     }
 
     pub fn put_with_embedding(
@@ -894,14 +1586,18 @@ impl CrdtStore {
     ) -> NodeId {
         let id = id.into();
         let actor = actor.into();
+        self.counters.puts.fetch_add(1, Ordering::Relaxed);
         let cache_embedding_in_memory = self.persistence.is_none();
         let emb_valid = !embedding.is_empty()
             && embedding.iter().all(|v| v.is_finite())
             && embedding.iter().any(|v| *v != 0.0);
         let emb_clone = embedding.clone();
-        self.nodes
+        let mut before_clock = None;
+        let after_clock = self
+            .nodes
             .entry(id.clone())
             .and_modify(|record| {
+                before_clock = Some(record.clock.clone());
                 record.merge_update(actor.clone(), data.clone());
                 record.embedding = if cache_embedding_in_memory {
                     Some(embedding.clone())
@@ -910,24 +1606,48 @@ impl CrdtStore {
                 };
             })
             .or_insert_with(|| {
-                let mut r = NodeRecord::new(id.clone(), actor, data.clone());
+                let mut r = NodeRecord::new(id.clone(), actor.clone(), data.clone());
                 if cache_embedding_in_memory {
                     r.embedding = Some(embedding.clone());
                 }
                 r
-            });
+            })
+            .clock
+            .clone();
         if emb_valid {
             self.vector_index.read().insert(&id, &emb_clone);
         }
         if let Some(entry) = self.nodes.get(&id) {
             self.persist_node(entry.value(), Some(embedding));
         }
+        self.reindex_node(&id);
+        self.notify_watchers(&id);
+        self.record_audit(AuditOp::Put, &id, Some(actor), before_clock, after_clock);
         if let Some(plugin) = &self.lm_plugin {
             plugin.on_node_written(&id, &data);
         }
         id
     }
 
+    /// [`put_with_embedding`](Self::put_with_embedding), but fails with
+    /// [`StoreError::Vector`], writing nothing, if the vector index would
+    /// reject `embedding`: it is empty, non-finite, all zero, or of another
+    /// dimension than the indexed ones.  `data` is checked as
+    /// [`try_put`](Self::try_put) checks it.
+    pub fn try_put_with_embedding(
+        &self,
+        id: impl Into<NodeId>,
+        actor: impl Into<ActorId>,
+        data: NodeData,
+        embedding: Vec<f32>,
+    ) -> Result<NodeId, StoreError> {
+        let id = id.into();
+        self.check_limits(&data)?;
+        self.check_type(&id, &data)?;
+        self.vector_index.read().validate(&embedding)?;
+        Ok(self.put_with_embedding(id, actor, data, embedding))
+    }
+
     #[cfg(feature = "native")]
     fn set_embedding_for_node(&self, node_id: &str, embedding: Vec<f32>) {
         let emb_valid = !embedding.is_empty()
@@ -949,32 +1669,202 @@ impl CrdtStore {
         }
     }
 
+    /// Delete a node, leaving a tombstone that carries its clock.
+    ///
+    /// The tombstone hides the node from [`get`](Self::get) and
+    /// [`list`](Self::list) and makes [`merge_record`](Self::merge_record)
+    /// ignore any write the deleted node had already seen.  A later local
+    /// [`put`](Self::put) recreates the node.  Tombstones are kept until
+    /// [`purge_tombstones`](Self::purge_tombstones) reclaims them.
     pub fn delete(&self, id: impl AsRef<str>) -> Result<(), StoreError> {
-        let id_ref = id.as_ref();
-        let in_sqlite = self.unpersist_node(id_ref);
-        let in_memory = self.nodes.remove(id_ref).is_some();
-        if in_memory || in_sqlite {
-            if let Some(plugin) = &self.lm_plugin {
-                plugin.on_node_deleted(&id_ref.to_owned());
+        self.delete_attributed(id.as_ref(), None)
+    }
+
+    /// [`delete`](Self::delete), recording `actor` as the deleter in the
+    /// [audit trail](Self::with_audit).
+    pub fn delete_by(
+        &self,
+        id: impl AsRef<str>,
+        actor: impl Into<ActorId>,
+    ) -> Result<(), StoreError> {
+        self.delete_attributed(id.as_ref(), Some(actor.into()))
+    }
+
+    fn delete_attributed(&self, id: &str, actor: Option<ActorId>) -> Result<(), StoreError> {
+        let deleted = self.delete_locked(id, actor, |record| {
+            if record.is_tombstone() {
+                return false;
             }
-            Ok(())
-        } else {
-            Err(StoreError::NotFound(id_ref.to_owned()))
+            record.mark_deleted();
+            true
+        });
+        if !deleted {
+            return Err(StoreError::NotFound(id.to_owned()));
+        }
+        self.counters.deletes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Let `mark` turn the record stored for `id` into a tombstone while the
+    /// node's entry is locked, so a concurrent write either lands first and
+    /// is what `mark` sees, or lands on the tombstone; it is never replaced
+    /// by a tombstone of an older clock.  `mark` returns whether it deleted
+    /// the record and must leave it alone otherwise.  Returns `false` for an
+    /// unknown id or when `mark` declined.
+    fn delete_locked(
+        &self,
+        id: &str,
+        actor: Option<ActorId>,
+        mark: impl FnOnce(&mut NodeRecord) -> bool,
+    ) -> bool {
+        let (before_clock, after_clock) = match self.nodes.entry(id.to_owned()) {
+            Entry::Occupied(mut slot) => {
+                let before_clock = slot.get().clock.clone();
+                if !mark(slot.get_mut()) {
+                    return false;
+                }
+                (before_clock, slot.get().clock.clone())
+            }
+            Entry::Vacant(slot) => {
+                // A persistent store may hold the node only on disk
+                let Some(mut record) = self.get_from_persistence(id) else {
+                    return false;
+                };
+                let before_clock = record.clock.clone();
+                if !mark(&mut record) {
+                    return false;
+                }
+                (before_clock, slot.insert(record).clock.clone())
+            }
+        };
+        if let Some(entry) = self.nodes.get(id) {
+            self.persist_node(entry.value(), None);
+            self.track_type_definition(entry.value());
+        }
+        let id = id.to_owned();
+        self.reindex_node(&id);
+        self.notify_watchers(&id);
+        self.record_audit(AuditOp::Delete, &id, actor, Some(before_clock), after_clock);
+        if let Some(plugin) = &self.lm_plugin {
+            plugin.on_node_deleted(&id);
+        }
+        true
+    }
+
+    /// Replace every expired node with a tombstone and return how many were
+    /// removed.
+    ///
+    /// The tombstones keep the expired records' clocks, so merging them into
+    /// a peer that has not swept yet deletes the node there too.  They are
+    /// reclaimed by [`purge_tombstones`](Self::purge_tombstones) like any
+    /// other.
+    pub fn sweep_expired(&self) -> usize {
+        let mut expired = Vec::new();
+        self.for_each_including_tombstones(&mut |record: &NodeRecord| {
+            if !record.is_tombstone() && record.is_expired() {
+                expired.push(record.id.clone());
+            }
+            true
+        });
+        let mut swept = 0;
+        for id in expired {
+            // Checked again under the entry lock, so a node rewritten since
+            // the scan is skipped and one rewritten now waits for the sweep
+            let deleted = self.delete_locked(&id, None, |record| {
+                if record.is_tombstone() || !record.is_expired() {
+                    return false;
+                }
+                record.mark_deleted();
+                true
+            });
+            swept += usize::from(deleted);
+        }
+        swept
+    }
+
+    /// Drop tombstones for nodes deleted before `before`, from memory and
+    /// persistence, and return how many were purged.
+    ///
+    /// Only purge tombstones every peer has already received: once a
+    /// tombstone is gone, a stale write of the node is accepted again.
+    pub fn purge_tombstones(&self, before: DateTime<Utc>) -> usize {
+        let mut expired = Vec::new();
+        self.for_each_including_tombstones(&mut |record: &NodeRecord| {
+            if record.deleted_at.is_some_and(|at| at < before) {
+                expired.push(record.id.clone());
+            }
+            true
+        });
+        let mut purged = 0;
+        for id in expired {
+            // Check again under the entry lock, which a write of the node
+            // needs too, so one that landed since the scan is kept
+            let past_retention =
+                |record: &NodeRecord| record.deleted_at.is_some_and(|at| at < before);
+            let removed = match self.nodes.entry(id.clone()) {
+                Entry::Occupied(slot) => {
+                    let remove = past_retention(slot.get());
+                    if remove {
+                        self.unpersist_node(&id);
+                        slot.remove();
+                    }
+                    remove
+                }
+                Entry::Vacant(_) => {
+                    self.get_from_persistence(&id)
+                        .is_some_and(|record| past_retention(&record))
+                        && self.unpersist_node(&id)
+                }
+            };
+            purged += usize::from(removed);
         }
+        purged
     }
 
     pub fn get(&self, id: impl AsRef<str>) -> Option<NodeRecord> {
-        let id = id.as_ref();
+        let record = self
+            .get_including_tombstones(id.as_ref())
+            .filter(NodeRecord::is_visible);
+        let counter = match record {
+            Some(_) => &self.counters.get_hits,
+            None => &self.counters.get_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Some(self.ensure_quality_score(record?))
+    }
+
+    /// Operation counts since the store was created, plus its current size.
+    ///
+    /// The counters are atomics, so this is cheap to poll, but `nodes` walks
+    /// persistence when the store has it, as [`len`](Self::len) does.
+    pub fn metrics(&self) -> StoreMetrics {
+        StoreMetrics {
+            puts: self.counters.puts.load(Ordering::Relaxed),
+            get_hits: self.counters.get_hits.load(Ordering::Relaxed),
+            get_misses: self.counters.get_misses.load(Ordering::Relaxed),
+            deletes: self.counters.deletes.load(Ordering::Relaxed),
+            nodes: self.len(),
+        }
+    }
+
+    /// The stored record for `id`, tombstone or not, without touching its
+    /// quality score.
+    fn get_including_tombstones(&self, id: &str) -> Option<NodeRecord> {
         if let Some(entry) = self.nodes.get(id) {
-            let record = entry.value().clone();
-            drop(entry);
-            return Some(self.ensure_quality_score(record));
+            return Some(entry.value().clone());
         }
         self.get_from_persistence(id)
-            .map(|record| self.ensure_quality_score(record))
     }
 
     pub fn list(&self) -> Vec<NodeRecord> {
+        self.list_including_tombstones()
+            .into_iter()
+            .filter(NodeRecord::is_visible)
+            .collect()
+    }
+
+    /// Every record, including tombstones of deleted nodes.
+    pub fn list_including_tombstones(&self) -> Vec<NodeRecord> {
         if let Some(storage) = &self.persistence {
             match Self::storage_list(storage.as_ref()) {
                 Ok(nodes) => {
@@ -1002,10 +1892,64 @@ impl CrdtStore {
             .collect()
     }
 
+    /// Live nodes whose data matches `filter`.
+    pub fn query_json(&self, filter: JsonFilter) -> Vec<NodeRecord> {
+        self.list()
+            .into_iter()
+            .filter(|record| filter.matches(&record.data))
+            .collect()
+    }
+
+    /// Live nodes whose id starts with `prefix`, ordered lexicographically
+    /// by id.
+    ///
+    /// Only matching records are cloned, and persistent backends are asked
+    /// for the prefix alone; `SledStorage` answers with a range scan.
+    /// In-memory entries shadow stored counterparts.
+    pub fn list_by_prefix(&self, prefix: &str) -> Vec<NodeRecord> {
+        let mut records: BTreeMap<NodeId, NodeRecord> = self
+            .nodes
+            .iter()
+            .filter(|entry| entry.key().starts_with(prefix))
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        if let Some(storage) = &self.persistence {
+            let result = Self::storage_for_each_by_prefix(
+                storage.as_ref(),
+                prefix,
+                &mut |stored: StoredNode| {
+                    if let Ok(record) = serde_json::from_value::<NodeRecord>(stored.payload) {
+                        records.entry(record.id.clone()).or_insert(record);
+                    }
+                    true
+                },
+            );
+            if let Err(e) = result {
+                tracing::error!("[CrdtStore] prefix scan of storage failed: {}", e);
+            }
+        }
+        records
+            .into_values()
+            .filter(NodeRecord::is_visible)
+            .collect()
+    }
+
+    /// The live record for each of `ids`, in the same order, with `None` for
+    /// ids that are missing or deleted.
+    pub fn get_many(&self, ids: &[&str]) -> Vec<Option<NodeRecord>> {
+        ids.iter().map(|id| self.get(id)).collect()
+    }
+
     /// Iterate over all nodes via a callback without collecting into a Vec.
     ///
     /// In-memory entries shadow stored counterparts.  Return `false` to stop.
     pub fn for_each_sync(&self, f: &mut (dyn FnMut(&NodeRecord) -> bool + Send)) {
+        self.for_each_including_tombstones(&mut |record: &NodeRecord| {
+            !record.is_visible() || f(record)
+        });
+    }
+
+    fn for_each_including_tombstones(&self, f: &mut (dyn FnMut(&NodeRecord) -> bool + Send)) {
         if let Some(storage) = &self.persistence {
             let mut seen = std::collections::HashSet::new();
             for entry in self.nodes.iter() {
@@ -1033,47 +1977,324 @@ impl CrdtStore {
         }
     }
 
-    pub fn apply(&self, op: CrdtOperation) -> Result<Option<NodeId>, StoreError> {
-        match op {
-            CrdtOperation::Put { id, actor, data } => Ok(Some(self.put(id, actor, data))),
-            CrdtOperation::Delete { id } => {
-                self.delete(&id)?;
-                Ok(None)
-            }
+    /// Number of nodes, counting in-memory and persisted nodes once each.
+    pub fn len(&self) -> usize {
+        if self.persistence.is_none() {
+            return self.nodes.iter().filter(|entry| entry.is_visible()).count();
         }
+        let mut count = 0;
+        self.for_each_sync(&mut |_: &NodeRecord| {
+            count += 1;
+            true
+        });
+        count
     }
 
-    pub fn operation_for(
+    pub fn is_empty(&self) -> bool {
+        let mut empty = true;
+        self.for_each_sync(&mut |_: &NodeRecord| {
+            empty = false;
+            false
+        });
+        empty
+    }
+
+    /// Count nodes by their string `data["type"]` in a single pass.  Nodes
+    /// without one are counted under [`UNTYPED_BUCKET`].
+    pub fn type_histogram(&self) -> HashMap<String, usize> {
+        let mut histogram: HashMap<String, usize> = HashMap::new();
+        self.for_each_sync(&mut |record: &NodeRecord| {
+            let ty = record
+                .data
+                .get("type")
+                .and_then(|v| v.as_str())
+                .unwrap_or(UNTYPED_BUCKET);
+            *histogram.entry(ty.to_string()).or_insert(0) += 1;
+            true
+        });
+        histogram
+    }
+
+    /// Nodes whose `timestamp` lies in `[from, to)`, oldest first.
+    ///
+    /// `None` leaves that side of the window open, so `(None, None)` returns
+    /// every node.  Ties are broken by id for a stable order.
+    pub fn range_by_time(
         &self,
-        actor: impl Into<ActorId>,
-        data: NodeData,
-    ) -> (NodeId, CrdtOperation) {
-        let id = Uuid::new_v4().to_string();
-        let op = CrdtOperation::Put {
-            id: id.clone(),
-            actor: actor.into(),
-            data,
-        };
-        (id, op)
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<NodeRecord> {
+        let mut records = Vec::new();
+        self.for_each_sync(&mut |record: &NodeRecord| {
+            let after_start = from.is_none_or(|from| record.timestamp >= from);
+            let before_end = to.is_none_or(|to| record.timestamp < to);
+            if after_start && before_end {
+                records.push(record.clone());
+            }
+            true
+        });
+        records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        records
     }
 
-    #[cfg(feature = "native")]
-    pub fn spawn_embedding_worker(store: Arc<Self>) -> std::thread::JoinHandle<()> {
-        let rx = store
-            .embedding_rx
-            .lock()
-            .take()
-            .expect("spawn_embedding_worker: no embedder attached or worker already started");
-        let store_w = Arc::clone(&store);
-        std::thread::Builder::new()
-            .name("pluresdb-embedding-worker".into())
-            .spawn(move || {
-                while let Ok(task) = rx.recv() {
-                    store_w
-                        .embedding_queue_depth
-                        .fetch_sub(1, Ordering::Relaxed);
-                    if let Some(embedder) = &store_w.embedder {
-                        if let Ok(mut batch) = embedder.embed(&[task.extracted_text.as_str()]) {
+    /// The store's causal frontier: for each actor, the highest clock counter
+    /// found on any node.
+    ///
+    /// Peers can exchange frontiers to cheaply tell whether one has seen
+    /// writes the other has not before running a full sync.
+    pub fn actor_frontier(&self) -> VectorClock {
+        let mut frontier = VectorClock::default();
+        self.for_each_including_tombstones(&mut |record: &NodeRecord| {
+            for (actor, &counter) in &record.clock {
+                let max = frontier.entry(actor.clone()).or_insert(0);
+                *max = (*max).max(counter);
+            }
+            true
+        });
+        frontier
+    }
+
+    /// The clock a peer sends to [`diff_since`](Self::diff_since) to ask for
+    /// what it is missing; the same as [`actor_frontier`](Self::actor_frontier).
+    pub fn state_clock(&self) -> VectorClock {
+        self.actor_frontier()
+    }
+
+    /// Every record a peer whose [`state_clock`](Self::state_clock) is
+    /// `other` may not have seen, for it to [`merge_record`](Self::merge_record).
+    ///
+    /// A live record is left out when `other` covers its clock.  Tombstones
+    /// are always included: a delete keeps the clock of the write it removed,
+    /// so `other` cannot show whether the peer saw it.
+    ///
+    /// Clocks count writes per node, so `other` can cover a node the peer
+    /// never received when its writer has written more to other nodes.
+    /// Replicas that do not exchange every write should still run a full
+    /// sync from [`list_including_tombstones`](Self::list_including_tombstones)
+    /// from time to time.
+    pub fn diff_since(&self, other: &VectorClock) -> Vec<NodeRecord> {
+        let mut diff = Vec::new();
+        self.for_each_including_tombstones(&mut |record: &NodeRecord| {
+            let seen = matches!(
+                clock::compare(&record.clock, other),
+                ClockOrdering::Equal | ClockOrdering::DominatedBy
+            );
+            if record.is_tombstone() || !seen {
+                diff.push(record.clone());
+            }
+            true
+        });
+        diff
+    }
+
+    /// Merge a record received from a peer into the store using
+    /// [`NodeRecord::merge_record`]'s rules, inserting it if the node is new.
+    /// Concurrent writes are reconciled with the node's
+    /// [`ConflictStrategy`], last-writer-wins unless configured otherwise.
+    pub fn merge_record(&self, record: NodeRecord) -> MergeOutcome {
+        let id = record.id.clone();
+        let outcome = match self.nodes.entry(id.clone()) {
+            dashmap::mapref::entry::Entry::Vacant(slot) => {
+                slot.insert(record);
+                MergeOutcome::Applied
+            }
+            dashmap::mapref::entry::Entry::Occupied(mut slot) => {
+                let strategy = self.conflict_strategies.for_records(slot.get(), &record);
+                slot.get_mut().merge_record_with(record, strategy)
+            }
+        };
+        if outcome != MergeOutcome::Ignored {
+            if let Some(entry) = self.nodes.get(&id) {
+                self.persist_node(entry.value(), None);
+                self.track_type_definition(entry.value());
+            }
+            self.reindex_node(&id);
+            self.notify_watchers(&id);
+        }
+        outcome
+    }
+
+    /// Merge a record received from a peer, as
+    /// [`merge_record`](Self::merge_record) does.  Returns `true` if the store
+    /// changed, so callers relaying changes can stop once peers agree.
+    pub fn merge_remote(&self, remote: NodeRecord) -> bool {
+        self.merge_record(remote) != MergeOutcome::Ignored
+    }
+
+    /// Insert `record` exactly as given, replacing any local copy without
+    /// comparing clocks.
+    ///
+    /// Meant for restoring a store from its own saved records: the clock and
+    /// timestamp are kept rather than bumped, so causal history survives the
+    /// reload and later merges with peers still order correctly.  A saved
+    /// embedding goes back into the vector index.  Records from peers should
+    /// go through [`merge_record`](Self::merge_record) instead.
+    pub fn insert_record(&self, mut record: NodeRecord) {
+        let id = record.id.clone();
+        let embedding = record.embedding.clone();
+        if self.persistence.is_some() {
+            record.embedding = None;
+        }
+        if let Some(embedding) = embedding.as_ref().filter(|_| !record.is_tombstone()) {
+            self.vector_index.read().insert(&id, embedding);
+        }
+        self.nodes.insert(id.clone(), record);
+        if let Some(entry) = self.nodes.get(&id) {
+            self.persist_node(entry.value(), embedding);
+            self.track_type_definition(entry.value());
+        }
+        self.reindex_node(&id);
+        self.notify_watchers(&id);
+    }
+
+    /// Apply a batch of operations received from a peer, each stamped with
+    /// the clock and the timestamp it was written at, and report what
+    /// happened to each.
+    ///
+    /// Puts go through [`merge_remote`](Self::merge_remote) keeping the
+    /// writer's timestamp, so every replica resolves concurrent writes to the
+    /// same winner.  A delete only takes effect if its clock covers the
+    /// local one, and leaves a tombstone with both clocks merged; a delete
+    /// concurrent with a local update leaves the node in place.  A
+    /// [`CrdtOperation::Batch`] is reported as one put per item.
+    pub fn apply_remote_batch(
+        &self,
+        ops: &[(CrdtOperation, VectorClock, DateTime<Utc>)],
+    ) -> Vec<ConflictPreview> {
+        expand_ops(ops)
+            .into_iter()
+            .map(|(op, clock, timestamp)| {
+                let local = self
+                    .get_including_tombstones(op_id(&op))
+                    .map(|record| record.clock);
+                let outcome = classify_remote_op(local.as_ref(), &op, clock);
+                match (&op, outcome) {
+                    (_, ConflictOutcome::Ignored) => {}
+                    (CrdtOperation::Put { id, data, .. }, _) => {
+                        self.merge_remote(NodeRecord {
+                            id: id.clone(),
+                            data: data.clone(),
+                            clock: clock.clone(),
+                            timestamp,
+                            embedding: None,
+                            quality_score: None,
+                            deleted_at: None,
+                            expires_at: None,
+                        });
+                    }
+                    (CrdtOperation::Delete { id }, ConflictOutcome::Applied) => {
+                        self.delete_locked(id, None, |record| {
+                            if clock::compare(&record.clock, clock) != ClockOrdering::DominatedBy {
+                                return false;
+                            }
+                            let merged = clock::merge(&record.clock, clock);
+                            record.mark_deleted();
+                            record.clock = merged;
+                            record.timestamp = timestamp;
+                            true
+                        });
+                    }
+                    (CrdtOperation::Delete { .. }, ConflictOutcome::Concurrent) => {}
+                    (CrdtOperation::Batch { .. }, _) => unreachable!("batches are expanded"),
+                }
+                ConflictPreview {
+                    id: op_id(&op).clone(),
+                    outcome,
+                }
+            })
+            .collect()
+    }
+
+    /// Dry run of [`apply_remote_batch`](Self::apply_remote_batch): report
+    /// what each operation would do without touching the store.
+    ///
+    /// Operations are evaluated in order, so a later operation on the same
+    /// id sees the effect of an earlier one in the batch, including the
+    /// clock of the tombstone an earlier delete leaves.
+    pub fn preview_remote_batch(
+        &self,
+        ops: &[(CrdtOperation, VectorClock, DateTime<Utc>)],
+    ) -> Vec<ConflictPreview> {
+        let mut pending: HashMap<NodeId, Option<VectorClock>> = HashMap::new();
+        expand_ops(ops)
+            .into_iter()
+            .map(|(op, clock, _)| {
+                let id = op_id(&op).clone();
+                let local = pending.entry(id.clone()).or_insert_with(|| {
+                    self.get_including_tombstones(&id)
+                        .map(|record| record.clock)
+                });
+                let outcome = classify_remote_op(local.as_ref(), &op, clock);
+                match (&op, outcome) {
+                    (_, ConflictOutcome::Ignored) => {}
+                    // An applied delete leaves a tombstone with the merged
+                    // clock, just as a put leaves a record with it
+                    (CrdtOperation::Put { .. }, _)
+                    | (CrdtOperation::Delete { .. }, ConflictOutcome::Applied) => {
+                        let merged =
+                            clock::merge(local.get_or_insert_with(VectorClock::default), clock);
+                        *local = Some(merged);
+                    }
+                    (CrdtOperation::Delete { .. }, ConflictOutcome::Concurrent) => {}
+                    (CrdtOperation::Batch { .. }, _) => unreachable!("batches are expanded"),
+                }
+                ConflictPreview { id, outcome }
+            })
+            .collect()
+    }
+
+    /// Apply `op` locally.  Returns the written id for a put; deletes and
+    /// batches return `None`.  Puts are checked against the type registry
+    /// as [`try_put`](Self::try_put) does, and every item of a batch against
+    /// the depth and size limits before any is written.
+    pub fn apply(&self, op: CrdtOperation) -> Result<Option<NodeId>, StoreError> {
+        match op {
+            CrdtOperation::Put { id, actor, data } => Ok(Some(self.try_put(id, actor, data)?)),
+            CrdtOperation::Delete { id } => {
+                self.delete(&id)?;
+                Ok(None)
+            }
+            CrdtOperation::Batch { actor, items } => {
+                for (_, data) in &items {
+                    self.check_limits(data)?;
+                }
+                self.put_batch(actor, items);
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn operation_for(
+        &self,
+        actor: impl Into<ActorId>,
+        data: NodeData,
+    ) -> (NodeId, CrdtOperation) {
+        let id = Uuid::new_v4().to_string();
+        let op = CrdtOperation::Put {
+            id: id.clone(),
+            actor: actor.into(),
+            data,
+        };
+        (id, op)
+    }
+
+    #[cfg(feature = "native")]
+    pub fn spawn_embedding_worker(store: Arc<Self>) -> std::thread::JoinHandle<()> {
+        let rx = store
+            .embedding_rx
+            .lock()
+            .take()
+            .expect("spawn_embedding_worker: no embedder attached or worker already started");
+        let store_w = Arc::clone(&store);
+        std::thread::Builder::new()
+            .name("pluresdb-embedding-worker".into())
+            .spawn(move || {
+                while let Ok(task) = rx.recv() {
+                    store_w
+                        .embedding_queue_depth
+                        .fetch_sub(1, Ordering::Relaxed);
+                    if let Some(embedder) = &store_w.embedder {
+                        if let Ok(mut batch) = embedder.embed(&[task.extracted_text.as_str()]) {
                             if let Some(embedding) = batch.pop() {
                                 store_w.set_embedding_for_node(&task.node_id, embedding);
                             }
@@ -1117,19 +2338,18 @@ impl CrdtStore {
             self.vector_index_ready.store(true, Ordering::Release);
         }
 
-        let candidates = self.vector_index.read().search(query_embedding, limit);
+        let candidates = match self.vector_index.read().search(query_embedding, limit, 0.0) {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                tracing::warn!("[CrdtStore] vector search skipped: {}", e);
+                return Vec::new();
+            }
+        };
         let now = Utc::now();
         let mut results: Vec<VectorSearchResult> = candidates
             .into_iter()
             .filter_map(|(id, vector_similarity)| {
-                let record = if let Some(entry) = self.nodes.get(&id) {
-                    let record = entry.value().clone();
-                    drop(entry);
-                    record
-                } else {
-                    self.get_from_persistence(&id)?
-                };
-                let record = self.ensure_quality_score(record);
+                let record = self.get(&id)?;
                 let quality = record.quality_score.unwrap_or(0.0);
                 let blended_score = Self::blended_search_score(
                     vector_similarity,
@@ -1154,6 +2374,30 @@ impl CrdtStore {
         });
         results
     }
+
+    /// The `k` live nodes whose embeddings are nearest to `query`, with a
+    /// raw [`DistanceMetric`] score (cosine similarity by default) of at
+    /// least `threshold`, nearest first.
+    ///
+    /// Unlike [`vector_search`](Self::vector_search) this ranks on similarity
+    /// alone, and rejects a bad query instead of returning nothing: one of
+    /// the wrong dimension fails with [`VectorError::DimensionMismatch`].
+    pub fn nearest_neighbors(
+        &self,
+        query: &[f32],
+        k: usize,
+        threshold: f32,
+    ) -> Result<Vec<(NodeRecord, f32)>, StoreError> {
+        if !self.vector_index_ready.load(Ordering::Acquire) {
+            self.build_vector_index_from_persistence();
+            self.vector_index_ready.store(true, Ordering::Release);
+        }
+        let hits = self.vector_index.read().search(query, k, threshold)?;
+        Ok(hits
+            .into_iter()
+            .filter_map(|(id, similarity)| Some((self.get(&id)?, similarity)))
+            .collect())
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -1213,6 +2457,123 @@ impl SqlValue {
             SqlValue::Blob(bytes) => json!(bytes),
         }
     }
+
+    /// Store `value` as its JSON text, for a `TEXT` column holding
+    /// documents.  Read it back with [`as_json`](Self::as_json).
+    pub fn json(value: &JsonValue) -> Self {
+        Self::Text(value.to_string())
+    }
+
+    /// Parse a `Text` or `Blob` value holding JSON, such as one written by
+    /// [`json`](Self::json).  Unlike [`to_json`](Self::to_json), which maps
+    /// each variant to the matching JSON scalar, this decodes the stored
+    /// document; `None` if the value is neither text nor a blob, or is not
+    /// valid JSON.
+    pub fn as_json(&self) -> Option<JsonValue> {
+        match self {
+            Self::Text(text) => serde_json::from_str(text).ok(),
+            Self::Blob(bytes) => serde_json::from_slice(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    /// Convert a JSON value from a host binding into a statement parameter.
+    ///
+    /// Integers must fit in `i64`, SQLite's integer range; a larger integer
+    /// such as `2^63` is rejected with [`DatabaseError::InvalidParameter`]
+    /// rather than silently rounded to a float.  Pass values beyond that range
+    /// as strings.  Numbers with a fractional part or exponent bind as `Real`,
+    /// which also covers integers past `u64::MAX` since `serde_json` already
+    /// parses those as floats.  Booleans bind as `0`/`1`, and arrays and
+    /// objects as their JSON text.
+    pub fn from_json(value: JsonValue) -> DbResult<Self> {
+        Ok(match value {
+            JsonValue::Null => Self::Null,
+            JsonValue::Bool(b) => Self::from(b),
+            JsonValue::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Self::Integer(i)
+                } else if n.is_u64() {
+                    return Err(DatabaseError::InvalidParameter(format!(
+                        "integer {n} is outside the 64-bit signed range; pass it as a string"
+                    )));
+                } else {
+                    Self::Real(n.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            JsonValue::String(s) => Self::Text(s),
+            other @ (JsonValue::Array(_) | JsonValue::Object(_)) => Self::Text(other.to_string()),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl From<&str> for SqlValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_owned())
+    }
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl From<String> for SqlValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl From<i64> for SqlValue {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl From<f64> for SqlValue {
+    fn from(value: f64) -> Self {
+        Self::Real(value)
+    }
+}
+
+/// Stored as `0`/`1`, matching SQLite's own boolean representation.
+#[cfg(feature = "sqlite-compat")]
+impl From<bool> for SqlValue {
+    fn from(value: bool) -> Self {
+        Self::Integer(i64::from(value))
+    }
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl From<Vec<u8>> for SqlValue {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Blob(value)
+    }
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// Build a `Vec<SqlValue>` from anything convertible into [`SqlValue`].
+///
+/// ```
+/// # use pluresdb_core::{sql_params, SqlValue};
+/// let params = sql_params!["Alice", 30, 1.5, true, None::<i64>];
+/// assert_eq!(params[0], SqlValue::Text("Alice".to_string()));
+/// assert_eq!(params[4], SqlValue::Null);
+/// ```
+#[cfg(feature = "sqlite-compat")]
+#[macro_export]
+macro_rules! sql_params {
+    () => {
+        ::std::vec::Vec::<$crate::SqlValue>::new()
+    };
+    ($($value:expr),+ $(,)?) => {
+        ::std::vec![$($crate::SqlValue::from($value)),+]
+    };
 }
 
 #[cfg(feature = "sqlite-compat")]
@@ -1220,7 +2581,10 @@ impl SqlValue {
 pub struct QueryResult {
     pub columns: Vec<String>,
     pub rows: Vec<Vec<SqlValue>>,
+    /// Rows the statement changed; 0 for read-only statements.
     pub changes: u64,
+    /// Rowid of the last insert on the connection; 0 for read-only
+    /// statements, which may run on a reader or come from the query cache.
     pub last_insert_rowid: i64,
 }
 
@@ -1278,7 +2642,10 @@ pub struct DatabaseOptions {
     pub apply_default_pragmas: bool,
     pub custom_pragmas: Vec<(String, String)>,
     pub busy_timeout: Option<Duration>,
+    pub busy_retries: u32,
     pub embedding_model: Option<String>,
+    pub max_read_connections: usize,
+    pub statement_cache_size: usize,
 }
 
 #[cfg(feature = "sqlite-compat")]
@@ -1291,7 +2658,10 @@ impl Default for DatabaseOptions {
             apply_default_pragmas: true,
             custom_pragmas: Vec::new(),
             busy_timeout: Some(Duration::from_millis(5_000)),
+            busy_retries: DEFAULT_BUSY_RETRIES,
             embedding_model: None,
+            max_read_connections: 0,
+            statement_cache_size: DEFAULT_STATEMENT_CACHE_SIZE,
         }
     }
 }
@@ -1334,17 +2704,113 @@ impl DatabaseOptions {
         self
     }
 
+    /// Retry a call up to `n` more times, backing off between attempts, when
+    /// SQLite still reports the database busy or locked after
+    /// [`busy_timeout`](Self::busy_timeout).  Once retries run out the call
+    /// fails with [`DatabaseError::Busy`].  See [`Database`] for which calls
+    /// are retried.
+    pub fn busy_retries(mut self, n: u32) -> Self {
+        self.busy_retries = n;
+        self
+    }
+
     pub fn with_embedding_model(mut self, model_id: impl Into<String>) -> Self {
         self.embedding_model = Some(model_id.into());
         self
     }
+
+    /// Serve read-only queries from a pool of up to `n` read-only
+    /// connections next to the single writer; `0` (the default) sends
+    /// everything through the writer.  Ignored for in-memory databases,
+    /// whose connections cannot share data.  See [`Database`] for which
+    /// calls are routed where.
+    pub fn max_read_connections(mut self, n: usize) -> Self {
+        self.max_read_connections = n;
+        self
+    }
+
+    /// Keep up to `n` compiled statements per connection, keyed by SQL text,
+    /// so repeated queries skip re-preparing.  `0` disables the cache.
+    pub fn statement_cache_size(mut self, n: usize) -> Self {
+        self.statement_cache_size = n;
+        self
+    }
+}
+
+/// Checkpoint mode for [`Database::wal_checkpoint`], mirroring the argument to
+/// SQLite's `PRAGMA wal_checkpoint`.
+#[cfg(feature = "sqlite-compat")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheckpointMode {
+    /// Checkpoint as many frames as possible without waiting on readers or writers.
+    #[default]
+    Passive,
+    /// Wait for writers to finish, then checkpoint every frame in the log.
+    Full,
+    /// Like `Full`, and also wait for readers so the next writer restarts the log.
+    Restart,
+    /// Like `Restart`, and also truncate the `-wal` file to zero bytes.
+    Truncate,
+}
+
+#[cfg(feature = "sqlite-compat")]
+impl CheckpointMode {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Passive => "PASSIVE",
+            Self::Full => "FULL",
+            Self::Restart => "RESTART",
+            Self::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+/// Frame counts reported by a WAL checkpoint.
+///
+/// Both counts are `-1` when the database is not in WAL mode (for example an
+/// in-memory database).
+#[cfg(feature = "sqlite-compat")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointResult {
+    /// `true` if the checkpoint could not complete because of a concurrent
+    /// reader or writer.
+    pub busy: bool,
+    /// Frames in the WAL when the checkpoint finished.
+    pub log_frames: i64,
+    /// Frames copied back into the main database file.
+    pub checkpointed_frames: i64,
 }
 
+/// A SQLite database handle, cheap to clone and safe to share between threads.
+///
+/// Every write goes through a single writer connection behind a mutex, so
+/// writes are serialized.  With [`DatabaseOptions::max_read_connections`],
+/// [`query`](Self::query) and [`Statement::all`] / [`Statement::get`] run
+/// read-only SQL on a pooled read-only connection instead, so reads proceed in
+/// parallel with each other and with the writer.  A pooled read sees the last
+/// committed state: it does not wait for, or see, a transaction still open on
+/// the writer.  The exception is a transaction opened with `exec("BEGIN")`:
+/// until it is committed or rolled back, reads run on the writer so they see
+/// its changes.  `exec`, `transaction`, `pragma`, `Statement::run`, and
+/// `Statement::stream` always use the writer.  Readers only run concurrently
+/// with the writer in WAL mode, which the default pragmas enable.
+///
+/// A call that finds the database busy or locked, after waiting out
+/// [`DatabaseOptions::busy_timeout`], is retried up to
+/// [`DatabaseOptions::busy_retries`] times with a doubling backoff, then
+/// fails with [`DatabaseError::Busy`].  Only an attempt that left no
+/// transaction open and changed no rows is retried, so a retry never repeats
+/// work.  `transaction` and `Statement::stream` are never retried, since
+/// their callbacks may have effects outside the database; they report a busy
+/// database as [`DatabaseError::Busy`] after one attempt.
 #[cfg(feature = "sqlite-compat")]
 #[derive(Debug, Clone)]
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
+    readers: Option<Arc<DatabasePool>>,
     path: DatabasePath,
+    query_cache: Option<Arc<QueryCache>>,
+    busy_retries: u32,
 }
 
 #[cfg(feature = "sqlite-compat")]
@@ -1352,6 +2818,16 @@ pub struct Database {
 pub enum DatabaseError {
     #[error("sqlite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
+    #[error("timed out after {0:?} waiting for a pooled connection")]
+    PoolTimeout(Duration),
+    /// SQLite reported the database busy or locked on every attempt.
+    #[error("database is busy after {attempts} attempt(s): {source}")]
+    Busy {
+        attempts: u32,
+        source: rusqlite::Error,
+    },
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(String),
 }
 
 #[cfg(feature = "sqlite-compat")]
@@ -1359,13 +2835,69 @@ impl DatabaseError {
     pub const fn code(&self) -> CoreErrorCode {
         match self {
             Self::Sqlite(_) => CoreErrorCode::SqliteError,
+            Self::PoolTimeout(_) => CoreErrorCode::PoolTimeout,
+            Self::Busy { .. } => CoreErrorCode::DatabaseBusy,
+            Self::InvalidParameter(_) => CoreErrorCode::InvalidInput,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        use rusqlite::ErrorCode;
+        match self {
+            Self::Sqlite(rusqlite::Error::QueryReturnedNoRows) => ErrorKind::NotFound,
+            Self::Sqlite(
+                rusqlite::Error::InvalidParameterCount(..)
+                | rusqlite::Error::InvalidParameterName(_),
+            ) => ErrorKind::InvalidInput,
+            Self::Sqlite(inner) => match inner.sqlite_error_code() {
+                Some(ErrorCode::ConstraintViolation) => ErrorKind::Constraint,
+                Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => ErrorKind::Busy,
+                Some(
+                    ErrorCode::PermissionDenied
+                    | ErrorCode::ReadOnly
+                    | ErrorCode::AuthorizationForStatementDenied,
+                ) => ErrorKind::AccessDenied,
+                _ => ErrorKind::Internal,
+            },
+            Self::PoolTimeout(_) | Self::Busy { .. } => ErrorKind::Busy,
+            Self::InvalidParameter(_) => ErrorKind::InvalidInput,
+        }
+    }
+
+    /// This error as [`Busy`](Self::Busy) after `attempts`, if SQLite
+    /// reported the database busy or locked.
+    fn into_busy(self, attempts: u32) -> Self {
+        match self {
+            Self::Sqlite(source) if is_busy(&source) => Self::Busy { attempts, source },
+            other => other,
         }
     }
 }
 
+#[cfg(feature = "sqlite-compat")]
+fn is_busy(error: &rusqlite::Error) -> bool {
+    use rusqlite::ErrorCode;
+    matches!(
+        error.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    )
+}
+
 #[cfg(feature = "sqlite-compat")]
 pub type DbResult<T> = Result<T, DatabaseError>;
 
+/// Default for [`DatabaseOptions::statement_cache_size`].
+#[cfg(feature = "sqlite-compat")]
+pub const DEFAULT_STATEMENT_CACHE_SIZE: usize = 16;
+
+/// Default for [`DatabaseOptions::busy_retries`].
+#[cfg(feature = "sqlite-compat")]
+pub const DEFAULT_BUSY_RETRIES: u32 = 3;
+
+/// Wait before the first retry of a busy call; doubled for each one after.
+#[cfg(feature = "sqlite-compat")]
+const BUSY_RETRY_BACKOFF: Duration = Duration::from_millis(10);
+
 #[cfg(feature = "sqlite-compat")]
 const DEFAULT_PRAGMAS: &[(&str, &str)] = &[
     ("journal_mode", "WAL"),
@@ -1389,6 +2921,7 @@ impl Database {
         if let Some(timeout) = options.busy_timeout {
             connection.busy_timeout(timeout)?;
         }
+        connection.set_prepared_statement_cache_capacity(options.statement_cache_size);
 
         if options.apply_default_pragmas {
             apply_pragmas(&connection, DEFAULT_PRAGMAS);
@@ -1403,12 +2936,52 @@ impl Database {
             apply_pragmas(&connection, &custom);
         }
 
+        let readers = match options.path {
+            DatabasePath::File(_) if options.max_read_connections > 0 => {
+                let reader_options = DatabaseOptions {
+                    read_only: true,
+                    max_read_connections: 0,
+                    ..options.clone()
+                };
+                Some(Arc::new(DatabasePool::new(
+                    reader_options,
+                    options.max_read_connections,
+                )))
+            }
+            _ => None,
+        };
+
         Ok(Self {
             conn: Arc::new(Mutex::new(connection)),
+            readers,
             path: options.path,
+            query_cache: None,
+            busy_retries: options.busy_retries,
         })
     }
 
+    /// Memoize read-only query results in a bounded LRU cache.
+    ///
+    /// Results are keyed on `(sql, params)` and expire after `ttl`. Any write
+    /// issued through this handle (or its clones) clears the cache; writes from
+    /// other connections are only picked up once entries expire. See the
+    /// `query_cache` module docs for the full staleness semantics.
+    pub fn with_query_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.query_cache = Some(Arc::new(QueryCache::new(capacity, ttl)));
+        self
+    }
+
+    /// Hit/miss counters for the query cache, or `None` if it is disabled.
+    pub fn query_cache_stats(&self) -> Option<QueryCacheStats> {
+        self.query_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    fn invalidate_query_cache(&self) {
+        if let Some(cache) = &self.query_cache {
+            cache.invalidate();
+        }
+    }
+
     pub fn path(&self) -> &DatabasePath {
         &self.path
     }
@@ -1421,13 +2994,17 @@ impl Database {
     }
 
     pub fn exec(&self, sql: &str) -> DbResult<ExecutionResult> {
-        self.with_connection(|conn| {
+        let result = self.with_connection(|conn| {
             conn.execute_batch(sql)?;
             Ok(ExecutionResult {
                 changes: conn.changes() as u64,
                 last_insert_rowid: conn.last_insert_rowid(),
             })
-        })
+        });
+        // Only once the write is in, so a concurrent read cannot cache the
+        // rows from before it
+        self.invalidate_query_cache();
+        result
     }
 
     pub fn query(&self, sql: &str, params: &[SqlValue]) -> DbResult<QueryResult> {
@@ -1438,33 +3015,152 @@ impl Database {
         .query_internal(params)
     }
 
+    /// Like [`query`](Self::query), binding `params` by name, e.g.
+    /// `("name", value)` for `:name`.  See [`Statement::run_named`].
+    pub fn query_named(&self, sql: &str, params: &[(&str, SqlValue)]) -> DbResult<QueryResult> {
+        Statement {
+            database: self.clone(),
+            sql: sql.to_owned(),
+        }
+        .all_named(params)
+    }
+
     pub fn pragma(&self, pragma: &str) -> DbResult<QueryResult> {
         let normalized = if pragma.trim_start().to_lowercase().starts_with("pragma") {
             pragma.trim().to_owned()
         } else {
             format!("PRAGMA {}", pragma)
         };
-        self.query(&normalized, &[])
+        // Pragmas configure the connection they run on, so keep them on the
+        // writer
+        Statement {
+            database: self.clone(),
+            sql: normalized,
+        }
+        .query_with(&[], false)
+    }
+
+    /// The value `PRAGMA <name>` returns, for pragmas that return a single
+    /// integer such as `page_size` or `user_version`.
+    ///
+    /// `name` must be a bare pragma name; fails if it is not, if the pragma
+    /// returns no row, or if its value is not an integer.
+    pub fn pragma_i64(&self, name: &str) -> DbResult<i64> {
+        self.pragma_value(name)
+    }
+
+    /// The value `PRAGMA <name>` returns, for pragmas that return a single
+    /// string such as `journal_mode` or `encoding`.
+    pub fn pragma_string(&self, name: &str) -> DbResult<String> {
+        self.pragma_value(name)
+    }
+
+    fn pragma_value<T: rusqlite::types::FromSql>(&self, name: &str) -> DbResult<T> {
+        // On the writer, like `pragma`, to see the settings it runs with
+        self.with_connection(|conn| Ok(conn.pragma_query_value(None, name, |row| row.get(0))?))
+    }
+
+    /// Pages in the main database file, free ones included.
+    pub fn page_count(&self) -> DbResult<i64> {
+        self.pragma_i64("page_count")
+    }
+
+    /// Pages in the main database file that hold no data; `VACUUM` returns
+    /// them to the filesystem.
+    pub fn freelist_count(&self) -> DbResult<i64> {
+        self.pragma_i64("freelist_count")
+    }
+
+    pub fn page_size(&self) -> DbResult<i64> {
+        self.pragma_i64("page_size")
+    }
+
+    /// Size of the main database file, as `page_count * page_size`.
+    ///
+    /// Does not count the `-wal` file, which holds writes not yet
+    /// checkpointed into the main file.
+    pub fn database_size_bytes(&self) -> DbResult<u64> {
+        let bytes = self.page_count()? * self.page_size()?;
+        Ok(u64::try_from(bytes).unwrap_or(0))
+    }
+
+    /// Run `PRAGMA wal_checkpoint(<mode>)` and return the reported frame counts.
+    pub fn wal_checkpoint(&self, mode: CheckpointMode) -> DbResult<CheckpointResult> {
+        let sql = format!("PRAGMA wal_checkpoint({})", mode.as_str());
+        self.with_connection(|conn| {
+            let result = conn.query_row(&sql, [], |row| {
+                Ok(CheckpointResult {
+                    busy: row.get::<_, i64>(0)? != 0,
+                    log_frames: row.get(1)?,
+                    checkpointed_frames: row.get(2)?,
+                })
+            })?;
+            Ok(result)
+        })
     }
 
     pub fn transaction<F, T>(&self, f: F) -> DbResult<T>
     where
         F: FnOnce(&Transaction<'_>) -> DbResult<T>,
     {
-        self.with_connection(|conn| {
+        let run = |conn: &mut Connection| -> DbResult<T> {
             let tx = conn.transaction()?;
             let result = f(&tx)?;
             tx.commit()?;
             Ok(result)
-        })
+        };
+        let result = run(&mut self.conn.lock()).map_err(|e| e.into_busy(1));
+        self.invalidate_query_cache();
+        result
+    }
+
+    /// Run `f` on the writer, retrying it while the database is busy as
+    /// described on [`Database`].
+    fn with_connection<T, F>(&self, mut f: F) -> DbResult<T>
+    where
+        F: FnMut(&mut Connection) -> DbResult<T>,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let mut conn = self.conn.lock();
+            let changes_before = conn.total_changes();
+            let source = match f(&mut conn) {
+                Err(DatabaseError::Sqlite(source)) if is_busy(&source) => source,
+                result => return result,
+            };
+            let repeatable = conn.is_autocommit() && conn.total_changes() == changes_before;
+            drop(conn);
+            if !repeatable || attempts > self.busy_retries {
+                return Err(DatabaseError::Busy { attempts, source });
+            }
+            // Capped at 64 times the initial wait
+            std::thread::sleep(BUSY_RETRY_BACKOFF * 2u32.pow((attempts - 1).min(6)));
+        }
     }
 
-    fn with_connection<T, F>(&self, f: F) -> DbResult<T>
+    /// Run `f` on a pooled reader if `sql` is read-only and readers are
+    /// configured, otherwise on the writer.  Reads stay on the writer while
+    /// it has a transaction open, which a reader could not see.
+    fn with_connection_for<T, F>(&self, sql: &str, f: F) -> DbResult<T>
     where
-        F: FnOnce(&mut Connection) -> DbResult<T>,
+        F: FnMut(&mut Connection) -> DbResult<T>,
     {
-        let mut guard = self.conn.lock();
-        f(&mut guard)
+        // A writer locked by another call is busy with a statement or a
+        // `transaction` closure, neither of which a read should wait on
+        let in_transaction = self
+            .conn
+            .try_lock()
+            .is_some_and(|conn| !conn.is_autocommit());
+        if let (Some(readers), false) = (&self.readers, in_transaction) {
+            let reader = readers.acquire()?;
+            let read_only =
+                reader.with_connection(|conn| Ok(conn.prepare_cached(sql)?.readonly()))?;
+            if read_only {
+                return reader.with_connection(f);
+            }
+        }
+        self.with_connection(f)
     }
 }
 
@@ -1482,21 +3178,39 @@ impl Statement {
     }
 
     pub fn run(&self, params: &[SqlValue]) -> DbResult<ExecutionResult> {
-        self.database.with_connection(|conn| {
-            let mut stmt = conn.prepare(&self.sql)?;
+        let result = self.database.with_connection(|conn| {
+            let mut stmt = conn.prepare_cached(&self.sql)?;
             let values = params_to_values(params);
             let changes = stmt.execute(params_from_iter(values.iter()))? as u64;
             Ok(ExecutionResult {
                 changes,
                 last_insert_rowid: conn.last_insert_rowid(),
             })
-        })
+        });
+        self.database.invalidate_query_cache();
+        result
     }
 
     pub fn all(&self, params: &[SqlValue]) -> DbResult<QueryResult> {
         self.query_internal(params)
     }
 
+    /// Like [`run`](Self::run), binding `params` by name.
+    ///
+    /// Names may be given with or without their `:`, `@`, or `$` prefix.
+    /// Every parameter in the SQL must be named and given a value; SQL that
+    /// also uses positional `?` parameters is rejected with
+    /// [`DatabaseError::InvalidParameter`], as is a name the SQL lacks.
+    pub fn run_named(&self, params: &[(&str, SqlValue)]) -> DbResult<ExecutionResult> {
+        self.run(&self.named_to_positional(params)?)
+    }
+
+    /// Like [`all`](Self::all), binding `params` by name as
+    /// [`run_named`](Self::run_named) does.
+    pub fn all_named(&self, params: &[(&str, SqlValue)]) -> DbResult<QueryResult> {
+        self.query_internal(&self.named_to_positional(params)?)
+    }
+
     pub fn get(&self, params: &[SqlValue]) -> DbResult<Option<HashMap<String, SqlValue>>> {
         let result = self.query_internal(params)?;
         Ok(result.rows_as_maps().into_iter().next())
@@ -1504,7 +3218,7 @@ impl Statement {
 
     pub fn columns(&self) -> DbResult<Vec<String>> {
         self.database.with_connection(|conn| {
-            let stmt = conn.prepare(&self.sql)?;
+            let stmt = conn.prepare_cached(&self.sql)?;
             Ok(stmt
                 .column_names()
                 .iter()
@@ -1513,9 +3227,109 @@ impl Statement {
         })
     }
 
+    /// Run the statement and hand each row to `on_row` as it is read,
+    /// without collecting the result set, and return the number of rows.
+    ///
+    /// Memory use stays constant however many rows the statement yields, so
+    /// this is the way to export large tables.  The query cache is bypassed.
+    /// The connection stays locked until the last row is read, and an error
+    /// from `on_row` stops the scan and is returned as-is.
+    ///
+    /// # Deadlocks
+    ///
+    /// `on_row` runs with the writer connection's mutex held, so it must not
+    /// use the same [`Database`]: `exec`, `transaction` and writes through
+    /// `query` or a [`Statement`] always need the writer and block forever,
+    /// and so do reads when no read connections are configured.  Collect
+    /// what the rows imply and act on it after `stream` returns.
+    pub fn stream<F, E>(&self, params: &[SqlValue], mut on_row: F) -> Result<u64, E>
+    where
+        F: FnMut(Vec<SqlValue>) -> Result<(), E>,
+        E: From<DatabaseError>,
+    {
+        let db_error = |e: rusqlite::Error| DatabaseError::from(e).into_busy(1);
+        let conn = self.database.conn.lock();
+        let mut stmt = conn.prepare_cached(&self.sql).map_err(db_error)?;
+        let read_only = stmt.readonly();
+        let column_count = stmt.column_count();
+        let values = params_to_values(params);
+        let mut scan = || -> Result<u64, E> {
+            let mut rows = stmt
+                .query(params_from_iter(values.iter()))
+                .map_err(db_error)?;
+            let mut count = 0;
+            while let Some(row) = rows.next().map_err(db_error)? {
+                on_row(read_row(row, column_count).map_err(db_error)?)?;
+                count += 1;
+            }
+            Ok(count)
+        };
+        let result = scan();
+        if !read_only {
+            self.database.invalidate_query_cache();
+        }
+        result
+    }
+
     fn query_internal(&self, params: &[SqlValue]) -> DbResult<QueryResult> {
-        self.database.with_connection(|conn| {
-            let mut stmt = conn.prepare(&self.sql)?;
+        self.query_with(params, true)
+    }
+
+    /// Order named `params` by the position of their parameter in the SQL,
+    /// so they can be bound positionally.
+    fn named_to_positional(&self, params: &[(&str, SqlValue)]) -> DbResult<Vec<SqlValue>> {
+        self.database.with_connection_for(&self.sql, |conn| {
+            let stmt = conn.prepare_cached(&self.sql)?;
+            let count = stmt.parameter_count();
+            if let Some(index) = (1..=count).find(|&index| {
+                stmt.parameter_name(index)
+                    .is_none_or(|name| name.starts_with('?'))
+            }) {
+                return Err(DatabaseError::InvalidParameter(format!(
+                    "parameter {index} of `{}` is positional; named and positional \
+                     parameters cannot be mixed",
+                    self.sql
+                )));
+            }
+
+            let mut values = vec![None; count];
+            for (name, value) in params {
+                let index = named_parameter_index(&stmt, name)?.ok_or_else(|| {
+                    DatabaseError::InvalidParameter(format!(
+                        "`{}` has no parameter named {name}",
+                        self.sql
+                    ))
+                })?;
+                values[index - 1] = Some(value.clone());
+            }
+            values
+                .into_iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    value.ok_or_else(|| {
+                        DatabaseError::InvalidParameter(format!(
+                            "no value given for parameter {}",
+                            stmt.parameter_name(i + 1).unwrap_or_default()
+                        ))
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Run the query, on a pooled reader if `use_readers` and the SQL is
+    /// read-only, otherwise on the writer.
+    fn query_with(&self, params: &[SqlValue], use_readers: bool) -> DbResult<QueryResult> {
+        let cache = self.database.query_cache.as_deref();
+        if let Some(hit) = cache.and_then(|cache| cache.get(&self.sql, params)) {
+            return Ok(hit);
+        }
+        // Taken before the query runs, so a write landing meanwhile keeps
+        // its result out of the cache
+        let epoch = cache.map(|cache| cache.epoch());
+        let run = |conn: &mut Connection| {
+            let mut stmt = conn.prepare_cached(&self.sql)?;
+            let read_only = stmt.readonly();
             let columns = stmt
                 .column_names()
                 .iter()
@@ -1523,18 +3337,41 @@ impl Statement {
                 .collect::<Vec<_>>();
             let values = params_to_values(params);
             let column_count = columns.len();
-            let mut rows_iter = stmt.query(params_from_iter(values.iter()))?;
-            let mut rows = Vec::new();
-            while let Some(row) = rows_iter.next()? {
-                rows.push(read_row(&row, column_count)?);
+            let read = (|| -> DbResult<Vec<Vec<SqlValue>>> {
+                let mut rows_iter = stmt.query(params_from_iter(values.iter()))?;
+                let mut rows = Vec::new();
+                while let Some(row) = rows_iter.next()? {
+                    rows.push(read_row(row, column_count)?);
+                }
+                Ok(rows)
+            })();
+            drop(stmt);
+            if !read_only {
+                self.database.invalidate_query_cache();
+                return Ok(QueryResult {
+                    columns,
+                    rows: read?,
+                    changes: conn.changes() as u64,
+                    last_insert_rowid: conn.last_insert_rowid(),
+                });
             }
-            Ok(QueryResult {
+            let result = QueryResult {
                 columns,
-                rows,
-                changes: conn.changes() as u64,
-                last_insert_rowid: conn.last_insert_rowid(),
-            })
-        })
+                rows: read?,
+                changes: 0,
+                last_insert_rowid: 0,
+            };
+            // Rows read inside an open transaction may yet be rolled back
+            if let (Some(cache), Some(epoch), true) = (cache, epoch, conn.is_autocommit()) {
+                cache.insert(&self.sql, params, epoch, result.clone());
+            }
+            Ok(result)
+        };
+        if use_readers {
+            self.database.with_connection_for(&self.sql, run)
+        } else {
+            self.database.with_connection(run)
+        }
     }
 }
 
@@ -1561,6 +3398,21 @@ fn apply_pragmas(connection: &Connection, pragmas: &[(&str, &str)]) {
     }
 }
 
+/// Index of the parameter called `name`, trying each prefix SQLite allows if
+/// `name` has none.
+#[cfg(feature = "sqlite-compat")]
+fn named_parameter_index(stmt: &rusqlite::Statement<'_>, name: &str) -> DbResult<Option<usize>> {
+    if name.starts_with([':', '@', '$']) {
+        return Ok(stmt.parameter_index(name)?);
+    }
+    for prefix in [':', '@', '$'] {
+        if let Some(index) = stmt.parameter_index(&format!("{prefix}{name}"))? {
+            return Ok(Some(index));
+        }
+    }
+    Ok(None)
+}
+
 #[cfg(feature = "sqlite-compat")]
 fn params_to_values(params: &[SqlValue]) -> Vec<SqliteValue> {
     params
@@ -1661,6 +3513,49 @@ impl FastEmbedder {
         })
     }
 
+    /// Load a sentence-transformers ONNX export from `dir` without touching
+    /// the network, e.g. all-MiniLM-L6-v2 for 384-dimensional embeddings.
+    ///
+    /// `dir` must hold `model.onnx`, `tokenizer.json`, `config.json`,
+    /// `special_tokens_map.json` and `tokenizer_config.json`.  Token
+    /// embeddings are mean-pooled as sentence-transformers does, and the
+    /// dimension is read off the model's output.
+    #[cfg(feature = "local-embeddings")]
+    pub fn from_dir(dir: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        use anyhow::Context;
+        use fastembed::{
+            InitOptionsUserDefined, Pooling, TextEmbedding, TokenizerFiles,
+            UserDefinedEmbeddingModel,
+        };
+
+        let dir = dir.as_ref();
+        let read = |name: &str| {
+            std::fs::read(dir.join(name))
+                .with_context(|| format!("failed to read {}", dir.join(name).display()))
+        };
+        let tokenizer_files = TokenizerFiles {
+            tokenizer_file: read("tokenizer.json")?,
+            config_file: read("config.json")?,
+            special_tokens_map_file: read("special_tokens_map.json")?,
+            tokenizer_config_file: read("tokenizer_config.json")?,
+        };
+        let user_model = UserDefinedEmbeddingModel::new(read("model.onnx")?, tokenizer_files)
+            .with_pooling(Pooling::Mean);
+        let mut model =
+            TextEmbedding::try_new_from_user_defined(user_model, InitOptionsUserDefined::default())
+                .with_context(|| format!("failed to load ONNX model from {}", dir.display()))?;
+        let dimension = model
+            .embed(vec!["dimension probe"], None)?
+            .first()
+            .map(Vec::len)
+            .context("model returned no embedding")?;
+        Ok(Self {
+            model: std::sync::Mutex::new(model),
+            dimension,
+            model_id: format!("local:{}", dir.display()),
+        })
+    }
+
     pub fn model_id(&self) -> &str {
         &self.model_id
     }
@@ -1758,22 +3653,722 @@ mod tests {
         store.put("b", "actor", serde_json::json!({}));
         assert_eq!(written.load(Ordering::Relaxed), 2);
 
-        store.put_with_embedding(
-            "c",
-            "actor",
-            serde_json::json!({"label": "c"}),
-            vec![1.0, 0.0, 0.0],
+        store.put_with_embedding(
+            "c",
+            "actor",
+            serde_json::json!({"label": "c"}),
+            vec![1.0, 0.0, 0.0],
+        );
+        assert_eq!(
+            written.load(Ordering::Relaxed),
+            3,
+            "put_with_embedding must also fire on_node_written"
+        );
+
+        store.delete("a").unwrap();
+        assert_eq!(deleted.load(Ordering::Relaxed), 1);
+
+        let _store2 = CrdtStore::default().with_lm_plugin(Arc::new(NoOpPlugin));
+    }
+
+    #[test]
+    fn range_by_time_filters_half_open_window() {
+        let store = CrdtStore::default();
+        let base = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for (id, hours) in [("t3", 3), ("t0", 0), ("t1", 1), ("t2", 2)] {
+            let mut record = NodeRecord::new(id.to_string(), "actor", serde_json::json!({}));
+            record.timestamp = base + chrono::Duration::hours(hours);
+            store.nodes.insert(id.to_string(), record);
+        }
+        let ids = |records: Vec<NodeRecord>| -> Vec<String> {
+            records.into_iter().map(|r| r.id).collect()
+        };
+        let at = |hours| Some(base + chrono::Duration::hours(hours));
+
+        assert_eq!(ids(store.range_by_time(at(1), at(3))), vec!["t1", "t2"]);
+        assert_eq!(ids(store.range_by_time(None, at(1))), vec!["t0"]);
+        assert_eq!(ids(store.range_by_time(at(2), None)), vec!["t2", "t3"]);
+        assert_eq!(
+            ids(store.range_by_time(None, None)),
+            vec!["t0", "t1", "t2", "t3"]
+        );
+        assert!(store.range_by_time(at(3), at(3)).is_empty());
+    }
+
+    #[test]
+    fn actor_frontier_takes_per_actor_max() {
+        let store = CrdtStore::default();
+        assert!(store.actor_frontier().is_empty());
+
+        for _ in 0..3 {
+            store.put("a", "actor-1", serde_json::json!({}));
+        }
+        store.put("a", "actor-2", serde_json::json!({}));
+        store.put("b", "actor-1", serde_json::json!({}));
+        for _ in 0..5 {
+            store.put("b", "actor-2", serde_json::json!({}));
+        }
+        store.put("c", "actor-3", serde_json::json!({}));
+
+        let frontier = store.actor_frontier();
+        assert_eq!(frontier.len(), 3);
+        assert_eq!(frontier["actor-1"], 3);
+        assert_eq!(frontier["actor-2"], 5);
+        assert_eq!(frontier["actor-3"], 1);
+    }
+
+    #[test]
+    fn diff_since_brings_diverged_stores_together() {
+        let sync = |from: &CrdtStore, to: &CrdtStore| {
+            for record in from.diff_since(&to.state_clock()) {
+                to.merge_record(record);
+            }
+        };
+        let ids = |store: &CrdtStore| {
+            let mut ids: Vec<NodeId> = store.list().into_iter().map(|record| record.id).collect();
+            ids.sort();
+            ids
+        };
+
+        let left = CrdtStore::default();
+        let right = CrdtStore::default();
+        left.put("shared", "alice", serde_json::json!({ "v": 1 }));
+        left.put("doomed", "alice", serde_json::json!({}));
+        sync(&left, &right);
+        assert_eq!(ids(&right), ["doomed", "shared"]);
+        assert!(left.diff_since(&right.state_clock()).is_empty());
+
+        left.put("shared", "alice", serde_json::json!({ "v": 2 }));
+        left.delete("doomed").unwrap();
+        right.put("mine", "bob", serde_json::json!({}));
+
+        let diff = left.diff_since(&right.state_clock());
+        let mut sent: Vec<NodeId> = diff.iter().map(|record| record.id.clone()).collect();
+        sent.sort();
+        assert_eq!(sent, ["doomed", "shared"]);
+        assert!(diff.iter().any(NodeRecord::is_tombstone));
+
+        sync(&left, &right);
+        sync(&right, &left);
+        assert_eq!(ids(&left), ["mine", "shared"]);
+        assert_eq!(ids(&right), ids(&left));
+        assert_eq!(
+            right.get("shared").unwrap().data,
+            serde_json::json!({ "v": 2 })
+        );
+        assert_eq!(left.state_clock(), right.state_clock());
+    }
+
+    #[test]
+    fn type_histogram_counts_untyped_nodes_separately() {
+        let store = CrdtStore::default();
+        assert!(store.is_empty());
+        store.put("u1", "actor", serde_json::json!({ "type": "user" }));
+        store.put("u2", "actor", serde_json::json!({ "type": "user" }));
+        store.put("p1", "actor", serde_json::json!({ "type": "post" }));
+        store.put("n1", "actor", serde_json::json!({ "name": "no type" }));
+        store.put("n2", "actor", serde_json::json!({ "type": 7 }));
+
+        let histogram = store.type_histogram();
+        assert_eq!(histogram.len(), 3);
+        assert_eq!(histogram["user"], 2);
+        assert_eq!(histogram["post"], 1);
+        assert_eq!(histogram[UNTYPED_BUCKET], 2);
+        assert_eq!(store.len(), 5);
+        assert!(!store.is_empty());
+    }
+
+    #[test]
+    fn preview_remote_batch_matches_apply_outcome() {
+        let clock = |entries: &[(&str, u64)]| -> VectorClock {
+            entries.iter().map(|(a, c)| (a.to_string(), *c)).collect()
+        };
+        let put = |id: &str, v: i64| CrdtOperation::Put {
+            id: id.to_string(),
+            actor: "remote".to_string(),
+            data: serde_json::json!({ "v": v }),
+        };
+        let delete = |id: &str| CrdtOperation::Delete { id: id.to_string() };
+
+        let store = CrdtStore::default();
+        store.put("stale", "local", serde_json::json!({ "v": 0 }));
+        store.put("shared", "local", serde_json::json!({ "v": 0 }));
+        store.put("doomed", "local", serde_json::json!({ "v": 0 }));
+        store.put("kept", "local", serde_json::json!({ "v": 0 }));
+        store.put("kept", "local", serde_json::json!({ "v": 1 }));
+
+        let now = Utc::now();
+        let batch = vec![
+            (put("new", 1), clock(&[("remote", 1)]), now),
+            (put("stale", 1), clock(&[("local", 1)]), now),
+            (put("shared", 1), clock(&[("remote", 1)]), now),
+            (delete("doomed"), clock(&[("local", 1), ("remote", 1)]), now),
+            (delete("kept"), clock(&[("local", 1), ("remote", 1)]), now),
+            (delete("missing"), clock(&[("remote", 1)]), now),
+            (put("new", 2), clock(&[("remote", 2)]), now),
+        ];
+        let before = store.list().len();
+
+        let preview = store.preview_remote_batch(&batch);
+        assert_eq!(store.list().len(), before, "preview must not mutate");
+        assert!(store.get("new").is_none());
+
+        let outcomes: Vec<_> = preview.iter().map(|p| p.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ConflictOutcome::Applied,
+                ConflictOutcome::Ignored,
+                ConflictOutcome::Concurrent,
+                ConflictOutcome::Applied,
+                ConflictOutcome::Concurrent,
+                ConflictOutcome::Ignored,
+                ConflictOutcome::Applied,
+            ]
+        );
+
+        assert_eq!(store.apply_remote_batch(&batch), preview);
+        assert_eq!(
+            store.get("new").unwrap().data,
+            serde_json::json!({ "v": 2 })
+        );
+        assert!(store.get("doomed").is_none());
+        assert!(store.get("kept").is_some());
+        assert_eq!(
+            store.get("stale").unwrap().data,
+            serde_json::json!({ "v": 0 })
+        );
+    }
+
+    #[test]
+    fn preview_remote_batch_sees_the_clock_of_a_deleted_node() {
+        let clock = |entries: &[(&str, u64)]| -> VectorClock {
+            entries.iter().map(|(a, c)| (a.to_string(), *c)).collect()
+        };
+        let store = CrdtStore::default();
+        store.put("doc", "local", serde_json::json!({ "v": 0 }));
+
+        // The put was written before the delete, so it must not revive the node
+        let now = Utc::now();
+        let batch = vec![
+            (
+                CrdtOperation::Delete {
+                    id: "doc".to_string(),
+                },
+                clock(&[("local", 1), ("remote", 1)]),
+                now,
+            ),
+            (
+                CrdtOperation::Put {
+                    id: "doc".to_string(),
+                    actor: "local".to_string(),
+                    data: serde_json::json!({ "v": 0 }),
+                },
+                clock(&[("local", 1)]),
+                now,
+            ),
+        ];
+        let preview = store.preview_remote_batch(&batch);
+        let outcomes: Vec<_> = preview.iter().map(|p| p.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![ConflictOutcome::Applied, ConflictOutcome::Ignored]
+        );
+
+        assert_eq!(store.apply_remote_batch(&batch), preview);
+        assert!(store.get("doc").is_none());
+        let tombstone = store.get_including_tombstones("doc").unwrap();
+        assert_eq!(tombstone.clock, clock(&[("local", 1), ("remote", 1)]));
+    }
+
+    #[test]
+    fn concurrent_remote_puts_converge_on_both_replicas() {
+        let left = CrdtStore::default();
+        let right = CrdtStore::default();
+        left.put("doc", "actor-a", serde_json::json!({ "from": "a" }));
+        right.put("doc", "actor-b", serde_json::json!({ "from": "b" }));
+        let as_op = |store: &CrdtStore, actor: &str| {
+            let record = store.get("doc").unwrap();
+            let op = CrdtOperation::Put {
+                id: record.id,
+                actor: actor.to_string(),
+                data: record.data,
+            };
+            vec![(op, record.clock, record.timestamp)]
+        };
+        let (from_left, from_right) = (as_op(&left, "actor-a"), as_op(&right, "actor-b"));
+
+        for (store, batch) in [(&left, &from_right), (&right, &from_left)] {
+            let outcomes = store.apply_remote_batch(batch);
+            assert_eq!(outcomes[0].outcome, ConflictOutcome::Concurrent);
+        }
+        let (l, r) = (left.get("doc").unwrap(), right.get("doc").unwrap());
+        assert_eq!(l.data, r.data);
+        assert_eq!(l.clock, r.clock);
+        assert_eq!(l.timestamp, r.timestamp);
+        // The right replica wrote last
+        assert_eq!(l.data, serde_json::json!({ "from": "b" }));
+    }
+
+    #[test]
+    fn merge_remote_converges_regardless_of_order() {
+        let origin = CrdtStore::default();
+        origin.put("doc", "actor-a", serde_json::json!({ "v": 1 }));
+        let first = origin.get("doc").unwrap();
+        origin.put("doc", "actor-a", serde_json::json!({ "v": 2 }));
+        let second = origin.get("doc").unwrap();
+
+        let replica = CrdtStore::default();
+        assert!(replica.merge_remote(second.clone()));
+        assert!(!replica.merge_remote(first));
+        assert!(!replica.merge_remote(second.clone()));
+
+        let merged = replica.get("doc").unwrap();
+        assert_eq!(merged.data, serde_json::json!({ "v": 2 }));
+        assert_eq!(merged.clock, second.clock);
+
+        replica.put("doc", "actor-b", serde_json::json!({ "v": 3 }));
+        assert!(origin.merge_remote(replica.get("doc").unwrap()));
+        assert_eq!(
+            origin.get("doc").unwrap().data,
+            serde_json::json!({ "v": 3 })
+        );
+        assert_eq!(origin.get("doc").unwrap().clock.len(), 2);
+    }
+
+    #[test]
+    fn put_batch_writes_every_item_with_a_fresh_clock() {
+        let store = CrdtStore::default();
+        let items: Vec<(NodeId, NodeData)> = (0..1000)
+            .map(|i| (format!("node-{i}"), serde_json::json!({ "i": i })))
+            .collect();
+        let expected: Vec<NodeId> = items.iter().map(|(id, _)| id.clone()).collect();
+
+        assert_eq!(store.put_batch("ingest", items), expected);
+        assert_eq!(store.len(), 1000);
+        for (i, id) in expected.iter().enumerate() {
+            let record = store.get(id).unwrap();
+            assert_eq!(record.data, serde_json::json!({ "i": i }));
+            assert_eq!(record.clock, VectorClock::from([("ingest".to_string(), 1)]));
+        }
+
+        let op = CrdtOperation::Batch {
+            actor: "ingest".to_string(),
+            items: vec![("node-0".to_string(), serde_json::json!({ "i": -1 }))],
+        };
+        assert_eq!(store.apply(op).unwrap(), None);
+        assert_eq!(store.get("node-0").unwrap().clock["ingest"], 2);
+    }
+
+    fn record_at(clock: &[(&str, u64)], secs: i64, v: i64) -> NodeRecord {
+        NodeRecord {
+            id: "doc".to_string(),
+            data: serde_json::json!({ "v": v }),
+            clock: clock.iter().map(|(a, c)| (a.to_string(), *c)).collect(),
+            timestamp: DateTime::from_timestamp(secs, 0).unwrap(),
+            embedding: None,
+            quality_score: None,
+            deleted_at: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn merge_record_follows_clock_dominance() {
+        let store = CrdtStore::default();
+        assert_eq!(
+            store.merge_record(record_at(&[("a", 1)], 10, 1)),
+            MergeOutcome::Applied
+        );
+        // Dominated, even though its timestamp is later.
+        assert_eq!(
+            store.merge_record(record_at(&[("a", 1)], 99, 0)),
+            MergeOutcome::Ignored
+        );
+        // Dominating, even though its timestamp is earlier.
+        assert_eq!(
+            store.merge_record(record_at(&[("a", 2)], 5, 2)),
+            MergeOutcome::Applied
+        );
+        assert_eq!(
+            store.get("doc").unwrap().data,
+            serde_json::json!({ "v": 2 })
+        );
+    }
+
+    #[test]
+    fn merge_record_breaks_concurrent_ties_the_same_way_everywhere() {
+        let cases = [
+            // Later timestamp wins.
+            (
+                record_at(&[("a", 1)], 20, 1),
+                record_at(&[("b", 1)], 10, 2),
+                1,
+            ),
+            // Equal timestamps: the greatest actor unseen by the other side wins.
+            (
+                record_at(&[("a", 1)], 10, 1),
+                record_at(&[("b", 1)], 10, 2),
+                2,
+            ),
+            (
+                record_at(&[("a", 2), ("c", 1)], 10, 1),
+                record_at(&[("a", 1), ("b", 3)], 10, 2),
+                1,
+            ),
+        ];
+        for (left, right, winner) in cases {
+            for (first, second) in [(&left, &right), (&right, &left)] {
+                let store = CrdtStore::default();
+                store.merge_record(first.clone());
+                assert_eq!(store.merge_record(second.clone()), MergeOutcome::Conflict);
+
+                let merged = store.get("doc").unwrap();
+                assert_eq!(merged.data, serde_json::json!({ "v": winner }));
+                for (actor, counter) in left.clock.iter().chain(&right.clock) {
+                    assert!(merged.clock[actor] >= *counter);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn delete_leaves_a_tombstone_that_stale_puts_cannot_revive() {
+        let origin = CrdtStore::default();
+        origin.put("doc", "actor-a", serde_json::json!({ "v": 1 }));
+        let stale = origin.get("doc").unwrap();
+
+        let replica = CrdtStore::default();
+        assert_eq!(replica.merge_record(stale.clone()), MergeOutcome::Applied);
+        replica.delete("doc").unwrap();
+        assert!(matches!(
+            replica.delete("doc"),
+            Err(StoreError::NotFound(_))
+        ));
+
+        assert_eq!(replica.merge_record(stale), MergeOutcome::Ignored);
+        assert!(replica.get("doc").is_none());
+        assert!(replica.list().is_empty());
+        assert_eq!(replica.len(), 0);
+        let tombstones = replica.list_including_tombstones();
+        assert_eq!(tombstones.len(), 1);
+        assert!(tombstones[0].is_tombstone());
+
+        // The tombstone replicates back and removes the node at the origin.
+        assert_eq!(
+            origin.merge_record(tombstones[0].clone()),
+            MergeOutcome::Applied
+        );
+        assert!(origin.get("doc").is_none());
+
+        // A newer local write recreates the node.
+        replica.put("doc", "actor-b", serde_json::json!({ "v": 2 }));
+        assert_eq!(
+            replica.get("doc").unwrap().data,
+            serde_json::json!({ "v": 2 })
+        );
+    }
+
+    #[test]
+    fn purge_tombstones_only_drops_tombstones_older_than_cutoff() {
+        let store = CrdtStore::default();
+        store.put("gone", "actor", serde_json::json!({}));
+        store.put("kept", "actor", serde_json::json!({}));
+        store.delete("gone").unwrap();
+
+        assert_eq!(
+            store.purge_tombstones(Utc::now() - chrono::Duration::hours(1)),
+            0
+        );
+        assert_eq!(store.list_including_tombstones().len(), 2);
+
+        assert_eq!(
+            store.purge_tombstones(Utc::now() + chrono::Duration::seconds(1)),
+            1
+        );
+        let remaining = store.list_including_tombstones();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "kept");
+    }
+
+    #[test]
+    fn purge_tombstones_keeps_nodes_put_again_since_its_scan() {
+        const NODES: usize = 500;
+        let store = CrdtStore::default();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..NODES {
+                    let id = format!("n{}", i);
+                    store.put(id.clone(), "actor", serde_json::json!({}));
+                    store.delete(&id).unwrap();
+                    store.put(id, "actor", serde_json::json!({ "i": i }));
+                }
+            });
+            scope.spawn(|| {
+                for _ in 0..200 {
+                    store.purge_tombstones(Utc::now() + chrono::Duration::hours(1));
+                }
+            });
+        });
+        assert_eq!(store.list().len(), NODES);
+    }
+
+    #[test]
+    fn delete_racing_puts_never_rolls_the_clock_back() {
+        const PUTS: u64 = 2_000;
+        let store = CrdtStore::default();
+        store.put("n", "writer", serde_json::json!({ "v": 0 }));
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for v in 1..=PUTS {
+                    store.put("n", "writer", serde_json::json!({ "v": v }));
+                }
+            });
+            scope.spawn(|| {
+                for _ in 0..PUTS {
+                    let _ = store.delete("n");
+                }
+            });
+        });
+        // Every put bumped the clock once; a tombstone overwriting a newer
+        // put would have made a later put reuse a counter
+        let record = store.get_including_tombstones("n").unwrap();
+        assert_eq!(record.clock.get("writer"), Some(&(PUTS + 1)));
+    }
+
+    #[test]
+    fn put_with_ttl_is_visible_until_it_expires() {
+        let store = CrdtStore::default();
+        store.put_with_ttl(
+            "session",
+            "actor",
+            serde_json::json!({ "user": "alice" }),
+            Duration::from_millis(200),
+        );
+        store.put_with_ttl(
+            "cache",
+            "actor",
+            serde_json::json!({}),
+            Duration::from_secs(3600),
+        );
+        let session = store.get("session").unwrap();
+        assert_eq!(
+            session.expires_at,
+            Some(session.timestamp + chrono::Duration::milliseconds(200))
+        );
+        assert_eq!(store.list().len(), 2);
+
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(store.get("session").is_none());
+        let live: Vec<NodeId> = store.list().into_iter().map(|record| record.id).collect();
+        assert_eq!(live, ["cache"]);
+        assert_eq!(store.len(), 1);
+
+        // A plain put on an expiring node makes it permanent
+        store.put("cache", "actor", serde_json::json!({ "v": 2 }));
+        assert_eq!(store.get("cache").unwrap().expires_at, None);
+    }
+
+    #[test]
+    fn sweep_expired_keeps_nodes_made_permanent_since_its_scan() {
+        const NODES: usize = 500;
+        let store = CrdtStore::default();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..NODES {
+                    let id = format!("n{}", i);
+                    store.put_with_ttl(id.clone(), "actor", serde_json::json!({}), Duration::ZERO);
+                    store.put(id, "actor", serde_json::json!({ "i": i }));
+                }
+            });
+            scope.spawn(|| {
+                for _ in 0..200 {
+                    store.sweep_expired();
+                }
+            });
+        });
+        assert_eq!(store.list().len(), NODES);
+    }
+
+    #[test]
+    fn sweep_expired_leaves_tombstones_that_replicate() {
+        let store = CrdtStore::default();
+        for id in ["a", "b"] {
+            store.put_with_ttl(id, "actor", serde_json::json!({}), Duration::ZERO);
+        }
+        store.put_with_ttl(
+            "c",
+            "actor",
+            serde_json::json!({}),
+            Duration::from_secs(3600),
+        );
+        store.put("d", "actor", serde_json::json!({}));
+
+        let replica = CrdtStore::default();
+        for record in store.list_including_tombstones() {
+            replica.merge_record(record);
+        }
+        assert_eq!(replica.len(), 2);
+
+        assert_eq!(store.sweep_expired(), 2);
+        assert_eq!(store.sweep_expired(), 0);
+        let tombstones: Vec<NodeRecord> = store
+            .list_including_tombstones()
+            .into_iter()
+            .filter(NodeRecord::is_tombstone)
+            .collect();
+        assert_eq!(tombstones.len(), 2);
+
+        // The tombstones replace the expired records on a peer that never swept
+        for record in tombstones {
+            assert_eq!(replica.merge_record(record), MergeOutcome::Applied);
+        }
+        assert_eq!(replica.sweep_expired(), 0);
+        let mut live: Vec<NodeId> = replica.list().into_iter().map(|record| record.id).collect();
+        live.sort();
+        assert_eq!(live, ["c", "d"]);
+    }
+
+    #[test]
+    fn metrics_count_local_operations_but_not_merges() {
+        let store = CrdtStore::default();
+        assert_eq!(store.metrics(), StoreMetrics::default());
+
+        store.put("a", "actor", serde_json::json!({}));
+        store.put("b", "actor", serde_json::json!({}));
+        assert!(store.get("a").is_some());
+        assert!(store.get("missing").is_none());
+        store.delete("a").unwrap();
+        assert!(store.delete("a").is_err());
+        assert!(store.get("a").is_none());
+
+        let peer = CrdtStore::default();
+        peer.put("c", "peer", serde_json::json!({}));
+        for record in peer.list() {
+            store.merge_record(record);
+        }
+
+        assert_eq!(
+            store.metrics(),
+            StoreMetrics {
+                puts: 2,
+                get_hits: 1,
+                get_misses: 2,
+                deletes: 1,
+                nodes: 2
+            }
+        );
+    }
+
+    #[test]
+    fn patch_merges_fields_and_leaves_others_untouched() {
+        let store = CrdtStore::default();
+        store.put(
+            "node-1",
+            "actor-a",
+            serde_json::json!({"name": "Alice", "tags": {"a": 1, "b": 2}}),
+        );
+
+        store
+            .patch(
+                "node-1",
+                "actor-b",
+                JsonPatch::merge(serde_json::json!({"age": 30, "tags": {"b": null}})),
+            )
+            .unwrap();
+
+        let record = store.get("node-1").expect("record should exist");
+        assert_eq!(
+            record.data,
+            serde_json::json!({"name": "Alice", "age": 30, "tags": {"a": 1}})
         );
+        assert_eq!(record.clock.get("actor-a"), Some(&1));
+        assert_eq!(record.clock.get("actor-b"), Some(&1));
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys_and_strips_whitespace() {
+        let a: JsonValue =
+            serde_json::from_str(r#"{ "b": [1, {"z": true, "y": null}], "a": "x" }"#).unwrap();
+        let b = serde_json::json!({"a": "x", "b": [1, {"y": null, "z": true}]});
+
         assert_eq!(
-            written.load(Ordering::Relaxed),
-            3,
-            "put_with_embedding must also fire on_node_written"
+            canonical_json(&a),
+            r#"{"a":"x","b":[1,{"y":null,"z":true}]}"#
+        );
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+        assert_ne!(
+            canonical_json(&a),
+            canonical_json(&serde_json::json!({"a": "x", "b": [1, {"y": null, "z": false}]}))
+        );
+    }
+
+    #[test]
+    fn content_hash_ids_ignore_key_order() {
+        let a = serde_json::json!({"name": "Alice", "tags": [1, 2], "nested": {"x": 1, "y": 2}});
+        let b = serde_json::json!({"nested": {"y": 2, "x": 1}, "tags": [1, 2], "name": "Alice"});
+        let id = IdStrategy::ContentHash.derive_id(&a);
+        assert_eq!(id, IdStrategy::ContentHash.derive_id(&b));
+        assert_eq!(id.len(), 64);
+        assert_ne!(
+            id,
+            IdStrategy::ContentHash.derive_id(&serde_json::json!({"tags": [2, 1]}))
         );
+    }
 
-        store.delete("a").unwrap();
-        assert_eq!(deleted.load(Ordering::Relaxed), 1);
+    #[test]
+    fn put_content_addressed_dedups_identical_content() {
+        let store = CrdtStore::default();
+        let first = store.put_content_addressed("actor-a", serde_json::json!({"v": 1}));
+        let second = store.put_content_addressed("actor-b", serde_json::json!({"v": 1}));
 
-        let _store2 = CrdtStore::default().with_lm_plugin(Arc::new(NoOpPlugin));
+        assert_eq!(first, second);
+        assert_eq!(store.list().len(), 1);
+        let record = store.get(&first).unwrap();
+        assert_eq!(record.clock.get("actor-b"), None);
+    }
+
+    #[test]
+    fn patch_missing_node_starts_from_empty_object() {
+        let store = CrdtStore::default();
+        store
+            .patch(
+                "fresh",
+                "actor-a",
+                JsonPatch::merge(serde_json::json!({"x": 1, "y": null})),
+            )
+            .unwrap();
+        let record = store.get("fresh").expect("patch should create the node");
+        assert_eq!(record.data, serde_json::json!({"x": 1}));
+    }
+
+    #[test]
+    fn concurrent_patches_to_different_fields_all_land() {
+        const THREADS: usize = 8;
+        let store = CrdtStore::default();
+        store.put("doc", "seed", serde_json::json!({}));
+        std::thread::scope(|scope| {
+            for t in 0..THREADS {
+                let store = &store;
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        let mut fields = serde_json::Map::new();
+                        fields.insert(format!("f{}_{}", t, i), i.into());
+                        store
+                            .patch(
+                                "doc",
+                                format!("actor-{}", t),
+                                JsonPatch::merge(JsonValue::Object(fields)),
+                            )
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        let data = store.get("doc").unwrap().data;
+        assert_eq!(data.as_object().unwrap().len(), THREADS * 50);
     }
 
     #[test]
@@ -1803,6 +4398,90 @@ mod tests {
         assert!(store.get("node-3").is_none());
     }
 
+    #[test]
+    fn node_under_the_size_limit_is_written() {
+        let store = CrdtStore::default().with_max_node_size(16);
+        // {"n":"0123456"} is exactly 15 bytes
+        let data = serde_json::json!({ "n": "0123456" });
+        store.try_put("small", "actor-a", data.clone()).unwrap();
+        assert_eq!(store.get("small").unwrap().data, data);
+        assert_eq!(store.max_node_size(), Some(16));
+    }
+
+    #[test]
+    fn node_over_the_size_limit_is_rejected() {
+        let store = CrdtStore::default().with_max_node_size(16);
+        let big = serde_json::json!({ "n": "0123456789" });
+        let err = store.try_put("big", "actor-a", big.clone()).unwrap_err();
+        assert!(matches!(
+            err,
+            StoreError::NodeTooLarge { size: 18, max: 16 }
+        ));
+        assert_eq!(err.code(), CoreErrorCode::NodeTooLarge);
+        assert!(store.get("big").is_none());
+
+        // A batch with one oversized item writes none of them
+        let batch = CrdtOperation::Batch {
+            actor: "actor-a".to_string(),
+            items: vec![
+                ("fits".to_string(), serde_json::json!({})),
+                ("big".to_string(), big.clone()),
+            ],
+        };
+        assert!(store.apply(batch).is_err());
+        assert!(store.get("fits").is_none());
+        assert!(store.update_with("big", "actor-a", |_| big).is_err());
+
+        // Unchecked puts are still accepted, and the default is unlimited
+        store.put(
+            "unchecked",
+            "actor-a",
+            serde_json::json!({ "n": "0123456789" }),
+        );
+        assert!(CrdtStore::default().max_node_size().is_none());
+    }
+
+    #[test]
+    fn shallow_node_is_written_under_a_depth_limit() {
+        assert_eq!(json_depth(&serde_json::json!("scalar")), 0);
+        assert_eq!(json_depth(&serde_json::json!([])), 1);
+        let data = serde_json::json!({ "a": [1, { "b": null }], "c": {} });
+        assert_eq!(json_depth(&data), 3);
+
+        let store = CrdtStore::default().with_max_node_depth(3);
+        store.try_put("shallow", "actor-a", data.clone()).unwrap();
+        assert_eq!(store.get("shallow").unwrap().data, data);
+    }
+
+    #[test]
+    fn node_nested_past_the_depth_limit_is_rejected() {
+        let mut deep = serde_json::json!(0);
+        for _ in 0..200 {
+            deep = serde_json::json!({ "next": deep });
+        }
+        assert_eq!(json_depth(&deep), 200);
+
+        let store = CrdtStore::default()
+            .with_max_node_depth(100)
+            .with_max_node_size(1 << 20);
+        let err = store.try_put("deep", "actor-a", deep.clone()).unwrap_err();
+        assert!(matches!(
+            err,
+            StoreError::TooDeep {
+                depth: 200,
+                max: 100
+            }
+        ));
+        assert_eq!(err.code(), CoreErrorCode::NodeTooDeep);
+        let put = CrdtOperation::Put {
+            id: "deep".to_string(),
+            actor: "actor-a".to_string(),
+            data: deep,
+        };
+        assert!(matches!(store.apply(put), Err(StoreError::TooDeep { .. })));
+        assert!(store.get("deep").is_none());
+    }
+
     #[test]
     fn put_with_embedding_stores_and_searches() {
         let store = CrdtStore::default();
@@ -1945,6 +4624,109 @@ mod tests {
         assert_eq!(results[0].record.id, "node");
     }
 
+    #[test]
+    fn nearest_neighbors_rank_by_similarity_and_reject_other_dimensions() {
+        let store = CrdtStore::default().with_vector_dimension(3);
+        store
+            .try_put_with_embedding("east", "actor", serde_json::json!({}), vec![1.0, 0.0, 0.0])
+            .unwrap();
+        store
+            .try_put_with_embedding("north", "actor", serde_json::json!({}), vec![0.0, 1.0, 0.0])
+            .unwrap();
+        store
+            .try_put_with_embedding("ne", "actor", serde_json::json!({}), vec![0.7, 0.7, 0.0])
+            .unwrap();
+
+        let hits = store.nearest_neighbors(&[0.9, 0.1, 0.0], 3, 0.5).unwrap();
+        let ids: Vec<&str> = hits.iter().map(|(record, _)| record.id.as_str()).collect();
+        assert_eq!(ids, ["east", "ne"]);
+
+        let err = store
+            .try_put_with_embedding("flat", "actor", serde_json::json!({}), vec![1.0, 0.0])
+            .unwrap_err();
+        assert_eq!(err.code(), CoreErrorCode::DimensionMismatch);
+        assert!(store.get("flat").is_none());
+        assert!(matches!(
+            store.nearest_neighbors(&[1.0, 0.0], 3, 0.0),
+            Err(StoreError::Vector(VectorError::DimensionMismatch {
+                expected: 3,
+                actual: 2
+            }))
+        ));
+    }
+
+    #[test]
+    fn vector_metric_survives_a_dimension_change_and_drives_search() {
+        let store = CrdtStore::default()
+            .with_vector_metric(DistanceMetric::Euclidean, false)
+            .with_vector_dimension(2);
+        assert_eq!(store.vector_metric(), DistanceMetric::Euclidean);
+        store
+            .try_put_with_embedding("far", "actor", serde_json::json!({}), vec![5.0, 0.5])
+            .unwrap();
+        store
+            .try_put_with_embedding("near", "actor", serde_json::json!({}), vec![0.8, 0.6])
+            .unwrap();
+
+        // Cosine would put "far" first; Euclidean distance does not
+        let hits = store.nearest_neighbors(&[1.0, 0.0], 2, 0.0).unwrap();
+        let ids: Vec<&str> = hits.iter().map(|(record, _)| record.id.as_str()).collect();
+        assert_eq!(ids, ["near", "far"]);
+    }
+
+    #[cfg(feature = "local-embeddings")]
+    #[test]
+    fn local_model_loads_only_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = FastEmbedder::from_dir(dir.path()).unwrap_err();
+        assert!(format!("{err:#}").contains("tokenizer.json"), "{err:#}");
+
+        // Every file present but the model unreadable: no download is tried
+        for name in [
+            "tokenizer.json",
+            "config.json",
+            "special_tokens_map.json",
+            "tokenizer_config.json",
+        ] {
+            std::fs::write(dir.path().join(name), "{}").unwrap();
+        }
+        std::fs::write(dir.path().join("model.onnx"), b"not a model").unwrap();
+        let err = FastEmbedder::from_dir(dir.path()).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("failed to load ONNX model"),
+            "{err:#}"
+        );
+    }
+
+    #[cfg(feature = "local-embeddings")]
+    #[test]
+    #[ignore] // Run with: PLURESDB_MINILM_DIR=<all-MiniLM-L6-v2 ONNX export> cargo test --features local-embeddings -- --ignored local_model
+    fn local_model_scores_paraphrases_above_unrelated_text() {
+        let dir = std::env::var("PLURESDB_MINILM_DIR").expect("PLURESDB_MINILM_DIR is not set");
+        let embedder = FastEmbedder::from_dir(&dir).unwrap();
+        assert_eq!(embedder.dimension(), 384);
+
+        let vectors = embedder
+            .embed(&[
+                "The cat sat on the mat.",
+                "A kitten was sitting on the rug.",
+                "Quarterly revenue grew by eight percent.",
+            ])
+            .unwrap();
+        let cosine = |a: &[f32], b: &[f32]| {
+            let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            dot / (norm(a) * norm(b))
+        };
+        let paraphrase = cosine(&vectors[0], &vectors[1]);
+        let unrelated = cosine(&vectors[0], &vectors[2]);
+        assert!(paraphrase > 0.5, "paraphrase similarity {paraphrase}");
+        assert!(
+            paraphrase > unrelated + 0.2,
+            "paraphrase {paraphrase} vs unrelated {unrelated}"
+        );
+    }
+
     #[test]
     fn extract_text_from_string_value() {
         let data = serde_json::json!("hello world");
@@ -2117,6 +4899,37 @@ mod tests {
         s as Arc<dyn SyncStorageEngine>
     }
 
+    #[test]
+    fn insert_record_keeps_clock_and_timestamp_across_a_reload() {
+        let original = CrdtStore::default();
+        original.put("doc", "alice", serde_json::json!({ "v": 1 }));
+        original.put("doc", "alice", serde_json::json!({ "v": 2 }));
+        original.put("gone", "alice", serde_json::json!({ "v": 1 }));
+        original.delete("gone").unwrap();
+        let stale = original.get("doc").unwrap();
+        original.put("doc", "bob", serde_json::json!({ "v": 3 }));
+
+        // Save every record, tombstones included, then load into a new store
+        let saved = serde_json::to_string(&original.list_including_tombstones()).unwrap();
+        let reloaded = CrdtStore::default();
+        for record in serde_json::from_str::<Vec<NodeRecord>>(&saved).unwrap() {
+            reloaded.insert_record(record);
+        }
+
+        let before = original.get("doc").unwrap();
+        let after = reloaded.get("doc").unwrap();
+        assert_eq!(after.clock, before.clock);
+        assert_eq!(after.clock.get("alice"), Some(&2));
+        assert_eq!(after.timestamp, before.timestamp);
+        assert!(reloaded.get("gone").is_none());
+
+        // History survived: an old write is recognised as already seen, and
+        // a new one continues the clock instead of restarting it
+        assert_eq!(reloaded.merge_record(stale), MergeOutcome::Ignored);
+        reloaded.put("doc", "alice", serde_json::json!({ "v": 4 }));
+        assert_eq!(reloaded.get("doc").unwrap().clock.get("alice"), Some(&3));
+    }
+
     #[test]
     fn with_storage_does_not_hydrate_into_memory() {
         let storage = Arc::new(MemoryStorage::default());
@@ -2131,6 +4944,7 @@ mod tests {
             StoredNode {
                 id: "node-pre".to_string(),
                 payload: serde_json::to_value(&pre_record).unwrap(),
+                meta: None,
             },
         )
         .expect("pre-populate storage");
@@ -2171,6 +4985,92 @@ mod tests {
         assert!(ids.contains(&"list-b"));
     }
 
+    fn seed_namespaces(store: &CrdtStore) {
+        for id in [
+            "user:2:profile",
+            "user:1:settings",
+            "user:1:profile",
+            "user:10:profile",
+            "team:1:members",
+            "users",
+        ] {
+            store.put(id, "actor", serde_json::json!({ "id": id }));
+        }
+        store.delete("user:2:profile").unwrap();
+    }
+
+    #[test]
+    fn list_by_prefix_returns_matching_live_nodes_in_id_order() {
+        let store = CrdtStore::default();
+        seed_namespaces(&store);
+
+        let ids = |prefix: &str| -> Vec<String> {
+            store
+                .list_by_prefix(prefix)
+                .into_iter()
+                .map(|record| record.id)
+                .collect()
+        };
+        assert_eq!(ids("user:1:"), ["user:1:profile", "user:1:settings"]);
+        assert_eq!(
+            ids("user:"),
+            ["user:1:profile", "user:1:settings", "user:10:profile"]
+        );
+        assert_eq!(ids("team:"), ["team:1:members"]);
+        assert!(ids("group:").is_empty());
+        assert_eq!(ids("").len(), 5);
+    }
+
+    #[test]
+    fn list_by_prefix_reads_persisted_nodes() {
+        let (store, storage) = make_storage_store();
+        seed_namespaces(&store);
+
+        let store2 = CrdtStore::default().with_persistence(wrap_mem_storage(storage));
+        store2.put("user:1:avatar", "actor", serde_json::json!({}));
+        let ids: Vec<String> = store2
+            .list_by_prefix("user:1:")
+            .into_iter()
+            .map(|record| record.id)
+            .collect();
+        assert_eq!(ids, ["user:1:avatar", "user:1:profile", "user:1:settings"]);
+    }
+
+    #[test]
+    fn get_many_preserves_order_and_reports_missing_ids() {
+        let store = CrdtStore::default();
+        seed_namespaces(&store);
+
+        let records = store.get_many(&["team:1:members", "missing", "user:2:profile", "users"]);
+        let ids: Vec<Option<&str>> = records
+            .iter()
+            .map(|record| record.as_ref().map(|record| record.id.as_str()))
+            .collect();
+        assert_eq!(ids, [Some("team:1:members"), None, None, Some("users")]);
+        assert!(store.get_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn put_preserves_storage_metadata() {
+        let (store, storage) = make_storage_store();
+        store.put("meta-node", "actor", serde_json::json!({"v": 1}));
+        let meta = serde_json::json!({"source": "peer-b"});
+        pluresdb_storage::SyncStorageEngine::set_meta(
+            storage.as_ref(),
+            "meta-node",
+            Some(meta.clone()),
+        )
+        .unwrap();
+
+        store.put("meta-node", "actor", serde_json::json!({"v": 2}));
+
+        let stored = pluresdb_storage::SyncStorageEngine::get(storage.as_ref(), "meta-node")
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.meta, Some(meta));
+        assert_eq!(store.get("meta-node").unwrap().data["v"], 2);
+    }
+
     #[test]
     fn delete_works_for_storage_only_node() {
         let (store, storage) = make_storage_store();
@@ -2194,6 +5094,8 @@ mod tests {
             .expect_err("should error for missing node");
         assert!(matches!(err, StoreError::NotFound(_)));
         assert_eq!(err.code(), CoreErrorCode::NodeNotFound);
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert_eq!(err.kind().as_str(), "NOT_FOUND");
     }
 
     #[test]
@@ -2278,6 +5180,161 @@ mod tests {
     mod sqlite_compat_tests {
         use super::*;
 
+        #[test]
+        fn statement_stream_visits_rows_in_order() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE nums (n INTEGER)")
+                .expect("create table");
+            db.exec(
+                "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 100) \
+                 INSERT INTO nums SELECT n FROM seq",
+            )
+            .expect("fill table");
+
+            let stmt = db
+                .prepare("SELECT n FROM nums WHERE n > ?1 ORDER BY n")
+                .expect("prepare");
+            let mut seen = Vec::new();
+            let count = stmt
+                .stream(&[SqlValue::Integer(90)], |row| {
+                    seen.push(row[0].as_i64().unwrap());
+                    Ok::<_, DatabaseError>(())
+                })
+                .expect("stream");
+            assert_eq!(count, 10);
+            assert_eq!(seen, (91..=100).collect::<Vec<_>>());
+
+            let stopped = stmt.stream(&[SqlValue::Integer(0)], |_| {
+                Err(DatabaseError::InvalidParameter("stop".to_string()))
+            });
+            assert!(matches!(stopped, Err(DatabaseError::InvalidParameter(_))));
+        }
+
+        #[test]
+        fn statement_stream_sums_many_rows_one_at_a_time() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE nums (n INTEGER)")
+                .expect("create table");
+            db.exec(
+                "WITH RECURSIVE seq(n) AS \
+                 (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 200000) \
+                 INSERT INTO nums SELECT n FROM seq",
+            )
+            .expect("fill table");
+
+            let mut sum = 0;
+            let count = db
+                .prepare("SELECT n FROM nums")
+                .expect("prepare")
+                .stream(&[], |row| {
+                    sum += row[0].as_i64().unwrap();
+                    Ok::<_, DatabaseError>(())
+                })
+                .expect("stream");
+            assert_eq!(count, 200_000);
+            assert_eq!(sum, 200_000 * 200_001 / 2);
+        }
+
+        #[test]
+        fn json_values_survive_a_store_and_fetch_cycle() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT)")
+                .expect("create table");
+
+            let documents = [
+                serde_json::json!({
+                    "name": "Alice",
+                    "address": { "city": "Oslo", "geo": [59.9, 10.7] },
+                    "tags": ["admin", { "since": 2021 }],
+                    "manager": null
+                }),
+                serde_json::json!([[1, 2], [], [{ "deep": { "deeper": true } }]]),
+                serde_json::json!("plain string"),
+            ];
+            for document in &documents {
+                db.query(
+                    "INSERT INTO docs (body) VALUES (?1)",
+                    &[SqlValue::json(document)],
+                )
+                .expect("insert document");
+            }
+
+            let result = db
+                .query("SELECT body FROM docs ORDER BY id", &[])
+                .expect("select documents");
+            let fetched: Vec<JsonValue> = result
+                .rows
+                .iter()
+                .map(|row| row[0].as_json().expect("stored JSON parses"))
+                .collect();
+            assert_eq!(fetched, documents);
+
+            let blob = SqlValue::Blob(br#"{"a":[1,{"b":2}]}"#.to_vec());
+            assert_eq!(
+                blob.as_json(),
+                Some(serde_json::json!({ "a": [1, { "b": 2 }] }))
+            );
+            assert_eq!(SqlValue::Text("not json".to_owned()).as_json(), None);
+            assert_eq!(SqlValue::Integer(7).as_json(), None);
+        }
+
+        #[test]
+        fn from_json_rejects_integers_outside_i64() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE big (n INTEGER)")
+                .expect("create table");
+
+            let max: JsonValue = serde_json::from_str("9223372036854775807").unwrap();
+            let param = SqlValue::from_json(max).expect("i64::MAX binds");
+            db.query("INSERT INTO big (n) VALUES (?1)", &[param])
+                .expect("insert i64::MAX");
+            let result = db.query("SELECT n FROM big", &[]).expect("select");
+            assert_eq!(result.rows[0][0], SqlValue::Integer(i64::MAX));
+
+            let too_big: JsonValue = serde_json::from_str("9223372036854775808").unwrap();
+            let err = SqlValue::from_json(too_big).expect_err("2^63 must not bind");
+            assert!(matches!(err, DatabaseError::InvalidParameter(_)), "{err:?}");
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+            assert_eq!(
+                SqlValue::from_json(serde_json::json!(1.5)).unwrap(),
+                SqlValue::Real(1.5)
+            );
+        }
+
+        #[test]
+        fn sql_params_macro_builds_query_params() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE people (name TEXT, age INT, score REAL, active INT, note TEXT)")
+                .expect("create table");
+            let insert = db
+                .prepare("INSERT INTO people VALUES (?1, ?2, ?3, ?4, ?5)")
+                .expect("prepare insert");
+            insert
+                .run(&sql_params!["Alice", 30, 4.5, true, None::<String>])
+                .expect("insert Alice");
+            insert
+                .run(&sql_params![
+                    String::from("Bob"),
+                    25,
+                    3.0,
+                    false,
+                    Some("new")
+                ])
+                .expect("insert Bob");
+
+            let result = db
+                .query(
+                    "SELECT name, note FROM people WHERE age > ?1 AND active = ?2",
+                    &sql_params![26, true],
+                )
+                .expect("query");
+            assert_eq!(result.rows.len(), 1);
+            assert_eq!(result.rows[0][0], SqlValue::from("Alice"));
+            assert_eq!(result.rows[0][1], SqlValue::Null);
+            assert!(sql_params![].is_empty());
+        }
+
         #[test]
         fn database_exec_and_query() {
             let db = Database::open(DatabaseOptions::default()).expect("open database");
@@ -2318,6 +5375,200 @@ mod tests {
             }
         }
 
+        #[test]
+        fn typed_pragmas_report_page_layout() {
+            let temp = tempfile::NamedTempFile::new().expect("create temp file");
+            let db =
+                Database::open(DatabaseOptions::with_file(temp.path())).expect("open database");
+            assert_eq!(db.page_size().unwrap(), 4096);
+            assert_eq!(db.pragma_i64("page_size").unwrap(), 4096);
+            assert_eq!(
+                db.pragma_string("journal_mode").unwrap().to_lowercase(),
+                "wal"
+            );
+
+            db.exec("CREATE TABLE t (v TEXT)").expect("create table");
+            let pages = db.page_count().unwrap();
+            assert!(pages > 0);
+            assert_eq!(db.database_size_bytes().unwrap(), pages as u64 * 4096);
+            assert!(db.freelist_count().unwrap() >= 0);
+
+            assert!(db.pragma_i64("journal_mode").is_err());
+            assert!(db.pragma_i64("page_size; DROP TABLE t").is_err());
+        }
+
+        #[test]
+        fn read_connections_serve_concurrent_reads_alongside_writes() {
+            let dir = tempfile::tempdir().expect("create temp dir");
+            let db = Database::open(
+                DatabaseOptions::with_file(dir.path().join("reads.db")).max_read_connections(4),
+            )
+            .expect("open database");
+            db.exec("CREATE TABLE t (v INTEGER)").expect("create table");
+            db.exec("INSERT INTO t (v) VALUES (1), (2), (3)")
+                .expect("seed table");
+
+            let count = |db: &Database| match db
+                .query("SELECT COUNT(*) FROM t", &[])
+                .expect("count rows")
+                .rows[0][0]
+            {
+                SqlValue::Integer(n) => n,
+                ref other => panic!("unexpected count: {other:?}"),
+            };
+            std::thread::scope(|scope| {
+                let writer = scope.spawn(|| {
+                    for v in 0..50 {
+                        db.transaction(|tx| {
+                            tx.execute("INSERT INTO t (v) VALUES (?1)", [v])?;
+                            Ok(())
+                        })
+                        .expect("insert row");
+                    }
+                });
+                let readers: Vec<_> = (0..16)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            for _ in 0..50 {
+                                let n = count(&db);
+                                assert!((3..=53).contains(&n), "{n}");
+                            }
+                        })
+                    })
+                    .collect();
+                writer.join().unwrap();
+                for reader in readers {
+                    reader.join().unwrap();
+                }
+            });
+            assert_eq!(count(&db), 53);
+
+            // Writes issued through `query` still reach the writer
+            db.query("INSERT INTO t (v) VALUES (?1)", &[SqlValue::Integer(7)])
+                .expect("insert through query");
+            assert_eq!(count(&db), 54);
+
+            // Inside an open transaction reads see its uncommitted rows
+            db.exec("BEGIN").expect("begin");
+            db.exec("INSERT INTO t (v) VALUES (8)")
+                .expect("insert in tx");
+            assert_eq!(count(&db), 55);
+            let select = db.prepare("SELECT v FROM t WHERE v = 8").expect("prepare");
+            assert!(select.get(&[]).expect("select in tx").is_some());
+            db.exec("ROLLBACK").expect("roll back");
+            assert_eq!(count(&db), 54);
+            let mode = db.pragma("journal_mode").expect("run pragma");
+            match &mode.rows[0][0] {
+                SqlValue::Text(mode) => assert_eq!(mode.to_lowercase(), "wal"),
+                other => panic!("unexpected pragma value: {other:?}"),
+            }
+        }
+
+        #[test]
+        fn repeated_statements_return_correct_results_with_and_without_the_cache() {
+            for cache_size in [DEFAULT_STATEMENT_CACHE_SIZE, 0] {
+                let db =
+                    Database::open(DatabaseOptions::default().statement_cache_size(cache_size))
+                        .expect("open database");
+                db.exec("CREATE TABLE kv (k INTEGER PRIMARY KEY, v TEXT)")
+                    .expect("create table");
+
+                let insert = db
+                    .prepare("INSERT INTO kv (k, v) VALUES (?1, ?2)")
+                    .expect("prepare insert");
+                for k in 0..200 {
+                    let result = insert
+                        .run(&[SqlValue::Integer(k), SqlValue::Text(format!("v{k}"))])
+                        .expect("insert row");
+                    assert_eq!((result.changes, result.last_insert_rowid), (1, k));
+                }
+
+                let select = db
+                    .prepare("SELECT v FROM kv WHERE k = ?1")
+                    .expect("prepare select");
+                for k in [0, 57, 199] {
+                    let row = select
+                        .get(&[SqlValue::Integer(k)])
+                        .expect("select row")
+                        .expect("row exists");
+                    assert_eq!(row["v"], SqlValue::Text(format!("v{k}")));
+                }
+                let total = db
+                    .query("SELECT COUNT(*), SUM(k) FROM kv", &[])
+                    .expect("aggregate");
+                assert_eq!(
+                    total.rows[0],
+                    [SqlValue::Integer(200), SqlValue::Integer(199 * 200 / 2)]
+                );
+            }
+        }
+
+        #[test]
+        fn named_parameters_bind_by_name_in_any_order() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE people (name TEXT, age INTEGER)")
+                .expect("create table");
+
+            let insert = db
+                .prepare("INSERT INTO people (name, age) VALUES (:name, @age)")
+                .expect("prepare insert");
+            for (name, age) in [("Alice", 34), ("Bob", 27), ("Carol", 41)] {
+                let result = insert
+                    .run_named(&[
+                        ("age", SqlValue::Integer(age)),
+                        (":name", SqlValue::Text(name.to_owned())),
+                    ])
+                    .expect("named insert");
+                assert_eq!(result.changes, 1);
+            }
+
+            let result = db
+                .query_named(
+                    "SELECT name FROM people WHERE age > :age AND name != $name ORDER BY name",
+                    &[
+                        ("name", SqlValue::Text("Carol".to_owned())),
+                        ("age", SqlValue::Integer(30)),
+                    ],
+                )
+                .expect("named select");
+            assert_eq!(result.rows, [[SqlValue::Text("Alice".to_owned())]]);
+        }
+
+        #[test]
+        fn named_parameters_reject_positional_sql_and_unknown_names() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            db.exec("CREATE TABLE people (name TEXT, age INTEGER)")
+                .expect("create table");
+            let age = ("age", SqlValue::Integer(30));
+
+            for sql in [
+                "SELECT name FROM people WHERE age > :age AND name = ?",
+                "SELECT name FROM people WHERE age > :age AND name = ?2",
+            ] {
+                let err = db
+                    .query_named(sql, &[age.clone()])
+                    .expect_err("mixed parameters must be rejected");
+                assert!(matches!(err, DatabaseError::InvalidParameter(_)), "{err:?}");
+                assert!(err.to_string().contains("cannot be mixed"), "{err}");
+            }
+
+            let sql = "SELECT name FROM people WHERE age > :age";
+            let err = db
+                .query_named(sql, &[age.clone(), ("name", SqlValue::Null)])
+                .expect_err("unknown name must be rejected");
+            assert!(err.to_string().contains("no parameter named name"), "{err}");
+            let err = db
+                .query_named(sql, &[])
+                .expect_err("missing value must be rejected");
+            assert_eq!(err.code(), CoreErrorCode::InvalidInput);
+
+            // The positional API still binds named parameters by index
+            db.prepare(sql)
+                .expect("prepare")
+                .all(&[SqlValue::Integer(30)])
+                .expect("positional binding");
+        }
+
         #[test]
         fn statement_get_returns_none_when_no_rows() {
             let db = Database::open(DatabaseOptions::default()).expect("open database");
@@ -2351,13 +5602,15 @@ mod tests {
                 .run(&[SqlValue::Text("alice@example.com".into())])
                 .expect_err("second insert should fail");
             match err {
-                DatabaseError::Sqlite(inner) => {
+                DatabaseError::Sqlite(ref inner) => {
                     assert_eq!(
                         inner.sqlite_error_code(),
                         Some(ErrorCode::ConstraintViolation)
                     );
                 }
+                other => panic!("unexpected error: {other:?}"),
             }
+            assert_eq!(err.kind(), ErrorKind::Constraint);
         }
 
         #[test]
@@ -2390,6 +5643,159 @@ mod tests {
             }
         }
 
+        #[test]
+        fn query_cache_hits_on_repeat_and_invalidates_on_write() {
+            let db = Database::open(DatabaseOptions::default())
+                .expect("open database")
+                .with_query_cache(16, Duration::from_secs(60));
+            db.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")
+                .expect("create table");
+            db.exec("INSERT INTO items (name) VALUES ('a')")
+                .expect("insert row");
+
+            let sql = "SELECT name FROM items ORDER BY id";
+            let first = db.query(sql, &[]).expect("first query");
+            let second = db.query(sql, &[]).expect("second query");
+            assert_eq!(first, second);
+            let stats = db.query_cache_stats().expect("cache enabled");
+            assert_eq!((stats.hits, stats.misses), (1, 1));
+
+            db.prepare("INSERT INTO items (name) VALUES (?1)")
+                .expect("prepare insert")
+                .run(&[SqlValue::Text("b".into())])
+                .expect("insert row");
+
+            let third = db.query(sql, &[]).expect("query after write");
+            assert_eq!(third.rows.len(), 2);
+            let stats = db.query_cache_stats().expect("cache enabled");
+            assert_eq!((stats.hits, stats.misses), (1, 2));
+        }
+
+        #[test]
+        fn query_cache_skips_rows_read_inside_a_transaction() {
+            let db = Database::open(DatabaseOptions::default())
+                .expect("open database")
+                .with_query_cache(16, Duration::from_secs(60));
+            db.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")
+                .expect("create table");
+            db.exec("INSERT INTO items (name) VALUES ('a')")
+                .expect("insert row");
+
+            // A read reports no changes of its own, cached or not
+            let sql = "SELECT COUNT(*) FROM items";
+            let fresh = db.query(sql, &[]).expect("first count");
+            assert_eq!((fresh.changes, fresh.last_insert_rowid), (0, 0));
+            assert_eq!(db.query(sql, &[]).expect("cached count"), fresh);
+
+            db.exec("BEGIN; INSERT INTO items (name) VALUES ('b')")
+                .expect("open transaction");
+            let pending = db.query(sql, &[]).expect("count in transaction");
+            assert_eq!(pending.rows[0], [SqlValue::Integer(2)]);
+            // Other handles must not be served the uncommitted count
+            let stats = db.query_cache_stats().expect("cache enabled");
+            assert_eq!(stats.entries, 0);
+            db.exec("ROLLBACK").expect("roll back");
+
+            let after = db.query(sql, &[]).expect("count after rollback");
+            assert_eq!(after.rows[0], [SqlValue::Integer(1)]);
+        }
+
+        #[test]
+        fn wal_checkpoint_reports_frames_and_truncates_log() {
+            let dir = tempfile::tempdir().expect("create temp dir");
+            let path = dir.path().join("checkpoint.db");
+            let db = Database::open(DatabaseOptions::with_file(&path)).expect("open database");
+            db.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")
+                .expect("create table");
+            let insert = db
+                .prepare("INSERT INTO items (name) VALUES (?1)")
+                .expect("prepare insert");
+            for i in 0..50 {
+                insert
+                    .run(&[SqlValue::Text(format!("item-{i}"))])
+                    .expect("insert row");
+            }
+
+            let passive = db
+                .wal_checkpoint(CheckpointMode::Passive)
+                .expect("passive checkpoint");
+            assert!(!passive.busy);
+            assert!(passive.log_frames > 0);
+            assert_eq!(passive.checkpointed_frames, passive.log_frames);
+
+            let truncate = db
+                .wal_checkpoint(CheckpointMode::Truncate)
+                .expect("truncate checkpoint");
+            assert!(!truncate.busy);
+            assert_eq!(truncate.log_frames, 0);
+            let wal_len = std::fs::metadata(dir.path().join("checkpoint.db-wal"))
+                .map(|m| m.len())
+                .unwrap_or(0);
+            assert_eq!(wal_len, 0);
+        }
+
+        #[test]
+        fn busy_writes_are_retried_until_the_lock_is_released() {
+            use std::sync::mpsc;
+
+            let dir = tempfile::tempdir().expect("create temp dir");
+            let path = dir.path().join("busy.db");
+            // No busy timeout, so contention surfaces at once and only the
+            // retries wait it out
+            let options = DatabaseOptions::with_file(&path).busy_timeout(None);
+            let holder = Database::open(options.clone()).expect("open holder");
+            holder
+                .exec("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)")
+                .expect("create table");
+
+            let (locked_tx, locked_rx) = mpsc::channel();
+            let (release_tx, release_rx) = mpsc::channel::<()>();
+            let holding = std::thread::spawn(move || {
+                holder
+                    .exec("BEGIN IMMEDIATE; INSERT INTO items (name) VALUES ('held')")
+                    .expect("take write lock");
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                std::thread::sleep(Duration::from_millis(100));
+                holder.exec("COMMIT").expect("commit");
+            });
+            locked_rx.recv().unwrap();
+
+            let impatient = Database::open(options.clone().busy_retries(0)).expect("open");
+            let err = impatient
+                .exec("INSERT INTO items (name) VALUES ('impatient')")
+                .expect_err("write lock is held");
+            assert!(matches!(err, DatabaseError::Busy { attempts: 1, .. }));
+            assert_eq!(err.kind(), ErrorKind::Busy);
+            assert_eq!(err.code(), CoreErrorCode::DatabaseBusy);
+
+            let patient = Database::open(options.busy_retries(10)).expect("open");
+            release_tx.send(()).unwrap();
+            patient
+                .prepare("INSERT INTO items (name) VALUES (?1)")
+                .expect("prepare insert")
+                .run(&[SqlValue::Text("patient".into())])
+                .expect("insert once the lock is released");
+            holding.join().unwrap();
+
+            let names = patient
+                .query("SELECT name FROM items ORDER BY id", &[])
+                .expect("query");
+            assert_eq!(
+                names.rows,
+                vec![
+                    vec![SqlValue::Text("held".into())],
+                    vec![SqlValue::Text("patient".into())]
+                ]
+            );
+        }
+
+        #[test]
+        fn query_cache_disabled_by_default() {
+            let db = Database::open(DatabaseOptions::default()).expect("open database");
+            assert!(db.query_cache_stats().is_none());
+        }
+
         #[test]
         fn database_options_with_embedding_model() {
             let opts = DatabaseOptions::default().with_embedding_model("BAAI/bge-small-en-v1.5");
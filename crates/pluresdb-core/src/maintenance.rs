@@ -0,0 +1,313 @@
+//! Periodic background maintenance for a [`CrdtStore`] and its storage.
+//!
+//! [`MaintenanceScheduler`] bundles the housekeeping an operator would
+//! otherwise trigger by hand: purging tombstones, compacting the write-ahead
+//! log, and asking the storage engine to reclaim space.  It runs on a spawned
+//! Tokio task at a fixed interval once [`start`](MaintenanceScheduler::start)ed,
+//! and [`run_once`](MaintenanceScheduler::run_once) performs a single pass
+//! inline, which is what tests and CLI commands should use.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use pluresdb_storage::{StorageEngine, WalOperation, WriteAheadLog};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::CrdtStore;
+
+/// Default interval between maintenance passes.
+pub const DEFAULT_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Default age a tombstone must reach before a pass purges it.
+pub const DEFAULT_TOMBSTONE_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Actor recorded on the checkpoint entries the scheduler appends to the WAL.
+const MAINTENANCE_ACTOR: &str = "pluresdb-maintenance";
+
+/// Outcome of a single maintenance pass.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaintenanceReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Tombstoned nodes physically removed from the store.
+    pub tombstones_purged: usize,
+    /// WAL segment files deleted after checkpointing.
+    pub wal_segments_removed: usize,
+    /// Bytes the storage engine reported as reclaimed.
+    pub storage_bytes_reclaimed: u64,
+    /// Failures from individual steps; a failed step does not abort the pass.
+    pub errors: Vec<String>,
+}
+
+impl MaintenanceReport {
+    /// Whether the pass actually reclaimed anything.
+    pub fn did_work(&self) -> bool {
+        self.tombstones_purged > 0
+            || self.wal_segments_removed > 0
+            || self.storage_bytes_reclaimed > 0
+    }
+}
+
+struct Inner {
+    storage: Arc<dyn StorageEngine>,
+    wal: Arc<WriteAheadLog>,
+    store: Arc<CrdtStore>,
+    last_report: Mutex<Option<MaintenanceReport>>,
+}
+
+impl Inner {
+    async fn run_once(&self, tombstone_retention: Duration) -> MaintenanceReport {
+        let mut report = MaintenanceReport {
+            started_at: Utc::now(),
+            ..Default::default()
+        };
+
+        // Purge before compacting so the storage engine reclaims the space.
+        // A retention too long to subtract from now leaves every tombstone.
+        let cutoff = chrono::Duration::from_std(tombstone_retention)
+            .ok()
+            .and_then(|retention| report.started_at.checked_sub_signed(retention));
+        if let Some(cutoff) = cutoff {
+            report.tombstones_purged = self.store.purge_tombstones(cutoff);
+        }
+
+        // Flush storage first so everything logged before the checkpoint is
+        // durable in the base data before its WAL segments are dropped.
+        match self.storage.compact().await {
+            Ok(bytes) => report.storage_bytes_reclaimed = bytes,
+            Err(e) => report
+                .errors
+                .push(format!("storage compaction failed: {e}")),
+        }
+
+        let base_seq = self.wal.next_sequence();
+        let checkpoint = self
+            .wal
+            .append(
+                MAINTENANCE_ACTOR.to_string(),
+                WalOperation::Checkpoint { base_seq },
+            )
+            .await;
+        match checkpoint {
+            Ok(_) => match self.wal.compact(base_seq).await {
+                Ok(removed) => report.wal_segments_removed = removed,
+                Err(e) => report.errors.push(format!("WAL compaction failed: {e}")),
+            },
+            Err(e) => report.errors.push(format!("WAL checkpoint failed: {e}")),
+        }
+
+        report.finished_at = Utc::now();
+        for error in &report.errors {
+            warn!("[MaintenanceScheduler] {}", error);
+        }
+        debug!(?report, "[MaintenanceScheduler] pass complete");
+        *self.last_report.lock() = Some(report.clone());
+        report
+    }
+}
+
+/// Runs storage, WAL, and store maintenance on a fixed interval.
+pub struct MaintenanceScheduler {
+    inner: Arc<Inner>,
+    interval: Duration,
+    tombstone_retention: Duration,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl std::fmt::Debug for MaintenanceScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MaintenanceScheduler")
+            .field("interval", &self.interval)
+            .field("tombstone_retention", &self.tombstone_retention)
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+impl MaintenanceScheduler {
+    pub fn new(
+        storage: Arc<dyn StorageEngine>,
+        wal: Arc<WriteAheadLog>,
+        store: Arc<CrdtStore>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                storage,
+                wal,
+                store,
+                last_report: Mutex::new(None),
+            }),
+            interval: DEFAULT_MAINTENANCE_INTERVAL,
+            tombstone_retention: DEFAULT_TOMBSTONE_RETENTION,
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Purge tombstones once they are `retention` old (default
+    /// [`DEFAULT_TOMBSTONE_RETENTION`]).
+    ///
+    /// Pick a retention longer than any peer stays offline: a peer that
+    /// missed the delete and syncs after its tombstone is purged brings the
+    /// node back.  See [`CrdtStore::purge_tombstones`].
+    pub fn with_tombstone_retention(mut self, retention: Duration) -> Self {
+        self.tombstone_retention = retention;
+        self
+    }
+
+    /// Spawn the background task.  The first pass runs after one interval.
+    ///
+    /// Must be called from within a Tokio runtime.  Calling `start` on a
+    /// running scheduler is a no-op.
+    pub fn start(&self) {
+        let mut task = self.task.lock();
+        if task.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return;
+        }
+        let inner = Arc::clone(&self.inner);
+        let period = self.interval;
+        let retention = self.tombstone_retention;
+        *task = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                inner.run_once(retention).await;
+            }
+        }));
+    }
+
+    /// Stop the background task.  A pass already in progress is cancelled at
+    /// its next await point.
+    pub fn stop(&self) {
+        if let Some(handle) = self.task.lock().take() {
+            handle.abort();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.task
+            .lock()
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Run a single maintenance pass inline and return its report.
+    pub async fn run_once(&self) -> MaintenanceReport {
+        self.inner.run_once(self.tombstone_retention).await
+    }
+
+    /// Report from the most recent pass, whether scheduled or via `run_once`.
+    pub fn last_report(&self) -> Option<MaintenanceReport> {
+        self.inner.last_report.lock().clone()
+    }
+}
+
+impl Drop for MaintenanceScheduler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pluresdb_storage::{DurabilityLevel, MemoryStorage};
+
+    async fn fill_wal(wal: &WriteAheadLog, entries: u64) {
+        for i in 0..entries {
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Put {
+                    id: format!("node-{i}"),
+                    data: serde_json::json!({ "i": i }),
+                },
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    fn scheduler(dir: &std::path::Path) -> (MaintenanceScheduler, Arc<WriteAheadLog>) {
+        let (scheduler, wal, _) = scheduler_with_store(dir);
+        (scheduler, wal)
+    }
+
+    fn scheduler_with_store(
+        dir: &std::path::Path,
+    ) -> (MaintenanceScheduler, Arc<WriteAheadLog>, Arc<CrdtStore>) {
+        // A tiny segment size forces a new segment almost every append.
+        let wal =
+            Arc::new(WriteAheadLog::open_with_options(dir, DurabilityLevel::Wal, 64).unwrap());
+        let storage: Arc<dyn StorageEngine> = Arc::new(MemoryStorage::default());
+        let store = Arc::new(CrdtStore::default().with_persistence(storage.clone()));
+        let scheduler = MaintenanceScheduler::new(storage, wal.clone(), store.clone());
+        (scheduler, wal, store)
+    }
+
+    #[tokio::test]
+    async fn run_once_compacts_obsolete_wal_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let (scheduler, wal) = scheduler(dir.path());
+        fill_wal(&wal, 8).await;
+
+        let report = scheduler.run_once().await;
+
+        assert!(report.errors.is_empty(), "errors: {:?}", report.errors);
+        assert!(report.wal_segments_removed >= 7, "report: {report:?}");
+        assert!(report.did_work());
+        assert_eq!(scheduler.last_report(), Some(report));
+    }
+
+    #[tokio::test]
+    async fn run_once_purges_tombstones_past_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let (scheduler, _wal, store) = scheduler_with_store(dir.path());
+        store.put("gone", "actor-1", serde_json::json!({}));
+        store.put("kept", "actor-1", serde_json::json!({}));
+        store.delete("gone").unwrap();
+
+        // Too recent for the default retention
+        let report = scheduler.run_once().await;
+        assert_eq!(report.tombstones_purged, 0);
+        assert_eq!(store.list_including_tombstones().len(), 2);
+
+        let scheduler = scheduler.with_tombstone_retention(Duration::ZERO);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let report = scheduler.run_once().await;
+        assert_eq!(report.tombstones_purged, 1, "report: {report:?}");
+        assert!(report.did_work());
+        let remaining = store.list_including_tombstones();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, "kept");
+    }
+
+    #[tokio::test]
+    async fn start_runs_passes_until_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let (scheduler, wal) = scheduler(dir.path());
+        let scheduler = scheduler.with_interval(Duration::from_millis(10));
+        fill_wal(&wal, 4).await;
+
+        scheduler.start();
+        assert!(scheduler.is_running());
+        for _ in 0..100 {
+            if scheduler.last_report().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        scheduler.stop();
+
+        assert!(!scheduler.is_running());
+        let report = scheduler.last_report().expect("scheduled pass should run");
+        assert!(report.wal_segments_removed > 0, "report: {report:?}");
+    }
+}
@@ -0,0 +1,255 @@
+//! Versioned schema migrations for a [`Database`].
+//!
+//! A database's schema version is the single row of the `schema_version`
+//! table, `0` for a database that has never been migrated.  Migrating to a
+//! later version applies the `up_sql` of every [`Migration`] in between, in
+//! order; migrating to an earlier one applies their `down_sql` in reverse,
+//! and is refused if any of them has none.  A run happens in one
+//! transaction, version bump included, so it applies entirely or not at
+//! all, and running it again once the target is reached changes nothing.
+//!
+//! Migration SQL must not open or commit transactions of its own.
+
+use rusqlite::OptionalExtension;
+
+use crate::sqlite_storage::SCHEMA as NODES_SCHEMA;
+use crate::{Database, DatabaseError, DbResult};
+
+/// Table holding the schema version.
+pub const SCHEMA_VERSION_TABLE: &str = "schema_version";
+
+/// One step in a database's schema history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    /// The schema version this migration brings the database to; `1` for
+    /// the first.
+    pub version: u32,
+    pub up_sql: &'static str,
+    /// Undoes `up_sql`, bringing the database back to the previous version.
+    pub down_sql: Option<&'static str>,
+}
+
+impl Migration {
+    pub const fn new(version: u32, up_sql: &'static str) -> Self {
+        Self {
+            version,
+            up_sql,
+            down_sql: None,
+        }
+    }
+
+    /// Allow migrating back past this version by running `down_sql`.
+    pub const fn with_down(self, down_sql: &'static str) -> Self {
+        Self {
+            down_sql: Some(down_sql),
+            ..self
+        }
+    }
+}
+
+/// PluresDB's own schema history, applied by [`run_migrations`].
+pub const MIGRATIONS: &[Migration] = &[
+    // The `nodes` table of `SqliteStorage`, which creates it itself when
+    // missing, so databases it opened before migrations existed match too
+    Migration::new(1, NODES_SCHEMA),
+];
+
+/// Versions before and after a migration run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl MigrationReport {
+    /// Whether the run changed the schema.
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+/// Migrate `db` through [`MIGRATIONS`] to `target`, or to the latest
+/// version if `None`.
+pub fn run_migrations(db: &Database, target: Option<u32>) -> DbResult<MigrationReport> {
+    run_migrations_with(db, MIGRATIONS, target)
+}
+
+/// Migrate `db` through `migrations`, which must be in strictly increasing
+/// version order, to `target`, or to the last of them if `None`.
+///
+/// Fails, changing nothing, if `target` is neither `0` nor one of the
+/// versions, if the database is already past the last version, or if
+/// reaching `target` means undoing a migration without `down_sql`.
+pub fn run_migrations_with(
+    db: &Database,
+    migrations: &[Migration],
+    target: Option<u32>,
+) -> DbResult<MigrationReport> {
+    if let Some(pair) = migrations
+        .windows(2)
+        .find(|pair| pair[0].version >= pair[1].version)
+    {
+        return Err(DatabaseError::InvalidParameter(format!(
+            "migration {} is listed after migration {}; versions must increase",
+            pair[1].version, pair[0].version
+        )));
+    }
+    if migrations.first().is_some_and(|m| m.version == 0) {
+        return Err(DatabaseError::InvalidParameter(
+            "migration versions start at 1; 0 is the unmigrated schema".into(),
+        ));
+    }
+    let latest = migrations.last().map_or(0, |m| m.version);
+    let target = target.unwrap_or(latest);
+    if target != 0 && !migrations.iter().any(|m| m.version == target) {
+        return Err(DatabaseError::InvalidParameter(format!(
+            "no migration has version {target}"
+        )));
+    }
+
+    db.transaction(|tx| {
+        tx.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {SCHEMA_VERSION_TABLE} (version INTEGER NOT NULL)"
+        ))?;
+        let from: u32 = tx
+            .query_row(
+                &format!("SELECT version FROM {SCHEMA_VERSION_TABLE}"),
+                [],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0);
+        if from == target {
+            return Ok(MigrationReport { from, to: target });
+        }
+
+        if from < target {
+            for migration in migrations
+                .iter()
+                .filter(|m| m.version > from && m.version <= target)
+            {
+                tx.execute_batch(migration.up_sql)?;
+            }
+        } else {
+            if from > latest {
+                return Err(DatabaseError::InvalidParameter(format!(
+                    "database is at schema version {from}, past the latest known \
+                     migration {latest}"
+                )));
+            }
+            for migration in migrations
+                .iter()
+                .rev()
+                .filter(|m| m.version > target && m.version <= from)
+            {
+                let down_sql = migration.down_sql.ok_or_else(|| {
+                    DatabaseError::InvalidParameter(format!(
+                        "cannot migrate from version {from} down to {target}: \
+                         migration {} has no down SQL",
+                        migration.version
+                    ))
+                })?;
+                tx.execute_batch(down_sql)?;
+            }
+        }
+
+        tx.execute(&format!("DELETE FROM {SCHEMA_VERSION_TABLE}"), [])?;
+        tx.execute(
+            &format!("INSERT INTO {SCHEMA_VERSION_TABLE} (version) VALUES (?1)"),
+            [target],
+        )?;
+        Ok(MigrationReport { from, to: target })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatabaseOptions, SqlValue};
+
+    const STEPS: &[Migration] = &[
+        Migration::new(1, "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)"),
+        Migration::new(2, "ALTER TABLE notes ADD COLUMN title TEXT")
+            .with_down("ALTER TABLE notes DROP COLUMN title"),
+        Migration::new(3, "CREATE INDEX notes_title ON notes (title)")
+            .with_down("DROP INDEX notes_title"),
+    ];
+
+    fn version(db: &Database) -> i64 {
+        let result = db.query("SELECT version FROM schema_version", &[]).unwrap();
+        match result.rows.as_slice() {
+            [row] => match row[0] {
+                SqlValue::Integer(version) => version,
+                ref other => panic!("unexpected version {other:?}"),
+            },
+            rows => panic!("expected one version row, got {rows:?}"),
+        }
+    }
+
+    fn in_schema(db: &Database, name: &str) -> bool {
+        let result = db
+            .query(
+                "SELECT name FROM sqlite_master WHERE name = ?1",
+                &[SqlValue::Text(name.into())],
+            )
+            .unwrap();
+        !result.rows.is_empty()
+    }
+
+    #[test]
+    fn fresh_database_migrates_to_latest_and_reruns_are_no_ops() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        let report = run_migrations_with(&db, STEPS, None).unwrap();
+        assert_eq!(report, MigrationReport { from: 0, to: 3 });
+        assert!(report.changed());
+        assert_eq!(version(&db), 3);
+        db.exec("INSERT INTO notes (body, title) VALUES ('b', 't')")
+            .unwrap();
+
+        let again = run_migrations_with(&db, STEPS, None).unwrap();
+        assert_eq!(again, MigrationReport { from: 3, to: 3 });
+        assert!(!again.changed());
+        assert_eq!(version(&db), 3);
+
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        assert_eq!(
+            run_migrations(&db, None).unwrap(),
+            MigrationReport { from: 0, to: 1 }
+        );
+        assert!(in_schema(&db, "nodes"));
+    }
+
+    #[test]
+    fn explicit_target_stops_there_and_later_runs_continue() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        run_migrations_with(&db, STEPS, Some(1)).unwrap();
+        assert_eq!(version(&db), 1);
+        assert!(db.exec("INSERT INTO notes (title) VALUES ('t')").is_err());
+
+        assert_eq!(
+            run_migrations_with(&db, STEPS, None).unwrap(),
+            MigrationReport { from: 1, to: 3 }
+        );
+        assert_eq!(
+            run_migrations_with(&db, STEPS, Some(1)).unwrap(),
+            MigrationReport { from: 3, to: 1 }
+        );
+        assert_eq!(version(&db), 1);
+        assert!(!in_schema(&db, "notes_title"));
+        assert!(run_migrations_with(&db, STEPS, Some(4)).is_err());
+    }
+
+    #[test]
+    fn downgrade_without_down_sql_is_refused_and_changes_nothing() {
+        let db = Database::open(DatabaseOptions::default()).unwrap();
+        run_migrations_with(&db, STEPS, None).unwrap();
+        let err = run_migrations_with(&db, STEPS, Some(0)).unwrap_err();
+        assert!(matches!(err, DatabaseError::InvalidParameter(_)));
+        assert_eq!(version(&db), 3);
+        assert!(in_schema(&db, "notes_title"));
+
+        // A database migrated by a newer build cannot be brought back
+        assert!(run_migrations_with(&db, &STEPS[..2], None).is_err());
+        assert_eq!(version(&db), 3);
+    }
+}
@@ -0,0 +1,193 @@
+//! A bounded pool of [`Database`] connections.
+//!
+//! Connections are opened lazily, up to `max_connections`, and handed out as
+//! [`PooledDatabase`] guards that return them to the pool on drop.  When every
+//! connection is checked out, callers wait for one to be returned, but never
+//! longer than the pool's timeout: an exhausted pool fails with
+//! [`DatabaseError::PoolTimeout`] instead of deadlocking.
+//!
+//! Every connection opens the same [`DatabaseOptions`], so pools are only
+//! useful for file-backed databases; each in-memory connection would be a
+//! separate, empty database.
+
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::{Database, DatabaseError, DatabaseOptions, DbResult};
+
+/// Default time [`DatabasePool::acquire`] waits for a free connection.
+pub const DEFAULT_POOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct PoolState {
+    idle: Vec<Database>,
+    in_use: usize,
+}
+
+#[derive(Debug)]
+pub struct DatabasePool {
+    options: DatabaseOptions,
+    max_connections: usize,
+    timeout: Duration,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+impl DatabasePool {
+    /// Create a pool that opens at most `max_connections` connections (at
+    /// least one) with `options`.  No connection is opened until first use.
+    pub fn new(options: DatabaseOptions, max_connections: usize) -> Self {
+        Self {
+            options,
+            max_connections: max_connections.max(1),
+            timeout: DEFAULT_POOL_TIMEOUT,
+            state: Mutex::new(PoolState::default()),
+            returned: Condvar::new(),
+        }
+    }
+
+    /// Set how long [`acquire`](Self::acquire) waits for a free connection.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    /// Check out a connection, waiting up to the pool's configured timeout.
+    pub fn acquire(&self) -> DbResult<PooledDatabase<'_>> {
+        self.acquire_timeout(self.timeout)
+    }
+
+    /// Check out a connection, waiting up to `timeout` for one to be returned
+    /// if the pool is exhausted.
+    pub fn acquire_timeout(&self, timeout: Duration) -> DbResult<PooledDatabase<'_>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock();
+        loop {
+            if let Some(database) = state.idle.pop() {
+                state.in_use += 1;
+                return Ok(self.guard(database));
+            }
+            if state.in_use < self.max_connections {
+                // Reserve the slot, then open without holding the lock.
+                state.in_use += 1;
+                drop(state);
+                return match Database::open(self.options.clone()) {
+                    Ok(database) => Ok(self.guard(database)),
+                    Err(e) => {
+                        self.state.lock().in_use -= 1;
+                        self.returned.notify_one();
+                        Err(e)
+                    }
+                };
+            }
+            if self.returned.wait_until(&mut state, deadline).timed_out() && state.idle.is_empty() {
+                return Err(DatabaseError::PoolTimeout(timeout));
+            }
+        }
+    }
+
+    /// Number of connections currently checked out.
+    pub fn in_use(&self) -> usize {
+        self.state.lock().in_use
+    }
+
+    /// Number of open connections waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.state.lock().idle.len()
+    }
+
+    fn guard(&self, database: Database) -> PooledDatabase<'_> {
+        PooledDatabase {
+            pool: self,
+            database: Some(database),
+        }
+    }
+
+    fn release(&self, database: Database) {
+        let mut state = self.state.lock();
+        state.in_use -= 1;
+        state.idle.push(database);
+        drop(state);
+        self.returned.notify_one();
+    }
+}
+
+/// A connection checked out of a [`DatabasePool`]; returned to it on drop.
+#[derive(Debug)]
+pub struct PooledDatabase<'a> {
+    pool: &'a DatabasePool,
+    database: Option<Database>,
+}
+
+impl Deref for PooledDatabase<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.database
+            .as_ref()
+            .expect("pooled database is present until drop")
+    }
+}
+
+impl Drop for PooledDatabase<'_> {
+    fn drop(&mut self) {
+        if let Some(database) = self.database.take() {
+            self.pool.release(database);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_pool(dir: &tempfile::TempDir, max_connections: usize) -> DatabasePool {
+        let options = DatabaseOptions::with_file(dir.path().join("pool.db"));
+        DatabasePool::new(options, max_connections)
+    }
+
+    #[test]
+    fn exhausted_pool_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = file_pool(&dir, 1);
+
+        let held = pool.acquire_timeout(Duration::from_millis(50)).unwrap();
+        assert_eq!((pool.in_use(), pool.idle()), (1, 0));
+
+        let err = pool
+            .acquire_timeout(Duration::from_millis(50))
+            .expect_err("second acquire should time out");
+        assert!(matches!(err, DatabaseError::PoolTimeout(_)), "{err:?}");
+        assert_eq!(err.kind(), crate::ErrorKind::Busy);
+
+        drop(held);
+        assert_eq!((pool.in_use(), pool.idle()), (0, 1));
+        pool.acquire_timeout(Duration::from_millis(50))
+            .expect("connection is reusable once returned");
+    }
+
+    #[test]
+    fn waiting_acquire_gets_returned_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = file_pool(&dir, 1);
+
+        let held = pool.acquire().unwrap();
+        held.exec("CREATE TABLE t (v INTEGER)").unwrap();
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                let db = pool.acquire_timeout(Duration::from_secs(5)).unwrap();
+                db.exec("INSERT INTO t (v) VALUES (1)").unwrap();
+            });
+            std::thread::sleep(Duration::from_millis(20));
+            drop(held);
+            waiter.join().unwrap();
+        });
+        assert_eq!((pool.in_use(), pool.idle()), (0, 1));
+    }
+}
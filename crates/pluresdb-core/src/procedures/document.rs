@@ -475,8 +475,9 @@ fn count_sentences(text: &str) -> usize {
         .max(1)
 }
 
-/// Common English stop-words to exclude from keyword extraction.
-const STOP_WORDS: &[&str] = &[
+/// Common English stop-words to exclude from keyword extraction and
+/// full-text search.
+pub(crate) const STOP_WORDS: &[&str] = &[
     "a", "an", "the", "and", "or", "but", "in", "on", "at", "to", "for", "of", "with", "by",
     "from", "is", "it", "its", "this", "that", "be", "as", "are", "was", "were", "has", "have",
     "had", "not", "no", "so", "if", "do", "did", "he", "she", "we", "you", "they", "i", "me", "my",
@@ -0,0 +1,212 @@
+//! Opt-in, bounded LRU cache for [`Database`](crate::Database) query results.
+//!
+//! # Staleness semantics
+//!
+//! The cache is keyed on `(sql, params)` and only ever stores results of
+//! read-only statements. Every write issued *through the owning `Database`*
+//! (`exec`, `Statement::run`, `transaction`, or a non-read-only `query`)
+//! clears the whole cache once it has landed, so a reader never observes a
+//! result older than the last local write.  Clearing also bumps an epoch,
+//! and a query that started before the clear does not cache its result, so
+//! one racing the write cannot put its pre-write rows back.  Nothing read
+//! inside an open transaction is cached.
+//!
+//! Only `columns` and `rows` mean anything in a cached result; the
+//! connection-specific `changes` and `last_insert_rowid` are always 0 for
+//! read-only statements.
+//!
+//! Writes made by another connection or process against the same file are
+//! invisible to the cache. Entries written before such an external change are
+//! served until they expire after the configured TTL or are evicted, so pick a
+//! TTL that matches how stale a dashboard is allowed to be.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::{QueryResult, SqlValue};
+
+/// Hit/miss counters and occupancy for a [`QueryCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    result: QueryResult,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<(String, String), CacheEntry>,
+    tick: u64,
+    /// Bumped by every [`QueryCache::invalidate`]
+    epoch: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct QueryCache {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn key(sql: &str, params: &[SqlValue]) -> (String, String) {
+        (sql.to_owned(), format!("{:?}", params))
+    }
+
+    pub(crate) fn get(&self, sql: &str, params: &[SqlValue]) -> Option<QueryResult> {
+        let key = Self::key(sql, params);
+        let mut state = self.state.lock();
+        state.tick += 1;
+        let tick = state.tick;
+        let fresh = match state.entries.get_mut(&key) {
+            Some(entry) if entry.inserted_at.elapsed() <= self.ttl => {
+                entry.last_used = tick;
+                Some(entry.result.clone())
+            }
+            _ => None,
+        };
+        if fresh.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            // Drop the expired entry, if any, so it stops counting towards capacity.
+            state.entries.remove(&key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        fresh
+    }
+
+    /// The current epoch, to pass to [`insert`](Self::insert) for a query
+    /// about to run.
+    pub(crate) fn epoch(&self) -> u64 {
+        self.state.lock().epoch
+    }
+
+    /// Cache `result`, unless the cache was invalidated since `epoch` was
+    /// read.
+    pub(crate) fn insert(&self, sql: &str, params: &[SqlValue], epoch: u64, result: QueryResult) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = Self::key(sql, params);
+        let mut state = self.state.lock();
+        if state.epoch != epoch {
+            return;
+        }
+        state.tick += 1;
+        let tick = state.tick;
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.capacity {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(
+            key,
+            CacheEntry {
+                result,
+                inserted_at: Instant::now(),
+                last_used: tick,
+            },
+        );
+    }
+
+    pub(crate) fn invalidate(&self) {
+        let mut state = self.state.lock();
+        state.epoch += 1;
+        state.entries.clear();
+    }
+
+    pub(crate) fn stats(&self) -> QueryCacheStats {
+        QueryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.state.lock().entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(n: i64) -> QueryResult {
+        QueryResult {
+            columns: vec!["n".to_string()],
+            rows: vec![vec![SqlValue::Integer(n)]],
+            changes: 0,
+            last_insert_rowid: 0,
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entry() {
+        let cache = QueryCache::new(2, Duration::from_secs(60));
+        cache.insert("SELECT 1", &[], 0, result(1));
+        cache.insert("SELECT 2", &[], 0, result(2));
+        assert!(cache.get("SELECT 1", &[]).is_some());
+
+        cache.insert("SELECT 3", &[], 0, result(3));
+
+        assert!(cache.get("SELECT 2", &[]).is_none());
+        assert!(cache.get("SELECT 1", &[]).is_some());
+        assert!(cache.get("SELECT 3", &[]).is_some());
+    }
+
+    #[test]
+    fn params_are_part_of_the_key() {
+        let cache = QueryCache::new(4, Duration::from_secs(60));
+        cache.insert("SELECT ?1", &[SqlValue::Integer(1)], 0, result(1));
+        assert!(cache.get("SELECT ?1", &[SqlValue::Integer(2)]).is_none());
+        assert!(cache.get("SELECT ?1", &[SqlValue::Integer(1)]).is_some());
+    }
+
+    #[test]
+    fn expired_entries_are_misses() {
+        let cache = QueryCache::new(4, Duration::ZERO);
+        cache.insert("SELECT 1", &[], 0, result(1));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(cache.get("SELECT 1", &[]).is_none());
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn results_from_before_an_invalidation_are_not_cached() {
+        let cache = QueryCache::new(4, Duration::from_secs(60));
+        let epoch = cache.epoch();
+        // A write lands while the query is running
+        cache.invalidate();
+        cache.insert("SELECT 1", &[], epoch, result(1));
+        assert!(cache.get("SELECT 1", &[]).is_none());
+
+        cache.insert("SELECT 1", &[], cache.epoch(), result(1));
+        assert!(cache.get("SELECT 1", &[]).is_some());
+    }
+}
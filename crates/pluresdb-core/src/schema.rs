@@ -0,0 +1,528 @@
+//! Named JSON Schemas for typed nodes.
+//!
+//! A node is typed by the string in its `"type"` field.  A [`TypeRegistry`]
+//! maps type names to schemas, and a [`CrdtStore`] given one with
+//! [`CrdtStore::with_type_registry`] rejects typed writes through
+//! [`CrdtStore::try_put`] that do not match.  Definitions are themselves
+//! nodes (`type:<name>`, see [`type_definition`]), so they replicate like any
+//! other data and the registry follows them as they are written or merged.
+//!
+//! Validation covers the commonly used part of JSON Schema: `type`, `enum`,
+//! `const`, `required`, `properties`, `additionalProperties`, `items`,
+//! `minLength`/`maxLength`, `minimum`/`maximum` and their exclusive forms,
+//! and `minItems`/`maxItems`.  Other keywords are accepted and ignored.
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{CrdtStore, NodeData, NodeId, NodeRecord, StoreError};
+
+/// `"type"` value marking a node as a type definition.
+pub const TYPE_DEFINITION: &str = "__type_definition__";
+
+/// Id of the node holding the definition of `name`.
+pub fn type_definition_id(name: &str) -> NodeId {
+    format!("type:{name}")
+}
+
+/// Data of the node defining `name` with `schema`.
+pub fn type_definition(name: &str, schema: Value) -> NodeData {
+    json!({
+        "type": TYPE_DEFINITION,
+        "name": name,
+        "schema": schema,
+        "created_at": Utc::now(),
+    })
+}
+
+/// One way in which data failed to match its schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    /// Where the mismatch is, e.g. `$.tags[1]`; `$` is the node data itself.
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_owned(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Schemas by type name.
+#[derive(Debug, Default)]
+pub struct TypeRegistry {
+    schemas: RwLock<HashMap<String, Value>>,
+}
+
+impl TypeRegistry {
+    /// A registry holding every type definition among the live nodes of
+    /// `store`.
+    pub fn load(store: &CrdtStore) -> Self {
+        let registry = Self::default();
+        for record in store.list() {
+            registry.register_definition(&record.data);
+        }
+        registry
+    }
+
+    /// Register `schema` for `name`, replacing any earlier schema.  Fails if
+    /// `schema` is neither an object nor a boolean.
+    pub fn define(&self, name: impl Into<String>, schema: Value) -> Result<(), ValidationError> {
+        check_schema(&schema)?;
+        self.schemas.write().insert(name.into(), schema);
+        Ok(())
+    }
+
+    /// Register the definition in `data` if it is one.  Returns whether it
+    /// was.
+    pub fn register_definition(&self, data: &NodeData) -> bool {
+        let Some((name, schema)) = parse_definition(data) else {
+            return false;
+        };
+        self.define(name, schema.clone()).is_ok()
+    }
+
+    /// Forget the schema for `name`.
+    pub fn remove(&self, name: &str) -> Option<Value> {
+        self.schemas.write().remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.schemas.read().get(name).cloned()
+    }
+
+    /// Registered type names, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.schemas.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Check `data` against the schema registered for `type_name`.  Types
+    /// without a schema accept anything.
+    pub fn validate(&self, type_name: &str, data: &Value) -> Result<(), Vec<ValidationError>> {
+        let schemas = self.schemas.read();
+        let Some(schema) = schemas.get(type_name) else {
+            return Ok(());
+        };
+        let mut errors = Vec::new();
+        validate_value(schema, data, "$", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check node data against the schema named by its `"type"` field.
+    /// Untyped data passes; type definitions are only checked for being
+    /// well-formed.
+    pub fn validate_node(&self, data: &NodeData) -> Result<(), Vec<ValidationError>> {
+        match data.get("type").and_then(Value::as_str) {
+            Some(TYPE_DEFINITION) => match parse_definition(data) {
+                Some((_, schema)) => check_schema(schema).map_err(|error| vec![error]),
+                None => Err(vec![ValidationError::new(
+                    "$",
+                    "type definition needs a string \"name\" and a \"schema\"",
+                )]),
+            },
+            Some(type_name) => self.validate(type_name, data),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_definition(data: &NodeData) -> Option<(&str, &Value)> {
+    if data.get("type").and_then(Value::as_str) != Some(TYPE_DEFINITION) {
+        return None;
+    }
+    Some((data.get("name")?.as_str()?, data.get("schema")?))
+}
+
+fn check_schema(schema: &Value) -> Result<(), ValidationError> {
+    if schema.is_object() || schema.is_boolean() {
+        Ok(())
+    } else {
+        Err(ValidationError::new(
+            "$",
+            "schema must be a JSON object or boolean",
+        ))
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let schema = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(ValidationError::new(path, "no value is allowed here"));
+            return;
+        }
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| type_matches(name, value)) {
+            errors.push(ValidationError::new(
+                path,
+                format!(
+                    "expected {}, found {}",
+                    allowed.join(" or "),
+                    type_name(value)
+                ),
+            ));
+            // The remaining keywords assume the declared type
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(ValidationError::new(
+                path,
+                format!("must be one of {}", Value::Array(options.clone())),
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(ValidationError::new(path, format!("must equal {expected}")));
+        }
+    }
+
+    match value {
+        Value::String(text) => {
+            let len = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(ValidationError::new(
+                        path,
+                        format!("must be at least {min} characters long"),
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(ValidationError::new(
+                        path,
+                        format!("must be at most {max} characters long"),
+                    ));
+                }
+            }
+        }
+        Value::Number(number) => {
+            let n = number.as_f64().unwrap_or_default();
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+            if let Some(min) = bound("minimum").filter(|&min| n < min) {
+                errors.push(ValidationError::new(path, format!("must be >= {min}")));
+            }
+            if let Some(max) = bound("maximum").filter(|&max| n > max) {
+                errors.push(ValidationError::new(path, format!("must be <= {max}")));
+            }
+            if let Some(min) = bound("exclusiveMinimum").filter(|&min| n <= min) {
+                errors.push(ValidationError::new(path, format!("must be > {min}")));
+            }
+            if let Some(max) = bound("exclusiveMaximum").filter(|&max| n >= max) {
+                errors.push(ValidationError::new(path, format!("must be < {max}")));
+            }
+        }
+        Value::Array(items) => {
+            let len = items.len() as u64;
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(ValidationError::new(
+                        path,
+                        format!("must have at least {min} items"),
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(ValidationError::new(
+                        path,
+                        format!("must have at most {max} items"),
+                    ));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{path}[{index}]"), errors);
+                }
+            }
+        }
+        Value::Object(fields) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !fields.contains_key(name) {
+                        errors.push(ValidationError::new(
+                            path,
+                            format!("missing required field \"{name}\""),
+                        ));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema.get("additionalProperties");
+            for (name, field) in fields {
+                let field_path = format!("{path}.{name}");
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(field_schema) => validate_value(field_schema, field, &field_path, errors),
+                    None => {
+                        if let Some(additional) = additional {
+                            validate_value(additional, field, &field_path, errors);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl CrdtStore {
+    /// Write `data` as [`put`](Self::put) does, but first check it against
+    /// the attached [`TypeRegistry`] and the store's
+    /// [`max_node_depth`](CrdtStore::max_node_depth) and
+    /// [`max_node_size`](CrdtStore::max_node_size).
+    ///
+    /// Fails with [`StoreError::Validation`] if the data names a type whose
+    /// schema it does not match, or is a malformed type definition, and with
+    /// [`StoreError::TooDeep`] or [`StoreError::NodeTooLarge`] if it is over
+    /// a limit.  Without a registry or limits this is `put`.
+    pub fn try_put(
+        &self,
+        id: impl Into<NodeId>,
+        actor: impl Into<crate::ActorId>,
+        data: NodeData,
+    ) -> Result<NodeId, StoreError> {
+        let id = id.into();
+        self.check_limits(&data)?;
+        self.check_type(&id, &data)?;
+        Ok(self.put(id, actor, data))
+    }
+
+    /// Check `data`, about to be written to `id`, against the attached
+    /// registry.
+    pub(crate) fn check_type(&self, id: &str, data: &NodeData) -> Result<(), StoreError> {
+        let Some(registry) = &self.type_registry else {
+            return Ok(());
+        };
+        registry
+            .validate_node(data)
+            .map_err(|errors| StoreError::Validation {
+                id: id.to_owned(),
+                errors,
+            })
+    }
+
+    /// Write the definition of type `name` as node `type:<name>`, registering
+    /// it with the attached registry.
+    pub fn define_type(
+        &self,
+        name: &str,
+        actor: impl Into<crate::ActorId>,
+        schema: Value,
+    ) -> Result<NodeId, StoreError> {
+        self.try_put(
+            type_definition_id(name),
+            actor,
+            type_definition(name, schema),
+        )
+    }
+
+    /// Live nodes whose `"type"` field is `type_name`, ordered by id.
+    ///
+    /// Uses the index on `"type"` if [`create_index`](Self::create_index)
+    /// made one, and scans every node otherwise.
+    pub fn list_by_type(&self, type_name: &str) -> Vec<NodeRecord> {
+        if self.has_index("type") {
+            return self.query_index("type", &Value::from(type_name));
+        }
+        let mut records: Vec<NodeRecord> = self
+            .list()
+            .into_iter()
+            .filter(|record| record.data.get("type").and_then(Value::as_str) == Some(type_name))
+            .collect();
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        records
+    }
+
+    /// Keep the attached registry in step with a written, merged, or
+    /// deleted type definition node.
+    pub(crate) fn track_type_definition(&self, record: &NodeRecord) {
+        let Some(registry) = &self.type_registry else {
+            return;
+        };
+        if record.is_tombstone() {
+            if let Some(name) = record.id.strip_prefix("type:") {
+                registry.remove(name);
+            }
+        } else {
+            registry.register_definition(&record.data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn user_schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["name", "email"],
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "email": { "type": "string" },
+                "age": { "type": "integer", "minimum": 0 },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            }
+        })
+    }
+
+    fn typed_store() -> CrdtStore {
+        let store = CrdtStore::default().with_type_registry(Arc::new(TypeRegistry::default()));
+        store.define_type("User", "admin", user_schema()).unwrap();
+        store
+    }
+
+    #[test]
+    fn valid_typed_insert_is_written() {
+        let store = typed_store();
+        let data = json!({ "type": "User", "name": "Alice", "email": "a@example.com", "age": 30 });
+        let id = store.try_put("user-1", "actor", data.clone()).unwrap();
+        assert_eq!(store.get(&id).unwrap().data, data);
+
+        // Types without a schema and untyped data are not checked
+        store
+            .try_put("note-1", "actor", json!({ "type": "Note" }))
+            .unwrap();
+        store.try_put("blob-1", "actor", json!([1, 2, 3])).unwrap();
+    }
+
+    #[test]
+    fn insert_missing_a_required_field_is_rejected() {
+        let store = typed_store();
+        let err = store
+            .try_put(
+                "user-2",
+                "actor",
+                json!({ "type": "User", "name": "Bob", "age": -1, "tags": ["a", 2] }),
+            )
+            .unwrap_err();
+        let StoreError::Validation { id, errors } = &err else {
+            panic!("expected a validation error, got {err:?}");
+        };
+        assert_eq!(id, "user-2");
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert!(messages.contains(&"$: missing required field \"email\"".to_string()));
+        assert!(messages.contains(&"$.age: must be >= 0".to_string()));
+        assert!(messages.contains(&"$.tags[1]: expected string, found number".to_string()));
+        assert_eq!(err.code(), crate::CoreErrorCode::InvalidInput);
+        assert!(store.get("user-2").is_none());
+    }
+
+    #[test]
+    fn instances_are_listed_by_type() {
+        let store = typed_store();
+        for (id, name) in [("user-b", "Bob"), ("user-a", "Alice")] {
+            store
+                .try_put(
+                    id,
+                    "actor",
+                    json!({ "type": "User", "name": name, "email": "x" }),
+                )
+                .unwrap();
+        }
+        store.put("post-1", "actor", json!({ "type": "Post" }));
+        store.delete("user-b").unwrap();
+
+        let ids: Vec<String> = store
+            .list_by_type("User")
+            .into_iter()
+            .map(|record| record.id)
+            .collect();
+        assert_eq!(ids, ["user-a"]);
+        assert_eq!(store.list_by_type("Post").len(), 1);
+    }
+
+    #[test]
+    fn registry_follows_replicated_and_deleted_definitions() {
+        let source = CrdtStore::default();
+        source.define_type("User", "peer-a", user_schema()).unwrap();
+
+        let replica = CrdtStore::default().with_type_registry(Arc::new(TypeRegistry::default()));
+        for record in source.list() {
+            replica.merge_record(record);
+        }
+        let registry = replica.type_registry().unwrap();
+        assert_eq!(registry.names(), ["User"]);
+        assert!(replica
+            .try_put("u", "peer-b", json!({ "type": "User" }))
+            .is_err());
+
+        replica.delete(type_definition_id("User")).unwrap();
+        assert!(registry.names().is_empty());
+        assert!(replica
+            .try_put("u", "peer-b", json!({ "type": "User" }))
+            .is_ok());
+        assert_eq!(TypeRegistry::load(&source).names(), ["User"]);
+    }
+
+    #[test]
+    fn malformed_definitions_are_rejected() {
+        let store = CrdtStore::default().with_type_registry(Arc::new(TypeRegistry::default()));
+        assert!(store.define_type("Bad", "admin", json!("string")).is_err());
+        assert!(store
+            .try_put("type:Odd", "admin", json!({ "type": TYPE_DEFINITION }))
+            .is_err());
+        assert!(store.type_registry().unwrap().names().is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+//! Binary snapshots of a [`CrdtStore`] for fast startup.
+//!
+//! A snapshot is [`SNAPSHOT_MAGIC`], a little-endian `u32` version and a
+//! `u64` record count, followed by that many records, each a `u32` length
+//! and that many bytes of `bincode`.  Records keep their clocks and
+//! tombstones, like a [backup](crate::backup), so a snapshot taken at a WAL
+//! checkpoint plus the entries logged after it rebuilds the store.
+
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use pluresdb_storage::WireValue;
+use serde::{Deserialize, Serialize};
+
+use crate::{CrdtStore, NodeRecord, VectorClock};
+
+/// First bytes of every snapshot.
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"PLURSNAP";
+
+/// Version written by [`CrdtStore::snapshot_to_writer`]; loading rejects
+/// any other.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Largest record a snapshot may hold, so a corrupt length cannot make a
+/// load allocate without bound.
+const MAX_RECORD_LEN: usize = 256 * 1024 * 1024;
+
+/// [`NodeRecord`] as bincode sees it: every field always present.
+#[derive(Serialize, Deserialize)]
+struct WireRecord {
+    id: String,
+    data: WireValue,
+    clock: VectorClock,
+    timestamp: DateTime<Utc>,
+    embedding: Option<Vec<f32>>,
+    quality_score: Option<f32>,
+    deleted_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<&NodeRecord> for WireRecord {
+    fn from(record: &NodeRecord) -> Self {
+        Self {
+            id: record.id.clone(),
+            data: WireValue::from(&record.data),
+            clock: record.clock.clone(),
+            timestamp: record.timestamp,
+            embedding: record.embedding.clone(),
+            quality_score: record.quality_score,
+            deleted_at: record.deleted_at,
+            expires_at: record.expires_at,
+        }
+    }
+}
+
+impl From<WireRecord> for NodeRecord {
+    fn from(wire: WireRecord) -> Self {
+        Self {
+            id: wire.id,
+            data: wire.data.into(),
+            clock: wire.clock,
+            timestamp: wire.timestamp,
+            embedding: wire.embedding,
+            quality_score: wire.quality_score,
+            deleted_at: wire.deleted_at,
+            expires_at: wire.expires_at,
+        }
+    }
+}
+
+impl CrdtStore {
+    /// Write every record, tombstones included, to `out` as a snapshot.
+    /// Returns the number of records written.
+    pub fn snapshot_to_writer<W: Write>(&self, mut out: W) -> Result<u64> {
+        let records = self.list_including_tombstones();
+        out.write_all(SNAPSHOT_MAGIC)?;
+        out.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        out.write_all(&(records.len() as u64).to_le_bytes())?;
+        for record in &records {
+            let bytes = bincode::serde::encode_to_vec(
+                WireRecord::from(record),
+                bincode::config::standard(),
+            )
+            .with_context(|| format!("failed to encode record {}", record.id))?;
+            out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            out.write_all(&bytes)?;
+        }
+        out.flush()?;
+        Ok(records.len() as u64)
+    }
+
+    /// A new store holding the records of the snapshot in `input`.
+    ///
+    /// Fails, without panicking, on a wrong magic or version, a record that
+    /// does not decode, and on input that ends early or runs past the last
+    /// record.  Configure the result with the `with_*` builders as usual.
+    pub fn load_from_reader<R: Read>(mut input: R) -> Result<CrdtStore> {
+        let mut magic = [0u8; 8];
+        input
+            .read_exact(&mut magic)
+            .context("snapshot is too short for a header")?;
+        if &magic != SNAPSHOT_MAGIC {
+            bail!("not a PluresDB snapshot");
+        }
+        let version =
+            u32::from_le_bytes(read_array(&mut input).context("snapshot header is truncated")?);
+        if version != SNAPSHOT_VERSION {
+            bail!(
+                "snapshot version {} is not the supported version {}",
+                version,
+                SNAPSHOT_VERSION
+            );
+        }
+        let count =
+            u64::from_le_bytes(read_array(&mut input).context("snapshot header is truncated")?);
+
+        let store = CrdtStore::default();
+        let mut bytes = Vec::new();
+        for index in 0..count {
+            let truncated = || format!("snapshot ends in record {} of {}", index + 1, count);
+            let len = u32::from_le_bytes(read_array(&mut input).with_context(truncated)?) as usize;
+            if len > MAX_RECORD_LEN {
+                bail!(
+                    "record {} claims an implausible length of {} bytes",
+                    index + 1,
+                    len
+                );
+            }
+            bytes.resize(len, 0);
+            input.read_exact(&mut bytes).with_context(truncated)?;
+            let (wire, _): (WireRecord, usize) =
+                bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+                    .with_context(|| format!("malformed record {} of {}", index + 1, count))?;
+            store.merge_record(wire.into());
+        }
+
+        match input.read(&mut [0u8]) {
+            Ok(0) => Ok(store),
+            Ok(_) => bail!("snapshot has data after its last record"),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    input.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sorted(mut records: Vec<NodeRecord>) -> Vec<NodeRecord> {
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        records
+    }
+
+    #[test]
+    fn snapshot_round_trips_records_clocks_and_tombstones() {
+        let store = CrdtStore::default();
+        store.put("a", "peer-1", json!({ "n": 1, "ratio": 0.5 }));
+        store.put("a", "peer-2", json!({ "n": -2, "tags": ["x", null] }));
+        store.put("b", "peer-1", json!("plain"));
+        store.put("gone", "peer-1", json!({}));
+        store.delete("gone").unwrap();
+
+        let mut snapshot = Vec::new();
+        assert_eq!(store.snapshot_to_writer(&mut snapshot).unwrap(), 3);
+        let loaded = CrdtStore::load_from_reader(snapshot.as_slice()).unwrap();
+        assert_eq!(
+            sorted(loaded.list_including_tombstones()),
+            sorted(store.list_including_tombstones())
+        );
+    }
+
+    #[test]
+    fn load_rejects_garbage_and_truncated_snapshots() {
+        let store = CrdtStore::default();
+        store.put("a", "peer-1", json!({ "n": 1 }));
+        store.put("b", "peer-1", json!({ "n": 2 }));
+        let mut snapshot = Vec::new();
+        store.snapshot_to_writer(&mut snapshot).unwrap();
+
+        for len in [0, 4, 12, 20, snapshot.len() - 1] {
+            let err = CrdtStore::load_from_reader(&snapshot[..len]).unwrap_err();
+            assert!(err.to_string().contains("snapshot"), "{len}: {err:#}");
+        }
+        let mut newer = snapshot.clone();
+        newer[8] = 99;
+        assert!(CrdtStore::load_from_reader(newer.as_slice()).is_err());
+        let mut trailing = snapshot.clone();
+        trailing.push(0);
+        assert!(CrdtStore::load_from_reader(trailing.as_slice()).is_err());
+        let mut corrupt = snapshot;
+        corrupt[24..].fill(0xff);
+        assert!(CrdtStore::load_from_reader(corrupt.as_slice()).is_err());
+        assert!(CrdtStore::load_from_reader(&b"{\"id\":\"a\"}\n"[..]).is_err());
+    }
+}
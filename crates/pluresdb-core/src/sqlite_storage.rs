@@ -0,0 +1,176 @@
+//! A [`StorageEngine`] that keeps nodes in a SQLite [`Database`].
+//!
+//! Nodes live in a single `nodes (id TEXT PRIMARY KEY, payload TEXT, meta
+//! TEXT)` table, with the payload and metadata stored as JSON text.  The
+//! database is opened through [`Database::open`], so the pragma configuration
+//! in [`DatabaseOptions`] applies as it does everywhere else.
+//!
+//! This lives in `pluresdb-core` rather than `pluresdb-storage` because the
+//! SQLite layer is here, and this crate already depends on the storage one.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use pluresdb_storage::{StorageEngine, StoredNode};
+
+use crate::{Database, DatabaseOptions, SqlValue};
+
+pub(crate) const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS nodes (
+    id TEXT PRIMARY KEY,
+    payload TEXT NOT NULL,
+    meta TEXT
+)";
+
+/// Durable storage in a SQLite `nodes` table.
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    db: Database,
+}
+
+impl SqliteStorage {
+    /// Open (or create) the database described by `options` and make sure
+    /// the `nodes` table exists.
+    pub fn open(options: DatabaseOptions) -> Result<Self> {
+        let db = Database::open(options)?;
+        db.exec(SCHEMA)?;
+        Ok(Self { db })
+    }
+
+    /// Access the underlying database, e.g. to query `nodes` directly.
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    fn decode(row: Vec<SqlValue>) -> Result<StoredNode> {
+        let mut columns = row.into_iter();
+        let (Some(SqlValue::Text(id)), Some(SqlValue::Text(payload)), Some(meta)) =
+            (columns.next(), columns.next(), columns.next())
+        else {
+            bail!("malformed row in nodes table");
+        };
+        let payload = serde_json::from_str(&payload)
+            .with_context(|| format!("invalid payload JSON for node '{}'", id))?;
+        let meta = match meta {
+            SqlValue::Text(meta) => Some(
+                serde_json::from_str(&meta)
+                    .with_context(|| format!("invalid meta JSON for node '{}'", id))?,
+            ),
+            _ => None,
+        };
+        Ok(StoredNode { id, payload, meta })
+    }
+}
+
+#[async_trait]
+impl StorageEngine for SqliteStorage {
+    async fn put(&self, node: StoredNode) -> Result<()> {
+        let meta = match &node.meta {
+            Some(meta) => SqlValue::Text(serde_json::to_string(meta)?),
+            None => SqlValue::Null,
+        };
+        self.db
+            .prepare("INSERT OR REPLACE INTO nodes (id, payload, meta) VALUES (?1, ?2, ?3)")?
+            .run(&[
+                SqlValue::Text(node.id),
+                SqlValue::Text(serde_json::to_string(&node.payload)?),
+                meta,
+            ])?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<StoredNode>> {
+        let result = self.db.query(
+            "SELECT id, payload, meta FROM nodes WHERE id = ?1",
+            &[SqlValue::Text(id.to_string())],
+        )?;
+        result.rows.into_iter().next().map(Self::decode).transpose()
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.db
+            .prepare("DELETE FROM nodes WHERE id = ?1")?
+            .run(&[SqlValue::Text(id.to_string())])?;
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<StoredNode>> {
+        let result = self
+            .db
+            .query("SELECT id, payload, meta FROM nodes ORDER BY id", &[])?;
+        result.rows.into_iter().map(Self::decode).collect()
+    }
+
+    async fn count(&self) -> Result<usize> {
+        let result = self.db.query("SELECT COUNT(*) FROM nodes", &[])?;
+        let count = result
+            .rows
+            .first()
+            .and_then(|row| row.first())
+            .and_then(SqlValue::as_i64)
+            .unwrap_or(0);
+        Ok(count as usize)
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        let result = self.db.query(
+            "SELECT 1 FROM nodes WHERE id = ?1",
+            &[SqlValue::Text(id.to_string())],
+        )?;
+        Ok(!result.rows.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str) -> StoredNode {
+        StoredNode {
+            id: id.to_string(),
+            payload: serde_json::json!({"name": "plures"}),
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn sqlite_storage_round_trip() {
+        let storage = SqliteStorage::open(DatabaseOptions::in_memory()).unwrap();
+        let node = node("1");
+        storage.put(node.clone()).await.unwrap();
+        let fetched = storage.get("1").await.unwrap().unwrap();
+        assert_eq!(fetched, node);
+        assert_eq!(storage.count().await.unwrap(), 1);
+        assert!(storage.exists("1").await.unwrap());
+        assert!(!storage.exists("2").await.unwrap());
+        storage.delete("1").await.unwrap();
+        assert!(storage.get("1").await.unwrap().is_none());
+        assert_eq!(storage.count().await.unwrap(), 0);
+        assert!(!storage.exists("1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn sqlite_storage_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nodes.db");
+        {
+            let storage = SqliteStorage::open(DatabaseOptions::with_file(&path)).unwrap();
+            storage.put(node("a")).await.unwrap();
+            storage.put(node("b")).await.unwrap();
+            storage
+                .set_meta("b", Some(serde_json::json!({"pinned": true})))
+                .await
+                .unwrap();
+        }
+
+        let reopened = SqliteStorage::open(DatabaseOptions::with_file(&path)).unwrap();
+        let nodes = reopened.list().await.unwrap();
+        assert_eq!(
+            nodes.iter().map(|n| n.id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(nodes[1].payload, serde_json::json!({"name": "plures"}));
+        assert_eq!(
+            reopened.get_meta("b").await.unwrap(),
+            Some(serde_json::json!({"pinned": true}))
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Point-in-time, read-only views of a [`CrdtStore`].
+//!
+//! [`CrdtStore::snapshot`] copies every live record into a map owned by the
+//! returned [`StoreSnapshot`], so a run of reads on it sees one state of the
+//! store however many writes land meanwhile.
+//!
+//! # Cost
+//!
+//! Taking a snapshot clones every live node, data included, and reads the
+//! whole of persistence if the store has it, so it needs as much memory
+//! again as the live data and time to match.  Tombstones and expired nodes
+//! are left out.  Cloning a snapshot only bumps a reference count, and the
+//! copy is freed when the last clone is dropped.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::{CrdtStore, JsonFilter, NodeId, NodeRecord};
+
+/// An immutable copy of a store's live nodes; see the
+/// [module docs](self).
+#[derive(Debug, Clone)]
+pub struct StoreSnapshot {
+    records: Arc<BTreeMap<NodeId, NodeRecord>>,
+    taken_at: DateTime<Utc>,
+}
+
+impl CrdtStore {
+    /// Copy the live nodes into a [`StoreSnapshot`] that later writes do not
+    /// change.
+    ///
+    /// The store is not locked as a whole while it is copied, so a write
+    /// racing the call may or may not make it in, but every node is copied
+    /// whole and nothing changes once the snapshot is returned.  Copies all
+    /// live data; see the [module docs](crate::store_snapshot) for the cost.
+    pub fn snapshot(&self) -> StoreSnapshot {
+        let taken_at = Utc::now();
+        let records = self
+            .list_including_tombstones()
+            .into_iter()
+            .filter(|record| {
+                !record.is_tombstone() && record.expires_at.is_none_or(|at| at > taken_at)
+            })
+            .map(|record| (record.id.clone(), record))
+            .collect();
+        StoreSnapshot {
+            records: Arc::new(records),
+            taken_at,
+        }
+    }
+}
+
+impl StoreSnapshot {
+    /// When the snapshot was taken; nodes expiring after this are included.
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+
+    pub fn get(&self, id: impl AsRef<str>) -> Option<&NodeRecord> {
+        self.records.get(id.as_ref())
+    }
+
+    /// Every node in the snapshot, ordered by id.
+    pub fn list(&self) -> Vec<&NodeRecord> {
+        self.records.values().collect()
+    }
+
+    /// Nodes whose data matches `filter`, ordered by id.
+    pub fn query(&self, filter: &JsonFilter) -> Vec<&NodeRecord> {
+        self.records
+            .values()
+            .filter(|record| filter.matches(&record.data))
+            .collect()
+    }
+
+    /// Nodes whose id starts with `prefix`, ordered by id.
+    pub fn list_by_prefix(&self, prefix: &str) -> Vec<&NodeRecord> {
+        self.records
+            .range::<str, _>(prefix..)
+            .take_while(|(id, _)| id.starts_with(prefix))
+            .map(|(_, record)| record)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn snapshot_keeps_the_values_it_was_taken_with() {
+        let store = CrdtStore::default();
+        store.put("report:a", "a", json!({ "total": 1 }));
+        store.put("report:b", "a", json!({ "total": 2 }));
+        store.put("gone", "a", json!({}));
+        store.delete("gone").unwrap();
+
+        let snapshot = store.snapshot();
+        store.put("report:a", "b", json!({ "total": 10 }));
+        store.delete("report:b").unwrap();
+        store.put("report:c", "b", json!({ "total": 3 }));
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(
+            snapshot.get("report:a").unwrap().data,
+            json!({ "total": 1 })
+        );
+        assert_eq!(
+            snapshot.get("report:b").unwrap().data,
+            json!({ "total": 2 })
+        );
+        assert!(snapshot.get("report:c").is_none());
+        assert!(snapshot.get("gone").is_none());
+        let ids: Vec<&str> = snapshot
+            .list_by_prefix("report:")
+            .into_iter()
+            .map(|record| record.id.as_str())
+            .collect();
+        assert_eq!(ids, ["report:a", "report:b"]);
+        assert_eq!(snapshot.query(&JsonFilter::gt("total", 1)).len(), 1);
+
+        assert_eq!(store.get("report:a").unwrap().data, json!({ "total": 10 }));
+        assert_eq!(store.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn nodes_expired_when_taken_are_left_out() {
+        let store = CrdtStore::default();
+        store.put_with_ttl("brief", "a", json!({}), Duration::ZERO);
+        store.put_with_ttl("lasting", "a", json!({}), Duration::from_secs(3600));
+        let snapshot = store.snapshot();
+        assert!(snapshot.get("brief").is_none());
+        assert!(snapshot.get("lasting").is_some());
+    }
+}
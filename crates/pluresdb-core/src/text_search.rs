@@ -0,0 +1,166 @@
+//! Full-text search over the string values in node data.
+//!
+//! [`CrdtStore::search`] builds an inverted index over the live nodes when it
+//! is called and ranks matches by TF-IDF, so a term that appears in few nodes
+//! outweighs one that every node repeats.  Text is split into terms the same
+//! way document keyword extraction does it: on whitespace and punctuation,
+//! case-folded, with stop-words dropped.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::procedures::document::STOP_WORDS;
+use crate::{CrdtStore, NodeRecord};
+
+/// Split `text` into lowercase, non-stop-word terms.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| c.is_whitespace() || (c.is_ascii_punctuation() && c != '\'' && c != '-'))
+        .filter_map(|word| {
+            let lower = word.to_lowercase();
+            let term = lower.trim_matches(|c: char| !c.is_alphanumeric());
+            if term.is_empty() || STOP_WORDS.contains(&term) {
+                return None;
+            }
+            Some(term.to_owned())
+        })
+}
+
+/// Add the terms of every string in `value` to `counts`; object keys are
+/// field names, not content, and are skipped.
+fn count_terms(value: &Value, counts: &mut HashMap<String, usize>) {
+    match value {
+        Value::String(text) => {
+            for term in terms(text) {
+                *counts.entry(term).or_insert(0) += 1;
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| count_terms(item, counts)),
+        Value::Object(fields) => fields.values().for_each(|field| count_terms(field, counts)),
+        _ => {}
+    }
+}
+
+impl CrdtStore {
+    /// Up to `limit` nodes whose string values contain terms from `query`,
+    /// best match first, with their TF-IDF scores.
+    ///
+    /// Each query term contributes its frequency within the node (as a share
+    /// of the node's terms) times `ln(1 + N / df)`, where `df` is the number
+    /// of the `N` live nodes containing it.  Nodes matching no term are left
+    /// out; ties are broken by id.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(NodeRecord, f32)> {
+        let mut query_terms: Vec<String> = terms(query).collect();
+        query_terms.sort();
+        query_terms.dedup();
+        if query_terms.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let records = self.list();
+        let total = records.len() as f32;
+        // term -> (record index, occurrences in that record), for query terms only
+        let mut postings: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+        let mut lengths = Vec::with_capacity(records.len());
+        for (index, record) in records.iter().enumerate() {
+            let mut counts = HashMap::new();
+            count_terms(&record.data, &mut counts);
+            lengths.push(counts.values().sum::<usize>());
+            for term in &query_terms {
+                if let Some(&count) = counts.get(term) {
+                    postings
+                        .entry(term.as_str())
+                        .or_default()
+                        .push((index, count));
+                }
+            }
+        }
+
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        for matches in postings.values() {
+            let idf = (1.0 + total / matches.len() as f32).ln();
+            for &(index, count) in matches {
+                let tf = count as f32 / lengths[index] as f32;
+                *scores.entry(index).or_insert(0.0) += tf * idf;
+            }
+        }
+
+        let mut ranked: Vec<(usize, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| {
+            b.1.total_cmp(&a.1)
+                .then_with(|| records[a.0].id.cmp(&records[b.0].id))
+        });
+        ranked.truncate(limit);
+
+        let mut records: Vec<Option<NodeRecord>> = records.into_iter().map(Some).collect();
+        ranked
+            .into_iter()
+            .filter_map(|(index, score)| Some((records[index].take()?, score)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rare_term_outranks_a_repeated_common_word() {
+        let store = CrdtStore::default();
+        store.put(
+            "repeats",
+            "a",
+            json!({ "text": "database database database database" }),
+        );
+        store.put("rare", "a", json!({ "text": "database quokka" }));
+        store.put("plain-1", "a", json!({ "text": "database storage" }));
+        store.put("plain-2", "a", json!({ "text": "database engine" }));
+
+        let results = store.search("database quokka", 10);
+        let ids: Vec<&str> = results
+            .iter()
+            .map(|(record, _)| record.id.as_str())
+            .collect();
+        assert_eq!(ids[0], "rare");
+        assert_eq!(ids.len(), 4);
+        assert!(results[0].1 > results[1].1);
+    }
+
+    #[test]
+    fn search_folds_case_and_ignores_stop_words_and_keys() {
+        let store = CrdtStore::default();
+        store.put(
+            "n1",
+            "a",
+            json!({ "title": "The Rust Book", "tags": ["Systems"] }),
+        );
+        store.put("n2", "a", json!({ "title": "the the the" }));
+
+        let ids = |query: &str| -> Vec<String> {
+            store
+                .search(query, 10)
+                .into_iter()
+                .map(|(record, _)| record.id)
+                .collect()
+        };
+        assert_eq!(ids("RUST"), vec!["n1"]);
+        assert_eq!(ids("systems"), vec!["n1"]);
+        assert!(ids("the").is_empty());
+        assert!(ids("title").is_empty());
+    }
+
+    #[test]
+    fn search_skips_deleted_nodes_and_respects_limit() {
+        let store = CrdtStore::default();
+        for i in 0..5 {
+            store.put(format!("n{i}"), "a", json!({ "text": "shared term" }));
+        }
+        store.delete("n0").unwrap();
+
+        let results = store.search("shared", 3);
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(record, _)| record.id != "n0"));
+        assert!(store.search("absent", 10).is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+//! Read-modify-write of a single node without interleaving writers.
+//!
+//! [`CrdtStore::update_with`] and [`CrdtStore::compare_and_swap`] hold the
+//! node's map entry from the read until the write is persisted, so no other
+//! local write to that id can land in between.  The entry lock also blocks
+//! the other ids in the same map shard, so keep the update closure short,
+//! and never touch the store from inside it: that deadlocks.
+
+use dashmap::mapref::entry::Entry;
+
+use crate::clock::{self, ClockOrdering};
+use crate::{ActorId, AuditOp, CrdtStore, NodeData, NodeId, NodeRecord, StoreError, VectorClock};
+
+impl CrdtStore {
+    /// Replace the data of `id` with `f(current data)` as a local write by
+    /// `actor`, and return the written record.
+    ///
+    /// `f` sees `None` for a missing or deleted node and runs while the
+    /// node's entry is locked, so concurrent `update_with` calls on one id
+    /// apply one after another and none is lost.  Fails with
+    /// [`StoreError::Validation`], writing nothing, if the result does not
+    /// match its type schema, or [`StoreError::TooDeep`] or
+    /// [`StoreError::NodeTooLarge`] if it is over one of the store's limits.
+    pub fn update_with<F>(
+        &self,
+        id: impl Into<NodeId>,
+        actor: impl Into<ActorId>,
+        f: F,
+    ) -> Result<NodeRecord, StoreError>
+    where
+        F: FnOnce(Option<&NodeData>) -> NodeData,
+    {
+        self.write_locked(id.into(), actor.into(), |current| {
+            let live = current.filter(|record| !record.is_tombstone());
+            Ok(f(live.map(|record| &record.data)))
+        })
+    }
+
+    /// Write `new_data` to `id` as `actor`, but only if the node's clock is
+    /// still `expected_clock`, i.e. nobody wrote it since it was read.
+    ///
+    /// A node that was never written has an empty clock; a deleted node keeps
+    /// the clock of its tombstone.  Fails with [`StoreError::ClockMismatch`]
+    /// if the clocks differ.
+    pub fn compare_and_swap(
+        &self,
+        id: impl Into<NodeId>,
+        actor: impl Into<ActorId>,
+        expected_clock: &VectorClock,
+        new_data: NodeData,
+    ) -> Result<NodeRecord, StoreError> {
+        let id = id.into();
+        self.write_locked(id.clone(), actor.into(), |current| {
+            let empty = VectorClock::default();
+            let actual = current.map_or(&empty, |record| &record.clock);
+            if clock::compare(actual, expected_clock) != ClockOrdering::Equal {
+                return Err(StoreError::ClockMismatch {
+                    id,
+                    expected: expected_clock.clone(),
+                    actual: actual.clone(),
+                });
+            }
+            Ok(new_data)
+        })
+    }
+
+    /// Compute and write the new data for `id` while holding its entry.
+    /// `compute` sees the stored record, tombstone or not.
+    fn write_locked(
+        &self,
+        id: NodeId,
+        actor: ActorId,
+        compute: impl FnOnce(Option<&NodeRecord>) -> Result<NodeData, StoreError>,
+    ) -> Result<NodeRecord, StoreError> {
+        let (record, before_clock) = match self.nodes.entry(id.clone()) {
+            Entry::Occupied(mut slot) => {
+                let data = compute(Some(slot.get()))?;
+                self.check_limits(&data)?;
+                self.check_type(&id, &data)?;
+                let before_clock = slot.get().clock.clone();
+                slot.get_mut().merge_update(actor.clone(), data);
+                self.persist_node(slot.get(), None);
+                (slot.get().clone(), Some(before_clock))
+            }
+            Entry::Vacant(slot) => {
+                // A persistent store may hold the node only on disk
+                let stored = self.get_from_persistence(&id);
+                let data = compute(stored.as_ref())?;
+                self.check_limits(&data)?;
+                self.check_type(&id, &data)?;
+                let before_clock = stored.as_ref().map(|record| record.clock.clone());
+                let record = match stored {
+                    Some(mut record) => {
+                        record.merge_update(actor.clone(), data);
+                        record
+                    }
+                    None => NodeRecord::new(id.clone(), actor.clone(), data),
+                };
+                self.persist_node(&record, None);
+                (slot.insert(record).clone(), before_clock)
+            }
+        };
+        self.track_type_definition(&record);
+        self.reindex_node(&id);
+        self.notify_watchers(&id);
+        self.record_audit(
+            AuditOp::Put,
+            &id,
+            Some(actor),
+            before_clock,
+            record.clock.clone(),
+        );
+        self.after_local_write(&id, &record.data);
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn count(record: &NodeRecord) -> u64 {
+        record.data["count"].as_u64().unwrap()
+    }
+
+    #[test]
+    fn concurrent_increments_are_not_lost() {
+        const THREADS: u64 = 8;
+        const INCREMENTS: u64 = 250;
+
+        let store = CrdtStore::default();
+        std::thread::scope(|scope| {
+            for t in 0..THREADS {
+                let store = &store;
+                scope.spawn(move || {
+                    for _ in 0..INCREMENTS {
+                        store
+                            .update_with("counter", format!("actor-{t}"), |current| {
+                                let n = current.and_then(|data| data["count"].as_u64());
+                                json!({ "count": n.unwrap_or(0) + 1 })
+                            })
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let record = store.get("counter").unwrap();
+        assert_eq!(count(&record), THREADS * INCREMENTS);
+        assert_eq!(record.clock.values().sum::<u64>(), THREADS * INCREMENTS);
+    }
+
+    #[test]
+    fn update_with_sees_none_for_missing_and_deleted_nodes() {
+        let store = CrdtStore::default();
+        let record = store
+            .update_with("n", "a", |current| {
+                assert!(current.is_none());
+                json!({ "count": 1 })
+            })
+            .unwrap();
+        assert_eq!(record.clock.get("a"), Some(&1));
+
+        store.delete("n").unwrap();
+        let record = store
+            .update_with("n", "a", |current| {
+                assert!(current.is_none());
+                json!({ "count": 5 })
+            })
+            .unwrap();
+        assert_eq!(count(&record), 5);
+        assert_eq!(count(&store.get("n").unwrap()), 5);
+    }
+
+    #[test]
+    fn compare_and_swap_fails_once_the_clock_moves() {
+        let store = CrdtStore::default();
+        let created = store
+            .compare_and_swap("n", "a", &VectorClock::default(), json!({ "v": 1 }))
+            .unwrap();
+
+        let swapped = store
+            .compare_and_swap("n", "a", &created.clock, json!({ "v": 2 }))
+            .unwrap();
+        assert_eq!(swapped.data["v"], 2);
+
+        // `created.clock` is stale now
+        let err = store
+            .compare_and_swap("n", "b", &created.clock, json!({ "v": 3 }))
+            .unwrap_err();
+        let StoreError::ClockMismatch { id, actual, .. } = &err else {
+            panic!("expected a clock mismatch, got {err:?}");
+        };
+        assert_eq!(id, "n");
+        assert_eq!(actual, &swapped.clock);
+        assert_eq!(store.get("n").unwrap().data["v"], 2);
+
+        let err = store
+            .compare_and_swap("fresh", "a", &swapped.clock, json!({}))
+            .unwrap_err();
+        assert!(matches!(err, StoreError::ClockMismatch { .. }));
+        assert!(store.get("fresh").is_none());
+    }
+}
@@ -0,0 +1,594 @@
+//! Nearest-neighbour search over node embeddings.
+//!
+//! Native builds index vectors in an HNSW graph ([`VectorIndex`]); WASM
+//! builds, which cannot use `hnsw_rs`, scan every vector
+//! ([`BruteForceVectorIndex`]).  Both hold vectors of one dimension: set it
+//! with `with_dimension`, or let the first vector added fix it.  Adding or
+//! searching with a vector of another length fails with
+//! [`VectorError::DimensionMismatch`].
+//!
+//! Vectors are compared by cosine similarity unless another
+//! [`DistanceMetric`] is chosen with `with_metric`, and `with_auto_normalize`
+//! scales every vector added or searched for to unit length first.  Either
+//! must be set before the first vector is added.
+
+use std::sync::OnceLock;
+
+use dashmap::DashMap;
+#[cfg(feature = "native")]
+use hnsw_rs::prelude::*;
+#[cfg(feature = "native")]
+use parking_lot::Mutex;
+use thiserror::Error;
+#[cfg(feature = "native")]
+use tracing::debug;
+
+use crate::{CoreErrorCode, NodeId};
+
+/// Why a vector was not added or searched for.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum VectorError {
+    #[error(
+        "embedding has {actual} dimensions but the index holds {expected}-dimensional vectors"
+    )]
+    DimensionMismatch { expected: usize, actual: usize },
+    #[error("embedding must be a non-empty array of finite numbers with a non-zero, finite norm")]
+    InvalidEmbedding,
+    #[error("vector index is full ({capacity} vectors)")]
+    CapacityExceeded { capacity: usize },
+}
+
+impl VectorError {
+    pub const fn code(&self) -> CoreErrorCode {
+        match self {
+            Self::DimensionMismatch { .. } => CoreErrorCode::DimensionMismatch,
+            Self::InvalidEmbedding => CoreErrorCode::InvalidInput,
+            Self::CapacityExceeded { .. } => CoreErrorCode::CapacityExceeded,
+        }
+    }
+}
+
+/// How vectors are compared, and what the scores returned by `search` mean.
+///
+/// Every score is higher for nearer vectors, so a search `threshold` is
+/// always a minimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DistanceMetric {
+    /// Cosine similarity, scored in `[0, 1]` with opposed vectors at 0.
+    #[default]
+    Cosine,
+    /// The dot product, scored as is.  Equals cosine similarity for unit
+    /// vectors, but is cheaper; pair it with auto-normalization or a model
+    /// that already emits normalized embeddings.
+    DotProduct,
+    /// Euclidean distance `d`, scored as `1 / (1 + d)` in `(0, 1]`.
+    Euclidean,
+}
+
+impl DistanceMetric {
+    /// How far apart `a` and `b` are: smaller is nearer.  Both must have a
+    /// non-zero norm, as [`check_embedding`] ensures.
+    fn distance(self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Self::Cosine => 1.0 - dot(a, b) / (vec_norm(a) * vec_norm(b)),
+            Self::DotProduct => -dot(a, b),
+            Self::Euclidean => a
+                .iter()
+                .zip(b)
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum::<f32>()
+                .sqrt(),
+        }
+    }
+
+    /// The score `search` reports for a neighbour at `distance`.
+    fn score(self, distance: f32) -> f32 {
+        match self {
+            Self::Cosine => (1.0 - distance).max(0.0),
+            Self::DotProduct => -distance,
+            Self::Euclidean => 1.0 / (1.0 + distance),
+        }
+    }
+}
+
+/// Lets the HNSW graph order neighbours by the configured metric.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy)]
+struct MetricDistance(DistanceMetric);
+
+#[cfg(feature = "native")]
+impl Distance<f32> for MetricDistance {
+    fn eval(&self, a: &[f32], b: &[f32]) -> f32 {
+        self.0.distance(a, b)
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn vec_norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// `embedding` scaled to unit length; it must have passed [`check_embedding`].
+fn normalized(embedding: &[f32]) -> Vec<f32> {
+    let norm = vec_norm(embedding);
+    embedding.iter().map(|v| v / norm).collect()
+}
+
+/// Check `embedding` can be compared with vectors of `dimension`, if that is
+/// set.  Its norm must be non-zero and finite, so no metric or normalization
+/// divides by zero or produces NaN scores.
+fn check_embedding(dimension: &OnceLock<usize>, embedding: &[f32]) -> Result<(), VectorError> {
+    let norm = vec_norm(embedding);
+    if embedding.is_empty()
+        || !embedding.iter().all(|v| v.is_finite())
+        || norm == 0.0
+        || !norm.is_finite()
+    {
+        return Err(VectorError::InvalidEmbedding);
+    }
+    match dimension.get() {
+        Some(&expected) if expected != embedding.len() => Err(VectorError::DimensionMismatch {
+            expected,
+            actual: embedding.len(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Check `embedding` as [`check_embedding`] does, fixing the dimension to
+/// its length if unset.
+fn claim_dimension(dimension: &OnceLock<usize>, embedding: &[f32]) -> Result<(), VectorError> {
+    check_embedding(dimension, embedding)?;
+    let expected = *dimension.get_or_init(|| embedding.len());
+    if expected != embedding.len() {
+        // Another thread fixed a different dimension first
+        return Err(VectorError::DimensionMismatch {
+            expected,
+            actual: embedding.len(),
+        });
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// VectorIndex — HNSW (native)
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "native")]
+pub struct VectorIndex {
+    hnsw: Hnsw<'static, f32, MetricDistance>,
+    id_to_idx: DashMap<NodeId, usize>,
+    idx_to_id: DashMap<usize, NodeId>,
+    next_idx: Mutex<usize>,
+    max_elements: usize,
+    dimension: OnceLock<usize>,
+    metric: DistanceMetric,
+    auto_normalize: bool,
+}
+
+#[cfg(feature = "native")]
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<VectorIndex>();
+};
+
+#[cfg(feature = "native")]
+impl std::fmt::Debug for VectorIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VectorIndex")
+            .field("indexed_nodes", &self.id_to_idx.len())
+            .field("dimension", &self.dimension.get())
+            .field("metric", &self.metric)
+            .field("auto_normalize", &self.auto_normalize)
+            .finish()
+    }
+}
+
+#[cfg(feature = "native")]
+impl VectorIndex {
+    pub fn new(max_elements: usize) -> Self {
+        Self::with_options(max_elements, DistanceMetric::default(), false)
+    }
+
+    fn with_options(max_elements: usize, metric: DistanceMetric, auto_normalize: bool) -> Self {
+        Self {
+            hnsw: Hnsw::new(16, max_elements, 16, 200, MetricDistance(metric)),
+            id_to_idx: DashMap::new(),
+            idx_to_id: DashMap::new(),
+            next_idx: Mutex::new(0),
+            max_elements,
+            dimension: OnceLock::new(),
+            metric,
+            auto_normalize,
+        }
+    }
+
+    /// Accept only vectors of `dimension` from the start.
+    pub fn with_dimension(self, dimension: usize) -> Self {
+        let _ = self.dimension.set(dimension);
+        self
+    }
+
+    /// Compare vectors by `metric`.  The graph is rebuilt for it, so any
+    /// vectors already added are dropped.
+    pub fn with_metric(self, metric: DistanceMetric) -> Self {
+        let index = Self::with_options(self.max_elements, metric, self.auto_normalize);
+        if let Some(&dimension) = self.dimension.get() {
+            return index.with_dimension(dimension);
+        }
+        index
+    }
+
+    /// Scale vectors to unit length before adding or searching for them.
+    pub fn with_auto_normalize(mut self, auto_normalize: bool) -> Self {
+        self.auto_normalize = auto_normalize;
+        self
+    }
+
+    /// Length of the vectors this index holds, once known.
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension.get().copied()
+    }
+
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
+    pub fn auto_normalize(&self) -> bool {
+        self.auto_normalize
+    }
+
+    /// Check `embedding` could be added without adding it.
+    pub fn validate(&self, embedding: &[f32]) -> Result<(), VectorError> {
+        check_embedding(&self.dimension, embedding)
+    }
+
+    /// Index `embedding` for node `id`, replacing any vector it had.
+    pub fn add(&self, id: impl Into<NodeId>, embedding: Vec<f32>) -> Result<(), VectorError> {
+        let id = id.into();
+        claim_dimension(&self.dimension, &embedding)?;
+        let embedding = if self.auto_normalize {
+            normalized(&embedding)
+        } else {
+            embedding
+        };
+        let idx = {
+            let mut n = self.next_idx.lock();
+            let current = *n;
+            if current >= self.max_elements {
+                return Err(VectorError::CapacityExceeded {
+                    capacity: self.max_elements,
+                });
+            }
+            if current + 1 >= (self.max_elements as f64 * 0.9) as usize {
+                debug!(
+                    "VectorIndex nearing capacity: {} / {} slots used",
+                    current + 1,
+                    self.max_elements
+                );
+            }
+            *n += 1;
+            current
+        };
+        self.id_to_idx.insert(id.clone(), idx);
+        self.idx_to_id.insert(idx, id.clone());
+        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.hnsw.insert((&embedding, idx));
+        }))
+        .is_err()
+        {
+            eprintln!("[VectorIndex] HNSW insert panicked for '{}'; skipping", id);
+            self.id_to_idx.remove(&id);
+            self.idx_to_id.remove(&idx);
+        }
+        Ok(())
+    }
+
+    /// Like [`add`](Self::add), but logs and drops a vector it rejects.
+    pub fn insert(&self, id: &str, embedding: &[f32]) {
+        if let Err(e) = self.add(id, embedding.to_vec()) {
+            debug!("VectorIndex insert for '{}' dropped: {}", id, e);
+        }
+    }
+
+    /// The `k` nodes nearest to `query` with a [`DistanceMetric`] score of
+    /// at least `threshold`, nearest first.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        threshold: f32,
+    ) -> Result<Vec<(NodeId, f32)>, VectorError> {
+        check_embedding(&self.dimension, query)?;
+        if self.id_to_idx.is_empty() || k == 0 {
+            return Ok(Vec::new());
+        }
+        let query = if self.auto_normalize {
+            normalized(query)
+        } else {
+            query.to_vec()
+        };
+        let neighbours = self.hnsw.search(&query, k, k.max(16));
+        let mut results: Vec<(NodeId, f32)> = neighbours
+            .into_iter()
+            .filter_map(|n| {
+                let node_id = self.idx_to_id.get(&n.d_id)?.clone();
+                let current_idx = self.id_to_idx.get(&*node_id)?;
+                if *current_idx != n.d_id {
+                    return None;
+                }
+                let score = self.metric.score(n.distance);
+                (score >= threshold).then(|| (node_id.clone(), score))
+            })
+            .collect();
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(results)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id_to_idx.is_empty()
+    }
+}
+
+#[cfg(feature = "native")]
+impl Default for VectorIndex {
+    fn default() -> Self {
+        Self::new(1_024)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BruteForceVectorIndex — WASM-safe fallback
+// ---------------------------------------------------------------------------
+
+#[cfg(not(feature = "native"))]
+#[derive(Debug)]
+pub struct BruteForceVectorIndex {
+    embeddings: DashMap<NodeId, Vec<f32>>,
+    dimension: OnceLock<usize>,
+    metric: DistanceMetric,
+    auto_normalize: bool,
+}
+
+#[cfg(not(feature = "native"))]
+impl BruteForceVectorIndex {
+    pub fn new(_max_elements: usize) -> Self {
+        Self {
+            embeddings: DashMap::new(),
+            dimension: OnceLock::new(),
+            metric: DistanceMetric::default(),
+            auto_normalize: false,
+        }
+    }
+
+    /// Accept only vectors of `dimension` from the start.
+    pub fn with_dimension(self, dimension: usize) -> Self {
+        let _ = self.dimension.set(dimension);
+        self
+    }
+
+    /// Compare vectors by `metric`.  Any vectors already added are dropped,
+    /// as by [`VectorIndex::with_metric`].
+    pub fn with_metric(self, metric: DistanceMetric) -> Self {
+        self.embeddings.clear();
+        Self { metric, ..self }
+    }
+
+    /// Scale vectors to unit length before adding or searching for them.
+    pub fn with_auto_normalize(mut self, auto_normalize: bool) -> Self {
+        self.auto_normalize = auto_normalize;
+        self
+    }
+
+    /// Length of the vectors this index holds, once known.
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension.get().copied()
+    }
+
+    pub fn metric(&self) -> DistanceMetric {
+        self.metric
+    }
+
+    pub fn auto_normalize(&self) -> bool {
+        self.auto_normalize
+    }
+
+    /// Check `embedding` could be added without adding it.
+    pub fn validate(&self, embedding: &[f32]) -> Result<(), VectorError> {
+        check_embedding(&self.dimension, embedding)
+    }
+
+    /// Index `embedding` for node `id`, replacing any vector it had.
+    pub fn add(&self, id: impl Into<NodeId>, embedding: Vec<f32>) -> Result<(), VectorError> {
+        claim_dimension(&self.dimension, &embedding)?;
+        let embedding = if self.auto_normalize {
+            normalized(&embedding)
+        } else {
+            embedding
+        };
+        self.embeddings.insert(id.into(), embedding);
+        Ok(())
+    }
+
+    /// Like [`add`](Self::add), but logs and drops a vector it rejects.
+    pub fn insert(&self, id: &str, embedding: &[f32]) {
+        if let Err(e) = self.add(id, embedding.to_vec()) {
+            tracing::debug!("BruteForceVectorIndex insert for '{}' dropped: {}", id, e);
+        }
+    }
+
+    /// The `k` nodes nearest to `query` with a [`DistanceMetric`] score of
+    /// at least `threshold`, nearest first.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        threshold: f32,
+    ) -> Result<Vec<(NodeId, f32)>, VectorError> {
+        check_embedding(&self.dimension, query)?;
+        let query = if self.auto_normalize {
+            normalized(query)
+        } else {
+            query.to_vec()
+        };
+        let mut results: Vec<(NodeId, f32)> = self
+            .embeddings
+            .iter()
+            .filter_map(|entry| {
+                let score = self
+                    .metric
+                    .score(self.metric.distance(&query, entry.value()));
+                (score >= threshold).then(|| (entry.key().clone(), score))
+            })
+            .collect();
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(k);
+        Ok(results)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.embeddings.is_empty()
+    }
+}
+
+#[cfg(not(feature = "native"))]
+impl Default for BruteForceVectorIndex {
+    fn default() -> Self {
+        Self::new(1_024)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Unified type alias for the vector index
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "native")]
+pub(crate) type ActiveVectorIndex = VectorIndex;
+
+#[cfg(not(feature = "native"))]
+pub(crate) type ActiveVectorIndex = BruteForceVectorIndex;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded() -> ActiveVectorIndex {
+        let index = ActiveVectorIndex::default().with_dimension(3);
+        index.add("x", vec![1.0, 0.0, 0.0]).unwrap();
+        index.add("near-x", vec![0.9, 0.1, 0.0]).unwrap();
+        index.add("xy", vec![1.0, 1.0, 0.0]).unwrap();
+        index.add("z", vec![0.0, 0.0, 1.0]).unwrap();
+        index
+    }
+
+    fn ids(results: &[(NodeId, f32)]) -> Vec<&str> {
+        results.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    #[test]
+    fn search_orders_by_cosine_similarity() {
+        let index = seeded();
+        let results = index.search(&[1.0, 0.0, 0.0], 3, 0.0).unwrap();
+        assert_eq!(ids(&results), ["x", "near-x", "xy"]);
+        assert!((results[0].1 - 1.0).abs() < 1e-4);
+        assert!(results.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn search_drops_neighbours_below_the_threshold() {
+        let index = seeded();
+        let results = index.search(&[1.0, 0.0, 0.0], 10, 0.9).unwrap();
+        assert_eq!(ids(&results), ["x", "near-x"]);
+        assert!(index.search(&[0.0, 1.0, 0.0], 10, 0.99).unwrap().is_empty());
+    }
+
+    #[test]
+    fn vectors_of_another_dimension_are_rejected() {
+        let index = seeded();
+        let mismatch = VectorError::DimensionMismatch {
+            expected: 3,
+            actual: 2,
+        };
+        assert_eq!(index.add("w", vec![1.0, 0.0]), Err(mismatch.clone()));
+        assert_eq!(index.search(&[1.0, 0.0], 1, 0.0), Err(mismatch));
+        assert_eq!(
+            index.add("nan", vec![f32::NAN, 0.0, 0.0]),
+            Err(VectorError::InvalidEmbedding)
+        );
+
+        // Without a configured dimension the first vector fixes it
+        let index = ActiveVectorIndex::default();
+        assert_eq!(index.dimension(), None);
+        index.add("a", vec![0.5, 0.5]).unwrap();
+        assert_eq!(index.dimension(), Some(2));
+        assert!(index.add("b", vec![0.5, 0.5, 0.5]).is_err());
+    }
+
+    /// Seen from `[1, 0]`, `long` points the right way but is far off, while
+    /// `short` is close by but at an angle.
+    fn angles_and_lengths(metric: DistanceMetric, auto_normalize: bool) -> ActiveVectorIndex {
+        let index = ActiveVectorIndex::default()
+            .with_metric(metric)
+            .with_auto_normalize(auto_normalize);
+        index.add("long", vec![3.0, 0.3]).unwrap();
+        index.add("short", vec![0.8, 0.6]).unwrap();
+        index.add("up", vec![0.0, 1.0]).unwrap();
+        index
+    }
+
+    #[test]
+    fn cosine_and_euclidean_rank_differently() {
+        let query = [1.0, 0.0];
+        let cosine = angles_and_lengths(DistanceMetric::Cosine, false);
+        assert_eq!(
+            ids(&cosine.search(&query, 3, 0.0).unwrap()),
+            ["long", "short", "up"]
+        );
+
+        let euclidean = angles_and_lengths(DistanceMetric::Euclidean, false);
+        assert_eq!(euclidean.metric(), DistanceMetric::Euclidean);
+        let results = euclidean.search(&query, 3, 0.0).unwrap();
+        assert_eq!(ids(&results), ["short", "up", "long"]);
+        // Scored as 1 / (1 + distance)
+        let short = 1.0 / (1.0 + (0.2_f32 * 0.2 + 0.6 * 0.6).sqrt());
+        assert!((results[0].1 - short).abs() < 1e-4);
+
+        // On unit vectors Euclidean distance follows the angle again
+        let normalized = angles_and_lengths(DistanceMetric::Euclidean, true);
+        assert_eq!(
+            ids(&normalized.search(&[2.0, 0.0], 3, 0.0).unwrap()),
+            ["long", "short", "up"]
+        );
+
+        let dot = angles_and_lengths(DistanceMetric::DotProduct, false);
+        let results = dot.search(&query, 3, 0.0).unwrap();
+        assert_eq!(ids(&results), ["long", "short", "up"]);
+        assert!((results[0].1 - 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn vectors_without_a_usable_norm_are_rejected() {
+        let index = ActiveVectorIndex::default()
+            .with_metric(DistanceMetric::Cosine)
+            .with_auto_normalize(true);
+        // Non-zero, but the squared norm underflows to zero
+        assert_eq!(
+            index.add("tiny", vec![1e-30, 0.0]),
+            Err(VectorError::InvalidEmbedding)
+        );
+        assert_eq!(
+            index.add("huge", vec![f32::MAX, f32::MAX]),
+            Err(VectorError::InvalidEmbedding)
+        );
+        index.add("ok", vec![1.0, 1.0]).unwrap();
+        assert_eq!(
+            index.search(&[0.0, 0.0], 1, 0.0),
+            Err(VectorError::InvalidEmbedding)
+        );
+        let results = index.search(&[1.0, 1.0], 1, 0.0).unwrap();
+        assert!(results.iter().all(|(_, score)| score.is_finite()));
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-node change subscriptions for [`CrdtStore::watch`].
+//!
+//! Each watched id keeps a list of channel senders.  A write notifies only
+//! the senders for its own id, so watching one node costs nothing for
+//! writes to any other, and dropping a [`NodeWatch`] removes its sender
+//! at once.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use dashmap::DashMap;
+use futures::Stream;
+use tokio::sync::mpsc;
+
+use crate::{CrdtStore, NodeId, NodeRecord};
+
+type Senders = DashMap<NodeId, Vec<mpsc::UnboundedSender<NodeRecord>>>;
+
+/// The watchers of every watched node.
+#[derive(Debug, Default)]
+pub(crate) struct Watchers {
+    senders: Arc<Senders>,
+}
+
+impl Watchers {
+    fn subscribe(&self, id: &str) -> NodeWatch {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.senders.entry(id.to_owned()).or_default().push(tx);
+        NodeWatch {
+            id: id.to_owned(),
+            rx,
+            senders: Arc::clone(&self.senders),
+            done: false,
+        }
+    }
+
+    /// Hand `record` to everyone watching its node.
+    fn notify(&self, record: &NodeRecord) {
+        if let Some(mut senders) = self.senders.get_mut(&record.id) {
+            senders.retain(|tx| tx.send(record.clone()).is_ok());
+        }
+    }
+}
+
+/// The stream returned by [`CrdtStore::watch`].
+#[derive(Debug)]
+pub struct NodeWatch {
+    id: NodeId,
+    rx: mpsc::UnboundedReceiver<NodeRecord>,
+    senders: Arc<Senders>,
+    done: bool,
+}
+
+impl NodeWatch {
+    /// The id being watched.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Stream for NodeWatch {
+    type Item = NodeRecord;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<NodeRecord>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let polled = self.rx.poll_recv(cx);
+        if let Poll::Ready(Some(record)) = &polled {
+            self.done = record.is_tombstone();
+        }
+        polled
+    }
+}
+
+impl Drop for NodeWatch {
+    fn drop(&mut self) {
+        self.rx.close();
+        self.senders.remove_if_mut(&self.id, |_, senders| {
+            senders.retain(|tx| !tx.is_closed());
+            senders.is_empty()
+        });
+    }
+}
+
+impl CrdtStore {
+    /// A stream of node `id` as it is after each write to it, local or
+    /// merged from a peer; writes to other nodes wake nothing.
+    ///
+    /// Deleting the node yields its tombstone (see
+    /// [`NodeRecord::is_tombstone`]) and ends the stream.  The stream starts
+    /// with the next write, not the current value, which [`get`](Self::get)
+    /// returns.  Concurrent writes may each be reported with the last one's
+    /// value.  Dropping the stream unsubscribes.
+    pub fn watch(&self, id: &str) -> NodeWatch {
+        self.watchers.subscribe(id)
+    }
+
+    /// Tell watchers of `id` about its current state.  Call after the
+    /// write, with no node entry held.
+    pub(crate) fn notify_watchers(&self, id: &str) {
+        if !self.watchers.senders.contains_key(id) {
+            return;
+        }
+        if let Some(record) = self.get_including_tombstones(id) {
+            self.watchers.notify(&record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{FutureExt, StreamExt};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn watch_yields_only_writes_to_its_node() {
+        let store = CrdtStore::default();
+        store.put("watched", "a", json!({ "n": 0 }));
+        let mut watch = store.watch("watched");
+
+        store.put("other", "a", json!({ "n": 1 }));
+        store.put("watched-too", "a", json!({ "n": 2 }));
+        store.delete("other").unwrap();
+        assert!(watch.next().now_or_never().is_none());
+
+        store.put("watched", "a", json!({ "n": 3 }));
+        let record = watch.next().now_or_never().flatten().unwrap();
+        assert_eq!(record.data, json!({ "n": 3 }));
+        assert!(watch.next().now_or_never().is_none());
+
+        store.delete("watched").unwrap();
+        let tombstone = watch.next().now_or_never().flatten().unwrap();
+        assert!(tombstone.is_tombstone());
+        assert_eq!(watch.next().now_or_never(), Some(None));
+    }
+
+    #[test]
+    fn dropping_a_watch_unsubscribes() {
+        let store = CrdtStore::default();
+        let first = store.watch("node");
+        let second = store.watch("node");
+        drop(first);
+        assert_eq!(store.watchers.senders.get("node").unwrap().len(), 1);
+        drop(second);
+        assert!(!store.watchers.senders.contains_key("node"));
+        store.put("node", "a", json!({}));
+    }
+}
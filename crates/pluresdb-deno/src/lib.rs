@@ -5,7 +5,8 @@
 
 use deno_bindgen::deno_bindgen;
 use pluresdb_core::{
-    CoreErrorCode, CrdtOperation, CrdtStore, Database, DatabaseOptions, NodeRecord, SqlValue,
+    ActorId, ActorIdExt, CoreErrorCode, CrdtOperation, CrdtStore, Database, DatabaseError,
+    DatabaseOptions, ErrorKind, IdStrategy, NodeRecord, SqlValue,
 };
 use pluresdb_sync::{SyncBroadcaster, SyncErrorCode, SyncEvent};
 use serde::{Deserialize, Serialize};
@@ -17,6 +18,21 @@ fn deno_error(code: &str, message: impl Into<String>) -> String {
     format!("[{}] {}", code, message.into())
 }
 
+/// Like [`deno_error`], but encoded as a JSON object `{ code, message }` where
+/// `code` is the coarse [`ErrorKind`] (e.g. `"NOT_FOUND"`), so TypeScript can
+/// branch with `JSON.parse(err.message).code` instead of matching text.
+fn deno_typed_error(kind: ErrorKind, code: &str, message: impl Into<String>) -> String {
+    serde_json::json!({
+        "code": kind.as_str(),
+        "message": deno_error(code, message),
+    })
+    .to_string()
+}
+
+fn map_database_error(error: DatabaseError) -> String {
+    deno_typed_error(error.kind(), error.code().as_str(), error.to_string())
+}
+
 /// Result returned by [`PluresDatabase::query`].
 ///
 /// Mirrors the shape of the underlying [`pluresdb_core`] query result and maps
@@ -74,6 +90,20 @@ pub struct SearchResult {
     pub timestamp: String,
 }
 
+/// A single result from [`PluresDatabase::vector_search`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorSearchResult {
+    /// Stable unique identifier of the matching node.
+    pub id: String,
+    /// Arbitrary JSON payload stored with the node.
+    pub data: serde_json::Value,
+    /// Cosine similarity in `[0, 1]` for vector queries.  For text queries this
+    /// is the match count from [`PluresDatabase::search`].
+    pub score: f64,
+    /// RFC 3339 timestamp of the last write that touched this node.
+    pub timestamp: String,
+}
+
 /// Aggregate statistics about the database returned by
 /// [`PluresDatabase::stats`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,9 +127,12 @@ pub struct PluresDatabase {
 #[deno_bindgen]
 impl PluresDatabase {
     /// Create a new PluresDB instance
+    ///
+    /// `actor_id` defaults to a random id unique to this instance; pass back
+    /// the one from `get_actor_id` to keep writing as the same actor.
     #[deno_bindgen(constructor)]
     pub fn new(actor_id: Option<String>, db_path: Option<String>) -> Result<Self, String> {
-        let actor_id = actor_id.unwrap_or_else(|| "deno-actor".to_string());
+        let actor_id = actor_id.unwrap_or_else(ActorId::new_random);
         let db = if let Some(path) = db_path {
             let options = DatabaseOptions::with_file(path).create_if_missing(true);
             Some(Arc::new(
@@ -125,19 +158,49 @@ impl PluresDatabase {
         let broadcaster = self.broadcaster.clone();
         let actor_id = self.actor_id.clone();
         
-        let node_id = {
+        let (node_id, event) = {
             let store = store.lock();
-            store.put(id.clone(), actor_id, data)
+            let node_id = store
+                .try_put(id.clone(), actor_id, data)
+                .map_err(|e| deno_typed_error(e.kind(), e.code().as_str(), e.to_string()))?;
+            (node_id.clone(), SyncEvent::upserted(&store, node_id))
         };
         
         // Publish sync event
         broadcaster
-            .publish(SyncEvent::NodeUpsert { id: node_id.clone() })
+            .publish(event)
             .map_err(|e| deno_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e.to_string()))?;
         
         Ok(node_id)
     }
 
+    /// Insert a node whose id is the BLAKE3 hash of its canonical JSON.
+    ///
+    /// Returns the id.  Storing content that already exists is a no-op that
+    /// returns the existing id.
+    #[deno_bindgen]
+    pub fn put_content_addressed(&self, data: serde_json::Value) -> Result<String, String> {
+        let node_id = IdStrategy::ContentHash.derive_id(&data);
+        let event = {
+            let store = self.store.lock();
+            let inserted = store.get(&node_id).is_none();
+            if inserted {
+                store
+                    .try_put(node_id.clone(), self.actor_id.clone(), data)
+                    .map_err(|e| deno_typed_error(e.kind(), e.code().as_str(), e.to_string()))?;
+            }
+            inserted.then(|| SyncEvent::upserted(&store, node_id.clone()))
+        };
+        
+        if let Some(event) = event {
+            self.broadcaster
+                .publish(event)
+                .map_err(|e| deno_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e.to_string()))?;
+        }
+        
+        Ok(node_id)
+    }
+
     /// Retrieve a node by ID
     #[deno_bindgen]
     pub fn get(&self, id: String) -> Result<Option<serde_json::Value>, String> {
@@ -178,6 +241,8 @@ impl PluresDatabase {
     }
 
     /// Delete a node by ID
+    ///
+    /// Fails with a typed error whose code is `NOT_FOUND` if the node is missing.
     #[deno_bindgen]
     pub fn delete(&self, id: String) -> Result<(), String> {
         let store = self.store.clone();
@@ -186,13 +251,13 @@ impl PluresDatabase {
         {
             let store = store.lock();
             store.delete(&id)
-                .map_err(|e| deno_error(e.code().as_str(), e.to_string()))?;
+                .map_err(|e| deno_typed_error(e.kind(), e.code().as_str(), e.to_string()))?;
         }
         
         // Publish sync event
         broadcaster
             .publish(SyncEvent::NodeDelete { id: id.clone() })
-            .map_err(|e| deno_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e.to_string()))?;
+            .map_err(|e| deno_typed_error(ErrorKind::Internal, SyncErrorCode::BroadcastPublishFailed.as_str(), e.to_string()))?;
         
         Ok(())
     }
@@ -222,24 +287,19 @@ impl PluresDatabase {
     }
 
     /// List nodes filtered by type
+    ///
+    /// Answered from the index on `type` when `create_index("type")` made one.
     #[deno_bindgen]
     pub fn list_by_type(&self, node_type: String) -> Result<Vec<serde_json::Value>, String> {
         let store = self.store.clone();
         
         let records = {
             let store = store.lock();
-            store.list()
+            store.list_by_type(&node_type)
         };
         
         let result: Vec<serde_json::Value> = records
             .into_iter()
-            .filter(|record| {
-                record.data
-                    .get("type")
-                    .and_then(|v| v.as_str())
-                    .map(|t| t == node_type)
-                    .unwrap_or(false)
-            })
             .map(|record| {
                 serde_json::json!({
                     "id": record.id,
@@ -252,6 +312,19 @@ impl PluresDatabase {
         Ok(result)
     }
 
+    /// Index nodes by their value at a dotted field path, e.g. `address.city`,
+    /// and return how many nodes have the field
+    #[deno_bindgen]
+    pub fn create_index(&self, field_path: String) -> u32 {
+        self.store.lock().create_index(&field_path) as u32
+    }
+
+    /// Drop the index on a field path; returns false if there was none
+    #[deno_bindgen]
+    pub fn drop_index(&self, field_path: String) -> bool {
+        self.store.lock().drop_index(&field_path)
+    }
+
     /// Execute SQL query
     #[deno_bindgen]
     pub fn query(
@@ -260,35 +333,18 @@ impl PluresDatabase {
         params: Option<Vec<serde_json::Value>>,
     ) -> Result<QueryResult, String> {
         let db = self.db.as_ref()
-            .ok_or_else(|| deno_error(CoreErrorCode::InvalidInput.as_str(), "SQL queries require a database (provide db_path in constructor)"))?;
+            .ok_or_else(|| deno_typed_error(ErrorKind::InvalidInput, CoreErrorCode::InvalidInput.as_str(), "SQL queries require a database (provide db_path in constructor)"))?;
         
         let sql_params: Vec<SqlValue> = if let Some(p) = params {
             p.into_iter()
-                .map(|v| -> Result<SqlValue, String> {
-                    Ok(match v {
-                        serde_json::Value::Null => SqlValue::Null,
-                        serde_json::Value::Number(n) => {
-                            if n.is_i64() {
-                                SqlValue::Integer(n.as_i64().unwrap())
-                            } else {
-                                SqlValue::Real(n.as_f64().unwrap())
-                            }
-                        }
-                        serde_json::Value::String(s) => SqlValue::Text(s),
-                        serde_json::Value::Bool(b) => SqlValue::Integer(if b { 1 } else { 0 }),
-                        serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
-                                SqlValue::Text(serde_json::to_string(&v)
-                                .map_err(|e| deno_error(CoreErrorCode::SerializationError.as_str(), e.to_string()))?)
-                        }
-                    })
-                })
-                .collect::<Result<Vec<_>, _>>()?
+                .map(SqlValue::from_json)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(map_database_error)?
         } else {
             vec![]
         };
         
-        let result = db.query(&sql, &sql_params)
-            .map_err(|e| deno_error(CoreErrorCode::SqliteError.as_str(), e.to_string()))?;
+        let result = db.query(&sql, &sql_params).map_err(map_database_error)?;
         
         Ok(QueryResult {
             columns: result.columns,
@@ -302,10 +358,9 @@ impl PluresDatabase {
     #[deno_bindgen]
     pub fn exec(&self, sql: String) -> Result<ExecutionResult, String> {
         let db = self.db.as_ref()
-            .ok_or_else(|| deno_error(CoreErrorCode::InvalidInput.as_str(), "SQL execution requires a database (provide db_path in constructor)"))?;
+            .ok_or_else(|| deno_typed_error(ErrorKind::InvalidInput, CoreErrorCode::InvalidInput.as_str(), "SQL execution requires a database (provide db_path in constructor)"))?;
         
-        let result = db.exec(&sql)
-            .map_err(|e| deno_error(CoreErrorCode::SqliteError.as_str(), e.to_string()))?;
+        let result = db.exec(&sql).map_err(map_database_error)?;
         
         Ok(ExecutionResult {
             changes: result.changes,
@@ -356,17 +411,80 @@ impl PluresDatabase {
         Ok(result)
     }
 
-    /// Vector similarity search (placeholder - returns text search results)
+    /// Insert or update a node together with an explicit embedding vector
+    #[deno_bindgen]
+    pub fn put_vector(
+        &self,
+        id: String,
+        data: serde_json::Value,
+        embedding: Vec<f32>,
+    ) -> Result<String, String> {
+        let (node_id, event) = {
+            let store = self.store.lock();
+            let node_id = store
+                .try_put_with_embedding(id, self.actor_id.clone(), data, embedding)
+                .map_err(|e| deno_typed_error(e.kind(), e.code().as_str(), e.to_string()))?;
+            (node_id.clone(), SyncEvent::upserted(&store, node_id))
+        };
+
+        self.broadcaster
+            .publish(event)
+            .map_err(|e| deno_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e.to_string()))?;
+
+        Ok(node_id)
+    }
+
+    /// Vector similarity search
+    ///
+    /// `query` is either a numeric embedding, whose nearest neighbours among
+    /// the vectors stored with `put_vector` are found in the HNSW index and
+    /// ranked by cosine similarity, or a string, which falls back to text
+    /// search.  `threshold` is a minimum cosine similarity.
     #[deno_bindgen]
     pub fn vector_search(
         &self,
-        query: String,
+        query: serde_json::Value,
         limit: Option<u32>,
-        _threshold: Option<f64>,
-    ) -> Result<Vec<SearchResult>, String> {
-        // For now, vector search falls back to text search
-        // In the future, this will use actual vector embeddings
-        self.search(query, limit)
+        threshold: Option<f64>,
+    ) -> Result<Vec<VectorSearchResult>, String> {
+        let embedding: Vec<f32> = match query {
+            serde_json::Value::String(text) => {
+                return Ok(self
+                    .search(text, limit)?
+                    .into_iter()
+                    .map(|r| VectorSearchResult {
+                        id: r.id,
+                        data: r.data,
+                        score: r.score as f64,
+                        timestamp: r.timestamp,
+                    })
+                    .collect());
+            }
+            other => serde_json::from_value(other).map_err(|_| {
+                deno_error(
+                    CoreErrorCode::InvalidInput.as_str(),
+                    "vector search query must be a string or an array of numbers",
+                )
+            })?,
+        };
+        let limit = limit.unwrap_or(10) as usize;
+        let threshold = threshold.unwrap_or(0.0) as f32;
+        let hits = {
+            let store = self.store.lock();
+            store
+                .nearest_neighbors(&embedding, limit, threshold)
+                .map_err(|e| deno_typed_error(e.kind(), e.code().as_str(), e.to_string()))?
+        };
+
+        Ok(hits
+            .into_iter()
+            .map(|(record, similarity)| VectorSearchResult {
+                id: record.id,
+                data: record.data,
+                score: similarity as f64,
+                timestamp: record.timestamp.to_rfc3339(),
+            })
+            .collect())
     }
 
     /// Get the actor ID for this database instance
@@ -378,22 +496,15 @@ impl PluresDatabase {
     /// Get database statistics
     #[deno_bindgen]
     pub fn stats(&self) -> Result<DatabaseStats, String> {
-        let store = self.store.clone();
-        
-        let records = {
-            let store = store.lock();
-            store.list()
-        };
-        
-        let mut type_counts: HashMap<String, u32> = HashMap::new();
-        for record in &records {
-            if let Some(t) = record.data.get("type").and_then(|v| v.as_str()) {
-                *type_counts.entry(t.to_string()).or_insert(0) += 1;
-            }
-        }
-        
+        let histogram = self.store.lock().type_histogram();
+        let total_nodes = histogram.values().sum::<usize>() as u64;
+        let type_counts = histogram
+            .into_iter()
+            .map(|(ty, count)| (ty, count as u32))
+            .collect();
+
         Ok(DatabaseStats {
-            total_nodes: records.len() as u64,
+            total_nodes,
             type_counts,
         })
     }
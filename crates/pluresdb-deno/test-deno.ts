@@ -102,10 +102,33 @@ async function test() {
   }
   console.log("");
 
-  // Test 4: Vector search (placeholder)
-  console.log("Test 4: Vector search (placeholder)");
-  const vectorResults = db.vectorSearch("Rust", 5, 0.7);
+  // Test 4: Vector search
+  console.log("Test 4: Vector search");
+  db.putVector("vec-a", { name: "east" }, [1, 0, 0]);
+  db.putVector("vec-b", { name: "north" }, [0, 1, 0]);
+  db.putVector("vec-c", { name: "north-east" }, [0.7, 0.7, 0]);
+
+  const vectorResults = db.vectorSearch([0.9, 0.1, 0], 3, 0.5);
   console.log("  ✓ Vector search:", vectorResults.length, "results");
+  if (vectorResults.length === 0 || vectorResults[0].id !== "vec-a") {
+    throw new Error("Vector search failed: expected vec-a as nearest result");
+  }
+  if (vectorResults[0].score < 0.5) {
+    throw new Error("Vector search failed: nearest score below threshold");
+  }
+
+  let dimensionRejected = false;
+  try {
+    db.putVector("vec-bad", { name: "wrong" }, [1, 0]);
+  } catch (_) {
+    dimensionRejected = true;
+  }
+  if (!dimensionRejected) {
+    throw new Error("putVector accepted an embedding with the wrong dimension");
+  }
+
+  const textFallback = db.vectorSearch("Rust", 5, 0.7);
+  console.log("  ✓ Text fallback:", textFallback.length, "results");
   console.log("");
 
   // Test 5: SQL queries (requires database)
@@ -155,6 +178,39 @@ async function test() {
   }
   console.log("");
 
+  // Test 7: Typed errors
+  console.log("Test 7: Typed errors");
+  let deleteError: { code?: string; message?: string } | null = null;
+  try {
+    db.delete("definitely-missing-node");
+  } catch (e) {
+    deleteError = JSON.parse((e as Error).message);
+  }
+  if (!deleteError || deleteError.code !== "NOT_FOUND") {
+    throw new Error(
+      `delete missing node: expected code NOT_FOUND, got ${deleteError?.code}`,
+    );
+  }
+  if (!deleteError.message?.includes("definitely-missing-node")) {
+    throw new Error("delete missing node: message lost the node id");
+  }
+  console.log("  ✓ delete missing node fails with code:", deleteError.code);
+  console.log("");
+
+  // Test 8: Content-addressed ids
+  console.log("Test 8: Content-addressed ids");
+  const countBefore = db.list().length;
+  const firstId = db.putContentAddressed({ title: "dedup me", tags: ["a", "b"] });
+  const secondId = db.putContentAddressed({ tags: ["a", "b"], title: "dedup me" });
+  if (firstId !== secondId) {
+    throw new Error(`content-addressed ids differ: ${firstId} vs ${secondId}`);
+  }
+  if (db.list().length !== countBefore + 1) {
+    throw new Error("identical content must be stored as a single node");
+  }
+  console.log("  ✓ identical payloads share id:", firstId);
+  console.log("");
+
   console.log("=== All tests passed! ===");
 }
 
@@ -13,18 +13,36 @@
  *
  * # Limitations
  *
- * - **Single Client**: Current implementation supports one client at a time.
- *   Multiple concurrent clients would require additional synchronization.
- * - **Polling**: Uses polling instead of event-driven synchronization for simplicity.
- *   Future versions could use condition variables for better performance.
+ * - **Bounded clients**: The shared memory region holds one request/response slot
+ *   per client. [`IPCServer::new`] creates [`DEFAULT_SLOTS`] slots and
+ *   [`IPCServer::new_with_slots`] up to [`MAX_SLOTS`]; that many clients can be
+ *   connected at once, and `IPCClient::connect` fails while every slot is taken.
+ *   A slot is released when its client is dropped, so a client process that dies
+ *   without unwinding keeps its slot until the server restarts.
+ * - **Large messages**: A slot holds one message of up to ~1MB. Larger requests and
+ *   responses are split into [`IPCMessage::Chunk`]s and reassembled on the other side,
+ *   up to [`DEFAULT_MAX_TRANSFER_SIZE`] unless changed with `with_max_transfer_size`.
+ *   While the server streams a chunked response it waits on that client and serves
+ *   no other slot.
+ * - **Wakeups**: On Linux the server and clients sleep on futexes in the shared
+ *   memory region and wake each other as soon as a request or response is written.
+ *   Elsewhere, or with the `polling` feature, both sides poll every
+ *   millisecond instead.
+ * - **SQL transactions**: With the `sqlite-compat` feature and a database attached via
+ *   [`IPCServer::with_database`], a client can open a transaction with `BeginTx` that
+ *   spans several `Query`/`Exec` messages. Only one transaction is open at a time; SQL
+ *   from any other client is rejected until the owner commits or rolls back. A client
+ *   dropped with its transaction open rolls it back, and the server rolls back a
+ *   transaction whose client's slot is released or claimed by a new client.
  * - **Platform-specific**: Shared memory behavior varies across platforms.
  *   Thoroughly test on target platforms (Windows, macOS, Linux).
  *
  * # Safety
  *
  * This crate uses `unsafe` code for shared memory access. Safety is ensured by:
- * - Single writer (server) per shared memory region
- * - Request/response flags prevent concurrent access
+ * - Each client claims its own slot with an atomic compare-and-swap
+ * - Atomic request/response flags hand a slot's buffer back and forth between
+ *   its client and the server, so only one side touches it at a time
  * - repr(C) layout ensures consistent memory structure
  * - Client waits for response before sending next request
  *
@@ -33,7 +51,8 @@
  * ```rust,no_run
  * use pluresdb_ipc::{IPCServer, IPCClient};
  * use pluresdb_core::CrdtStore;
- * use std::sync::{Arc, Mutex};
+ * use parking_lot::Mutex;
+ * use std::sync::Arc;
  *
  * // Server process
  * let store = Arc::new(Mutex::new(CrdtStore::default()));
@@ -53,12 +72,84 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use shared_memory::{Shmem, ShmemConf};
 use parking_lot::Mutex;
+use pluresdb_core::{ActorId, ActorIdExt};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::time::Duration;
-use std::thread;
 
-const SHMEM_SIZE: usize = 1024 * 1024; // 1MB shared memory
-const MAX_MESSAGE_SIZE: usize = SHMEM_SIZE - 256; // Reserve space for metadata
+#[cfg(feature = "sqlite-compat")]
+use pluresdb_core::{Database, SqlValue};
+
+const SLOT_SIZE: usize = 1024 * 1024; // 1MB per client slot
+const MAX_MESSAGE_SIZE: usize = SLOT_SIZE - 256; // Reserve space for metadata
+/// Payload bytes per [`IPCMessage::Chunk`], leaving room for its framing
+const CHUNK_SIZE: usize = MAX_MESSAGE_SIZE - 64;
+
+/// Largest message either side reassembles from chunks unless told otherwise
+pub const DEFAULT_MAX_TRANSFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// Slots created by [`IPCServer::new`]
+pub const DEFAULT_SLOTS: usize = 8;
+/// Most slots, and so concurrent clients, a server can be created with
+pub const MAX_SLOTS: usize = 64;
+
+/// How long a client waits for a response unless told otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest an idle server sleeps before re-checking whether it was stopped
+const SERVER_IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// Cross-process wait/wake on a `u32` in shared memory
+#[cfg(all(target_os = "linux", not(feature = "polling")))]
+mod wakeup {
+    use std::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    /// Sleep until `atomic` is woken or `timeout` passes, unless it no longer
+    /// holds `expected`.  May return spuriously; callers re-check.
+    pub(crate) fn wait(atomic: &AtomicU32, expected: u32, timeout: Duration) {
+        let timeout = libc::timespec {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as libc::c_long,
+        };
+        // Safety: `atomic` is a valid, aligned u32 for the duration of the call.
+        // FUTEX_WAIT (not the _PRIVATE variant) works across processes that map
+        // the same memory.
+        unsafe {
+            libc::syscall(
+                libc::SYS_futex,
+                atomic.as_ptr(),
+                libc::FUTEX_WAIT,
+                expected,
+                &timeout as *const libc::timespec,
+            );
+        }
+    }
+
+    /// Wake every thread waiting on `atomic`.
+    pub(crate) fn wake_all(atomic: &AtomicU32) {
+        // Safety: as for `wait`
+        unsafe {
+            libc::syscall(libc::SYS_futex, atomic.as_ptr(), libc::FUTEX_WAKE, i32::MAX);
+        }
+    }
+}
+
+/// Polling fallback for platforms without futexes
+#[cfg(not(all(target_os = "linux", not(feature = "polling"))))]
+mod wakeup {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    pub(crate) fn wait(atomic: &AtomicU32, expected: u32, timeout: Duration) {
+        if atomic.load(Ordering::Acquire) == expected {
+            std::thread::sleep(timeout.min(POLL_INTERVAL));
+        }
+    }
+
+    pub(crate) fn wake_all(_atomic: &AtomicU32) {}
+}
 
 /// IPC message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +169,29 @@ pub enum IPCMessage {
     },
     /// List all nodes request
     List,
+    /// SQL query (SELECT) against the server's database
+    Query {
+        client_id: u64,
+        sql: String,
+        params: Vec<Value>,
+    },
+    /// SQL statement (INSERT, UPDATE, DELETE, DDL) against the server's database
+    Exec {
+        client_id: u64,
+        sql: String,
+    },
+    /// Open a transaction owned by `client_id`
+    BeginTx {
+        client_id: u64,
+    },
+    /// Commit the transaction owned by `client_id`
+    CommitTx {
+        client_id: u64,
+    },
+    /// Roll back the transaction owned by `client_id`
+    RollbackTx {
+        client_id: u64,
+    },
     /// Response with data
     Response {
         data: Option<Value>,
@@ -92,43 +206,167 @@ pub enum IPCMessage {
     },
     /// Shutdown signal
     Shutdown,
+    /// Piece `seq` of `total` of an encoded message too large for one slot
+    Chunk {
+        seq: u32,
+        total: u32,
+        bytes: Vec<u8>,
+    },
+}
+
+fn encode(message: &IPCMessage) -> Result<Vec<u8>> {
+    bincode::serde::encode_to_vec(message, bincode::config::standard())
+        .context("Failed to serialize message")
+}
+
+fn decode(data: &[u8]) -> Result<IPCMessage> {
+    bincode::serde::decode_from_slice(data, bincode::config::standard())
+        .context("Failed to deserialize message")
+        .map(|(v, _)| v)
 }
 
-/// Shared memory layout
+/// Split an encoded message into encoded `Chunk`s that each fit in a slot
+fn chunk_frames(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let total = data.len().div_ceil(CHUNK_SIZE) as u32;
+    data.chunks(CHUNK_SIZE)
+        .enumerate()
+        .map(|(seq, bytes)| {
+            encode(&IPCMessage::Chunk {
+                seq: seq as u32,
+                total,
+                bytes: bytes.to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Collects `Chunk`s back into the encoded message they were split from
+#[derive(Default)]
+struct Reassembly {
+    buffer: Vec<u8>,
+    next_seq: u32,
+}
+
+impl Reassembly {
+    /// Add a chunk, returning the whole message once the last one arrives.
+    /// Chunk 0 always starts a new message, discarding any abandoned one.
+    fn push(&mut self, seq: u32, total: u32, bytes: Vec<u8>, limit: usize) -> Result<Option<Vec<u8>>> {
+        if seq == 0 {
+            *self = Self::default();
+        }
+        if seq != self.next_seq || seq >= total {
+            let expected = self.next_seq;
+            *self = Self::default();
+            anyhow::bail!("Chunk {} of {} out of order, expected {}", seq, total, expected);
+        }
+        if self.buffer.len() + bytes.len() > limit {
+            *self = Self::default();
+            anyhow::bail!("Message exceeds the {} byte transfer limit", limit);
+        }
+        self.buffer.extend_from_slice(&bytes);
+        self.next_seq += 1;
+        if self.next_seq == total {
+            return Ok(Some(std::mem::take(self).buffer));
+        }
+        Ok(None)
+    }
+}
+
+/// A mapped shared memory region that can move between threads
+///
+/// [`Shmem`] is not `Send` only because it holds a raw pointer to the
+/// mapping.  The mapping stays valid wherever its owner lives, and every
+/// access goes through the slot protocol's atomics, so moving it to another
+/// thread is as safe as sharing it with another process.
+struct SharedRegion(Shmem);
+
+// Safety: see above
+unsafe impl Send for SharedRegion {}
+
+impl std::ops::Deref for SharedRegion {
+    type Target = Shmem;
+
+    fn deref(&self) -> &Shmem {
+        &self.0
+    }
+}
+
+/// Header at the start of the shared memory region, followed by
+/// `slot_count` [`ShmemSlot`]s
 #[repr(C)]
 struct ShmemLayout {
+    /// Number of slots after the header
+    slot_count: u32,
+    /// Bumped by clients after writing a request; the idle server waits on it
+    doorbell: AtomicU32,
+    /// Reserved bytes for future use
+    _reserved: [u8; 248],
+}
+
+impl ShmemLayout {
+    fn region_size(slots: usize) -> usize {
+        std::mem::size_of::<ShmemLayout>() + slots * std::mem::size_of::<ShmemSlot>()
+    }
+
+    /// Safety: `shmem` must be at least `region_size(index + 1)` bytes and laid
+    /// out by [`IPCServer::new_with_slots`].
+    unsafe fn slot(shmem: &Shmem, index: usize) -> *mut ShmemSlot {
+        let offset = std::mem::size_of::<ShmemLayout>() + index * std::mem::size_of::<ShmemSlot>();
+        shmem.as_ptr().add(offset) as *mut ShmemSlot
+    }
+}
+
+/// One client's request/response slot
+#[repr(C)]
+struct ShmemSlot {
+    /// Claimed flag (1 = owned by a connected client, 0 = free)
+    claimed: AtomicU32,
     /// Request ready flag (1 = request available, 0 = no request)
-    request_ready: u8,
+    request_ready: AtomicU32,
     /// Response ready flag (1 = response available, 0 = no response)
-    response_ready: u8,
+    response_ready: AtomicU32,
     /// Request data length
     request_len: u32,
     /// Response data length
     response_len: u32,
     /// Reserved bytes for future use
-    _reserved: [u8; 240],
+    _reserved: [u8; 236],
     /// Request/response data buffer
     data: [u8; MAX_MESSAGE_SIZE],
 }
 
-impl ShmemLayout {
+impl ShmemSlot {
+    fn try_claim(&self) -> bool {
+        self.claimed
+            .compare_exchange(0, 1, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    fn release(&self) {
+        self.request_ready.store(0, Ordering::Release);
+        self.response_ready.store(0, Ordering::Release);
+        self.claimed.store(0, Ordering::Release);
+        // A server streaming a chunked response may be waiting on this slot
+        wakeup::wake_all(&self.response_ready);
+    }
+
     fn write_request(&mut self, data: &[u8]) -> Result<()> {
         if data.len() > MAX_MESSAGE_SIZE {
             anyhow::bail!("Message too large: {} > {}", data.len(), MAX_MESSAGE_SIZE);
         }
         self.request_len = data.len() as u32;
         self.data[..data.len()].copy_from_slice(data);
-        self.request_ready = 1;
+        self.request_ready.store(1, Ordering::Release);
         Ok(())
     }
 
     fn read_request(&mut self) -> Option<Vec<u8>> {
-        if self.request_ready == 0 {
+        if self.request_ready.load(Ordering::Acquire) == 0 {
             return None;
         }
         let len = self.request_len as usize;
         let data = self.data[..len].to_vec();
-        self.request_ready = 0;
+        self.request_ready.store(0, Ordering::Release);
         Some(data)
     }
 
@@ -138,17 +376,19 @@ impl ShmemLayout {
         }
         self.response_len = data.len() as u32;
         self.data[..data.len()].copy_from_slice(data);
-        self.response_ready = 1;
+        self.response_ready.store(1, Ordering::Release);
+        wakeup::wake_all(&self.response_ready);
         Ok(())
     }
 
     fn read_response(&mut self) -> Option<Vec<u8>> {
-        if self.response_ready == 0 {
+        if self.response_ready.load(Ordering::Acquire) == 0 {
             return None;
         }
         let len = self.response_len as usize;
         let data = self.data[..len].to_vec();
-        self.response_ready = 0;
+        self.response_ready.store(0, Ordering::Release);
+        wakeup::wake_all(&self.response_ready);
         Some(data)
     }
 }
@@ -156,28 +396,100 @@ impl ShmemLayout {
 /// IPC server for handling requests
 pub struct IPCServer {
     channel_name: String,
-    shmem: Shmem,
+    shmem: SharedRegion,
+    slots: usize,
     store: Arc<Mutex<pluresdb_core::CrdtStore>>,
+    /// Actor that clients' puts are written as
+    actor_id: ActorId,
     running: Arc<Mutex<bool>>,
+    /// Largest chunked request the server reassembles
+    max_transfer_size: usize,
+    /// Partially received chunked request, per slot
+    incoming: Mutex<Vec<Reassembly>>,
+    #[cfg(feature = "sqlite-compat")]
+    database: Option<Database>,
+    /// Client that owns the open SQL transaction, if any
+    #[cfg(feature = "sqlite-compat")]
+    tx_owner: Mutex<Option<TxOwner>>,
+}
+
+/// The client holding the server's open SQL transaction, and the slot it
+/// sends from
+#[cfg(feature = "sqlite-compat")]
+#[derive(Clone, Copy)]
+struct TxOwner {
+    client_id: u64,
+    slot: usize,
 }
 
 impl IPCServer {
-    /// Create a new IPC server
+    /// Create a new IPC server with [`DEFAULT_SLOTS`] client slots
     pub fn new(channel_name: &str, store: Arc<Mutex<pluresdb_core::CrdtStore>>) -> Result<Self> {
+        Self::new_with_slots(channel_name, store, DEFAULT_SLOTS)
+    }
+
+    /// Create a new IPC server that serves up to `slots` concurrent clients
+    /// (at most [`MAX_SLOTS`])
+    pub fn new_with_slots(
+        channel_name: &str,
+        store: Arc<Mutex<pluresdb_core::CrdtStore>>,
+        slots: usize,
+    ) -> Result<Self> {
+        if slots == 0 || slots > MAX_SLOTS {
+            anyhow::bail!("Slot count must be between 1 and {}, got {}", MAX_SLOTS, slots);
+        }
         let shmem = ShmemConf::new()
-            .size(SHMEM_SIZE)
+            .size(ShmemLayout::region_size(slots))
             .os_id(channel_name)
             .create()
             .context("Failed to create shared memory")?;
 
+        // Safety: the region was just created with room for the header, and no
+        // client can open it before this returns.
+        unsafe {
+            (*(shmem.as_ptr() as *mut ShmemLayout)).slot_count = slots as u32;
+        }
+
         Ok(Self {
             channel_name: channel_name.to_string(),
-            shmem,
+            shmem: SharedRegion(shmem),
+            slots,
             store,
+            actor_id: ActorId::new_random(),
             running: Arc::new(Mutex::new(false)),
+            max_transfer_size: DEFAULT_MAX_TRANSFER_SIZE,
+            incoming: Mutex::new(std::iter::repeat_with(Reassembly::default).take(slots).collect()),
+            #[cfg(feature = "sqlite-compat")]
+            database: None,
+            #[cfg(feature = "sqlite-compat")]
+            tx_owner: Mutex::new(None),
         })
     }
 
+    /// Refuse chunked requests larger than `bytes` (default [`DEFAULT_MAX_TRANSFER_SIZE`])
+    pub fn with_max_transfer_size(mut self, bytes: usize) -> Self {
+        self.max_transfer_size = bytes;
+        self
+    }
+
+    /// Write clients' puts as `actor_id` (default a random id unique to this server)
+    pub fn with_actor_id(mut self, actor_id: impl Into<ActorId>) -> Self {
+        self.actor_id = actor_id.into();
+        self
+    }
+
+    /// The actor that clients' puts are written as
+    pub fn actor_id(&self) -> &str {
+        &self.actor_id
+    }
+
+    /// Serve `Query`, `Exec`, and transaction messages from `database`
+    #[cfg(feature = "sqlite-compat")]
+    pub fn with_database(mut self, database: Database) -> Self {
+        self.database = Some(database);
+        self
+    }
+
     /// Start the IPC server
     pub fn start(&mut self) -> Result<()> {
         *self.running.lock() = true;
@@ -185,44 +497,132 @@ impl IPCServer {
         // Process messages in a loop
         while *self.running.lock() {
             self.process_one_message()?;
-            thread::sleep(Duration::from_millis(10));
         }
 
         Ok(())
     }
 
-    /// Process one message from the shared memory
+    /// Process the pending request, if any, in every slot.  If none was
+    /// pending, wait until a client rings the doorbell (or a short idle
+    /// timeout passes) before returning.
     fn process_one_message(&self) -> Result<()> {
-        // Safety: We control the shared memory lifecycle and ensure single writer.
-        // The ShmemLayout is repr(C) and matches the memory layout exactly.
-        let layout = unsafe {
-            &mut *(self.shmem.as_ptr() as *mut ShmemLayout)
+        // Safety: the region starts with the header written by `new_with_slots`
+        let doorbell = unsafe { &(*(self.shmem.as_ptr() as *const ShmemLayout)).doorbell };
+        // Read before scanning so a request written mid-scan cuts the wait short
+        let rung = doorbell.load(Ordering::Acquire);
+        // Before serving anyone, so SQL sent after the owner let go is not refused
+        self.release_abandoned_tx();
+
+        let mut handled = false;
+        for index in 0..self.slots {
+            // Safety: the region was created with `self.slots` slots, and the
+            // request flag keeps the client off the buffer until we respond.
+            let slot = unsafe { &mut *ShmemLayout::slot(&self.shmem, index) };
+            handled |= self.process_slot(index, slot)
+                .with_context(|| format!("Failed to process slot {}", index))?;
+        }
+
+        if !handled {
+            wakeup::wait(doorbell, rung, SERVER_IDLE_WAIT);
+        }
+        Ok(())
+    }
+
+    /// Roll back the open transaction if its client's slot has been released,
+    /// so a client that went away cannot block every other client's SQL
+    #[cfg(feature = "sqlite-compat")]
+    fn release_abandoned_tx(&self) {
+        let mut owner = self.tx_owner.lock();
+        let Some(current) = *owner else {
+            return;
         };
+        // Safety: the owner's slot index came from `process_one_message`
+        let slot = unsafe { &*ShmemLayout::slot(&self.shmem, current.slot) };
+        if slot.claimed.load(Ordering::Acquire) == 0 {
+            self.rollback_abandoned(&mut owner);
+        }
+    }
 
-        if let Some(request_data) = layout.read_request() {
-            let message: IPCMessage = bincode::serde::decode_from_slice(&request_data, bincode::config::standard())
-                .context("Failed to deserialize request")
-                .map(|(v, _)| v)?;
+    #[cfg(not(feature = "sqlite-compat"))]
+    fn release_abandoned_tx(&self) {}
 
-            let response = self.handle_message(message);
-            let response_data = bincode::serde::encode_to_vec(&response, bincode::config::standard())
-                .context("Failed to serialize response")?;
+    /// Roll back the transaction held by `owner` on behalf of a client that is gone
+    #[cfg(feature = "sqlite-compat")]
+    fn rollback_abandoned(&self, owner: &mut Option<TxOwner>) {
+        if let Some(db) = self.database.as_ref() {
+            // Nobody is left to report a failure to
+            let _ = db.exec("ROLLBACK");
+        }
+        *owner = None;
+    }
 
-            layout.write_response(&response_data)
-                .context("Failed to write response")?;
+    /// Answer the slot's pending request, returning whether there was one
+    fn process_slot(&self, index: usize, slot: &mut ShmemSlot) -> Result<bool> {
+        let Some(request_data) = slot.read_request() else {
+            return Ok(false);
+        };
+
+        let request = match decode(&request_data) {
+            Ok(IPCMessage::Chunk { seq, total, bytes }) => {
+                let assembled = self.incoming.lock()[index].push(seq, total, bytes, self.max_transfer_size);
+                match assembled {
+                    // Acknowledge so the client sends the next chunk
+                    Ok(None) => Ok(IPCMessage::Response { data: None }),
+                    Ok(Some(data)) => decode(&data).map(|message| self.handle_message(index, message)),
+                    Err(e) => Err(e),
+                }
+            }
+            Ok(message) => Ok(self.handle_message(index, message)),
+            Err(e) => Err(e),
+        };
+        // Answer errors too so one bad client cannot wedge its slot
+        let response = request.unwrap_or_else(|e| IPCMessage::Error {
+            message: format!("Failed to read request: {:#}", e),
+        });
+
+        self.send_response(slot, &response)?;
+        Ok(true)
+    }
+
+    /// Write `response` to the slot, streaming it as chunks if it is too large
+    /// for one handoff
+    fn send_response(&self, slot: &mut ShmemSlot, response: &IPCMessage) -> Result<()> {
+        let response_data = encode(response)?;
+        if response_data.len() <= MAX_MESSAGE_SIZE {
+            return slot.write_response(&response_data)
+                .context("Failed to write response");
         }
 
+        for frame in chunk_frames(&response_data)? {
+            // Every chunk reuses the slot's buffer, so wait for the client to
+            // take the previous one
+            let deadline = std::time::Instant::now() + DEFAULT_TIMEOUT;
+            while slot.response_ready.load(Ordering::Acquire) == 1 {
+                let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                if remaining.is_zero() || slot.claimed.load(Ordering::Acquire) == 0 {
+                    // The client gave up or went away; drop the rest of the response
+                    return Ok(());
+                }
+                wakeup::wait(&slot.response_ready, 1, remaining);
+            }
+            slot.write_response(&frame)
+                .context("Failed to write response chunk")?;
+        }
         Ok(())
     }
 
-    /// Handle an IPC message
-    fn handle_message(&self, message: IPCMessage) -> IPCMessage {
+    /// Handle an IPC message that arrived on slot `index`
+    fn handle_message(&self, index: usize, message: IPCMessage) -> IPCMessage {
         match message {
             IPCMessage::Put { id, data } => {
                 let mut store = self.store.lock();
-                let node_id = store.put(id, "ipc".to_string(), data);
-                IPCMessage::Response {
-                    data: Some(Value::String(node_id)),
+                match store.try_put(id, self.actor_id.clone(), data) {
+                    Ok(node_id) => IPCMessage::Response {
+                        data: Some(Value::String(node_id)),
+                    },
+                    Err(e) => IPCMessage::Error {
+                        message: e.to_string(),
+                    },
                 }
             }
             IPCMessage::Get { id } => {
@@ -252,6 +652,15 @@ impl IPCServer {
                     .collect();
                 IPCMessage::ListResponse { items }
             }
+            IPCMessage::Query { client_id, sql, params } => {
+                self.handle_sql(index, client_id, SqlRequest::Query { sql, params })
+            }
+            IPCMessage::Exec { client_id, sql } => {
+                self.handle_sql(index, client_id, SqlRequest::Exec { sql })
+            }
+            IPCMessage::BeginTx { client_id } => self.handle_sql(index, client_id, SqlRequest::Begin),
+            IPCMessage::CommitTx { client_id } => self.handle_sql(index, client_id, SqlRequest::Commit),
+            IPCMessage::RollbackTx { client_id } => self.handle_sql(index, client_id, SqlRequest::Rollback),
             IPCMessage::Shutdown => {
                 *self.running.lock() = false;
                 IPCMessage::Response { data: None }
@@ -262,6 +671,93 @@ impl IPCServer {
         }
     }
 
+    /// Run a SQL request for `client_id` on slot `slot`, enforcing transaction ownership
+    #[cfg(feature = "sqlite-compat")]
+    fn handle_sql(&self, slot: usize, client_id: u64, request: SqlRequest) -> IPCMessage {
+        let Some(db) = self.database.as_ref() else {
+            return IPCMessage::Error {
+                message: "SQL requires a database (attach one with IPCServer::with_database)".to_string(),
+            };
+        };
+
+        // Held for the whole request so the ownership check and the statement are atomic
+        let mut owner = self.tx_owner.lock();
+        if let Some(current) = *owner {
+            if current.slot == slot && current.client_id != client_id {
+                // The owner released its slot and a new client claimed it
+                // before the server noticed
+                self.rollback_abandoned(&mut owner);
+            } else if current.client_id != client_id {
+                return IPCMessage::Error {
+                    message: format!("Transaction in progress for another client ({})", current.client_id),
+                };
+            }
+        }
+
+        let result = match request {
+            SqlRequest::Query { sql, params } => params
+                .into_iter()
+                .map(SqlValue::from_json)
+                .collect::<Result<Vec<_>, _>>()
+                .and_then(|params| db.query(&sql, &params))
+                .map(|result| {
+                    serde_json::json!({
+                        "columns": result.columns,
+                        "rows": result.rows_as_json(),
+                        "changes": result.changes,
+                        "lastInsertRowid": result.last_insert_rowid
+                    })
+                }),
+            SqlRequest::Exec { sql } => db.exec(&sql).map(|result| {
+                serde_json::json!({
+                    "changes": result.changes,
+                    "lastInsertRowid": result.last_insert_rowid
+                })
+            }),
+            SqlRequest::Begin => {
+                if owner.is_some() {
+                    return IPCMessage::Error {
+                        message: "Transaction already open".to_string(),
+                    };
+                }
+                db.exec("BEGIN").map(|_| {
+                    *owner = Some(TxOwner { client_id, slot });
+                    Value::Null
+                })
+            }
+            SqlRequest::Commit | SqlRequest::Rollback => {
+                if owner.is_none() {
+                    return IPCMessage::Error {
+                        message: "No transaction open".to_string(),
+                    };
+                }
+                let rollback = matches!(request, SqlRequest::Rollback);
+                let result = db.exec(if rollback { "ROLLBACK" } else { "COMMIT" }).map(|_| Value::Null);
+                // A failed COMMIT leaves SQLite's transaction open, so the owner keeps
+                // it and can still roll back
+                if result.is_ok() || rollback {
+                    *owner = None;
+                }
+                result
+            }
+        };
+
+        match result {
+            Ok(Value::Null) => IPCMessage::Response { data: None },
+            Ok(data) => IPCMessage::Response { data: Some(data) },
+            Err(e) => IPCMessage::Error {
+                message: e.to_string(),
+            },
+        }
+    }
+
+    #[cfg(not(feature = "sqlite-compat"))]
+    fn handle_sql(&self, _slot: usize, _client_id: u64, _request: SqlRequest) -> IPCMessage {
+        IPCMessage::Error {
+            message: "SQL requires the 'sqlite-compat' feature to be enabled".to_string(),
+        }
+    }
+
     /// Stop the IPC server
     pub fn stop(&self) {
         *self.running.lock() = false;
@@ -274,58 +770,152 @@ impl Drop for IPCServer {
     }
 }
 
+/// SQL work extracted from an [`IPCMessage`]
+#[cfg_attr(not(feature = "sqlite-compat"), allow(dead_code))]
+enum SqlRequest {
+    Query { sql: String, params: Vec<Value> },
+    Exec { sql: String },
+    Begin,
+    Commit,
+    Rollback,
+}
+
+/// Source of per-process-unique client ids
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(0);
+
 /// IPC client for sending requests
 pub struct IPCClient {
     channel_name: String,
-    shmem: Shmem,
+    shmem: SharedRegion,
+    /// Slot claimed in the server's shared memory
+    slot: usize,
+    /// How long to wait for each response
+    timeout: Duration,
+    /// Largest chunked response the client reassembles
+    max_transfer_size: usize,
+    /// Identifies this client to the server for transaction ownership
+    client_id: u64,
+    /// Whether this client has a transaction open on the server
+    in_tx: bool,
 }
 
 impl IPCClient {
-    /// Connect to an existing IPC server
+    /// Connect to an existing IPC server, claiming one of its free slots
     pub fn connect(channel_name: &str) -> Result<Self> {
         let shmem = ShmemConf::new()
-            .size(SHMEM_SIZE)
             .os_id(channel_name)
             .open()
             .context("Failed to open shared memory. Is the server running?")?;
 
+        if shmem.len() < ShmemLayout::region_size(0) {
+            anyhow::bail!("Shared memory too small for an IPC header");
+        }
+        // Safety: checked above that the header fits
+        let slots = unsafe { (*(shmem.as_ptr() as *const ShmemLayout)).slot_count } as usize;
+        if slots > MAX_SLOTS || shmem.len() < ShmemLayout::region_size(slots) {
+            anyhow::bail!("Shared memory does not match the IPC layout");
+        }
+        // Safety: checked above that every slot fits
+        let slot = (0..slots)
+            .find(|&index| unsafe { (*ShmemLayout::slot(&shmem, index)).try_claim() })
+            .with_context(|| format!("All {} IPC slots are in use", slots))?;
+
         Ok(Self {
             channel_name: channel_name.to_string(),
-            shmem,
+            shmem: SharedRegion(shmem),
+            slot,
+            timeout: DEFAULT_TIMEOUT,
+            max_transfer_size: DEFAULT_MAX_TRANSFER_SIZE,
+            client_id: ((std::process::id() as u64) << 32) | NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed),
+            in_tx: false,
         })
     }
 
+    /// Wait at most `timeout` for each response (default [`DEFAULT_TIMEOUT`])
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Refuse to send or reassemble messages larger than `bytes` (default
+    /// [`DEFAULT_MAX_TRANSFER_SIZE`])
+    pub fn with_max_transfer_size(mut self, bytes: usize) -> Self {
+        self.max_transfer_size = bytes;
+        self
+    }
+
     /// Send a message and wait for response
     fn send_message(&mut self, message: IPCMessage) -> Result<IPCMessage> {
-        // Safety: We control the shared memory lifecycle and ensure proper synchronization.
-        // The ShmemLayout is repr(C) and matches the memory layout exactly.
-        let layout = unsafe {
-            &mut *(self.shmem.as_ptr() as *mut ShmemLayout)
-        };
+        let request_data = encode(&message)?;
+        if request_data.len() <= MAX_MESSAGE_SIZE {
+            return self.round_trip(&request_data);
+        }
+        if request_data.len() > self.max_transfer_size {
+            anyhow::bail!("Message too large: {} > {}", request_data.len(), self.max_transfer_size);
+        }
 
-        // Serialize and send request
-        let request_data = bincode::serde::encode_to_vec(&message, bincode::config::standard())
-            .context("Failed to serialize message")?;
-        layout.write_request(&request_data)
+        let frames = chunk_frames(&request_data)?;
+        let (last, rest) = frames.split_last().context("Chunked message has no chunks")?;
+        for frame in rest {
+            // The server acknowledges every chunk but the last; anything else
+            // is an error that ends the transfer early
+            match self.round_trip(frame)? {
+                IPCMessage::Response { data: None } => {}
+                response => return Ok(response),
+            }
+        }
+        self.round_trip(last)
+    }
+
+    /// Hand one encoded frame to the server and wait for its reply,
+    /// reassembling the reply if it comes back in chunks
+    fn round_trip(&mut self, frame: &[u8]) -> Result<IPCMessage> {
+        // Safety: `connect` checked the slot is inside the region, and this
+        // client owns it until dropped.
+        let layout = unsafe { &mut *ShmemLayout::slot(&self.shmem, self.slot) };
+
+        layout.write_request(frame)
             .context("Failed to write request")?;
 
-        // Wait for response (with timeout)
-        let timeout = Duration::from_secs(5);
-        let start = std::time::Instant::now();
+        // Safety: `connect` checked the region starts with the header
+        let doorbell = unsafe { &(*(self.shmem.as_ptr() as *const ShmemLayout)).doorbell };
+        doorbell.fetch_add(1, Ordering::Release);
+        wakeup::wake_all(doorbell);
+
+        // Wait for response (with timeout, restarted by each chunk)
+        let mut deadline = std::time::Instant::now() + self.timeout;
+        let mut incoming = Reassembly::default();
+        let mut failure = None;
 
         loop {
             if let Some(response_data) = layout.read_response() {
-                let response: IPCMessage = bincode::serde::decode_from_slice(&response_data, bincode::config::standard())
-                    .context("Failed to deserialize response")
-                    .map(|(v, _)| v)?;
-                return Ok(response);
+                let (seq, total, bytes) = match decode(&response_data)? {
+                    IPCMessage::Chunk { seq, total, bytes } => (seq, total, bytes),
+                    response => return Ok(response),
+                };
+                match incoming.push(seq, total, bytes, self.max_transfer_size) {
+                    Ok(Some(data)) => return decode(&data),
+                    Ok(None) => {}
+                    // Keep taking chunks so the server is not left waiting on the slot
+                    Err(e) => {
+                        failure.get_or_insert(e);
+                    }
+                }
+                if seq + 1 >= total {
+                    if let Some(e) = failure {
+                        return Err(e.context("Failed to reassemble response"));
+                    }
+                }
+                deadline = std::time::Instant::now() + self.timeout;
+                continue;
             }
 
-            if start.elapsed() > timeout {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            if remaining.is_zero() {
                 anyhow::bail!("Request timeout");
             }
 
-            thread::sleep(Duration::from_millis(10));
+            wakeup::wait(&layout.response_ready, 0, remaining);
         }
     }
 
@@ -380,6 +970,72 @@ impl IPCClient {
         }
     }
 
+    /// Run a SQL query on the server's database
+    ///
+    /// Returns `{ columns, rows, changes, lastInsertRowid }`.
+    pub fn query(&mut self, sql: &str, params: Vec<Value>) -> Result<Value> {
+        let message = IPCMessage::Query {
+            client_id: self.client_id,
+            sql: sql.to_string(),
+            params,
+        };
+
+        match self.send_message(message)? {
+            IPCMessage::Response { data: Some(data) } => Ok(data),
+            IPCMessage::Error { message } => anyhow::bail!("Query failed: {}", message),
+            _ => anyhow::bail!("Unexpected response type"),
+        }
+    }
+
+    /// Execute a SQL statement on the server's database
+    ///
+    /// Returns `{ changes, lastInsertRowid }`.
+    pub fn exec(&mut self, sql: &str) -> Result<Value> {
+        let message = IPCMessage::Exec {
+            client_id: self.client_id,
+            sql: sql.to_string(),
+        };
+
+        match self.send_message(message)? {
+            IPCMessage::Response { data: Some(data) } => Ok(data),
+            IPCMessage::Error { message } => anyhow::bail!("Exec failed: {}", message),
+            _ => anyhow::bail!("Unexpected response type"),
+        }
+    }
+
+    /// Open a transaction on the server. Later `query`/`exec` calls from this
+    /// client run inside it until `commit_tx` or `rollback_tx`.
+    pub fn begin_tx(&mut self) -> Result<()> {
+        let message = IPCMessage::BeginTx { client_id: self.client_id };
+        self.send_tx_message(message, "Begin transaction")?;
+        self.in_tx = true;
+        Ok(())
+    }
+
+    /// Commit this client's open transaction
+    pub fn commit_tx(&mut self) -> Result<()> {
+        let message = IPCMessage::CommitTx { client_id: self.client_id };
+        self.send_tx_message(message, "Commit")?;
+        self.in_tx = false;
+        Ok(())
+    }
+
+    /// Roll back this client's open transaction
+    pub fn rollback_tx(&mut self) -> Result<()> {
+        let message = IPCMessage::RollbackTx { client_id: self.client_id };
+        // The server gives the transaction up whether or not ROLLBACK succeeds
+        self.in_tx = false;
+        self.send_tx_message(message, "Rollback")
+    }
+
+    fn send_tx_message(&mut self, message: IPCMessage, action: &str) -> Result<()> {
+        match self.send_message(message)? {
+            IPCMessage::Response { .. } => Ok(()),
+            IPCMessage::Error { message } => anyhow::bail!("{} failed: {}", action, message),
+            _ => anyhow::bail!("Unexpected response type"),
+        }
+    }
+
     /// Send shutdown signal to the server
     pub fn shutdown(&mut self) -> Result<()> {
         let message = IPCMessage::Shutdown;
@@ -388,10 +1044,22 @@ impl IPCClient {
     }
 }
 
+impl Drop for IPCClient {
+    fn drop(&mut self) {
+        if self.in_tx {
+            // Best effort: the server also rolls back once it sees the slot released
+            let _ = self.rollback_tx();
+        }
+        // Safety: `connect` checked the slot is inside the region
+        unsafe { (*ShmemLayout::slot(&self.shmem, self.slot)).release() };
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use pluresdb_core::CrdtStore;
+    use std::thread;
 
     #[test]
     fn test_ipc_server_creation() {
@@ -406,14 +1074,8 @@ mod tests {
         let store = Arc::new(Mutex::new(CrdtStore::default()));
         let mut server = IPCServer::new("test-channel-ops", store.clone()).unwrap();
 
-        // Start server in a thread
-        let server_handle = thread::spawn(move || {
-            // Run for a limited time
-            for _ in 0..100 {
-                let _ = server.process_one_message();
-                thread::sleep(Duration::from_millis(10));
-            }
-        });
+        // Start server in a thread; it serves until the client sends Shutdown
+        let server_handle = thread::spawn(move || server.start());
 
         // Give server time to start
         thread::sleep(Duration::from_millis(100));
@@ -437,6 +1099,184 @@ mod tests {
         // Shutdown server
         let _ = client.shutdown();
 
-        server_handle.join().unwrap();
+        server_handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_ipc_concurrent_clients_get_their_own_responses() {
+        let store = Arc::new(Mutex::new(CrdtStore::default()));
+        let mut server = IPCServer::new_with_slots("test-channel-multi", store.clone(), 4).unwrap();
+        let server_handle = thread::spawn(move || server.start());
+        thread::sleep(Duration::from_millis(50));
+
+        let workers: Vec<_> = (0..4)
+            .map(|worker| {
+                thread::spawn(move || {
+                    let mut client = IPCClient::connect("test-channel-multi").unwrap();
+                    for i in 0..20 {
+                        let id = format!("worker:{}:{}", worker, i);
+                        let data = serde_json::json!({ "worker": worker, "i": i });
+                        assert_eq!(client.put(&id, data.clone()).unwrap(), id);
+                        assert_eq!(client.get(&id).unwrap(), Some(data));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(store.lock().list().len(), 80);
+        let _ = IPCClient::connect("test-channel-multi").unwrap().shutdown();
+        server_handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_ipc_chunks_messages_larger_than_a_slot() {
+        let store = Arc::new(Mutex::new(CrdtStore::default()));
+        let mut server = IPCServer::new("test-channel-chunked", store)
+            .unwrap()
+            .with_max_transfer_size(16 * 1024 * 1024);
+        let server_handle = thread::spawn(move || server.start());
+        thread::sleep(Duration::from_millis(50));
+
+        let mut client = IPCClient::connect("test-channel-chunked").unwrap();
+        let blob = serde_json::json!({ "blob": "x".repeat(5 * 1024 * 1024) });
+        assert_eq!(client.put("big", blob.clone()).unwrap(), "big");
+        assert_eq!(client.get("big").unwrap(), Some(blob.clone()));
+        assert_eq!(client.list().unwrap(), vec![serde_json::json!({ "id": "big", "data": blob })]);
+
+        // Past the server's limit the put is refused, and the slot keeps working
+        let huge = serde_json::json!({ "blob": "x".repeat(20 * 1024 * 1024) });
+        assert!(client.put("huge", huge).is_err());
+        assert_eq!(client.get("big").unwrap().unwrap()["blob"].as_str().unwrap().len(), 5 * 1024 * 1024);
+
+        // A client with a lower limit refuses the response but stays usable
+        let mut small = IPCClient::connect("test-channel-chunked")
+            .unwrap()
+            .with_max_transfer_size(1024 * 1024);
+        assert!(small.get("big").is_err());
+        assert_eq!(small.get("missing").unwrap(), None);
+
+        let _ = client.shutdown();
+        server_handle.join().unwrap().unwrap();
+    }
+
+    #[cfg(all(target_os = "linux", not(feature = "polling")))]
+    #[test]
+    fn test_ipc_round_trip_is_well_under_the_old_poll_interval() {
+        let store = Arc::new(Mutex::new(CrdtStore::default()));
+        let mut server = IPCServer::new("test-channel-latency", store).unwrap();
+        let server_handle = thread::spawn(move || server.start());
+        thread::sleep(Duration::from_millis(50));
+
+        let mut client = IPCClient::connect("test-channel-latency").unwrap();
+        client.put("k", serde_json::json!(1)).unwrap();
+        let mut samples: Vec<Duration> = (0..50)
+            .map(|_| {
+                let start = std::time::Instant::now();
+                client.get("k").unwrap();
+                start.elapsed()
+            })
+            .collect();
+        samples.sort();
+
+        // Polling slept 10ms on each side; a futex wakeup should be far below that
+        let median = samples[samples.len() / 2];
+        assert!(median < Duration::from_millis(2), "median round trip was {:?}", median);
+
+        let _ = client.shutdown();
+        server_handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_ipc_connect_fails_when_all_slots_are_claimed() {
+        assert!(IPCServer::new_with_slots("test-channel-zero", Arc::default(), 0).is_err());
+
+        let store = Arc::new(Mutex::new(CrdtStore::default()));
+        let _server = IPCServer::new_with_slots("test-channel-full", store, 1).unwrap();
+
+        let first = IPCClient::connect("test-channel-full").unwrap();
+        assert!(IPCClient::connect("test-channel-full").is_err());
+        drop(first);
+        assert!(IPCClient::connect("test-channel-full").is_ok());
+    }
+
+    #[cfg(feature = "sqlite-compat")]
+    #[test]
+    fn test_ipc_transaction_rollback() {
+        use pluresdb_core::DatabaseOptions;
+
+        let store = Arc::new(Mutex::new(CrdtStore::default()));
+        let database = Database::open(DatabaseOptions::in_memory()).unwrap();
+        let mut server = IPCServer::new("test-channel-tx", store)
+            .unwrap()
+            .with_database(database);
+
+        let server_handle = thread::spawn(move || server.start());
+        thread::sleep(Duration::from_millis(100));
+
+        let mut client = IPCClient::connect("test-channel-tx").unwrap();
+        let mut other = IPCClient::connect("test-channel-tx").unwrap();
+        client.exec("CREATE TABLE items (name TEXT)").unwrap();
+
+        client.begin_tx().unwrap();
+        client.exec("INSERT INTO items (name) VALUES ('pending')").unwrap();
+
+        // Another client may not interleave with the open transaction
+        assert!(other.exec("INSERT INTO items (name) VALUES ('intruder')").is_err());
+        assert!(other.begin_tx().is_err());
+        assert!(other.rollback_tx().is_err());
+
+        client.rollback_tx().unwrap();
+
+        let result = other.query("SELECT COUNT(*) AS n FROM items", vec![]).unwrap();
+        assert_eq!(result["rows"][0]["n"], 0);
+
+        let _ = client.shutdown();
+        server_handle.join().unwrap().unwrap();
+    }
+
+    #[cfg(feature = "sqlite-compat")]
+    #[test]
+    fn test_ipc_transaction_of_dropped_client_is_rolled_back() {
+        use pluresdb_core::DatabaseOptions;
+
+        let store = Arc::new(Mutex::new(CrdtStore::default()));
+        let database = Database::open(DatabaseOptions::in_memory()).unwrap();
+        let mut server = IPCServer::new("test-channel-tx-drop", store)
+            .unwrap()
+            .with_database(database);
+
+        let server_handle = thread::spawn(move || server.start());
+        thread::sleep(Duration::from_millis(100));
+
+        let mut other = IPCClient::connect("test-channel-tx-drop").unwrap();
+        other.exec("CREATE TABLE items (name TEXT)").unwrap();
+
+        // Dropping the owner rolls its transaction back
+        let mut client = IPCClient::connect("test-channel-tx-drop").unwrap();
+        client.begin_tx().unwrap();
+        client.exec("INSERT INTO items (name) VALUES ('dropped')").unwrap();
+        drop(client);
+        other.exec("INSERT INTO items (name) VALUES ('kept')").unwrap();
+
+        // A client that goes away without rolling back, as one that crashed
+        // would, only releases its slot; the server rolls back for it
+        let mut crashed = IPCClient::connect("test-channel-tx-drop").unwrap();
+        crashed.begin_tx().unwrap();
+        crashed.exec("INSERT INTO items (name) VALUES ('crashed')").unwrap();
+        crashed.in_tx = false;
+        drop(crashed);
+        other.exec("INSERT INTO items (name) VALUES ('after')").unwrap();
+
+        let result = other.query("SELECT name FROM items ORDER BY rowid", vec![]).unwrap();
+        assert_eq!(
+            result["rows"],
+            serde_json::json!([{ "name": "kept" }, { "name": "after" }])
+        );
+
+        let _ = other.shutdown();
+        server_handle.join().unwrap().unwrap();
     }
 }
@@ -0,0 +1,63 @@
+// onChange(cb): node upserts and deletes reach every attached listener as
+// { id, op }, and unsubscribe() detaches one without affecting the others.
+// Run from the crate dir AFTER build: node __tests__/on-change.gate.mjs
+import { createRequire } from "node:module";
+const require = createRequire(import.meta.url);
+const { PluresDatabase } = require("../index.js");
+
+const sleep = (ms) => new Promise((r) => setTimeout(r, ms));
+let failures = 0;
+function check(name, cond) {
+  console.log(`  ${cond ? "PASS" : "FAIL"}  ${name}`);
+  if (!cond) failures++;
+}
+
+// Resolves with the first `count` changes the listener receives
+function listen(db, count) {
+  const received = [];
+  let done;
+  const all = new Promise((res) => { done = res; });
+  const id = db.onChange((change) => {
+    received.push(change);
+    if (received.length === count) done(received);
+  });
+  return { id, received, all: Promise.race([all, sleep(3000).then(() => received)]) };
+}
+
+async function main() {
+  const db = new PluresDatabase("on-change-actor");
+
+  const first = listen(db, 2);
+  const second = listen(db, 2);
+  check("onChange returns distinct numeric ids",
+    typeof first.id === "number" && first.id !== second.id);
+
+  db.put("oc-a", { name: "Alice" });
+  db.delete("oc-a");
+
+  for (const [name, listener] of [["first", first], ["second", second]]) {
+    const changes = await listener.all;
+    check(`${name} listener got the upsert`,
+      changes[0] && changes[0].id === "oc-a" && changes[0].op === "upsert");
+    check(`${name} listener got the delete`,
+      changes[1] && changes[1].id === "oc-a" && changes[1].op === "delete");
+  }
+
+  db.unsubscribe(first.id);
+  await sleep(100);
+  db.put("oc-b", { name: "Bob" });
+  await sleep(500);
+  check("no callback after unsubscribe", first.received.length === 2);
+  check("other listeners keep receiving",
+    second.received.length === 3 && second.received[2].id === "oc-b");
+  db.unsubscribe(second.id);
+
+  console.log(`\nON_CHANGE_GATE: ${failures === 0 ? "PASS" : "FAIL"} (${failures} failures)`);
+  process.exit(failures === 0 ? 0 : 1);
+}
+
+main().catch((e) => {
+  console.log("ON_CHANGE_GATE_ERROR:", e && e.message ? e.message.split("\n")[0] : String(e));
+  console.log("ON_CHANGE_GATE: FAIL");
+  process.exit(1);
+});
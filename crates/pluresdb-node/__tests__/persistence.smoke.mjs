@@ -0,0 +1,56 @@
+// CRDT data written through the Node binding survives reopening its db_path.
+//
+// Run AFTER `napi build --release`:  node __tests__/persistence.smoke.mjs
+
+import { createRequire } from 'node:module';
+import { mkdtempSync, rmSync } from 'node:fs';
+import { tmpdir } from 'node:os';
+import { join } from 'node:path';
+
+const require = createRequire(import.meta.url);
+const { PluresDatabase } = require('../index.js');
+
+let failures = 0;
+function check(name, cond, detail) {
+  if (cond) {
+    console.log(`  ✓ ${name}`);
+  } else {
+    failures += 1;
+    console.error(`  ✗ ${name}${detail ? ' — ' + detail : ''}`);
+  }
+}
+
+function main() {
+  console.log('=== Persistence across reopen ===\n');
+
+  const dbDir = mkdtempSync(join(tmpdir(), 'pluresdb-persist-'));
+  const dbPath = join(dbDir, 'store');
+  try {
+    const db = new PluresDatabase('persist-actor', dbPath);
+    db.put('user-1', { name: 'Alice', type: 'User' });
+    db.put('user-2', { name: 'Bob', type: 'User' });
+    db.delete('user-2');
+    db.close();
+
+    const reopened = new PluresDatabase('persist-actor', dbPath);
+    const alice = reopened.get('user-1');
+    check('put survives reopening the same path', alice && alice.name === 'Alice',
+      `got ${JSON.stringify(alice)}`);
+    check('delete survives reopening the same path', reopened.get('user-2') === null);
+    check('listByType() includes persisted nodes',
+      reopened.listByType('User').some((node) => node.id === 'user-1'));
+    reopened.close();
+
+    const inMemory = new PluresDatabase('persist-actor');
+    inMemory.put('user-1', { name: 'Alice' });
+    check('instances without a path keep data in memory only',
+      new PluresDatabase('persist-actor').get('user-1') === null);
+  } finally {
+    rmSync(dbDir, { recursive: true, force: true });
+  }
+
+  console.log(`\nPERSISTENCE_SMOKE: ${failures === 0 ? 'PASS' : 'FAIL'} (${failures} failures)`);
+  process.exitCode = failures === 0 ? 0 : 1;
+}
+
+main();
@@ -0,0 +1,78 @@
+// SQL through the Node binding: query()/exec() against the SQLite file kept in
+// the db_path directory, alongside the sled-backed CRDT store.
+//
+// Needs an addon built with the `sqlite-compat` feature:
+//   napi build --release --features sqlite-compat && node __tests__/sql.smoke.mjs
+// Against a default build the script reports SKIP and exits 0.
+
+import { createRequire } from 'node:module';
+import { mkdtempSync, rmSync } from 'node:fs';
+import { tmpdir } from 'node:os';
+import { join } from 'node:path';
+
+const require = createRequire(import.meta.url);
+const { PluresDatabase } = require('../index.js');
+
+let failures = 0;
+function check(name, cond, detail) {
+  if (cond) {
+    console.log(`  ✓ ${name}`);
+  } else {
+    failures += 1;
+    console.error(`  ✗ ${name}${detail ? ' — ' + detail : ''}`);
+  }
+}
+
+function thrown(fn) {
+  try { fn(); } catch (err) { return String(err && err.message ? err.message : err); }
+  return null;
+}
+
+function main() {
+  console.log('=== SQL via query()/exec() ===\n');
+
+  const inMemory = new PluresDatabase('sql-smoke');
+  const noDbError = thrown(() => inMemory.exec('SELECT 1'));
+  if (noDbError && noDbError.includes('CORE_FEATURE_DISABLED')) {
+    console.log('SKIP: addon built without the sqlite-compat feature');
+    return;
+  }
+  check('exec without a db_path throws', noDbError && noDbError.includes('provide db_path'),
+    `got ${noDbError}`);
+  check('query without a db_path throws',
+    (thrown(() => inMemory.query('SELECT 1')) || '').includes('provide db_path'));
+
+  const dbDir = mkdtempSync(join(tmpdir(), 'pluresdb-sql-'));
+  const dbPath = join(dbDir, 'store');
+  try {
+    const db = new PluresDatabase('sql-smoke', dbPath);
+
+    const created = db.exec('CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER)');
+    check('exec(CREATE TABLE) reports no changes', created.changes === 0, JSON.stringify(created));
+
+    const first = db.exec("INSERT INTO users (name, age) VALUES ('Alice', 30)");
+    check('exec(INSERT) reports one change', first.changes === 1, JSON.stringify(first));
+    check('exec(INSERT) reports the new rowid', first.lastInsertRowid === 1, JSON.stringify(first));
+    db.exec("INSERT INTO users (name, age) VALUES ('Bob', 25)");
+
+    const result = db.query('SELECT name, age FROM users WHERE age > ? ORDER BY name', [20]);
+    check('query returns the selected columns',
+      JSON.stringify(result.columns) === JSON.stringify(['name', 'age']), JSON.stringify(result.columns));
+    check('query returns rows as objects',
+      JSON.stringify(result.rows) === JSON.stringify([{ name: 'Alice', age: 30 }, { name: 'Bob', age: 25 }]),
+      JSON.stringify(result.rows));
+    check('query binds parameters',
+      db.query('SELECT name FROM users WHERE name = ?', ['Bob']).rows.length === 1);
+
+    // The CRDT store shares db_path with the SQL database
+    db.put('node-1', { name: 'Alice' });
+    check('CRDT puts still work next to SQL', db.get('node-1') && db.get('node-1').name === 'Alice');
+  } finally {
+    rmSync(dbDir, { recursive: true, force: true });
+  }
+
+  console.log(`\nSQL_SMOKE: ${failures === 0 ? 'PASS' : 'FAIL'} (${failures} failures)`);
+  process.exitCode = failures === 0 ? 0 : 1;
+}
+
+main();
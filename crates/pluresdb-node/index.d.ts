@@ -27,11 +27,22 @@ export declare class PluresDatabase {
   static newWithEmbeddings(model: string, actorId?: string | undefined | null, dbPath?: string | undefined | null): PluresDatabase
   /** Insert or update a node */
   put(id: string, data: any): string
+  /**
+   * Insert a node whose id is the BLAKE3 hash of its canonical JSON.
+   *
+   * Returns the id.  Storing content that already exists is a no-op that
+   * returns the existing id.
+   */
+  putContentAddressed(data: any): string
   /** Retrieve a node by ID */
   get(id: string): any | null
   /** Get a node with full metadata (including vector clock and timestamp) */
   getWithMetadata(id: string): any | null
-  /** Delete a node by ID */
+  /**
+   * Delete a node by ID
+   *
+   * Throws with `err.code === "NOT_FOUND"` if the node does not exist.
+   */
   delete(id: string): void
   /** List all nodes */
   list(): Array<any>
@@ -40,13 +51,15 @@ export declare class PluresDatabase {
   /**
    * Execute SQL query
    *
-   * Requires the `sqlite-compat` cargo feature to be enabled.
+   * Requires the `sqlite-compat` cargo feature to be enabled. Errors carry
+   * `err.code` (e.g. `"CONSTRAINT"`, `"BUSY"`, `"ACCESS_DENIED"`).
    */
   query(sql: string, params?: Array<any> | undefined | null): any
   /**
    * Execute SQL statement (INSERT, UPDATE, DELETE)
    *
-   * Requires the `sqlite-compat` cargo feature to be enabled.
+   * Requires the `sqlite-compat` cargo feature to be enabled. Errors carry
+   * `err.code` as for [`query`](Self::query).
    */
   exec(sql: string): any
   /** Search nodes by text content */
@@ -89,6 +102,15 @@ export declare class PluresDatabase {
    * the next event (or when the channel closes as the database is dropped).
    */
   subscribe(callback: ((arg: SyncEventJs) => void)): number
+  /**
+   * Call `callback` with `{ id, op }` whenever a node is upserted or deleted.
+   *
+   * Like [`subscribe`][PluresDatabase::subscribe], but peer lifecycle
+   * events are left out. Each call registers an independent listener on
+   * its own thread, so any number can be attached; the returned id stops
+   * one via [`unsubscribe`][PluresDatabase::unsubscribe].
+   */
+  onChange(callback: ((arg: ChangeEventJs) => void)): number
   /**
    * Stop a live subscription created by [`subscribe`][PluresDatabase::subscribe].
    *
@@ -145,6 +167,13 @@ export declare class PluresDatabase {
   embeddingDimension(): number | null
   /** Get the actor ID for this database instance */
   getActorId(): string
+  /**
+   * Release the on-disk storage opened from `db_path` so the same path can
+   * be reopened, e.g. by a new instance in this process, without waiting
+   * for garbage collection. Every write was already flushed when it was
+   * made. Afterwards this instance is an empty in-memory database.
+   */
+  close(): void
   /**
    * Execute a DSL query string against the CRDT store.
    *
@@ -430,6 +459,15 @@ export declare class PluresDatabase {
   agensTimerReschedule(timerId: string): boolean
 }
 
+/**
+ * A node change delivered to JavaScript `onChange` callbacks: `op` is
+ * `"upsert"` or `"delete"`, and `id` is the node id.
+ */
+export interface ChangeEventJs {
+  id: string
+  op: string
+}
+
 /**
  * Compress a single message/chunk body, routing by content type exactly like
  * the production `compress_one`:
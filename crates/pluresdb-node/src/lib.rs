@@ -10,7 +10,10 @@ use parking_lot::Mutex;
 
 /// Real ported headroom token-compression algorithm (no stubs, no agens dep).
 mod headroom;
-use pluresdb_core::{CoreErrorCode, CrdtStore, NodeRecord, StoreError};
+use pluresdb_core::{
+    ActorId, ActorIdExt, CoreErrorCode, CrdtStore, ErrorKind, IdStrategy, NodeRecord, StoreError,
+    ACTOR_ID_FILE,
+};
 use pluresdb_procedures::agens::{AgensEvent, AgensRuntime};
 use pluresdb_procedures::engine::ProcedureEngine;
 use pluresdb_px::db::procedures as px_procedures;
@@ -26,11 +29,12 @@ use pluresdb_px::px::{expr_to_string as px_expr_to_string, Statement as PxStatem
 use pluresdb_storage::{SledStorage, StorageEngine, StorageErrorCode};
 use pluresdb_sync::{SyncBroadcaster, SyncErrorCode, SyncEvent};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 
 #[cfg(feature = "sqlite-compat")]
-use pluresdb_core::{Database, DatabaseOptions, SqlValue};
+use pluresdb_core::{Database, DatabaseError, DatabaseOptions, SqlValue};
 
 fn node_error(code: &str, message: impl Into<String>) -> Error {
     Error::from_reason(format!("[{}] {}", code, message.into()))
@@ -40,6 +44,45 @@ fn map_node_error<E: std::fmt::Display>(code: &str, error: E) -> Error {
     node_error(code, error.to_string())
 }
 
+/// `actor_id` if given, else the id kept in the database directory at
+/// `db_path`, else a random one for this instance alone.
+fn resolve_actor_id(actor_id: Option<String>, db_path: Option<&str>) -> Result<String> {
+    match (actor_id, db_path) {
+        (Some(actor_id), _) => Ok(actor_id),
+        (None, Some(path)) => ActorId::load_or_create(Path::new(path).join(ACTOR_ID_FILE))
+            .map_err(|e| map_node_error(StorageErrorCode::OpenFailed.as_str(), e)),
+        (None, None) => Ok(ActorId::new_random()),
+    }
+}
+
+/// Like [`node_error`], but the thrown JavaScript error also carries
+/// `err.code` set to the coarse [`ErrorKind`] (e.g. `"NOT_FOUND"`), so callers
+/// can branch on the failure without parsing the message.
+fn typed_error(kind: ErrorKind, code: &str, message: impl Into<String>) -> Error<ErrorKind> {
+    Error::new(kind, format!("[{}] {}", code, message.into()))
+}
+
+#[cfg(feature = "sqlite-compat")]
+fn map_database_error(error: DatabaseError) -> Error<ErrorKind> {
+    typed_error(error.kind(), error.code().as_str(), error.to_string())
+}
+
+/// SQLite file kept inside the `db_path` directory, next to the sled files
+/// that persist the CRDT store.
+#[cfg(feature = "sqlite-compat")]
+const SQL_DATABASE_FILE: &str = "sql.sqlite3";
+
+/// Open the SQL database for `db_path`. Sled has already claimed `db_path`
+/// itself as a directory, so the SQLite file lives inside it.
+#[cfg(feature = "sqlite-compat")]
+fn open_sql_database(db_path: &str) -> Result<Arc<Database>> {
+    let path = std::path::Path::new(db_path).join(SQL_DATABASE_FILE);
+    let options = DatabaseOptions::with_file(path).create_if_missing(true);
+    Database::open(options)
+        .map(Arc::new)
+        .map_err(|e| map_node_error(CoreErrorCode::SqliteError.as_str(), e))
+}
+
 /// A live change event delivered to JavaScript `subscribe` callbacks.
 ///
 /// Mirrors [`pluresdb_sync::SyncEvent`] in a Node-friendly shape: `kind` is
@@ -53,7 +96,7 @@ pub struct SyncEventJs {
 impl From<SyncEvent> for SyncEventJs {
     fn from(event: SyncEvent) -> Self {
         match event {
-            SyncEvent::NodeUpsert { id } => SyncEventJs {
+            SyncEvent::NodeUpsert { id } | SyncEvent::NodeUpserted { id, .. } => SyncEventJs {
                 kind: "upsert".to_string(),
                 id,
             },
@@ -71,12 +114,40 @@ impl From<SyncEvent> for SyncEventJs {
                 kind: "peer-disconnected".to_string(),
                 id: peer_id,
             },
+            // `id` carries the number of missed events.
+            SyncEvent::ResyncRequired { missed } => SyncEventJs {
+                kind: "resync-required".to_string(),
+                id: missed.to_string(),
+            },
         }
     }
 }
 
-fn map_store_error(error: StoreError) -> Error {
-    node_error(error.code().as_str(), error.to_string())
+/// A node change delivered to JavaScript `onChange` callbacks: `op` is
+/// `"upsert"` or `"delete"`, and `id` is the node id.
+#[napi(object)]
+pub struct ChangeEventJs {
+    pub id: String,
+    pub op: String,
+}
+
+impl ChangeEventJs {
+    /// The change `event` describes, or `None` for peer and resync events.
+    fn from_event(event: SyncEvent) -> Option<Self> {
+        let (id, op) = match event {
+            SyncEvent::NodeUpsert { id } | SyncEvent::NodeUpserted { id, .. } => (id, "upsert"),
+            SyncEvent::NodeDelete { id } => (id, "delete"),
+            _ => return None,
+        };
+        Some(ChangeEventJs {
+            id,
+            op: op.to_string(),
+        })
+    }
+}
+
+fn map_store_error(error: StoreError) -> Error<ErrorKind> {
+    typed_error(error.kind(), error.code().as_str(), error.to_string())
 }
 
 /// A reactive praxis-evaluation result delivered to `subscribePx` callbacks.
@@ -116,27 +187,31 @@ pub struct PxEventJs {
 /// This projects the honest, evaluable context; it never invents field values.
 fn context_for_event(store: &Arc<Mutex<CrdtStore>>, event: &SyncEvent) -> PxAgentContext {
     let (kind, id): (&str, &str) = match event {
-        SyncEvent::NodeUpsert { id } => ("upsert", id.as_str()),
+        SyncEvent::NodeUpsert { id } | SyncEvent::NodeUpserted { id, .. } => {
+            ("upsert", id.as_str())
+        }
         SyncEvent::NodeDelete { id } => ("delete", id.as_str()),
         SyncEvent::PeerConnected { peer_id } => ("peer-connected", peer_id.as_str()),
         SyncEvent::PeerDisconnected { peer_id } => ("peer-disconnected", peer_id.as_str()),
+        SyncEvent::ResyncRequired { .. } => ("resync-required", ""),
     };
 
     let mut ctx = PxAgentContext::new(kind, id, PxSessionType::Main);
 
     // Only an upsert has a current node body to read; fold its object fields
     // into metadata so field-path predicates (`metadata.<field>`) can see them.
-    if matches!(event, SyncEvent::NodeUpsert { .. }) {
-        let record = {
+    // A `NodeUpserted` event already carries the written data.
+    let data = match event {
+        SyncEvent::NodeUpserted { data, .. } => Some(data.clone()),
+        SyncEvent::NodeUpsert { .. } => {
             let store = store.lock();
-            store.get(id)
-        };
-        if let Some(record) = record {
-            if let serde_json::Value::Object(map) = record.data {
-                for (k, v) in map {
-                    ctx.metadata.insert(k, v);
-                }
-            }
+            store.get(id).map(|record| record.data)
+        }
+        _ => None,
+    };
+    if let Some(serde_json::Value::Object(map)) = data {
+        for (k, v) in map {
+            ctx.metadata.insert(k, v);
         }
     }
 
@@ -201,12 +276,13 @@ fn persist_constraint(
     constraint: &PxConstraint,
 ) -> Result<()> {
     let data = constraint_node_data(constraint)?;
-    let node_id = {
+    let event = {
         let store = store.lock();
-        store.put(constraint.id.clone(), actor_id.to_string(), data)
+        let node_id = store.put(constraint.id.clone(), actor_id.to_string(), data);
+        SyncEvent::upserted(&store, node_id)
     };
     broadcaster
-        .publish(SyncEvent::NodeUpsert { id: node_id })
+        .publish(event)
         .map_err(|e| map_node_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e))?;
     Ok(())
 }
@@ -302,7 +378,7 @@ impl PluresDatabase {
     /// Create a new PluresDB instance
     #[napi(constructor)]
     pub fn new(actor_id: Option<String>, db_path: Option<String>) -> Result<Self> {
-        let actor_id = actor_id.unwrap_or_else(|| "node-actor".to_string());
+        let actor_id = resolve_actor_id(actor_id, db_path.as_deref())?;
 
         let (store, storage) = if let Some(path) = &db_path {
             let sled_storage = Arc::new(
@@ -317,14 +393,7 @@ impl PluresDatabase {
         };
 
         #[cfg(feature = "sqlite-compat")]
-        let db = if let Some(path) = db_path {
-            let options = DatabaseOptions::with_file(path).create_if_missing(true);
-            Some(Arc::new(Database::open(options).map_err(|e| {
-                map_node_error(CoreErrorCode::SqliteError.as_str(), e)
-            })?))
-        } else {
-            None
-        };
+        let db = db_path.as_deref().map(open_sql_database).transpose()?;
 
         let core_store = Arc::new(Mutex::new(store));
         // Seed the built-in praxis constraints into the CrdtStore (the single
@@ -369,10 +438,9 @@ impl PluresDatabase {
         actor_id: Option<String>,
         db_path: Option<String>,
     ) -> Result<Self> {
-        let actor_id = actor_id.unwrap_or_else(|| "node-actor".to_string());
-
         #[cfg(feature = "embeddings")]
         {
+            let actor_id = resolve_actor_id(actor_id, db_path.as_deref())?;
             use pluresdb_core::FastEmbedder;
             let embedder = FastEmbedder::new(&model).map_err(|e| {
                 node_error(
@@ -395,14 +463,7 @@ impl PluresDatabase {
             };
 
             #[cfg(feature = "sqlite-compat")]
-            let db = if let Some(ref path) = db_path {
-                let options = DatabaseOptions::with_file(path).create_if_missing(true);
-                Some(Arc::new(Database::open(options).map_err(|e| {
-                    map_node_error(CoreErrorCode::SqliteError.as_str(), e)
-                })?))
-            } else {
-                None
-            };
+            let db = db_path.as_deref().map(open_sql_database).transpose()?;
 
             let core_store = Arc::new(Mutex::new(store));
             seed_praxis_into_crdt(&core_store, &actor_id);
@@ -432,23 +493,79 @@ impl PluresDatabase {
     }
 
     /// Insert or update a node
+    ///
+    /// Throws with `err.code === "INVALID_INPUT"` if `data` serializes to
+    /// more than the limit set with `setMaxNodeSize`, or nests deeper than
+    /// the one set with `setMaxNodeDepth`.
     #[napi]
-    pub fn put(&self, id: String, data: serde_json::Value) -> Result<String> {
+    pub fn put(&self, id: String, data: serde_json::Value) -> Result<String, ErrorKind> {
         let store = self.store.clone();
         let broadcaster = self.broadcaster.clone();
         let actor_id = self.actor_id.clone();
 
-        let node_id = {
+        let (node_id, event) = {
             let store = store.lock();
-            store.put(id.clone(), actor_id, data)
+            let node_id = store
+                .try_put(id.clone(), actor_id, data)
+                .map_err(map_store_error)?;
+            (node_id.clone(), SyncEvent::upserted(&store, node_id))
         };
 
         // Publish sync event
-        broadcaster
-            .publish(SyncEvent::NodeUpsert {
-                id: node_id.clone(),
-            })
-            .map_err(|e| map_node_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e))?;
+        broadcaster.publish(event).map_err(|e| {
+            typed_error(
+                ErrorKind::Internal,
+                SyncErrorCode::BroadcastPublishFailed.as_str(),
+                e.to_string(),
+            )
+        })?;
+
+        Ok(node_id)
+    }
+
+    /// Reject later `put`s whose data serializes to more than `bytes` of
+    /// JSON.  Nodes are unlimited until this is called.
+    #[napi]
+    pub fn set_max_node_size(&self, bytes: u32) {
+        let mut store = self.store.lock();
+        *store = std::mem::take(&mut *store).with_max_node_size(bytes as usize);
+    }
+
+    /// Reject later `put`s whose data nests arrays and objects more than
+    /// `depth` levels deep.  Nodes are unlimited until this is called.
+    #[napi]
+    pub fn set_max_node_depth(&self, depth: u32) {
+        let mut store = self.store.lock();
+        *store = std::mem::take(&mut *store).with_max_node_depth(depth as usize);
+    }
+
+    /// Insert a node whose id is the BLAKE3 hash of its canonical JSON.
+    ///
+    /// Returns the id.  Storing content that already exists is a no-op that
+    /// returns the existing id.
+    #[napi]
+    pub fn put_content_addressed(&self, data: serde_json::Value) -> Result<String, ErrorKind> {
+        let node_id = IdStrategy::ContentHash.derive_id(&data);
+        let event = {
+            let store = self.store.lock();
+            let inserted = store.get(&node_id).is_none();
+            if inserted {
+                store
+                    .try_put(node_id.clone(), self.actor_id.clone(), data)
+                    .map_err(map_store_error)?;
+            }
+            inserted.then(|| SyncEvent::upserted(&store, node_id.clone()))
+        };
+
+        if let Some(event) = event {
+            self.broadcaster.publish(event).map_err(|e| {
+                typed_error(
+                    ErrorKind::Internal,
+                    SyncErrorCode::BroadcastPublishFailed.as_str(),
+                    e.to_string(),
+                )
+            })?;
+        }
 
         Ok(node_id)
     }
@@ -494,8 +611,10 @@ impl PluresDatabase {
     }
 
     /// Delete a node by ID
+    ///
+    /// Throws with `err.code === "NOT_FOUND"` if the node does not exist.
     #[napi]
-    pub fn delete(&self, id: String) -> Result<()> {
+    pub fn delete(&self, id: String) -> Result<(), ErrorKind> {
         let store = self.store.clone();
         let broadcaster = self.broadcaster.clone();
 
@@ -507,7 +626,13 @@ impl PluresDatabase {
         // Publish sync event
         broadcaster
             .publish(SyncEvent::NodeDelete { id: id.clone() })
-            .map_err(|e| map_node_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e))?;
+            .map_err(|e| {
+                typed_error(
+                    ErrorKind::Internal,
+                    SyncErrorCode::BroadcastPublishFailed.as_str(),
+                    e.to_string(),
+                )
+            })?;
 
         Ok(())
     }
@@ -571,17 +696,19 @@ impl PluresDatabase {
 
     /// Execute SQL query
     ///
-    /// Requires the `sqlite-compat` cargo feature to be enabled.
+    /// Requires the `sqlite-compat` cargo feature to be enabled. Errors carry
+    /// `err.code` (e.g. `"CONSTRAINT"`, `"BUSY"`, `"ACCESS_DENIED"`).
     #[napi]
     pub fn query(
         &self,
         sql: String,
         params: Option<Vec<serde_json::Value>>,
-    ) -> Result<serde_json::Value> {
+    ) -> Result<serde_json::Value, ErrorKind> {
         #[cfg(feature = "sqlite-compat")]
         {
             let db = self.db.as_ref().ok_or_else(|| {
-                node_error(
+                typed_error(
+                    ErrorKind::InvalidInput,
                     CoreErrorCode::InvalidInput.as_str(),
                     "SQL queries require a database (provide db_path in constructor)".to_string(),
                 )
@@ -589,33 +716,14 @@ impl PluresDatabase {
 
             let sql_params: Vec<SqlValue> = if let Some(p) = params {
                 p.into_iter()
-                    .map(|v| -> Result<SqlValue> {
-                        Ok(match v {
-                            serde_json::Value::Null => SqlValue::Null,
-                            serde_json::Value::Number(n) => {
-                                if n.is_i64() {
-                                    SqlValue::Integer(n.as_i64().unwrap())
-                                } else {
-                                    SqlValue::Real(n.as_f64().unwrap())
-                                }
-                            }
-                            serde_json::Value::String(s) => SqlValue::Text(s),
-                            serde_json::Value::Bool(b) => SqlValue::Integer(if b { 1 } else { 0 }),
-                            serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
-                                SqlValue::Text(serde_json::to_string(&v).map_err(|e| {
-                                    map_node_error(CoreErrorCode::SerializationError.as_str(), e)
-                                })?)
-                            }
-                        })
-                    })
-                    .collect::<Result<Vec<_>>>()?
+                    .map(SqlValue::from_json)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(map_database_error)?
             } else {
                 vec![]
             };
 
-            let result = db
-                .query(&sql, &sql_params)
-                .map_err(|e| map_node_error(CoreErrorCode::SqliteError.as_str(), e))?;
+            let result = db.query(&sql, &sql_params).map_err(map_database_error)?;
 
             return Ok(serde_json::json!({
                 "columns": result.columns,
@@ -628,7 +736,8 @@ impl PluresDatabase {
         #[cfg(not(feature = "sqlite-compat"))]
         {
             let _ = (sql, params);
-            Err(node_error(
+            Err(typed_error(
+                ErrorKind::Unsupported,
                 CoreErrorCode::FeatureDisabled.as_str(),
                 "SQL queries require the 'sqlite-compat' cargo feature to be enabled".to_string(),
             ))
@@ -637,22 +746,22 @@ impl PluresDatabase {
 
     /// Execute SQL statement (INSERT, UPDATE, DELETE)
     ///
-    /// Requires the `sqlite-compat` cargo feature to be enabled.
+    /// Requires the `sqlite-compat` cargo feature to be enabled. Errors carry
+    /// `err.code` as for [`query`](Self::query).
     #[napi]
-    pub fn exec(&self, sql: String) -> Result<serde_json::Value> {
+    pub fn exec(&self, sql: String) -> Result<serde_json::Value, ErrorKind> {
         #[cfg(feature = "sqlite-compat")]
         {
             let db = self.db.as_ref().ok_or_else(|| {
-                node_error(
+                typed_error(
+                    ErrorKind::InvalidInput,
                     CoreErrorCode::InvalidInput.as_str(),
                     "SQL execution requires a database (provide db_path in constructor)"
                         .to_string(),
                 )
             })?;
 
-            let result = db
-                .exec(&sql)
-                .map_err(|e| map_node_error(CoreErrorCode::SqliteError.as_str(), e))?;
+            let result = db.exec(&sql).map_err(map_database_error)?;
 
             return Ok(serde_json::json!({
                 "changes": result.changes,
@@ -663,7 +772,8 @@ impl PluresDatabase {
         #[cfg(not(feature = "sqlite-compat"))]
         {
             let _ = sql;
-            Err(node_error(
+            Err(typed_error(
+                ErrorKind::Unsupported,
                 CoreErrorCode::FeatureDisabled.as_str(),
                 "SQL execution requires the 'sqlite-compat' cargo feature to be enabled"
                     .to_string(),
@@ -793,15 +903,17 @@ impl PluresDatabase {
         id: String,
         data: serde_json::Value,
         embedding: Vec<f64>,
-    ) -> Result<String> {
+    ) -> Result<String, ErrorKind> {
         if embedding.is_empty() {
-            return Err(node_error(
+            return Err(typed_error(
+                ErrorKind::InvalidInput,
                 CoreErrorCode::InvalidInput.as_str(),
                 "embedding must not be empty",
             ));
         }
         if embedding.iter().any(|v| !v.is_finite()) {
-            return Err(node_error(
+            return Err(typed_error(
+                ErrorKind::InvalidInput,
                 CoreErrorCode::InvalidInput.as_str(),
                 "embedding contains non-finite values (NaN or Inf)",
             ));
@@ -813,16 +925,21 @@ impl PluresDatabase {
 
         let emb_f32: Vec<f32> = embedding.iter().map(|&v| v as f32).collect();
 
-        let node_id = {
+        let (node_id, event) = {
             let store = store.lock();
-            store.put_with_embedding(id, actor_id, data, emb_f32)
+            let node_id = store
+                .try_put_with_embedding(id, actor_id, data, emb_f32)
+                .map_err(map_store_error)?;
+            (node_id.clone(), SyncEvent::upserted(&store, node_id))
         };
 
-        broadcaster
-            .publish(SyncEvent::NodeUpsert {
-                id: node_id.clone(),
-            })
-            .map_err(|e| map_node_error(SyncErrorCode::BroadcastPublishFailed.as_str(), e))?;
+        broadcaster.publish(event).map_err(|e| {
+            typed_error(
+                ErrorKind::Internal,
+                SyncErrorCode::BroadcastPublishFailed.as_str(),
+                e.to_string(),
+            )
+        })?;
 
         Ok(node_id)
     }
@@ -890,6 +1007,58 @@ impl PluresDatabase {
         Ok(id)
     }
 
+    /// Call `callback` with `{ id, op }` whenever a node is upserted or deleted.
+    ///
+    /// Like [`subscribe`][PluresDatabase::subscribe], but peer lifecycle
+    /// events are left out. Each call registers an independent listener on
+    /// its own thread, so any number can be attached; the returned id stops
+    /// one via [`unsubscribe`][PluresDatabase::unsubscribe].
+    #[napi]
+    pub fn on_change(
+        &self,
+        callback: ThreadsafeFunction<ChangeEventJs, (), ChangeEventJs, Status, false>,
+    ) -> Result<u32> {
+        let id = self.next_sub_id.fetch_add(1, Ordering::SeqCst);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.subscriptions.lock().insert(id, cancelled.clone());
+
+        let mut receiver = self.broadcaster.subscribe();
+        let subscriptions = self.subscriptions.clone();
+
+        std::thread::Builder::new()
+            .name(format!("pluresdb-change-{id}"))
+            .spawn(move || {
+                loop {
+                    match receiver.blocking_recv() {
+                        Ok(event) => {
+                            if cancelled.load(Ordering::SeqCst) {
+                                break;
+                            }
+                            let Some(payload) = ChangeEventJs::from_event(event) else {
+                                continue;
+                            };
+                            let status =
+                                callback.call(payload, ThreadsafeFunctionCallMode::NonBlocking);
+                            if status == Status::Closing {
+                                break;
+                            }
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                            if cancelled.load(Ordering::SeqCst) {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                subscriptions.lock().remove(&id);
+            })
+            .map_err(|e| node_error("NODE_ON_CHANGE_THREAD_SPAWN_FAILED", e.to_string()))?;
+
+        Ok(id)
+    }
+
     /// Stop a live subscription created by [`subscribe`][PluresDatabase::subscribe].
     ///
     /// Idempotent: unknown or already-removed ids are a no-op. After this
@@ -1046,6 +1215,20 @@ impl PluresDatabase {
         self.actor_id.clone()
     }
 
+    /// Release the on-disk storage opened from `db_path` so the same path can
+    /// be reopened, e.g. by a new instance in this process, without waiting
+    /// for garbage collection. Every write was already flushed when it was
+    /// made. Afterwards this instance is an empty in-memory database.
+    #[napi]
+    pub fn close(&mut self) {
+        *self.store.lock() = CrdtStore::default();
+        self.storage = None;
+        #[cfg(feature = "sqlite-compat")]
+        {
+            self.db = None;
+        }
+    }
+
     /// Execute a DSL query string against the CRDT store.
     ///
     /// Returns the procedure result as a JSON object with `nodes`, and
@@ -1250,7 +1433,9 @@ impl PluresDatabase {
                 .get(&constraint_id)
                 .and_then(|r| constraint_from_node_data(&r.data));
             if existing.is_some() {
-                store.delete(&constraint_id).map_err(map_store_error)?;
+                store
+                    .delete(&constraint_id)
+                    .map_err(|e| map_node_error(e.code().as_str(), e))?;
             }
             existing
         };
@@ -1400,16 +1585,8 @@ impl PluresDatabase {
         let store = self.store.clone();
         let store = store.lock();
 
-        let mut total_nodes = 0u64;
-        let mut type_counts: HashMap<String, u32> = HashMap::new();
-
-        store.for_each_sync(&mut |record| {
-            total_nodes += 1;
-            if let Some(t) = record.data.get("type").and_then(|v| v.as_str()) {
-                *type_counts.entry(t.to_string()).or_insert(0) += 1;
-            }
-            true
-        });
+        let type_counts = store.type_histogram();
+        let total_nodes: usize = type_counts.values().sum();
 
         Ok(serde_json::json!({
             "totalNodes": total_nodes,
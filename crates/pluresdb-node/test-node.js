@@ -227,6 +227,37 @@ async function test() {
   console.log('  ✓ execDsl invalid query throws');
   console.log('');
 
+  // Test 8: Typed errors
+  console.log('Test 8: Typed errors');
+  let deleteError = null;
+  try {
+    db.delete('definitely-missing-node');
+  } catch (e) {
+    deleteError = e;
+  }
+  if (!deleteError || deleteError.code !== 'NOT_FOUND') {
+    throw new Error('delete missing node: expected err.code NOT_FOUND, got ' + (deleteError && deleteError.code));
+  }
+  if (!deleteError.message.includes('definitely-missing-node')) {
+    throw new Error('delete missing node: message lost the node id: ' + deleteError.message);
+  }
+  console.log('  ✓ delete missing node throws code:', deleteError.code);
+  console.log('');
+
+  // Test 9: Content-addressed ids
+  console.log('Test 9: Content-addressed ids');
+  const before = db.list().length;
+  const firstId = db.putContentAddressed({ title: 'dedup me', tags: ['a', 'b'] });
+  const secondId = db.putContentAddressed({ tags: ['a', 'b'], title: 'dedup me' });
+  if (firstId !== secondId) {
+    throw new Error(`content-addressed ids differ: ${firstId} vs ${secondId}`);
+  }
+  if (db.list().length !== before + 1) {
+    throw new Error('identical content must be stored as a single node');
+  }
+  console.log('  ✓ identical payloads share id:', firstId);
+  console.log('');
+
   console.log('=== All tests passed! ===');
 }
 
@@ -276,6 +276,7 @@ mod tests {
             .map(|i| StoredNode {
                 id: format!("node-{i}"),
                 payload: serde_json::json!({"index": i, "name": format!("node-{i}")}),
+                meta: None,
             })
             .collect()
     }
@@ -196,6 +196,7 @@ mod tests {
             .map(|i| StoredNode {
                 id: format!("n{i}"),
                 payload: serde_json::json!({"i": i}),
+                meta: None,
             })
             .collect()
     }
@@ -0,0 +1,354 @@
+//! A [`StorageEngine`] decorator that keeps recently read nodes in memory.
+//!
+//! Reads are served from an LRU of decoded [`StoredNode`]s and fall back to
+//! the wrapped engine on a miss, caching what it returns.  Writes and
+//! deletes always reach the wrapped engine before they return; the cache
+//! then takes the written node (write-through, the default) or drops its
+//! copy.  Whole-store operations (`list`, `count`, iteration) go straight
+//! to the wrapped engine, which stays the source of truth.
+//!
+//! Writes made on the wrapped engine directly, not through the
+//! [`CachingStorage`], are not seen by the cache.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use crate::{StorageChange, StorageEngine, StorageMetrics, StoredNode};
+
+/// Hit/miss counters for a [`CachingStorage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads passed to the wrapped engine, whether or not it held the node.
+    pub misses: u64,
+    /// Nodes currently cached.
+    pub len: usize,
+}
+
+/// The least recently used nodes, up to a capacity.
+#[derive(Debug, Default)]
+struct Lru {
+    capacity: usize,
+    /// Each node with the tick it was last used at.
+    entries: HashMap<String, (StoredNode, u64)>,
+    /// Node ids by last use, oldest first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    /// Bumped by every write, so a read that raced one does not cache what
+    /// it read from before it.
+    epoch: u64,
+}
+
+impl Lru {
+    fn get(&mut self, id: &str) -> Option<StoredNode> {
+        self.tick += 1;
+        let (node, used) = self.entries.get_mut(id)?;
+        self.recency.remove(used);
+        *used = self.tick;
+        self.recency.insert(self.tick, id.to_string());
+        Some(node.clone())
+    }
+
+    fn insert(&mut self, node: StoredNode) {
+        self.tick += 1;
+        self.recency.insert(self.tick, node.id.clone());
+        if let Some((_, used)) = self.entries.insert(node.id.clone(), (node, self.tick)) {
+            self.recency.remove(&used);
+        }
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some((_, used)) = self.entries.remove(id) {
+            self.recency.remove(&used);
+        }
+    }
+}
+
+/// LRU read cache in front of any [`StorageEngine`]; see the
+/// [module docs](self).
+pub struct CachingStorage<E> {
+    inner: E,
+    cache: Mutex<Lru>,
+    write_through: bool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<E: StorageEngine> CachingStorage<E> {
+    /// Cache up to `capacity` nodes read from or written to `inner`.
+    pub fn new(inner: E, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(Lru {
+                capacity,
+                ..Lru::default()
+            }),
+            write_through: true,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether writes put the written node in the cache (the default) or
+    /// only evict the old one, leaving the next read to fetch it.  Turn
+    /// this off when written nodes are rarely read back.
+    pub fn with_write_through(mut self, write_through: bool) -> Self {
+        self.write_through = write_through;
+        self
+    }
+
+    /// The wrapped engine.  Writes made on it directly bypass the cache.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self.cache.lock().entries.len(),
+        }
+    }
+
+    /// Drop every cached node.
+    pub fn clear(&self) {
+        let mut cache = self.cache.lock();
+        cache.epoch += 1;
+        cache.entries.clear();
+        cache.recency.clear();
+    }
+
+    fn epoch(&self) -> u64 {
+        self.cache.lock().epoch
+    }
+
+    /// Bring the cache up to date with a write of `nodes` to `inner` that
+    /// started at `epoch` and succeeded if `applied`.
+    fn written(&self, nodes: impl IntoIterator<Item = StoredNode>, epoch: u64, applied: bool) {
+        let mut cache = self.cache.lock();
+        // A failed write may have reached `inner` in part, and another
+        // write finishing meanwhile may have reached it after this one, so
+        // in either case only evicting is safe
+        let cacheable = self.write_through && applied && cache.epoch == epoch;
+        cache.epoch += 1;
+        for node in nodes {
+            if cacheable {
+                cache.insert(node);
+            } else {
+                cache.remove(&node.id);
+            }
+        }
+    }
+
+    /// Forget nodes just deleted from `inner`, or that a failed delete may
+    /// have removed.
+    fn deleted<'a>(&self, ids: impl IntoIterator<Item = &'a str>) {
+        let mut cache = self.cache.lock();
+        cache.epoch += 1;
+        for id in ids {
+            cache.remove(id);
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + StorageEngine> std::fmt::Debug for CachingStorage<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachingStorage")
+            .field("inner", &self.inner)
+            .field("write_through", &self.write_through)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<E: StorageEngine> StorageEngine for CachingStorage<E> {
+    async fn put(&self, node: StoredNode) -> Result<()> {
+        let epoch = self.epoch();
+        let result = self.inner.put(node.clone()).await;
+        self.written([node], epoch, result.is_ok());
+        result
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<StoredNode>> {
+        let epoch = {
+            let mut cache = self.cache.lock();
+            if let Some(node) = cache.get(id) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(Some(node));
+            }
+            cache.epoch
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let node = self.inner.get(id).await?;
+        if let Some(node) = &node {
+            let mut cache = self.cache.lock();
+            if cache.epoch == epoch {
+                cache.insert(node.clone());
+            }
+        }
+        Ok(node)
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let result = self.inner.delete(id).await;
+        self.deleted([id]);
+        result
+    }
+
+    async fn list(&self) -> Result<Vec<StoredNode>> {
+        self.inner.list().await
+    }
+
+    async fn put_batch(&self, nodes: Vec<StoredNode>) -> Result<()> {
+        let epoch = self.epoch();
+        let result = self.inner.put_batch(nodes.clone()).await;
+        self.written(nodes, epoch, result.is_ok());
+        result
+    }
+
+    async fn delete_batch(&self, ids: Vec<String>) -> Result<()> {
+        let result = self.inner.delete_batch(ids.clone()).await;
+        self.deleted(ids.iter().map(String::as_str));
+        result
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.inner.count().await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        if self.cache.lock().entries.contains_key(id) {
+            return Ok(true);
+        }
+        self.inner.exists(id).await
+    }
+
+    async fn for_each(&self, f: &mut (dyn FnMut(StoredNode) -> bool + Send)) -> Result<()> {
+        self.inner.for_each(f).await
+    }
+
+    async fn for_each_by_prefix(
+        &self,
+        prefix: &str,
+        f: &mut (dyn FnMut(StoredNode) -> bool + Send),
+    ) -> Result<()> {
+        self.inner.for_each_by_prefix(prefix, f).await
+    }
+
+    async fn compact(&self) -> Result<u64> {
+        self.inner.compact().await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.inner.flush().await
+    }
+
+    fn changes(&self) -> Option<broadcast::Receiver<StorageChange>> {
+        self.inner.changes()
+    }
+
+    /// The wrapped engine's counts; cache hits never reach it, so they are
+    /// in [`stats`](CachingStorage::stats) instead.
+    fn metrics(&self) -> Option<StorageMetrics> {
+        self.inner.metrics()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStorage;
+
+    fn node(id: &str, n: i64) -> StoredNode {
+        StoredNode {
+            id: id.to_string(),
+            payload: serde_json::json!({ "n": n }),
+            meta: None,
+        }
+    }
+
+    /// Reads that reached the wrapped engine.
+    fn inner_reads(storage: &CachingStorage<MemoryStorage>) -> u64 {
+        let metrics = storage.inner().metrics().unwrap();
+        metrics.get_hits + metrics.get_misses
+    }
+
+    #[tokio::test]
+    async fn repeated_gets_are_served_from_the_cache() {
+        let inner = MemoryStorage::default();
+        StorageEngine::put(&inner, node("a", 1)).await.unwrap();
+        let storage = CachingStorage::new(inner, 8);
+
+        assert_eq!(storage.get("a").await.unwrap(), Some(node("a", 1)));
+        assert_eq!(inner_reads(&storage), 1);
+        assert_eq!(storage.get("a").await.unwrap(), Some(node("a", 1)));
+        assert_eq!(inner_reads(&storage), 1);
+        assert_eq!(
+            storage.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                len: 1
+            }
+        );
+
+        // Write-through: the new version is served without a read
+        storage.put(node("a", 2)).await.unwrap();
+        assert_eq!(storage.get("a").await.unwrap(), Some(node("a", 2)));
+        assert_eq!(inner_reads(&storage), 1);
+
+        storage.delete("a").await.unwrap();
+        assert_eq!(storage.get("a").await.unwrap(), None);
+        assert_eq!(inner_reads(&storage), 2);
+        // Absent nodes are not cached
+        assert_eq!(storage.get("a").await.unwrap(), None);
+        assert_eq!(inner_reads(&storage), 3);
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_nodes_are_evicted_first() {
+        let storage = CachingStorage::new(MemoryStorage::default(), 2);
+        storage
+            .put_batch(vec![node("a", 1), node("b", 2)])
+            .await
+            .unwrap();
+        // Touch "a" so "b" is the oldest when "c" arrives
+        storage.get("a").await.unwrap();
+        storage.put(node("c", 3)).await.unwrap();
+        assert_eq!(storage.stats().len, 2);
+        assert_eq!(inner_reads(&storage), 0);
+
+        storage.get("a").await.unwrap();
+        storage.get("c").await.unwrap();
+        assert_eq!(inner_reads(&storage), 0);
+        assert_eq!(storage.get("b").await.unwrap(), Some(node("b", 2)));
+        assert_eq!(inner_reads(&storage), 1);
+    }
+
+    #[tokio::test]
+    async fn without_write_through_writes_evict_instead() {
+        let storage = CachingStorage::new(MemoryStorage::default(), 8).with_write_through(false);
+        storage.put(node("a", 1)).await.unwrap();
+        assert_eq!(storage.stats().len, 0);
+        assert_eq!(storage.get("a").await.unwrap(), Some(node("a", 1)));
+        assert_eq!(storage.stats().len, 1);
+
+        storage.put(node("a", 2)).await.unwrap();
+        assert_eq!(storage.stats().len, 0);
+        assert_eq!(storage.get("a").await.unwrap(), Some(node("a", 2)));
+        assert_eq!(inner_reads(&storage), 2);
+    }
+}
@@ -336,6 +336,52 @@ impl EncryptionMetadata {
     }
 }
 
+/// An encrypted value as stored on disk: the scheme it was sealed with, its
+/// nonce, and the ciphertext.  The key itself is never recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct EncryptedRecord {
+    pub encryption: EncryptionMetadata,
+    /// Base64-encoded AES-GCM nonce
+    pub nonce: String,
+    /// Base64-encoded ciphertext, including the authentication tag
+    pub ciphertext: String,
+}
+
+impl EncryptedRecord {
+    /// Encrypts `plaintext` under `config`.
+    pub(crate) fn seal(config: &EncryptionConfig, plaintext: &[u8]) -> Result<Self> {
+        if !config.is_enabled() {
+            anyhow::bail!("cannot seal record: encryption is disabled");
+        }
+        let sealed = config.encrypt(plaintext)?;
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        Ok(Self {
+            encryption: EncryptionMetadata::from_config(config),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// Decrypts the record with `config`, failing cleanly if it holds a
+    /// different key.
+    pub(crate) fn open(&self, config: &EncryptionConfig) -> Result<Vec<u8>> {
+        if self.encryption.cipher != "aes-256-gcm" {
+            anyhow::bail!("unsupported cipher: {}", self.encryption.cipher);
+        }
+        let mut sealed = BASE64
+            .decode(&self.nonce)
+            .context("Failed to decode nonce")?;
+        sealed.extend(
+            BASE64
+                .decode(&self.ciphertext)
+                .context("Failed to decode ciphertext")?,
+        );
+        config
+            .decrypt(&sealed)
+            .context("failed to decrypt record: wrong encryption key or corrupted data")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
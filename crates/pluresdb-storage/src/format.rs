@@ -0,0 +1,189 @@
+//! On-disk encodings of [`StoredNode`] for [`SledStorage`](crate::SledStorage).
+//!
+//! A database records its format in a small metadata tree when it is created
+//! with [`SledStorage::open_with_format`](crate::SledStorage::open_with_format),
+//! so later opens pick the right decoder.  Databases without a marker predate
+//! formats and are JSON.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+use crate::StoredNode;
+
+/// Name of the sled tree holding database-wide settings such as the format.
+pub(crate) const METADATA_TREE: &str = "__pluresdb_metadata";
+/// Key of the format marker in [`METADATA_TREE`].
+pub(crate) const FORMAT_KEY: &str = "format";
+
+/// How [`SledStorage`](crate::SledStorage) encodes nodes on disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageFormat {
+    /// Human-readable `serde_json`; the format of databases that have no
+    /// marker.
+    #[default]
+    Json,
+    /// Compact `bincode`, smaller and faster to decode than JSON.
+    Bincode,
+}
+
+impl StorageFormat {
+    /// The name stored in the format marker.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Bincode => "bincode",
+        }
+    }
+
+    /// Parse a format marker written by [`as_str`](Self::as_str).
+    pub(crate) fn from_marker(marker: &[u8]) -> Result<Self> {
+        match marker {
+            b"json" => Ok(Self::Json),
+            b"bincode" => Ok(Self::Bincode),
+            other => anyhow::bail!(
+                "unknown storage format marker '{}'",
+                String::from_utf8_lossy(other)
+            ),
+        }
+    }
+
+    pub(crate) fn encode(self, node: &StoredNode) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(node)?),
+            Self::Bincode => {
+                let wire = WireNode {
+                    id: node.id.clone(),
+                    payload: WireValue::from(&node.payload),
+                    meta: node.meta.as_ref().map(WireValue::from),
+                };
+                bincode::serde::encode_to_vec(&wire, bincode::config::standard())
+                    .context("failed to encode node as bincode")
+            }
+        }
+    }
+
+    pub(crate) fn decode(self, bytes: &[u8]) -> Result<StoredNode> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::Bincode => {
+                let (wire, _): (WireNode, usize) =
+                    bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+                        .context("failed to decode bincode node")?;
+                Ok(StoredNode {
+                    id: wire.id,
+                    payload: wire.payload.into(),
+                    meta: wire.meta.map(Value::from),
+                })
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StorageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// [`StoredNode`] as bincode sees it: every field always present.
+#[derive(Serialize, Deserialize)]
+struct WireNode {
+    id: String,
+    payload: WireValue,
+    meta: Option<WireValue>,
+}
+
+/// A JSON value in a shape bincode can decode, which `serde_json::Value`
+/// is not, since it needs a self-describing format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WireValue {
+    Null,
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    String(String),
+    Array(Vec<WireValue>),
+    Object(Vec<(String, WireValue)>),
+}
+
+impl From<&Value> for WireValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(b) => Self::Bool(*b),
+            Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(u), _) => Self::U64(u),
+                (None, Some(i)) => Self::I64(i),
+                _ => Self::F64(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => Self::String(s.clone()),
+            Value::Array(items) => Self::Array(items.iter().map(Self::from).collect()),
+            Value::Object(fields) => Self::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Self::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<WireValue> for Value {
+    fn from(value: WireValue) -> Self {
+        match value {
+            WireValue::Null => Value::Null,
+            WireValue::Bool(b) => Value::Bool(b),
+            WireValue::U64(u) => Value::Number(u.into()),
+            WireValue::I64(i) => Value::Number(i.into()),
+            WireValue::F64(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+            WireValue::String(s) => Value::String(s),
+            WireValue::Array(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            WireValue::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn both_formats_round_trip_every_kind_of_json_value() {
+        let node = StoredNode {
+            id: "n1".into(),
+            payload: json!({
+                "null": null,
+                "flag": true,
+                "count": 42,
+                "offset": -7,
+                "ratio": 0.25,
+                "name": "Alice",
+                "tags": ["a", 1, [false]],
+                "nested": { "deep": { "list": [] } },
+            }),
+            meta: Some(json!({ "source": "peer-b" })),
+        };
+        for format in [StorageFormat::Json, StorageFormat::Bincode] {
+            let bytes = format.encode(&node).unwrap();
+            assert_eq!(format.decode(&bytes).unwrap(), node, "{format}");
+            assert_eq!(
+                StorageFormat::from_marker(format.as_str().as_bytes()).unwrap(),
+                format
+            );
+        }
+        assert!(StorageFormat::from_marker(b"yaml").is_err());
+
+        let without_meta = StoredNode { meta: None, ..node };
+        let bytes = StorageFormat::Bincode.encode(&without_meta).unwrap();
+        assert_eq!(StorageFormat::Bincode.decode(&bytes).unwrap(), without_meta);
+    }
+}
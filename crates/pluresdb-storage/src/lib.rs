@@ -9,15 +9,26 @@ pub mod blob;
 #[cfg(feature = "native")]
 pub mod bridge;
 #[cfg(feature = "native")]
+pub mod cache;
+#[cfg(feature = "native")]
 pub mod encryption;
 #[cfg(feature = "native")]
+pub mod format;
+#[cfg(feature = "native")]
 pub mod rad;
 #[cfg(feature = "native")]
 pub mod replay;
 #[cfg(feature = "native")]
+pub mod testing;
+#[cfg(feature = "native")]
+pub mod tiered;
+#[cfg(feature = "native")]
 pub mod wal;
+#[cfg(feature = "native")]
+pub mod wal_backed;
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use anyhow::Result;
@@ -25,13 +36,23 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
+#[cfg(feature = "native")]
+use anyhow::Context;
 #[cfg(feature = "native")]
 use async_trait::async_trait;
 #[cfg(feature = "native")]
+use encryption::EncryptedRecord;
+#[cfg(feature = "native")]
 use sled::IVec;
 #[cfg(feature = "native")]
 use std::path::Path;
 #[cfg(feature = "native")]
+use std::sync::OnceLock;
+#[cfg(feature = "native")]
+use std::time::Duration;
+#[cfg(feature = "native")]
+use tokio::sync::broadcast;
+#[cfg(feature = "native")]
 use tracing::info;
 
 #[cfg(feature = "native")]
@@ -41,13 +62,27 @@ pub use bridge::{
     BlobObjectBridge, ChunkRef, Manifest, ObjectBridge, ObjectRestorer, SnapshotManager, WalFlusher,
 };
 #[cfg(feature = "native")]
+pub use cache::{CacheStats, CachingStorage};
+#[cfg(feature = "native")]
 pub use encryption::{EncryptionConfig, EncryptionMetadata};
 #[cfg(feature = "native")]
+pub use format::{StorageFormat, WireValue};
+#[cfg(feature = "native")]
 pub use rad::{RadAdapter, SledRadAdapter};
 #[cfg(feature = "native")]
-pub use replay::{metadata_pruning, rebuild_from_wal, replay_wal, ReplayStats};
+pub use replay::{
+    metadata_pruning, rebuild_from_wal, replay_wal, verify_consistency, ConsistencyReport,
+    ReplayStats,
+};
 #[cfg(feature = "native")]
-pub use wal::{DurabilityLevel, WalEntry, WalError, WalOperation, WalValidation, WriteAheadLog};
+pub use tiered::{TieredStats, TieredStorage};
+#[cfg(feature = "native")]
+pub use wal::{
+    DurabilityLevel, RecoveryPolicy, WalCompression, WalCursor, WalEntry, WalError, WalOperation,
+    WalValidation, WriteAheadLog,
+};
+#[cfg(feature = "native")]
+pub use wal_backed::WalBackedStorage;
 
 /// Stable, documented error codes emitted by `pluresdb-storage`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -57,6 +92,8 @@ pub enum StorageErrorCode {
     SerializationError,
     WalImplausibleEntrySize,
     WalTruncatedEntry,
+    WalCorruptionDetected,
+    WalChecksumMismatch,
 }
 
 impl StorageErrorCode {
@@ -67,6 +104,8 @@ impl StorageErrorCode {
             Self::SerializationError => "STORAGE_SERIALIZATION_ERROR",
             Self::WalImplausibleEntrySize => "STORAGE_WAL_IMPLAUSIBLE_ENTRY_SIZE",
             Self::WalTruncatedEntry => "STORAGE_WAL_TRUNCATED_ENTRY",
+            Self::WalCorruptionDetected => "STORAGE_WAL_CORRUPTION_DETECTED",
+            Self::WalChecksumMismatch => "STORAGE_WAL_CHECKSUM_MISMATCH",
         }
     }
 }
@@ -79,15 +118,90 @@ impl std::fmt::Display for StorageErrorCode {
 
 /// A node persisted by a storage engine.
 ///
-/// Wraps an arbitrary JSON `payload` under a stable string `id`.
+/// Wraps an arbitrary JSON `payload` under a stable string `id`, with optional
+/// `meta` kept alongside it for provenance, tags, and other bookkeeping that
+/// should not leak into user data.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct StoredNode {
     /// Stable, unique identifier for this node.
     pub id: String,
     /// Arbitrary JSON payload associated with the node.
     pub payload: serde_json::Value,
+    /// Optional metadata, e.g. which peer or source last wrote the node.
+    /// Read and written independently via `get_meta` / `set_meta`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+/// A mutation observed by a storage engine, as delivered by
+/// [`StorageEngine::changes`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageChange {
+    /// The node with this id was written.
+    Put(String),
+    /// The node with this id was removed.
+    Delete(String),
+}
+
+/// Cumulative operation counts for a storage engine, from
+/// [`StorageEngine::metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageMetrics {
+    /// Nodes written, counting every node of a batch.
+    pub puts: u64,
+    /// Reads that found the node.
+    pub get_hits: u64,
+    /// Reads for a node that is not stored.
+    pub get_misses: u64,
+    /// Deletes requested, counting every id of a batch, whether or not the
+    /// node existed.
+    pub deletes: u64,
+}
+
+/// The atomics behind [`StorageMetrics`], shared by every clone of an
+/// engine and bumped with relaxed ordering since they are only read as a
+/// snapshot.
+#[derive(Debug, Default)]
+struct StorageCounters {
+    puts: AtomicU64,
+    get_hits: AtomicU64,
+    get_misses: AtomicU64,
+    deletes: AtomicU64,
+}
+
+impl StorageCounters {
+    fn record_puts(&self, count: usize) {
+        self.puts.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    fn record_get<T>(&self, found: &Option<T>) {
+        let counter = match found {
+            Some(_) => &self.get_hits,
+            None => &self.get_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_deletes(&self, count: usize) {
+        self.deletes.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "native")]
+    fn snapshot(&self) -> StorageMetrics {
+        StorageMetrics {
+            puts: self.puts.load(Ordering::Relaxed),
+            get_hits: self.get_hits.load(Ordering::Relaxed),
+            get_misses: self.get_misses.load(Ordering::Relaxed),
+            deletes: self.deletes.load(Ordering::Relaxed),
+        }
+    }
 }
 
+/// Buffered changes per subscriber; a receiver that falls further behind
+/// gets [`broadcast::error::RecvError::Lagged`].
+#[cfg(feature = "native")]
+const CHANGE_CHANNEL_CAPACITY: usize = 1024;
+
 // ---------------------------------------------------------------------------
 // Synchronous storage trait (always available, WASM-safe)
 // ---------------------------------------------------------------------------
@@ -111,6 +225,11 @@ pub trait SyncStorageEngine: Send + Sync {
         Ok(self.list()?.len())
     }
 
+    /// Whether a node with `id` is stored, without decoding it.
+    fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.get(id)?.is_some())
+    }
+
     /// Iterate over nodes one at a time via callback, avoiding full
     /// materialization.  Return `false` from `f` to stop early.
     fn for_each(&self, f: &mut (dyn FnMut(StoredNode) -> bool + Send)) -> Result<()> {
@@ -137,6 +256,22 @@ pub trait SyncStorageEngine: Send + Sync {
             }
         })
     }
+
+    /// Return the metadata attached to `id`, or `None` if the node is absent
+    /// or carries no metadata.
+    fn get_meta(&self, id: &str) -> Result<Option<serde_json::Value>> {
+        Ok(self.get(id)?.and_then(|node| node.meta))
+    }
+
+    /// Replace the metadata on an existing node, leaving its payload untouched.
+    /// Passing `None` clears it.  Fails if no node with `id` exists.
+    fn set_meta(&self, id: &str, meta: Option<serde_json::Value>) -> Result<()> {
+        let Some(mut node) = self.get(id)? else {
+            anyhow::bail!("cannot set metadata: node '{}' not found", id);
+        };
+        node.meta = meta;
+        self.put(node)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -168,6 +303,11 @@ pub trait StorageEngine: Send + Sync {
         Ok(self.list().await?.len())
     }
 
+    /// Whether a node with `id` is stored, without decoding it.
+    async fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.get(id).await?.is_some())
+    }
+
     /// Iterate over nodes one at a time via callback, avoiding full
     /// materialization.  Return `false` from `f` to stop early.
     async fn for_each(&self, f: &mut (dyn FnMut(StoredNode) -> bool + Send)) -> Result<()> {
@@ -195,33 +335,140 @@ pub trait StorageEngine: Send + Sync {
         })
         .await
     }
+
+    /// Return the metadata attached to `id`, or `None` if the node is absent
+    /// or carries no metadata.
+    async fn get_meta(&self, id: &str) -> Result<Option<serde_json::Value>> {
+        Ok(self.get(id).await?.and_then(|node| node.meta))
+    }
+
+    /// Replace the metadata on an existing node, leaving its payload untouched.
+    /// Passing `None` clears it.  Fails if no node with `id` exists.
+    async fn set_meta(&self, id: &str, meta: Option<serde_json::Value>) -> Result<()> {
+        let Some(mut node) = self.get(id).await? else {
+            anyhow::bail!("cannot set metadata: node '{}' not found", id);
+        };
+        node.meta = meta;
+        self.put(node).await
+    }
+
+    /// Persist every node in `nodes`, as if by [`put`](Self::put) in order.
+    ///
+    /// The default calls `put` once per node and stops at the first error,
+    /// leaving the nodes before it written.  [`SledStorage`] instead applies
+    /// the whole batch atomically with a single flush, and [`MemoryStorage`]
+    /// takes its lock once, so readers see all of the batch or none of it.
+    async fn put_batch(&self, nodes: Vec<StoredNode>) -> Result<()> {
+        for node in nodes {
+            self.put(node).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove every node in `ids`, as if by [`delete`](Self::delete).
+    ///
+    /// Atomicity is as for [`put_batch`](Self::put_batch): the default
+    /// deletes one id at a time, while [`SledStorage`] and [`MemoryStorage`]
+    /// remove them all at once.
+    async fn delete_batch(&self, ids: Vec<String>) -> Result<()> {
+        for id in ids {
+            self.delete(&id).await?;
+        }
+        Ok(())
+    }
+
+    /// Reclaim space held by obsolete data and return the number of bytes
+    /// freed on disk.  Backends with nothing to reclaim return `Ok(0)`.
+    async fn compact(&self) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Make every completed write durable on disk.  Non-durable backends
+    /// return `Ok(())` immediately.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Subscribe to the puts and deletes this engine performs from now on,
+    /// whichever API they were made through.  Engines that cannot observe
+    /// their own mutations return `None`.
+    fn changes(&self) -> Option<broadcast::Receiver<StorageChange>> {
+        None
+    }
+
+    /// Operation counts since the engine was opened.  Engines that do not
+    /// keep counters return `None`.
+    fn metrics(&self) -> Option<StorageMetrics> {
+        None
+    }
 }
 
 /// A non-persistent storage backend useful for tests and in-memory deployments.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct MemoryStorage {
     inner: Arc<RwLock<HashMap<String, StoredNode>>>,
+    counters: Arc<StorageCounters>,
+    #[cfg(feature = "native")]
+    changes: broadcast::Sender<StorageChange>,
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self {
+            inner: Arc::default(),
+            counters: Arc::default(),
+            #[cfg(feature = "native")]
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl MemoryStorage {
+    #[cfg(feature = "native")]
+    fn notify(&self, change: StorageChange) {
+        // Sending fails only when nobody is subscribed
+        let _ = self.changes.send(change);
+    }
+
+    #[cfg(not(feature = "native"))]
+    fn notify(&self, _change: StorageChange) {}
 }
 
 impl SyncStorageEngine for MemoryStorage {
     #[instrument(skip(self, node))]
     fn put(&self, node: StoredNode) -> Result<()> {
+        let id = node.id.clone();
         self.inner.write().insert(node.id.clone(), node);
+        self.counters.record_puts(1);
+        self.notify(StorageChange::Put(id));
         Ok(())
     }
 
     fn get(&self, id: &str) -> Result<Option<StoredNode>> {
-        Ok(self.inner.read().get(id).cloned())
+        let node = self.inner.read().get(id).cloned();
+        self.counters.record_get(&node);
+        Ok(node)
     }
 
     fn delete(&self, id: &str) -> Result<()> {
-        self.inner.write().remove(id);
+        self.counters.record_deletes(1);
+        if self.inner.write().remove(id).is_some() {
+            self.notify(StorageChange::Delete(id.to_string()));
+        }
         Ok(())
     }
 
     fn list(&self) -> Result<Vec<StoredNode>> {
         Ok(self.inner.read().values().cloned().collect())
     }
+
+    fn count(&self) -> Result<usize> {
+        Ok(self.inner.read().len())
+    }
+
+    fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.inner.read().contains_key(id))
+    }
 }
 
 #[cfg(feature = "native")]
@@ -243,27 +490,339 @@ impl StorageEngine for MemoryStorage {
     async fn list(&self) -> Result<Vec<StoredNode>> {
         SyncStorageEngine::list(self)
     }
+
+    async fn count(&self) -> Result<usize> {
+        SyncStorageEngine::count(self)
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        SyncStorageEngine::exists(self, id)
+    }
+
+    /// Inserts every node under one write lock.
+    async fn put_batch(&self, nodes: Vec<StoredNode>) -> Result<()> {
+        let ids: Vec<String> = nodes.iter().map(|node| node.id.clone()).collect();
+        self.counters.record_puts(ids.len());
+        {
+            let mut inner = self.inner.write();
+            for node in nodes {
+                inner.insert(node.id.clone(), node);
+            }
+        }
+        for id in ids {
+            self.notify(StorageChange::Put(id));
+        }
+        Ok(())
+    }
+
+    /// Removes every id under one write lock.
+    async fn delete_batch(&self, ids: Vec<String>) -> Result<()> {
+        self.counters.record_deletes(ids.len());
+        let removed: Vec<String> = {
+            let mut inner = self.inner.write();
+            ids.into_iter()
+                .filter(|id| inner.remove(id).is_some())
+                .collect()
+        };
+        for id in removed {
+            self.notify(StorageChange::Delete(id));
+        }
+        Ok(())
+    }
+
+    fn changes(&self) -> Option<broadcast::Receiver<StorageChange>> {
+        Some(self.changes.subscribe())
+    }
+
+    fn metrics(&self) -> Option<StorageMetrics> {
+        Some(self.counters.snapshot())
+    }
+}
+
+/// When [`SledStorage`] makes writes durable.
+///
+/// Until sled flushes, a write is visible to readers but lives only in
+/// memory: if the process or machine crashes, every write since the last
+/// flush is lost, though the database still opens in a consistent state.
+/// The deferred policies trade that window for throughput on bulk loads;
+/// call [`StorageEngine::flush`] at the points the data must survive.
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Flush after every put and delete, and once per batch, before it
+    /// returns.  An acknowledged write always survives a crash.
+    #[default]
+    EveryWrite,
+    /// Only flush when asked to.  A crash loses everything written since
+    /// the last explicit flush.
+    Manual,
+    /// Flush in the background at this interval.  A crash loses at most
+    /// the writes of the last interval.
+    Interval(Duration),
 }
 
 /// Durable storage based on the sled embedded database.
+///
+/// Opened with [`SledStorage::open_with_wal`], every async `put` and `delete`
+/// is appended to a [`WriteAheadLog`] before it reaches sled, so a write that
+/// was logged but never committed is replayed on the next open.  Opened with
+/// [`SledStorage::open_encrypted`], every node is encrypted before it is
+/// written.  Nodes are encoded as JSON unless the database was created with
+/// [`SledStorage::open_with_format`].  Each write is flushed to disk before
+/// it returns unless the database was opened with a deferred
+/// [`FlushPolicy`].
 #[cfg(feature = "native")]
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SledStorage {
     db: sled::Db,
+    flush_policy: FlushPolicy,
+    wal: Option<Arc<WriteAheadLog>>,
+    encryption: Option<EncryptionConfig>,
+    format: StorageFormat,
+    counters: Arc<StorageCounters>,
+    changes: broadcast::Sender<StorageChange>,
+    /// Set once the thread forwarding sled's watch events to `changes` runs.
+    watcher: Arc<OnceLock<()>>,
+    /// Held shared by each write from its WAL append until it is in sled,
+    /// and exclusively by [`checkpoint`](Self::checkpoint), so no write
+    /// logged before a checkpoint can miss the flush it relies on.
+    checkpoint_gate: Arc<tokio::sync::RwLock<()>>,
+}
+
+#[cfg(feature = "native")]
+impl std::fmt::Debug for SledStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The encryption config holds the key, so only report whether it is set.
+        f.debug_struct("SledStorage")
+            .field("db", &self.db)
+            .field("wal", &self.wal)
+            .field("encrypted", &self.encryption.is_some())
+            .field("format", &self.format)
+            .field("flush_policy", &self.flush_policy)
+            .finish()
+    }
 }
 
 #[cfg(feature = "native")]
 impl SledStorage {
     const DEFAULT_CACHE_CAPACITY_BYTES: u64 = 256 * 1024 * 1024;
+    const WAL_SEGMENT_SIZE: u64 = 64 * 1024 * 1024;
+    const WAL_ACTOR: &'static str = "sled-storage";
 
     /// Open (or create) a sled database at `path`.
+    ///
+    /// Nodes are decoded in the [`StorageFormat`] the database was created
+    /// with; a new database, or one without a format marker, uses JSON.
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        info!(path = %path.as_ref().display(), "opening sled storage");
-        let db = sled::Config::default()
+        Self::open_with_flush_policy(path, FlushPolicy::EveryWrite)
+    }
+
+    /// Open (or create) a sled database at `path` that flushes writes as
+    /// `policy` says; see [`FlushPolicy`] for what a crash may lose.
+    pub fn open_with_flush_policy(path: impl AsRef<Path>, policy: FlushPolicy) -> Result<Self> {
+        info!(path = %path.as_ref().display(), ?policy, "opening sled storage");
+        let config = sled::Config::default()
             .path(path)
-            .cache_capacity(Self::DEFAULT_CACHE_CAPACITY_BYTES)
-            .open()?;
-        Ok(Self { db })
+            .cache_capacity(Self::DEFAULT_CACHE_CAPACITY_BYTES);
+        // Sled's own background flush is left at its default unless the
+        // policy replaces it
+        let config = match policy {
+            FlushPolicy::EveryWrite => config,
+            FlushPolicy::Manual => config.flush_every_ms(None),
+            FlushPolicy::Interval(interval) => {
+                config.flush_every_ms(Some(interval.as_millis().max(1) as u64))
+            }
+        };
+        let db = config.open()?;
+        let format = match Self::format_marker(&db)? {
+            Some(marker) => StorageFormat::from_marker(&marker)?,
+            None => StorageFormat::Json,
+        };
+        Ok(Self {
+            db,
+            flush_policy: policy,
+            wal: None,
+            encryption: None,
+            format,
+            counters: Arc::default(),
+            changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+            watcher: Arc::default(),
+            checkpoint_gate: Arc::default(),
+        })
+    }
+
+    /// Open (or create) a sled database at `path` that encodes nodes as
+    /// `format`, recording the choice so [`open`](Self::open) decodes them
+    /// correctly later.
+    ///
+    /// Fails if the database was created with another format, or if it holds
+    /// nodes from before format markers, which are JSON, and `format` is not.
+    pub fn open_with_format(path: impl AsRef<Path>, format: StorageFormat) -> Result<Self> {
+        let storage = Self::open(path)?;
+        if Self::format_marker(&storage.db)?.is_none() {
+            if format != StorageFormat::Json && !storage.db.is_empty() {
+                anyhow::bail!(
+                    "cannot open sled storage as {}: it already holds JSON nodes",
+                    format
+                );
+            }
+            storage
+                .db
+                .open_tree(format::METADATA_TREE)?
+                .insert(format::FORMAT_KEY, format.as_str())?;
+            storage.db.flush()?;
+            return Ok(Self { format, ..storage });
+        }
+        if storage.format != format {
+            anyhow::bail!(
+                "cannot open sled storage as {}: it is stored as {}",
+                format,
+                storage.format
+            );
+        }
+        Ok(storage)
+    }
+
+    fn format_marker(db: &sled::Db) -> Result<Option<IVec>> {
+        Ok(db
+            .open_tree(format::METADATA_TREE)?
+            .get(format::FORMAT_KEY)?)
+    }
+
+    /// The encoding nodes are stored in.
+    pub fn format(&self) -> StorageFormat {
+        self.format
+    }
+
+    /// When writes are flushed to disk.
+    pub fn flush_policy(&self) -> FlushPolicy {
+        self.flush_policy
+    }
+
+    /// Flush after a write if the [`FlushPolicy`] says to, or the WAL is
+    /// at [`DurabilityLevel::Full`].  Called once the write is in sled, so
+    /// the flush covers it.
+    fn flush_write(&self) -> Result<()> {
+        if self.flushes_every_write() {
+            self.db.flush()?;
+        }
+        Ok(())
+    }
+
+    async fn flush_write_async(&self) -> Result<()> {
+        if self.flushes_every_write() {
+            self.db.flush_async().await?;
+        }
+        Ok(())
+    }
+
+    fn flushes_every_write(&self) -> bool {
+        self.flush_policy == FlushPolicy::EveryWrite
+            || self
+                .wal
+                .as_ref()
+                .is_some_and(|wal| wal.durability() == DurabilityLevel::Full)
+    }
+
+    /// Open a sled database at `path` that encrypts every node with `config`.
+    ///
+    /// Each value on disk holds the ciphertext together with its nonce and
+    /// [`EncryptionMetadata`]; the key never leaves memory.  Reading nodes
+    /// written under a different key fails with a decryption error.
+    pub fn open_encrypted(path: impl AsRef<Path>, config: EncryptionConfig) -> Result<Self> {
+        if !config.is_enabled() {
+            anyhow::bail!("cannot open encrypted sled storage: encryption is disabled");
+        }
+        let mut storage = Self::open(path)?;
+        storage.encryption = Some(config);
+        Ok(storage)
+    }
+
+    /// Open a sled database at `path` whose writes are logged to a WAL in
+    /// `wal_dir` first.
+    ///
+    /// Entries logged after the last [`checkpoint`](Self::checkpoint) are
+    /// replayed into sled before returning, recovering writes that were
+    /// interrupted between the WAL append and the sled commit.
+    pub async fn open_with_wal(
+        path: impl AsRef<Path>,
+        wal_dir: impl AsRef<Path>,
+        durability: DurabilityLevel,
+    ) -> Result<Self> {
+        let mut storage = Self::open(path)?;
+        let wal = WriteAheadLog::open_with_options(wal_dir, durability, Self::WAL_SEGMENT_SIZE)?;
+        let replayed = storage.replay_since_checkpoint(&wal).await?;
+        info!(replayed, "replayed uncheckpointed WAL entries into sled");
+        storage.wal = Some(Arc::new(wal));
+        Ok(storage)
+    }
+
+    /// Flush sled, record a [`WalOperation::Checkpoint`], and drop the WAL
+    /// segments that only hold entries before it.  Returns the checkpoint's
+    /// base sequence; later opens replay only entries from there on.
+    ///
+    /// Waits for writes already logged to reach sled, and holds back new
+    /// ones until the checkpoint is recorded.
+    pub async fn checkpoint(&self) -> Result<u64> {
+        let Some(wal) = &self.wal else {
+            anyhow::bail!("cannot checkpoint: sled storage was opened without a WAL");
+        };
+        let _checkpointing = self.checkpoint_gate.write().await;
+        self.db.flush_async().await?;
+        let base_seq = wal.next_sequence();
+        let operation = WalOperation::Checkpoint { base_seq };
+        wal.append(Self::WAL_ACTOR.to_string(), operation).await?;
+        wal.compact(base_seq).await?;
+        Ok(base_seq)
+    }
+
+    /// Re-apply every `Put` and `Delete` at or after the last checkpoint.
+    /// Applying an entry sled already holds is harmless, so the whole tail
+    /// is replayed rather than working out where sled stopped.
+    async fn replay_since_checkpoint(&self, wal: &WriteAheadLog) -> Result<usize> {
+        let entries = wal.read_all().await?;
+        let start = entries
+            .iter()
+            .rev()
+            .find_map(|entry| match entry.operation {
+                WalOperation::Checkpoint { base_seq } => Some(base_seq),
+                _ => None,
+            })
+            .unwrap_or(0);
+        let mut replayed = 0;
+        for entry in entries {
+            if entry.seq < start || !entry.validate_checksum() {
+                continue;
+            }
+            match entry.operation {
+                WalOperation::Put { id, data } => {
+                    let meta = SyncStorageEngine::get_meta(self, &id)?;
+                    let node = StoredNode {
+                        id,
+                        payload: data,
+                        meta,
+                    };
+                    let bytes = self.serialize(&node)?;
+                    self.db.insert(node.id.as_bytes(), bytes)?;
+                }
+                WalOperation::Delete { id } => {
+                    self.db.remove(id.as_bytes())?;
+                }
+                _ => continue,
+            }
+            replayed += 1;
+        }
+        self.db.flush_async().await?;
+        Ok(replayed)
+    }
+
+    /// Writes that cannot await the WAL would bypass it and be lost to
+    /// replay, so they are refused on WAL-backed storage.
+    fn ensure_no_wal(&self) -> Result<()> {
+        if self.wal.is_some() {
+            anyhow::bail!("sled storage opened with a WAL only accepts writes via StorageEngine");
+        }
+        Ok(())
     }
 
     /// Access the underlying sled database for advanced operations.
@@ -271,12 +830,44 @@ impl SledStorage {
         &self.db
     }
 
-    fn serialize(node: &StoredNode) -> Result<Vec<u8>> {
-        Ok(serde_json::to_vec(node)?)
+    /// Return the stored bytes for `id` without deserializing them.
+    ///
+    /// The bytes are in this backend's on-disk encoding of a [`StoredNode`]
+    /// (see [`format`](Self::format)) and are only meaningful to another
+    /// `SledStorage` with the same format, holding the same key if this one
+    /// is encrypted.  Useful for forwarding nodes in proxy and sync paths
+    /// without a deserialize/serialize round-trip.
+    pub fn get_raw(&self, id: &str) -> Result<Option<IVec>> {
+        Ok(self.db.get(id.as_bytes())?)
     }
 
-    fn deserialize(bytes: IVec) -> Result<StoredNode> {
-        Ok(serde_json::from_slice(&bytes)?)
+    /// Store bytes previously obtained from [`get_raw`](Self::get_raw) under `id`.
+    ///
+    /// The bytes are written as-is; passing anything other than this backend's
+    /// encoding of a `StoredNode` with the same `id` makes later reads fail.
+    /// Fails on storage opened with [`open_with_wal`](Self::open_with_wal).
+    pub fn put_raw(&self, id: &str, bytes: impl Into<IVec>) -> Result<()> {
+        self.ensure_no_wal()?;
+        self.db.insert(id.as_bytes(), bytes.into())?;
+        self.flush_write()?;
+        Ok(())
+    }
+
+    fn serialize(&self, node: &StoredNode) -> Result<Vec<u8>> {
+        let bytes = self.format.encode(node)?;
+        match &self.encryption {
+            Some(config) => Ok(serde_json::to_vec(&EncryptedRecord::seal(config, &bytes)?)?),
+            None => Ok(bytes),
+        }
+    }
+
+    fn deserialize(&self, bytes: IVec) -> Result<StoredNode> {
+        let Some(config) = &self.encryption else {
+            return self.format.decode(&bytes);
+        };
+        let record: EncryptedRecord =
+            serde_json::from_slice(&bytes).context("stored node is not encrypted")?;
+        self.format.decode(&record.open(config)?)
     }
 }
 
@@ -284,22 +875,78 @@ impl SledStorage {
 #[async_trait]
 impl StorageEngine for SledStorage {
     async fn put(&self, node: StoredNode) -> Result<()> {
-        let bytes = Self::serialize(&node)?;
+        let bytes = self.serialize(&node)?;
+        let _writing = self.checkpoint_gate.read().await;
+        if let Some(wal) = &self.wal {
+            let operation = WalOperation::Put {
+                id: node.id.clone(),
+                data: node.payload.clone(),
+            };
+            wal.append(Self::WAL_ACTOR.to_string(), operation).await?;
+        }
         self.db.insert(node.id.as_bytes(), bytes)?;
-        self.db.flush()?;
+        self.flush_write()?;
+        self.counters.record_puts(1);
         Ok(())
     }
 
     async fn get(&self, id: &str) -> Result<Option<StoredNode>> {
-        match self.db.get(id.as_bytes())? {
-            Some(bytes) => Ok(Some(Self::deserialize(bytes)?)),
-            None => Ok(None),
-        }
+        SyncStorageEngine::get(self, id)
     }
 
     async fn delete(&self, id: &str) -> Result<()> {
+        self.counters.record_deletes(1);
+        let _writing = self.checkpoint_gate.read().await;
+        if let Some(wal) = &self.wal {
+            let operation = WalOperation::Delete { id: id.to_string() };
+            wal.append(Self::WAL_ACTOR.to_string(), operation).await?;
+        }
         self.db.remove(id.as_bytes())?;
-        self.db.flush()?;
+        self.flush_write()?;
+        Ok(())
+    }
+
+    /// Applies the nodes as one sled batch, so either all of them or none
+    /// are written, and flushes once unless the [`FlushPolicy`] defers it.
+    /// With a WAL, every put is logged before the batch is applied.
+    async fn put_batch(&self, nodes: Vec<StoredNode>) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        for node in &nodes {
+            batch.insert(node.id.as_bytes(), self.serialize(node)?);
+        }
+        let _writing = self.checkpoint_gate.read().await;
+        if let Some(wal) = &self.wal {
+            for node in &nodes {
+                let operation = WalOperation::Put {
+                    id: node.id.clone(),
+                    data: node.payload.clone(),
+                };
+                wal.append(Self::WAL_ACTOR.to_string(), operation).await?;
+            }
+        }
+        self.db.apply_batch(batch)?;
+        self.flush_write_async().await?;
+        self.counters.record_puts(nodes.len());
+        Ok(())
+    }
+
+    /// Removes the ids as one sled batch with a single flush, logging each
+    /// delete to the WAL first if there is one.
+    async fn delete_batch(&self, ids: Vec<String>) -> Result<()> {
+        self.counters.record_deletes(ids.len());
+        let mut batch = sled::Batch::default();
+        for id in &ids {
+            batch.remove(id.as_bytes());
+        }
+        let _writing = self.checkpoint_gate.read().await;
+        if let Some(wal) = &self.wal {
+            for id in ids {
+                let operation = WalOperation::Delete { id };
+                wal.append(Self::WAL_ACTOR.to_string(), operation).await?;
+            }
+        }
+        self.db.apply_batch(batch)?;
+        self.flush_write_async().await?;
         Ok(())
     }
 
@@ -307,31 +954,108 @@ impl StorageEngine for SledStorage {
         let mut out = Vec::new();
         for entry in self.db.iter() {
             let (_, value) = entry?;
-            out.push(Self::deserialize(value)?);
+            out.push(self.deserialize(value)?);
         }
         Ok(out)
     }
+
+    /// The length of sled's node tree; nothing is read or decoded.
+    async fn count(&self) -> Result<usize> {
+        SyncStorageEngine::count(self)
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        SyncStorageEngine::exists(self, id)
+    }
+
+    /// Flush dirty pages so sled's segment cleaner can rewrite and release
+    /// segments made obsolete by overwrites and deletes.
+    async fn compact(&self) -> Result<u64> {
+        let before = self.db.size_on_disk()?;
+        self.db.flush_async().await?;
+        let after = self.db.size_on_disk()?;
+        Ok(before.saturating_sub(after))
+    }
+
+    /// Under a deferred [`FlushPolicy`], the point after which earlier
+    /// writes survive a crash.
+    async fn flush(&self) -> Result<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+
+    /// Bridges sled's `watch_prefix` events, so writes made through any
+    /// handle on this database are reported, including raw and sync ones.
+    /// The forwarding thread starts on the first call and stops when the
+    /// database is dropped.
+    fn changes(&self) -> Option<broadcast::Receiver<StorageChange>> {
+        let receiver = self.changes.subscribe();
+        self.watcher.get_or_init(|| {
+            let subscriber = self.db.watch_prefix(Vec::<u8>::new());
+            let sender = self.changes.clone();
+            let spawned = std::thread::Builder::new()
+                .name("pluresdb-sled-watch".into())
+                .spawn(move || {
+                    for event in subscriber {
+                        let change = match event {
+                            sled::Event::Insert { key, .. } => {
+                                StorageChange::Put(String::from_utf8_lossy(&key).into_owned())
+                            }
+                            sled::Event::Remove { key } => {
+                                StorageChange::Delete(String::from_utf8_lossy(&key).into_owned())
+                            }
+                        };
+                        let _ = sender.send(change);
+                    }
+                });
+            if let Err(e) = spawned {
+                tracing::warn!("failed to start sled change watcher: {}", e);
+            }
+        });
+        Some(receiver)
+    }
+
+    /// Range scan over the keys starting with `prefix`, in key order.
+    async fn for_each_by_prefix(
+        &self,
+        prefix: &str,
+        f: &mut (dyn FnMut(StoredNode) -> bool + Send),
+    ) -> Result<()> {
+        SyncStorageEngine::for_each_by_prefix(self, prefix, f)
+    }
+
+    /// Counts calls made through this handle and its clones; writes made
+    /// directly on [`db`](SledStorage::db) or via `put_raw` are not seen.
+    fn metrics(&self) -> Option<StorageMetrics> {
+        Some(self.counters.snapshot())
+    }
 }
 
 #[cfg(feature = "native")]
 impl SyncStorageEngine for SledStorage {
     fn put(&self, node: StoredNode) -> Result<()> {
-        let bytes = Self::serialize(&node)?;
+        self.ensure_no_wal()?;
+        let bytes = self.serialize(&node)?;
         self.db.insert(node.id.as_bytes(), bytes)?;
-        self.db.flush()?;
+        self.flush_write()?;
+        self.counters.record_puts(1);
         Ok(())
     }
 
     fn get(&self, id: &str) -> Result<Option<StoredNode>> {
-        match self.db.get(id.as_bytes())? {
-            Some(bytes) => Ok(Some(Self::deserialize(bytes)?)),
-            None => Ok(None),
-        }
+        let node = match self.db.get(id.as_bytes())? {
+            Some(bytes) => Some(self.deserialize(bytes)?),
+            None => None,
+        };
+        self.counters.record_get(&node);
+        Ok(node)
     }
 
     fn delete(&self, id: &str) -> Result<()> {
+        self.ensure_no_wal()?;
+        self.counters.record_deletes(1);
         self.db.remove(id.as_bytes())?;
-        self.db.flush()?;
+        self.flush_write()?;
         Ok(())
     }
 
@@ -339,7 +1063,7 @@ impl SyncStorageEngine for SledStorage {
         let mut out = Vec::new();
         for entry in self.db.iter() {
             let (_, value) = entry?;
-            out.push(Self::deserialize(value)?);
+            out.push(self.deserialize(value)?);
         }
         Ok(out)
     }
@@ -348,10 +1072,14 @@ impl SyncStorageEngine for SledStorage {
         Ok(self.db.len())
     }
 
+    fn exists(&self, id: &str) -> Result<bool> {
+        Ok(self.db.contains_key(id.as_bytes())?)
+    }
+
     fn for_each(&self, f: &mut (dyn FnMut(StoredNode) -> bool + Send)) -> Result<()> {
         for entry in self.db.iter() {
             let (_, value) = entry?;
-            let node = Self::deserialize(value)?;
+            let node = self.deserialize(value)?;
             if !f(node) {
                 break;
             }
@@ -366,7 +1094,7 @@ impl SyncStorageEngine for SledStorage {
     ) -> Result<()> {
         for entry in self.db.scan_prefix(prefix.as_bytes()) {
             let (_, value) = entry?;
-            let node = Self::deserialize(value)?;
+            let node = self.deserialize(value)?;
             if !f(node) {
                 break;
             }
@@ -385,6 +1113,7 @@ mod tests {
         let node = StoredNode {
             id: "1".to_string(),
             payload: serde_json::json!({"name": "plures"}),
+            meta: None,
         };
         SyncStorageEngine::put(&storage, node.clone()).unwrap();
         let fetched = SyncStorageEngine::get(&storage, "1").unwrap().unwrap();
@@ -400,6 +1129,7 @@ mod tests {
         let node = StoredNode {
             id: "1".to_string(),
             payload: serde_json::json!({"name": "plures"}),
+            meta: None,
         };
         StorageEngine::put(&storage, node.clone()).await.unwrap();
         let fetched = StorageEngine::get(&storage, "1").await.unwrap().unwrap();
@@ -408,6 +1138,109 @@ mod tests {
         assert!(StorageEngine::get(&storage, "1").await.unwrap().is_none());
     }
 
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn count_and_exists_track_puts_and_deletes_on_every_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let sled: Arc<dyn StorageEngine> = Arc::new(SledStorage::open(dir.path()).unwrap());
+        let memory: Arc<dyn StorageEngine> = Arc::new(MemoryStorage::default());
+        let tiered: Arc<dyn StorageEngine> = Arc::new(TieredStorage::new(
+            Arc::new(MemoryStorage::default()),
+            Arc::new(MemoryStorage::default()),
+        ));
+        for storage in [sled, memory, tiered] {
+            assert_eq!(storage.count().await.unwrap(), 0);
+            assert!(!storage.exists("alpha").await.unwrap());
+
+            storage.put(node("alpha")).await.unwrap();
+            storage.put(node("beta")).await.unwrap();
+            storage.put(node("alpha")).await.unwrap();
+            assert_eq!(storage.count().await.unwrap(), 2);
+            assert!(storage.exists("alpha").await.unwrap());
+            assert!(!storage.exists("gamma").await.unwrap());
+
+            storage.delete("alpha").await.unwrap();
+            assert_eq!(storage.count().await.unwrap(), 1);
+            assert!(!storage.exists("alpha").await.unwrap());
+            assert!(storage.exists("beta").await.unwrap());
+        }
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn memory_batches_write_and_report_every_node() {
+        let storage = MemoryStorage::default();
+        let mut changes = StorageEngine::changes(&storage).unwrap();
+        let nodes: Vec<StoredNode> = (0..3).map(|i| node(&format!("n{i}"))).collect();
+        StorageEngine::put_batch(&storage, nodes.clone())
+            .await
+            .unwrap();
+        assert_eq!(StorageEngine::count(&storage).await.unwrap(), 3);
+
+        StorageEngine::delete_batch(&storage, vec!["n0".into(), "missing".into(), "n2".into()])
+            .await
+            .unwrap();
+        assert_eq!(
+            StorageEngine::list(&storage).await.unwrap(),
+            vec![nodes[1].clone()]
+        );
+
+        let mut seen = Vec::new();
+        while let Ok(change) = changes.try_recv() {
+            seen.push(change);
+        }
+        assert_eq!(
+            seen,
+            [
+                StorageChange::Put("n0".into()),
+                StorageChange::Put("n1".into()),
+                StorageChange::Put("n2".into()),
+                StorageChange::Delete("n0".into()),
+                StorageChange::Delete("n2".into()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn metrics_count_every_node_of_single_and_batched_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let sled: Arc<dyn StorageEngine> = Arc::new(SledStorage::open(dir.path()).unwrap());
+        let memory: Arc<dyn StorageEngine> = Arc::new(MemoryStorage::default());
+        for storage in [sled, memory] {
+            assert_eq!(storage.metrics(), Some(StorageMetrics::default()));
+
+            storage.put(node("alpha")).await.unwrap();
+            storage
+                .put_batch(vec![node("beta"), node("gamma")])
+                .await
+                .unwrap();
+            assert!(storage.get("alpha").await.unwrap().is_some());
+            assert!(storage.get("delta").await.unwrap().is_none());
+            storage.delete("alpha").await.unwrap();
+            storage
+                .delete_batch(vec!["beta".into(), "missing".into()])
+                .await
+                .unwrap();
+
+            assert_eq!(
+                storage.metrics(),
+                Some(StorageMetrics {
+                    puts: 3,
+                    get_hits: 1,
+                    get_misses: 1,
+                    deletes: 3,
+                })
+            );
+        }
+
+        let tiered = TieredStorage::new(
+            Arc::new(MemoryStorage::default()),
+            Arc::new(MemoryStorage::default()),
+        );
+        assert_eq!(StorageEngine::metrics(&tiered), None);
+    }
+
     // -----------------------------------------------------------------------
     // Helpers
     // -----------------------------------------------------------------------
@@ -416,6 +1249,23 @@ mod tests {
         StoredNode {
             id: id.to_string(),
             payload: serde_json::json!({ "id": id }),
+            meta: None,
+        }
+    }
+
+    /// Copy the files under `from` to `to` as they are on disk right now,
+    /// like a crash would leave them.
+    #[cfg(feature = "native")]
+    fn copy_dir(from: &Path, to: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            let target = to.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &target);
+            } else {
+                std::fs::copy(entry.path(), target).unwrap();
+            }
         }
     }
 
@@ -442,6 +1292,10 @@ mod tests {
             StorageErrorCode::WalTruncatedEntry.as_str(),
             "STORAGE_WAL_TRUNCATED_ENTRY"
         );
+        assert_eq!(
+            StorageErrorCode::WalChecksumMismatch.as_str(),
+            "STORAGE_WAL_CHECKSUM_MISMATCH"
+        );
     }
 
     #[test]
@@ -670,6 +1524,414 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "native")]
+    #[test]
+    fn sled_raw_bytes_round_trip_to_the_same_node() {
+        let (storage, _dir) = sled_storage();
+        let n1 = node("alpha");
+        SyncStorageEngine::put(&storage, n1.clone()).unwrap();
+
+        let raw = storage
+            .get_raw("alpha")
+            .unwrap()
+            .expect("raw bytes present");
+        let decoded: StoredNode = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(decoded, n1);
+        assert_eq!(storage.get_raw("missing").unwrap(), None);
+
+        // Forward the raw bytes into a second store without re-encoding.
+        let (other, _other_dir) = sled_storage();
+        other.put_raw("alpha", raw).unwrap();
+        assert_eq!(SyncStorageEngine::get(&other, "alpha").unwrap(), Some(n1));
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn sled_meta_persists_separately_from_payload_across_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = serde_json::json!({ "source": "peer-b", "tags": ["imported"] });
+        {
+            let storage = SledStorage::open(dir.path()).unwrap();
+            SyncStorageEngine::put(&storage, node("alpha")).unwrap();
+            SyncStorageEngine::set_meta(&storage, "alpha", Some(meta.clone())).unwrap();
+        }
+
+        let storage = SledStorage::open(dir.path()).unwrap();
+        let reloaded = SyncStorageEngine::get(&storage, "alpha").unwrap().unwrap();
+        assert_eq!(reloaded.payload, node("alpha").payload);
+        assert_eq!(reloaded.meta, Some(meta.clone()));
+        assert_eq!(
+            SyncStorageEngine::get_meta(&storage, "alpha").unwrap(),
+            Some(meta)
+        );
+
+        SyncStorageEngine::set_meta(&storage, "alpha", None).unwrap();
+        assert_eq!(
+            SyncStorageEngine::get_meta(&storage, "alpha").unwrap(),
+            None
+        );
+        assert!(SyncStorageEngine::set_meta(&storage, "missing", None).is_err());
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn sled_nodes_round_trip_in_each_format_and_reopen_with_the_recorded_one() {
+        for format in [StorageFormat::Json, StorageFormat::Bincode] {
+            let dir = tempfile::tempdir().unwrap();
+            let stored = StoredNode {
+                meta: Some(serde_json::json!({ "source": "peer-b" })),
+                ..node("alpha")
+            };
+            {
+                let storage = SledStorage::open_with_format(dir.path(), format).unwrap();
+                SyncStorageEngine::put(&storage, stored.clone()).unwrap();
+                SyncStorageEngine::put(&storage, node("beta")).unwrap();
+            }
+
+            // Plain open picks the format up from the marker
+            let storage = SledStorage::open(dir.path()).unwrap();
+            assert_eq!(storage.format(), format);
+            assert_eq!(
+                SyncStorageEngine::get(&storage, "alpha").unwrap(),
+                Some(stored)
+            );
+            assert_eq!(SyncStorageEngine::count(&storage).unwrap(), 2);
+            drop(storage);
+            assert!(SledStorage::open_with_format(dir.path(), format).is_ok());
+        }
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_put_batch_flushes_once_and_lands_every_node() {
+        let nodes: Vec<StoredNode> = (0..1000).map(|i| node(&format!("n{i:04}"))).collect();
+
+        // One flush per put...
+        let (one_by_one, _dir) = sled_storage();
+        let started = std::time::Instant::now();
+        for node in nodes.clone() {
+            StorageEngine::put(&one_by_one, node).await.unwrap();
+        }
+        let unbatched = started.elapsed();
+
+        // ...against one for the whole batch
+        let (storage, _dir) = sled_storage();
+        let started = std::time::Instant::now();
+        StorageEngine::put_batch(&storage, nodes.clone())
+            .await
+            .unwrap();
+        let batched = started.elapsed();
+        assert!(
+            batched < unbatched,
+            "batch took {batched:?}, single puts {unbatched:?}"
+        );
+
+        assert_eq!(StorageEngine::count(&storage).await.unwrap(), 1000);
+        for node in &nodes {
+            assert_eq!(
+                StorageEngine::get(&storage, &node.id)
+                    .await
+                    .unwrap()
+                    .as_ref(),
+                Some(node)
+            );
+        }
+
+        let ids = nodes
+            .iter()
+            .step_by(2)
+            .map(|node| node.id.clone())
+            .collect();
+        StorageEngine::delete_batch(&storage, ids).await.unwrap();
+        assert_eq!(StorageEngine::count(&storage).await.unwrap(), 500);
+        assert!(StorageEngine::get(&storage, "n0000")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(StorageEngine::get(&storage, "n0001")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_manual_flush_policy_defers_flushing_to_one_explicit_flush() {
+        let nodes: Vec<StoredNode> = (0..500).map(|i| node(&format!("n{i:04}"))).collect();
+
+        let (every_write, _dir) = sled_storage();
+        assert_eq!(every_write.flush_policy(), FlushPolicy::EveryWrite);
+        let started = std::time::Instant::now();
+        for node in nodes.clone() {
+            StorageEngine::put(&every_write, node).await.unwrap();
+        }
+        let flushed_each = started.elapsed();
+
+        let dir = tempfile::tempdir().unwrap();
+        let manual = SledStorage::open_with_flush_policy(dir.path(), FlushPolicy::Manual).unwrap();
+        let started = std::time::Instant::now();
+        for node in nodes.clone() {
+            StorageEngine::put(&manual, node).await.unwrap();
+        }
+        StorageEngine::flush(&manual).await.unwrap();
+        let flushed_once = started.elapsed();
+        assert!(
+            flushed_once < flushed_each,
+            "manual took {flushed_once:?}, every write {flushed_each:?}"
+        );
+
+        drop(manual);
+        let reopened = SledStorage::open(dir.path()).unwrap();
+        assert_eq!(StorageEngine::count(&reopened).await.unwrap(), 500);
+        assert_eq!(
+            StorageEngine::get(&reopened, "n0499").await.unwrap(),
+            Some(node("n0499"))
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn sled_refuses_to_open_a_database_in_another_format() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage =
+                SledStorage::open_with_format(dir.path(), StorageFormat::Bincode).unwrap();
+            SyncStorageEngine::put(&storage, node("alpha")).unwrap();
+            let raw = storage.get_raw("alpha").unwrap().unwrap();
+            assert!(serde_json::from_slice::<StoredNode>(&raw).is_err());
+        }
+        let err = SledStorage::open_with_format(dir.path(), StorageFormat::Json).unwrap_err();
+        assert!(err.to_string().contains("stored as bincode"), "{err}");
+
+        // A database written before format markers existed is JSON
+        let legacy = tempfile::tempdir().unwrap();
+        {
+            let storage = SledStorage::open(legacy.path()).unwrap();
+            SyncStorageEngine::put(&storage, node("alpha")).unwrap();
+        }
+        assert!(SledStorage::open_with_format(legacy.path(), StorageFormat::Bincode).is_err());
+        let storage = SledStorage::open_with_format(legacy.path(), StorageFormat::Json).unwrap();
+        assert_eq!(
+            SyncStorageEngine::get(&storage, "alpha").unwrap(),
+            Some(node("alpha"))
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_wal_replays_writes_that_never_reached_sled() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, wal_dir) = (dir.path().join("db"), dir.path().join("wal"));
+        {
+            let storage = SledStorage::open_with_wal(&path, &wal_dir, DurabilityLevel::Wal)
+                .await
+                .unwrap();
+            StorageEngine::put(&storage, node("alpha")).await.unwrap();
+            StorageEngine::put(&storage, node("beta")).await.unwrap();
+            assert!(SyncStorageEngine::put(&storage, node("gamma")).is_err());
+
+            // Crash between the WAL append and the sled commit: log the
+            // writes but drop the storage before applying them.
+            let wal = storage.wal.as_ref().unwrap();
+            let put = WalOperation::Put {
+                id: "gamma".to_string(),
+                data: node("gamma").payload,
+            };
+            wal.append("test".to_string(), put).await.unwrap();
+            let delete = WalOperation::Delete {
+                id: "alpha".to_string(),
+            };
+            wal.append("test".to_string(), delete).await.unwrap();
+        }
+
+        let storage = SledStorage::open_with_wal(&path, &wal_dir, DurabilityLevel::Wal)
+            .await
+            .unwrap();
+        assert_eq!(StorageEngine::get(&storage, "alpha").await.unwrap(), None);
+        assert_eq!(
+            StorageEngine::get(&storage, "beta").await.unwrap(),
+            Some(node("beta"))
+        );
+        assert_eq!(
+            StorageEngine::get(&storage, "gamma").await.unwrap(),
+            Some(node("gamma"))
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_wal_checkpoint_stops_replay_of_earlier_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, wal_dir) = (dir.path().join("db"), dir.path().join("wal"));
+        {
+            let storage = SledStorage::open_with_wal(&path, &wal_dir, DurabilityLevel::Wal)
+                .await
+                .unwrap();
+            StorageEngine::put(&storage, node("alpha")).await.unwrap();
+            let base_seq = storage.checkpoint().await.unwrap();
+            assert_eq!(storage.wal.as_ref().unwrap().next_sequence(), base_seq + 1);
+            StorageEngine::put(&storage, node("beta")).await.unwrap();
+
+            // Remove both nodes behind the WAL's back; only the write after
+            // the checkpoint should come back.
+            storage.db().remove("alpha").unwrap();
+            storage.db().remove("beta").unwrap();
+            storage.db().flush().unwrap();
+        }
+
+        let storage = SledStorage::open_with_wal(&path, &wal_dir, DurabilityLevel::Wal)
+            .await
+            .unwrap();
+        assert_eq!(StorageEngine::get(&storage, "alpha").await.unwrap(), None);
+        assert_eq!(
+            StorageEngine::get(&storage, "beta").await.unwrap(),
+            Some(node("beta"))
+        );
+        assert!(SledStorage::open(dir.path().join("plain"))
+            .unwrap()
+            .checkpoint()
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn sled_wal_checkpoint_keeps_writes_in_flight_when_it_runs() {
+        const WRITERS: usize = 4;
+        const PUTS: usize = 100;
+        let dir = tempfile::tempdir().unwrap();
+        let (path, wal_dir) = (dir.path().join("db"), dir.path().join("wal"));
+        // Without its own flushes, sled only has on disk what the
+        // checkpoints flushed; everything else must come from the WAL
+        let mut storage = SledStorage::open_with_flush_policy(&path, FlushPolicy::Manual).unwrap();
+        let wal =
+            WriteAheadLog::open_with_options(&wal_dir, DurabilityLevel::Wal, 4 * 1024).unwrap();
+        storage.wal = Some(Arc::new(wal));
+        let storage = Arc::new(storage);
+
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|w| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    for i in 0..PUTS {
+                        let id = format!("w{w}-{i}");
+                        StorageEngine::put(storage.as_ref(), node(&id))
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        while !writers.iter().all(|writer| writer.is_finished()) {
+            storage.checkpoint().await.unwrap();
+            tokio::task::yield_now().await;
+        }
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        // Crash: reopen copies of the files without sled's final flush
+        let crashed = dir.path().join("crashed");
+        copy_dir(&path, &crashed.join("db"));
+        copy_dir(&wal_dir, &crashed.join("wal"));
+        let recovered = SledStorage::open_with_wal(
+            crashed.join("db"),
+            crashed.join("wal"),
+            DurabilityLevel::Wal,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            StorageEngine::count(&recovered).await.unwrap(),
+            WRITERS * PUTS
+        );
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_full_durability_flushes_each_write_once_applied() {
+        for (durability, flushed) in [(DurabilityLevel::Full, true), (DurabilityLevel::Wal, false)]
+        {
+            let dir = tempfile::tempdir().unwrap();
+            // Nothing else flushes sled, so any flush comes from the write
+            let mut storage =
+                SledStorage::open_with_flush_policy(dir.path().join("db"), FlushPolicy::Manual)
+                    .unwrap();
+            let wal = WriteAheadLog::open_with_options(
+                dir.path().join("wal"),
+                durability,
+                SledStorage::WAL_SEGMENT_SIZE,
+            )
+            .unwrap();
+            storage.wal = Some(Arc::new(wal));
+
+            StorageEngine::put(&storage, node("alpha")).await.unwrap();
+            assert_eq!(
+                storage.db().flush().unwrap() == 0,
+                flushed,
+                "{durability:?}"
+            );
+            StorageEngine::put_batch(&storage, vec![node("beta"), node("gamma")])
+                .await
+                .unwrap();
+            assert_eq!(
+                storage.db().flush().unwrap() == 0,
+                flushed,
+                "{durability:?}"
+            );
+            StorageEngine::delete(&storage, "alpha").await.unwrap();
+            assert_eq!(
+                storage.db().flush().unwrap() == 0,
+                flushed,
+                "{durability:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn sled_encrypted_nodes_need_the_same_key_to_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = EncryptionConfig::new().unwrap();
+        {
+            let storage = SledStorage::open_encrypted(dir.path(), config.clone()).unwrap();
+            SyncStorageEngine::put(&storage, node("alpha")).unwrap();
+            assert_eq!(
+                SyncStorageEngine::get(&storage, "alpha").unwrap(),
+                Some(node("alpha"))
+            );
+            let raw = storage.get_raw("alpha").unwrap().unwrap();
+            let record: serde_json::Value = serde_json::from_slice(&raw).unwrap();
+            assert_eq!(record["encryption"]["cipher"], "aes-256-gcm");
+            assert!(record.get("payload").is_none());
+        }
+
+        let wrong_key = EncryptionConfig::new().unwrap();
+        let other = SledStorage::open_encrypted(dir.path(), wrong_key).unwrap();
+        let err = SyncStorageEngine::get(&other, "alpha").unwrap_err();
+        assert!(format!("{err:#}").contains("failed to decrypt"), "{err:#}");
+        assert!(SyncStorageEngine::list(&other).is_err());
+        drop(other);
+
+        let storage = SledStorage::open_encrypted(dir.path(), config).unwrap();
+        assert_eq!(
+            SyncStorageEngine::list(&storage).unwrap(),
+            vec![node("alpha")]
+        );
+        assert!(SledStorage::open_encrypted(dir.path(), EncryptionConfig::default()).is_err());
+    }
+
+    #[test]
+    fn stored_node_without_meta_keeps_legacy_encoding() {
+        let encoded = serde_json::to_value(node("alpha")).unwrap();
+        assert_eq!(
+            encoded,
+            serde_json::json!({ "id": "alpha", "payload": { "id": "alpha" } })
+        );
+        let decoded: StoredNode = serde_json::from_value(encoded).unwrap();
+        assert_eq!(decoded.meta, None);
+    }
+
     #[cfg(feature = "native")]
     #[test]
     fn sled_sync_count_reflects_node_set() {
@@ -766,4 +2028,60 @@ mod tests {
             Some(n2)
         );
     }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_async_for_each_by_prefix_scans_the_range_in_key_order() {
+        let (storage, _dir) = sled_storage();
+        for id in ["user:2", "job:1", "user:10", "user:1", "users"] {
+            StorageEngine::put(&storage, node(id)).await.unwrap();
+        }
+
+        let mut visited = Vec::new();
+        StorageEngine::for_each_by_prefix(&storage, "user:", &mut |n: StoredNode| {
+            visited.push(n.id);
+            true
+        })
+        .await
+        .unwrap();
+        assert_eq!(visited, ["user:1", "user:10", "user:2"]);
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn memory_changes_report_puts_and_deletes() {
+        let storage = MemoryStorage::default();
+        let mut changes = StorageEngine::changes(&storage).unwrap();
+
+        StorageEngine::put(&storage, node("a")).await.unwrap();
+        SyncStorageEngine::put(&storage, node("b")).unwrap();
+        StorageEngine::delete(&storage, "a").await.unwrap();
+        // Deleting an absent node changes nothing
+        StorageEngine::delete(&storage, "missing").await.unwrap();
+
+        assert_eq!(changes.try_recv().unwrap(), StorageChange::Put("a".into()));
+        assert_eq!(changes.try_recv().unwrap(), StorageChange::Put("b".into()));
+        assert_eq!(
+            changes.try_recv().unwrap(),
+            StorageChange::Delete("a".into())
+        );
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[cfg(feature = "native")]
+    #[tokio::test]
+    async fn sled_changes_bridge_watch_events() {
+        let (storage, _dir) = sled_storage();
+        let mut changes = StorageEngine::changes(&storage).unwrap();
+
+        StorageEngine::put(&storage, node("a")).await.unwrap();
+        SyncStorageEngine::delete(&storage, "a").unwrap();
+
+        // Watch events arrive from sled's notifier thread
+        let timeout = std::time::Duration::from_secs(5);
+        let put = tokio::time::timeout(timeout, changes.recv()).await.unwrap();
+        assert_eq!(put.unwrap(), StorageChange::Put("a".into()));
+        let delete = tokio::time::timeout(timeout, changes.recv()).await.unwrap();
+        assert_eq!(delete.unwrap(), StorageChange::Delete("a".into()));
+    }
 }
@@ -108,7 +108,7 @@ impl RadAdapter for SledRadAdapter {
         let mut nodes = Vec::new();
         for entry in self.0.db().scan_prefix(prefix.as_bytes()) {
             let (_, value) = entry?;
-            let node: StoredNode = serde_json::from_slice(&value)?;
+            let node = self.0.deserialize(value)?;
             nodes.push(node);
         }
         nodes.sort_by(|a, b| a.id.cmp(&b.id));
@@ -127,7 +127,7 @@ impl RadAdapter for SledRadAdapter {
         let mut nodes = Vec::new();
         for entry in iter {
             let (_, value) = entry?;
-            let node: StoredNode = serde_json::from_slice(&value)?;
+            let node = self.0.deserialize(value)?;
             nodes.push(node);
         }
         nodes.sort_by(|a, b| a.id.cmp(&b.id));
@@ -152,6 +152,7 @@ mod tests {
                 .put(StoredNode {
                     id: id.to_string(),
                     payload: payload.clone(),
+                    meta: None,
                 })
                 .await
                 .unwrap();
@@ -257,7 +258,7 @@ mod tests {
     #[tokio::test]
     async fn test_sled_put_get_roundtrip() {
         let (_d, a) = sled_adapter();
-        a.put(StoredNode { id: "k1".into(), payload: json!({"v": 7}) })
+        a.put(StoredNode { id: "k1".into(), payload: json!({"v": 7}), meta: None })
             .await
             .unwrap();
         let got = a.get("k1").await.unwrap();
@@ -269,7 +270,7 @@ mod tests {
     #[tokio::test]
     async fn test_sled_delete_then_get_none() {
         let (_d, a) = sled_adapter();
-        a.put(StoredNode { id: "d1".into(), payload: json!(1) }).await.unwrap();
+        a.put(StoredNode { id: "d1".into(), payload: json!(1), meta: None }).await.unwrap();
         assert!(a.get("d1").await.unwrap().is_some());
         a.delete("d1").await.unwrap();
         assert!(a.get("d1").await.unwrap().is_none(), "delete must remove (delete no-op survives otherwise)");
@@ -279,7 +280,7 @@ mod tests {
     async fn test_sled_list_returns_all() {
         let (_d, a) = sled_adapter();
         for id in ["a", "b", "c"] {
-            a.put(StoredNode { id: id.into(), payload: json!(id) }).await.unwrap();
+            a.put(StoredNode { id: id.into(), payload: json!(id), meta: None }).await.unwrap();
         }
         let all = a.list().await.unwrap();
         assert_eq!(all.len(), 3, "list must return all nodes (empty-vec mutant survives otherwise)");
@@ -289,7 +290,7 @@ mod tests {
     async fn test_sled_prefix_scan_native() {
         let (_d, a) = sled_adapter();
         for (id, p) in [("user:alice", json!(1)), ("user:bob", json!(2)), ("post:1", json!(3))] {
-            a.put(StoredNode { id: id.into(), payload: p }).await.unwrap();
+            a.put(StoredNode { id: id.into(), payload: p, meta: None }).await.unwrap();
         }
         let users = a.prefix_scan("user:").await.unwrap();
         assert_eq!(users.len(), 2);
@@ -302,7 +303,7 @@ mod tests {
     async fn test_sled_range_scan_native() {
         let (_d, a) = sled_adapter();
         for id in ["a", "b", "c", "d"] {
-            a.put(StoredNode { id: id.into(), payload: json!(id) }).await.unwrap();
+            a.put(StoredNode { id: id.into(), payload: json!(id), meta: None }).await.unwrap();
         }
         let range = a.range_scan("b", Some("d")).await.unwrap();
         assert_eq!(range.len(), 2);
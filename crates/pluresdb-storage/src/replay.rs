@@ -3,9 +3,10 @@
 //! This module provides tools to rebuild database state from WAL operations,
 //! including CRDT state reconstruction and index rebuilding.
 
-use crate::wal::{WalOperation, WriteAheadLog};
+use crate::wal::{WalEntry, WalOperation, WriteAheadLog};
+use crate::StorageEngine;
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
 use tracing::{debug, info};
 
@@ -30,6 +31,9 @@ pub struct ReplayStats {
     /// Entries skipped due to errors
     pub errors: u64,
 
+    /// Sequence number of the last entry applied, if any
+    pub last_seq: Option<u64>,
+
     /// Final node count
     pub final_node_count: usize,
 }
@@ -44,6 +48,34 @@ impl ReplayStats {
     }
 }
 
+/// Differences between the state a WAL replays to and a storage engine's
+/// contents, as reported by [`verify_consistency`].
+///
+/// Each list holds node ids in sorted order.
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyReport {
+    /// Nodes the WAL leaves in place that storage does not have.
+    pub missing_in_storage: Vec<String>,
+
+    /// Nodes in storage that the WAL never wrote or has since deleted.
+    pub extra_in_storage: Vec<String>,
+
+    /// Nodes present in both whose stored payload differs from the replayed data.
+    pub mismatched_payloads: Vec<String>,
+
+    /// Statistics from replaying the WAL.
+    pub replay: ReplayStats,
+}
+
+impl ConsistencyReport {
+    /// Returns `true` when storage matches the replayed WAL exactly.
+    pub fn is_consistent(&self) -> bool {
+        self.missing_in_storage.is_empty()
+            && self.extra_in_storage.is_empty()
+            && self.mismatched_payloads.is_empty()
+    }
+}
+
 /// Replays WAL operations to reconstruct state.
 pub async fn replay_wal(
     wal_path: &Path,
@@ -56,6 +88,13 @@ pub async fn replay_wal(
 
     let entries = wal.read_all().await.context("Failed to read WAL entries")?;
 
+    Ok(apply_entries(entries, filter_actor))
+}
+
+fn apply_entries(
+    entries: Vec<WalEntry>,
+    filter_actor: Option<&str>,
+) -> (HashMap<String, serde_json::Value>, ReplayStats) {
     let mut state = HashMap::new();
     let mut stats = ReplayStats {
         total_entries: entries.len() as u64,
@@ -76,6 +115,7 @@ pub async fn replay_wal(
             debug!(seq = entry.seq, "Skipping entry with invalid checksum");
             continue;
         }
+        stats.last_seq = Some(entry.seq);
 
         // Apply operation
         match &entry.operation {
@@ -105,7 +145,53 @@ pub async fn replay_wal(
         "WAL replay completed"
     );
 
-    Ok((state, stats))
+    (state, stats)
+}
+
+/// Replays `wal` into a scratch map and diffs the result against `storage`.
+///
+/// Stored payloads are compared verbatim against the data carried by the last
+/// `Put` for each id, so this is meaningful for stores that persist WAL data
+/// as-is.  Only the history still present in the WAL is replayed: after
+/// [`WriteAheadLog::compact`] has dropped older segments, nodes written before
+/// the checkpoint show up as `extra_in_storage`.
+pub async fn verify_consistency(
+    wal: &WriteAheadLog,
+    storage: &dyn StorageEngine,
+) -> Result<ConsistencyReport> {
+    let entries = wal.read_all().await.context("Failed to read WAL entries")?;
+    let (expected, replay) = apply_entries(entries, None);
+
+    let mut report = ConsistencyReport {
+        replay,
+        ..Default::default()
+    };
+    let mut seen = BTreeSet::new();
+    for node in storage.list().await.context("Failed to list storage")? {
+        match expected.get(&node.id) {
+            Some(data) if *data == node.payload => {}
+            Some(_) => report.mismatched_payloads.push(node.id.clone()),
+            None => report.extra_in_storage.push(node.id.clone()),
+        }
+        seen.insert(node.id);
+    }
+    report.missing_in_storage = expected
+        .into_keys()
+        .filter(|id| !seen.contains(id))
+        .collect();
+
+    report.missing_in_storage.sort();
+    report.extra_in_storage.sort();
+    report.mismatched_payloads.sort();
+
+    info!(
+        missing = report.missing_in_storage.len(),
+        extra = report.extra_in_storage.len(),
+        mismatched = report.mismatched_payloads.len(),
+        "WAL/storage consistency check completed"
+    );
+
+    Ok(report)
 }
 
 /// Rebuilds database state from WAL with validation.
@@ -419,6 +505,71 @@ mod tests {
         assert_eq!(stats.success_rate(), 1.0);
     }
 
+    #[tokio::test]
+    async fn verify_consistency_reports_divergence() {
+        use crate::{MemoryStorage, StoredNode};
+
+        let temp_dir = TempDir::new().unwrap();
+        let wal = WriteAheadLog::open(temp_dir.path()).unwrap();
+        let storage = MemoryStorage::default();
+
+        for (id, value) in [("same", 1), ("changed", 2), ("lost", 3)] {
+            let data = serde_json::json!({ "value": value });
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Put {
+                    id: id.to_string(),
+                    data: data.clone(),
+                },
+            )
+            .await
+            .unwrap();
+            StorageEngine::put(
+                &storage,
+                StoredNode {
+                    id: id.to_string(),
+                    payload: data,
+                    meta: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let clean = verify_consistency(&wal, &storage).await.unwrap();
+        assert!(clean.is_consistent(), "report: {clean:?}");
+        assert_eq!(clean.replay.puts, 3);
+
+        // Diverge: drop one node, rewrite another, and add one the WAL never saw.
+        StorageEngine::delete(&storage, "lost").await.unwrap();
+        StorageEngine::put(
+            &storage,
+            StoredNode {
+                id: "changed".to_string(),
+                payload: serde_json::json!({ "value": 99 }),
+                meta: None,
+            },
+        )
+        .await
+        .unwrap();
+        StorageEngine::put(
+            &storage,
+            StoredNode {
+                id: "stray".to_string(),
+                payload: serde_json::json!({}),
+                meta: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let report = verify_consistency(&wal, &storage).await.unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.missing_in_storage, vec!["lost".to_string()]);
+        assert_eq!(report.extra_in_storage, vec!["stray".to_string()]);
+        assert_eq!(report.mismatched_payloads, vec!["changed".to_string()]);
+    }
+
     // ---------------------------------------------------------------------
     // Mutation-hardening tests (Level-0 #6).
     // ---------------------------------------------------------------------
@@ -0,0 +1,242 @@
+//! Deterministic crash injection for write-ahead log recovery tests.
+//!
+//! [`CrashInjector`] appends operations to a real [`WriteAheadLog`] while
+//! recording where each entry lands on disk, then simulates a crash by
+//! rewriting the log files the way an interrupted write would leave them.
+//! Reopening with [`CrashInjector::recover`] and checking the result with
+//! [`assert_valid_prefix`] verifies that recovery never accepts a torn entry
+//! and never loses one that was fully written before the crash point.
+
+use std::fs::{self, OpenOptions};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+
+use crate::wal::{
+    DurabilityLevel, RecoveryPolicy, WalEntry, WalOperation, WalValidation, WriteAheadLog,
+};
+
+const ACTOR: &str = "crash-injector";
+
+/// Wraps a [`WriteAheadLog`] and can cut it off at any byte.
+#[derive(Debug)]
+pub struct CrashInjector {
+    dir: PathBuf,
+    wal: Option<WriteAheadLog>,
+    intended: Vec<WalOperation>,
+    /// Log length, across all segments, after each appended entry.
+    ends: Vec<u64>,
+}
+
+impl CrashInjector {
+    /// Open a fresh log in `dir`.  All entries go to a single segment so byte
+    /// offsets map directly onto entries.
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let wal = WriteAheadLog::open_with_options(&dir, DurabilityLevel::Wal, u64::MAX)?;
+        Ok(Self {
+            dir,
+            wal: Some(wal),
+            intended: Vec::new(),
+            ends: Vec::new(),
+        })
+    }
+
+    /// Append `operation` and record where its entry ends.
+    pub async fn append(&mut self, operation: WalOperation) -> Result<u64> {
+        let Some(wal) = &self.wal else {
+            bail!("cannot append: the log has already crashed");
+        };
+        let seq = wal.append(ACTOR.to_string(), operation.clone()).await?;
+        self.intended.push(operation);
+        self.ends.push(self.log_len()?);
+        Ok(seq)
+    }
+
+    /// Operations appended so far, in order.
+    pub fn intended(&self) -> &[WalOperation] {
+        &self.intended
+    }
+
+    /// Byte range the `index`th appended entry occupies in the log.
+    pub fn entry_range(&self, index: usize) -> Range<u64> {
+        let start = index.checked_sub(1).map_or(0, |prev| self.ends[prev]);
+        start..self.ends[index]
+    }
+
+    /// Total bytes written to the log.
+    pub fn log_len(&self) -> Result<u64> {
+        let mut len = 0;
+        for path in WriteAheadLog::segment_paths(&self.dir)? {
+            len += fs::metadata(path)?.len();
+        }
+        Ok(len)
+    }
+
+    /// Number of entries that were completely written within the first
+    /// `bytes` of the log.
+    pub fn entries_within(&self, bytes: u64) -> usize {
+        self.ends.iter().take_while(|&&end| end <= bytes).count()
+    }
+
+    /// Crash as if the process died after `bytes` of the log reached disk.
+    ///
+    /// Everything past that point is dropped, which may leave a partial
+    /// length prefix or payload at the end of the log.
+    pub fn crash_after(&mut self, bytes: u64) -> Result<()> {
+        self.wal = None;
+        let mut remaining = bytes;
+        for path in WriteAheadLog::segment_paths(&self.dir)? {
+            let len = fs::metadata(&path)?.len();
+            if remaining >= len {
+                remaining -= len;
+            } else if remaining == 0 {
+                fs::remove_file(&path)?;
+            } else {
+                let file = OpenOptions::new().write(true).open(&path)?;
+                file.set_len(remaining)?;
+                file.sync_all()?;
+                remaining = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Crash while the `index`th entry was being fsynced: the file was
+    /// extended to cover the entry but its data never reached disk, so the
+    /// entry reads back as zeros.  Later entries are lost entirely.
+    pub fn crash_mid_fsync(&mut self, index: usize) -> Result<()> {
+        let range = self.entry_range(index);
+        self.crash_after(range.start)?;
+        let path = match WriteAheadLog::segment_paths(&self.dir)?.pop() {
+            Some(path) => path,
+            None => self.dir.join(format!("{:016x}.wal", 1)),
+        };
+        let file = OpenOptions::new().create(true).write(true).open(&path)?;
+        file.set_len(fs::metadata(&path)?.len() + (range.end - range.start))?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    /// Reopen the log with `policy` and read back every surviving entry.
+    pub async fn recover(&self, policy: RecoveryPolicy) -> Result<(Vec<WalEntry>, WalValidation)> {
+        let (wal, report) = WriteAheadLog::open_with_recovery(&self.dir, policy)?;
+        Ok((wal.read_all().await?, report))
+    }
+}
+
+/// Assert that `recovered` is a prefix of `intended`: the same operations in
+/// the same order with consecutive sequence numbers and valid checksums.
+/// Returns the prefix length.
+pub fn assert_valid_prefix(recovered: &[WalEntry], intended: &[WalOperation]) -> usize {
+    assert!(
+        recovered.len() <= intended.len(),
+        "recovered {} entries but only {} were written",
+        recovered.len(),
+        intended.len()
+    );
+    for (i, (entry, operation)) in recovered.iter().zip(intended).enumerate() {
+        assert!(entry.validate_checksum(), "entry {i} has a bad checksum");
+        assert_eq!(
+            &entry.operation, operation,
+            "entry {i} differs from what was written"
+        );
+        assert_eq!(
+            entry.seq,
+            recovered[0].seq + i as u64,
+            "entry {i} is out of sequence"
+        );
+    }
+    recovered.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn injector(dir: &Path) -> CrashInjector {
+        let mut injector = CrashInjector::new(dir).unwrap();
+        for i in 0..4 {
+            injector
+                .append(WalOperation::Put {
+                    id: format!("node-{i}"),
+                    data: serde_json::json!({ "n": i }),
+                })
+                .await
+                .unwrap();
+        }
+        injector
+    }
+
+    #[tokio::test]
+    async fn crash_mid_entry_recovers_the_written_prefix() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut injector = injector(temp_dir.path()).await;
+        let torn = injector.entry_range(2);
+        injector
+            .crash_after(torn.start + (torn.end - torn.start) / 2)
+            .unwrap();
+
+        assert!(injector.recover(RecoveryPolicy::Strict).await.is_err());
+        let (recovered, report) = injector
+            .recover(RecoveryPolicy::TruncateToLastValid)
+            .await
+            .unwrap();
+        assert_eq!(assert_valid_prefix(&recovered, injector.intended()), 2);
+        assert!(report.discarded_bytes > 0);
+        assert!(injector.recover(RecoveryPolicy::Strict).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn crash_mid_fsync_rejects_the_zeroed_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut injector = injector(temp_dir.path()).await;
+        injector.crash_mid_fsync(1).unwrap();
+
+        assert!(injector.recover(RecoveryPolicy::Strict).await.is_err());
+        let (skipped, _) = injector.recover(RecoveryPolicy::SkipCorrupt).await.unwrap();
+        assert_valid_prefix(&skipped, injector.intended());
+        let (recovered, _) = injector
+            .recover(RecoveryPolicy::TruncateToLastValid)
+            .await
+            .unwrap();
+        assert_eq!(assert_valid_prefix(&recovered, injector.intended()), 1);
+    }
+
+    /// A crashed copy of `template`'s log in `dir`, without re-appending.
+    fn copy_of(template: &CrashInjector, dir: &Path) -> CrashInjector {
+        for path in WriteAheadLog::segment_paths(&template.dir).unwrap() {
+            fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+        }
+        CrashInjector {
+            dir: dir.to_path_buf(),
+            wal: None,
+            intended: template.intended.clone(),
+            ends: template.ends.clone(),
+        }
+    }
+
+    #[tokio::test]
+    async fn every_crash_point_recovers_a_consistent_prefix() {
+        let template_dir = TempDir::new().unwrap();
+        let template = injector(template_dir.path()).await;
+
+        for cut in 0..=template.log_len().unwrap() {
+            let temp_dir = TempDir::new().unwrap();
+            let mut injector = copy_of(&template, temp_dir.path());
+            injector.crash_after(cut).unwrap();
+
+            let (recovered, _) = injector
+                .recover(RecoveryPolicy::TruncateToLastValid)
+                .await
+                .unwrap();
+            assert_eq!(
+                assert_valid_prefix(&recovered, injector.intended()),
+                injector.entries_within(cut),
+                "crash after {cut} bytes"
+            );
+        }
+    }
+}
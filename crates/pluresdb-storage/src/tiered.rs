@@ -0,0 +1,185 @@
+//! A caching [`StorageEngine`] composed of a fast hot tier over a durable
+//! cold tier.
+//!
+//! Writes and deletes go through to the cold tier first, so it is always the
+//! source of truth, and are then mirrored into the hot tier.  Reads check the
+//! hot tier and fall back to the cold tier, copying cold hits into the hot
+//! tier so the next read is served from it.  Whole-store operations (`list`,
+//! `count`, iteration) read the cold tier, since the hot tier only holds the
+//! nodes that have been touched.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+
+use crate::{StorageChange, StorageEngine, StoredNode};
+
+/// Hot-tier hit/miss counters for a [`TieredStorage`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TieredStats {
+    /// Reads served from the hot tier.
+    pub hits: u64,
+    /// Reads that had to consult the cold tier, whether or not it held the node.
+    pub misses: u64,
+}
+
+/// Write-through cache of one [`StorageEngine`] over another.
+pub struct TieredStorage {
+    hot: Arc<dyn StorageEngine>,
+    cold: Arc<dyn StorageEngine>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl TieredStorage {
+    /// Cache reads from `cold` in `hot`.
+    pub fn new(hot: Arc<dyn StorageEngine>, cold: Arc<dyn StorageEngine>) -> Self {
+        Self {
+            hot,
+            cold,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> TieredStats {
+        TieredStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl std::fmt::Debug for TieredStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TieredStorage")
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl StorageEngine for TieredStorage {
+    async fn put(&self, node: StoredNode) -> Result<()> {
+        self.cold.put(node.clone()).await?;
+        self.hot.put(node).await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<StoredNode>> {
+        if let Some(node) = self.hot.get(id).await? {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(node));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let node = self.cold.get(id).await?;
+        if let Some(node) = &node {
+            self.hot.put(node.clone()).await?;
+        }
+        Ok(node)
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.cold.delete(id).await?;
+        self.hot.delete(id).await
+    }
+
+    async fn list(&self) -> Result<Vec<StoredNode>> {
+        self.cold.list().await
+    }
+
+    async fn put_batch(&self, nodes: Vec<StoredNode>) -> Result<()> {
+        self.cold.put_batch(nodes.clone()).await?;
+        self.hot.put_batch(nodes).await
+    }
+
+    async fn delete_batch(&self, ids: Vec<String>) -> Result<()> {
+        self.cold.delete_batch(ids.clone()).await?;
+        self.hot.delete_batch(ids).await
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.cold.count().await
+    }
+
+    async fn exists(&self, id: &str) -> Result<bool> {
+        self.cold.exists(id).await
+    }
+
+    async fn for_each(&self, f: &mut (dyn FnMut(StoredNode) -> bool + Send)) -> Result<()> {
+        self.cold.for_each(f).await
+    }
+
+    async fn for_each_by_prefix(
+        &self,
+        prefix: &str,
+        f: &mut (dyn FnMut(StoredNode) -> bool + Send),
+    ) -> Result<()> {
+        self.cold.for_each_by_prefix(prefix, f).await
+    }
+
+    async fn compact(&self) -> Result<u64> {
+        Ok(self.cold.compact().await? + self.hot.compact().await?)
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.cold.flush().await
+    }
+
+    /// Changes to the cold tier, which every write and delete reaches.
+    fn changes(&self) -> Option<broadcast::Receiver<StorageChange>> {
+        self.cold.changes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStorage, SledStorage};
+
+    fn node(id: &str, n: i64) -> StoredNode {
+        StoredNode {
+            id: id.to_string(),
+            payload: serde_json::json!({ "n": n }),
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_warm_the_hot_tier() {
+        let dir = tempfile::tempdir().unwrap();
+        let hot = Arc::new(MemoryStorage::default());
+        let cold = Arc::new(SledStorage::open(dir.path()).unwrap());
+        StorageEngine::put(cold.as_ref(), node("cold-only", 1))
+            .await
+            .unwrap();
+        let tiered = TieredStorage::new(hot.clone(), cold.clone());
+
+        assert_eq!(
+            tiered.get("cold-only").await.unwrap(),
+            Some(node("cold-only", 1))
+        );
+        assert_eq!(tiered.stats(), TieredStats { hits: 0, misses: 1 });
+        assert!(StorageEngine::get(hot.as_ref(), "cold-only")
+            .await
+            .unwrap()
+            .is_some());
+
+        tiered.get("cold-only").await.unwrap();
+        assert_eq!(tiered.stats(), TieredStats { hits: 1, misses: 1 });
+
+        tiered.put(node("written", 2)).await.unwrap();
+        tiered.delete("cold-only").await.unwrap();
+        assert!(StorageEngine::get(cold.as_ref(), "written")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(StorageEngine::get(hot.as_ref(), "cold-only")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(tiered.count().await.unwrap(), 1);
+    }
+}
@@ -5,15 +5,16 @@
 //! to reconstruct database state after a crash.
 
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufReader, Read, Write};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tracing::{debug, info, instrument, warn};
 
 use crate::StorageErrorCode;
@@ -24,6 +25,15 @@ use crate::StorageErrorCode;
 /// as the prefix is almost certainly the result of a corrupt or partial write.
 const MAX_ENTRY_SIZE: usize = 16 * 1024 * 1024;
 
+/// Magic bytes opening the header of a compressed segment.
+///
+/// Read as a length prefix they claim far more than [`MAX_ENTRY_SIZE`], so a
+/// headerless segment can never be mistaken for one with a header.
+const SEGMENT_MAGIC: &[u8; 4] = b"PWAL";
+
+/// Length of a segment header: the magic followed by one codec byte.
+const SEGMENT_HEADER_LEN: usize = SEGMENT_MAGIC.len() + 1;
+
 /// Errors specific to WAL corruption and recovery.
 ///
 /// These errors carry actionable guidance so operators can quickly recover from
@@ -37,7 +47,9 @@ pub enum WalError {
          prefix is likely corrupt.\n\
          Recovery options:\n  \
          1. Delete segment '{segment}' and restart — earlier segments remain intact.\n  \
-         2. Run `pluresdb-cli wal recover --path <wal-dir>` to salvage all valid entries."
+         2. Run `pluresdb wal verify --dir <wal-dir>` to check the rest of the log, and \
+            `pluresdb wal repair --dir <wal-dir>` if this is a torn tail at the end of \
+            the last segment."
     )]
     ImplausibleEntrySize {
         /// Path of the corrupted segment file.
@@ -57,7 +69,8 @@ pub enum WalError {
          likely caused by a process crash mid-write.\n\
          Recovery options:\n  \
          1. Delete segment '{segment}' and restart — fully-written earlier segments are intact.\n  \
-         2. Run `pluresdb-cli wal recover --path <wal-dir>` to replay only intact entries."
+         2. Run `pluresdb wal repair --dir <wal-dir>` to cut the partial entry off the end \
+            of the log, then `pluresdb wal verify --dir <wal-dir>` to check the rest."
     )]
     TruncatedEntry {
         /// Path of the truncated segment file.
@@ -67,6 +80,33 @@ pub enum WalError {
         /// Number of bytes that were expected but could not be read.
         expected_bytes: usize,
     },
+
+    /// Corruption was found while opening the WAL with [`RecoveryPolicy::Strict`].
+    #[error("{guidance}")]
+    CorruptionDetected {
+        /// Summary and recovery steps from [`WalValidation::recovery_guidance`].
+        guidance: String,
+    },
+
+    /// A WAL entry decoded but its stored checksum does not match its content.
+    #[error(
+        "WAL segment '{segment}' holds entry {seq} at byte offset {offset} whose \
+         checksum does not match its content.\n\
+         Recovery options:\n  \
+         1. Run `pluresdb wal verify --dir <wal-dir>` to list every damaged entry.\n  \
+         2. Run `pluresdb wal repair --dir <wal-dir>` if the damage is a torn tail at \
+            the end of the last segment.\n  \
+         3. Otherwise skip the entry if later entries do not depend on it, or open the \
+            WAL with `RecoveryPolicy::TruncateToLastValid` to truncate the log before it."
+    )]
+    ChecksumMismatch {
+        /// Path of the segment holding the entry.
+        segment: String,
+        /// Byte offset of the entry's length prefix within the segment.
+        offset: u64,
+        /// Sequence number recorded in the entry.
+        seq: u64,
+    },
 }
 
 impl WalError {
@@ -77,6 +117,8 @@ impl WalError {
         match self {
             Self::ImplausibleEntrySize { .. } => StorageErrorCode::WalImplausibleEntrySize,
             Self::TruncatedEntry { .. } => StorageErrorCode::WalTruncatedEntry,
+            Self::CorruptionDetected { .. } => StorageErrorCode::WalCorruptionDetected,
+            Self::ChecksumMismatch { .. } => StorageErrorCode::WalChecksumMismatch,
         }
     }
 }
@@ -92,7 +134,123 @@ pub enum DurabilityLevel {
     Wal,
 
     /// Fsync WAL and data - slowest, most durable
+    ///
+    /// `SledStorage` logging to a WAL at this level also flushes sled after
+    /// every put, delete, and batch has been applied, whatever its
+    /// `FlushPolicy`, so an acknowledged write survives power loss even if
+    /// the WAL is later compacted.  Every write then pays for two synchronous
+    /// disk flushes instead of one, which typically adds milliseconds of
+    /// latency per write on spinning disks and hundreds of microseconds on
+    /// SSDs.
     Full,
+
+    /// Fsync WAL only, once for each group of appends
+    ///
+    /// An append writes its entry, then waits up to `window` for concurrent
+    /// appends to join it so that one fsync covers the whole group.  `append`
+    /// still returns only once its entry is on disk; under many concurrent
+    /// writers this trades up to `window` of extra latency per write for far
+    /// fewer fsyncs.  A lone writer just pays the latency.
+    GroupCommit { window: Duration },
+}
+
+/// How [`WriteAheadLog::open_with_recovery`] handles corruption found on open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryPolicy {
+    /// Refuse to open, returning [`WalError::CorruptionDetected`].
+    Strict,
+
+    /// Cut the log back to its longest valid prefix: the first damaged
+    /// segment is truncated just before its first bad entry and every later
+    /// segment is deleted.
+    TruncateToLastValid,
+
+    /// Cut a partial record, as a crash mid-append leaves it, off the end of
+    /// the last segment so that segment reads cleanly again.  Complete
+    /// entries are never dropped, and any other corruption is left in place
+    /// as with [`RecoveryPolicy::SkipCorrupt`].
+    RepairTornTail,
+
+    /// Open anyway and leave corrupt data in place; readers skip entries they
+    /// cannot decode.  This is what [`WriteAheadLog::open`] does.
+    #[default]
+    SkipCorrupt,
+}
+
+/// How [`WriteAheadLog`] stores entries in the segments it creates.
+///
+/// Compressed segments start with a header naming their codec, and segments
+/// without one are plain, so a log can hold segments written under
+/// different settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalCompression {
+    /// Entries are stored as plain JSON, with no segment header.
+    #[default]
+    None,
+
+    /// Each entry is stored as its own zstd frame, so records still frame
+    /// and decode one at a time.
+    Zstd,
+}
+
+impl WalCompression {
+    /// Codec byte recorded in the segment header.
+    const fn id(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Zstd => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::None),
+            1 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Encodes a serialized entry for storage.
+    fn encode(self, json: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(json),
+            Self::Zstd => zstd::bulk::compress(&json, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .context("failed to compress WAL entry"),
+        }
+    }
+
+    /// Decodes one record's payload back into an entry.
+    fn decode_entry(self, record: &[u8]) -> Result<WalEntry> {
+        let entry = match self {
+            Self::None => serde_json::from_slice(record)?,
+            Self::Zstd => {
+                let json = zstd::bulk::decompress(record, MAX_ENTRY_SIZE)
+                    .context("failed to decompress WAL entry")?;
+                serde_json::from_slice(&json)?
+            }
+        };
+        Ok(entry)
+    }
+}
+
+/// Codec of the segment starting with `head`, and how many bytes its header
+/// takes up.
+fn segment_header(head: &[u8]) -> Result<(WalCompression, usize)> {
+    match head.get(..SEGMENT_HEADER_LEN) {
+        Some([magic @ .., id]) if magic == SEGMENT_MAGIC => WalCompression::from_id(*id)
+            .map(|codec| (codec, SEGMENT_HEADER_LEN))
+            .with_context(|| format!("unsupported WAL segment codec {id}")),
+        _ => Ok((WalCompression::None, 0)),
+    }
+}
+
+/// Reads the header of the segment at `path`.
+fn read_segment_header(path: &Path) -> Result<(WalCompression, usize)> {
+    let mut head = Vec::with_capacity(SEGMENT_HEADER_LEN);
+    File::open(path)
+        .and_then(|file| file.take(SEGMENT_HEADER_LEN as u64).read_to_end(&mut head))
+        .with_context(|| format!("failed to read WAL segment header: {}", path.display()))?;
+    segment_header(&head).with_context(|| format!("in WAL segment: {}", path.display()))
 }
 
 /// A single entry in the write-ahead log.
@@ -184,6 +342,21 @@ pub struct WriteAheadLog {
 
     /// Maximum segment size in bytes (default: 64MB)
     max_segment_size: u64,
+
+    /// Codec for entries in newly created segments
+    compression: WalCompression,
+
+    /// Entries written to segments so far, counted in file order
+    written: AtomicU64,
+
+    /// How many of the `written` entries the last group fsync covered
+    synced: watch::Sender<u64>,
+
+    /// Whether an append is currently leading a group fsync
+    group_leader: AtomicBool,
+
+    /// Fsyncs issued since the log was opened
+    fsyncs: AtomicU64,
 }
 
 impl WriteAheadLog {
@@ -192,6 +365,109 @@ impl WriteAheadLog {
         Self::open_with_options(dir, DurabilityLevel::default(), 64 * 1024 * 1024)
     }
 
+    /// Opens or creates a WAL after checking every segment, handling any
+    /// corruption according to `policy`.
+    ///
+    /// The returned [`WalValidation`] describes what was found on disk before
+    /// any repair; under [`RecoveryPolicy::TruncateToLastValid`] and
+    /// [`RecoveryPolicy::RepairTornTail`] its `discarded_bytes` records how
+    /// much was cut.
+    pub fn open_with_recovery(
+        dir: impl AsRef<Path>,
+        policy: RecoveryPolicy,
+    ) -> Result<(Self, WalValidation)> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create WAL directory: {}", dir.display()))?;
+
+        let mut report = WalValidation::default();
+        let mut scans = Vec::new();
+        for path in Self::segment_paths(dir)? {
+            let scan = WalSegment::open_read(&path)?.scan()?;
+            report.total_segments += 1;
+            report.total_entries += scan.entries;
+            report.valid_entries += scan.entries - scan.corrupted_entries;
+            report.corrupted_entries += scan.corrupted_entries;
+            if scan.damaged {
+                report.corrupted_segments += 1;
+            }
+            scans.push((path, scan));
+        }
+
+        if !report.is_healthy() {
+            match policy {
+                RecoveryPolicy::Strict => {
+                    return Err(WalError::CorruptionDetected {
+                        guidance: report.recovery_guidance().unwrap_or_default(),
+                    }
+                    .into());
+                }
+                RecoveryPolicy::TruncateToLastValid => {
+                    report.discarded_bytes = Self::truncate_to_last_valid(&scans)?;
+                    warn!(
+                        discarded_bytes = report.discarded_bytes,
+                        "truncated WAL to its last valid entry"
+                    );
+                }
+                RecoveryPolicy::RepairTornTail => {
+                    report.discarded_bytes = Self::truncate_torn_tail(scans.last())?;
+                    warn!(
+                        discarded_bytes = report.discarded_bytes,
+                        "cut a torn entry off the end of the WAL"
+                    );
+                }
+                RecoveryPolicy::SkipCorrupt => {
+                    warn!(?report, "opening WAL with corrupt entries left in place");
+                }
+            }
+        }
+
+        Ok((Self::open(dir)?, report))
+    }
+
+    /// Truncates the first segment containing a bad entry to its valid prefix
+    /// and deletes all segments after it.  Returns the number of bytes removed.
+    fn truncate_to_last_valid(scans: &[(PathBuf, SegmentScan)]) -> Result<u64> {
+        let Some(first_bad) = scans.iter().position(|(_, scan)| !scan.is_clean()) else {
+            return Ok(0);
+        };
+
+        let (path, scan) = &scans[first_bad];
+        let mut discarded = scan.len - scan.valid_len;
+        if scan.valid_len == 0 {
+            fs::remove_file(path)?;
+        } else {
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(scan.valid_len)?;
+            file.sync_all()?;
+        }
+        info!(?path, valid_len = scan.valid_len, "truncated WAL segment");
+
+        for (path, scan) in &scans[first_bad + 1..] {
+            fs::remove_file(path)?;
+            discarded += scan.len;
+            info!(?path, "removed WAL segment after truncation point");
+        }
+        Ok(discarded)
+    }
+
+    /// Truncates the last segment just before a partial record at its end.
+    /// Returns the number of bytes removed.
+    fn truncate_torn_tail(last: Option<&(PathBuf, SegmentScan)>) -> Result<u64> {
+        let Some((path, scan)) = last else {
+            return Ok(0);
+        };
+        let Some(torn_at) = scan.torn_at else {
+            return Ok(0);
+        };
+
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(torn_at)?;
+        file.sync_all()?;
+        info!(?path, valid_len = torn_at, "truncated torn WAL entry");
+        Ok(scan.len - torn_at)
+    }
+
     /// Opens or creates a WAL with custom options.
     pub fn open_with_options(
         dir: impl AsRef<Path>,
@@ -213,42 +489,140 @@ impl WriteAheadLog {
             next_seq: AtomicU64::new(next_seq),
             durability,
             max_segment_size,
+            compression: WalCompression::None,
+            written: AtomicU64::new(0),
+            synced: watch::channel(0).0,
+            group_leader: AtomicBool::new(false),
+            fsyncs: AtomicU64::new(0),
         })
     }
 
+    /// Compress the entries of segments created from now on with
+    /// `compression`.
+    ///
+    /// Existing segments keep the codec they were written with and are read
+    /// as before.
+    pub fn with_compression(mut self, compression: WalCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// When appends reach disk.
+    pub fn durability(&self) -> DurabilityLevel {
+        self.durability
+    }
+
+    /// Sequence number the next appended entry will receive.
+    pub fn next_sequence(&self) -> u64 {
+        self.next_seq.load(Ordering::SeqCst)
+    }
+
+    /// Number of fsyncs the log has issued since it was opened.
+    pub fn fsync_count(&self) -> u64 {
+        self.fsyncs.load(Ordering::SeqCst)
+    }
+
     /// Appends an operation to the WAL.
+    ///
+    /// Unless the durability level is [`DurabilityLevel::None`], the entry
+    /// is on disk once this returns its sequence number.
     #[instrument(skip(self, operation))]
     pub async fn append(&self, actor: String, operation: WalOperation) -> Result<u64> {
-        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
-        let entry = WalEntry::new(seq, actor, operation);
+        let group_commit = matches!(self.durability, DurabilityLevel::GroupCommit { .. });
 
         let mut guard = self.current_segment.lock().await;
+        // Numbered under the lock, so segment files hold ascending sequence
+        // numbers and each file name is a lower bound for the segments after it
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        let entry = WalEntry::new(seq, actor, operation);
 
         // Create new segment if needed
         if guard.is_none() || Self::should_rotate(&guard, self.max_segment_size)? {
-            let segment = WalSegment::create(&self.dir, seq)?;
+            if let Some(full) = guard.as_mut().filter(|_| group_commit) {
+                // Entries still waiting for their group fsync must reach
+                // disk before the segment is swapped out
+                self.fsync_segment(full)?;
+            }
+            let segment = WalSegment::create(&self.dir, seq, self.compression)?;
             *guard = Some(segment);
         }
 
+        let mut ticket = 0;
         if let Some(segment) = guard.as_mut() {
             segment.append(&entry)?;
 
             // Fsync based on durability level
-            if self.durability != DurabilityLevel::None {
-                segment.fsync()?;
+            match self.durability {
+                DurabilityLevel::None => {}
+                DurabilityLevel::GroupCommit { .. } => {
+                    ticket = self.written.fetch_add(1, Ordering::SeqCst) + 1;
+                }
+                DurabilityLevel::Wal | DurabilityLevel::Full => self.fsync_segment(segment)?,
             }
         }
+        drop(guard);
+
+        if let DurabilityLevel::GroupCommit { window } = self.durability {
+            self.group_commit(ticket, window).await?;
+        }
 
         Ok(seq)
     }
 
+    /// Waits until a group fsync covers the `ticket`-th written entry,
+    /// leading the next group fsync itself if no other append is.
+    async fn group_commit(&self, ticket: u64, window: Duration) -> Result<()> {
+        let mut synced = self.synced.subscribe();
+        loop {
+            if *synced.borrow_and_update() >= ticket {
+                return Ok(());
+            }
+            if self
+                .group_leader
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                let leader = GroupLeader(self);
+                tokio::time::sleep(window).await;
+                let covered = self.sync_group().await?;
+                // Publish quietly; dropping `leader` wakes the waiters once
+                // the flag is clear, so none can miss the result
+                self.synced.send_if_modified(|synced| {
+                    *synced = (*synced).max(covered);
+                    false
+                });
+                drop(leader);
+            } else {
+                // The sender lives as long as `self`, so this cannot fail
+                let _ = synced.changed().await;
+            }
+        }
+    }
+
+    /// Fsyncs the active segment and returns how many written entries that
+    /// covers.
+    async fn sync_group(&self) -> Result<u64> {
+        let mut guard = self.current_segment.lock().await;
+        let covered = self.written.load(Ordering::SeqCst);
+        if let Some(segment) = guard.as_mut() {
+            self.fsync_segment(segment)?;
+        }
+        Ok(covered)
+    }
+
+    fn fsync_segment(&self, segment: &mut WalSegment) -> Result<()> {
+        segment.fsync()?;
+        self.fsyncs.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
     /// Reads all entries from the WAL in sequence order.
     pub async fn read_all(&self) -> Result<Vec<WalEntry>> {
         // First, ensure current segment is flushed
         {
             let mut guard = self.current_segment.lock().await;
             if let Some(segment) = guard.as_mut() {
-                segment.fsync()?;
+                self.fsync_segment(segment)?;
             }
         }
 
@@ -274,13 +648,45 @@ impl WriteAheadLog {
         Ok(entries)
     }
 
+    /// Lazily reads the entries with sequence numbers from `start_seq` on,
+    /// in order.
+    ///
+    /// Segments are opened one at a time as the cursor reaches them, and
+    /// segments that end before `start_seq` are never opened. Each entry's
+    /// checksum is checked: a mismatch yields
+    /// [`WalError::ChecksumMismatch`], and a truncated or implausible record
+    /// yields the matching [`WalError`] and skips the rest of its segment.
+    /// Iteration may continue past an `Err`.
+    ///
+    /// The segment list is taken when this is called; a record still being
+    /// appended at the tail of the active segment can read as truncated.
+    pub fn read_from(&self, start_seq: u64) -> Result<WalCursor> {
+        let paths = self.list_segments()?;
+        let starts: Vec<Option<u64>> = paths.iter().map(|path| segment_start(path)).collect();
+        let segments: Vec<PathBuf> = paths
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                // Segment `i` ends just before the next one starts
+                !matches!(starts.get(i + 1), Some(Some(next)) if *next <= start_seq)
+            })
+            .map(|(_, path)| path)
+            .collect();
+
+        Ok(WalCursor {
+            segments: segments.into_iter(),
+            current: None,
+            start_seq,
+        })
+    }
+
     /// Validates all entries and returns statistics about corruption.
     pub async fn validate(&self) -> Result<WalValidation> {
         // First, ensure current segment is flushed
         {
             let mut guard = self.current_segment.lock().await;
             if let Some(segment) = guard.as_mut() {
-                segment.fsync()?;
+                self.fsync_segment(segment)?;
             }
         }
 
@@ -317,9 +723,13 @@ impl WriteAheadLog {
     }
 
     /// Compacts the WAL by removing entries before the checkpoint.
-    pub async fn compact(&self, checkpoint_seq: u64) -> Result<()> {
+    ///
+    /// Returns the number of segment files deleted.
+    pub async fn compact(&self, checkpoint_seq: u64) -> Result<usize> {
         info!(checkpoint_seq, "compacting WAL");
 
+        let mut removed = 0;
+
         for segment_path in self.list_segments()? {
             // Check if this segment only contains entries before checkpoint
             if let Ok(segment) = WalSegment::open_read(&segment_path) {
@@ -328,19 +738,42 @@ impl WriteAheadLog {
                         // All entries in this segment are before checkpoint, safe to delete
                         debug!(?segment_path, "removing compacted WAL segment");
                         fs::remove_file(&segment_path)?;
+                        removed += 1;
                     }
                 }
             }
         }
 
-        Ok(())
+        Ok(removed)
+    }
+
+    /// Base sequence of the most recent [`WalOperation::Checkpoint`], or
+    /// `None` if the log holds no checkpoint.
+    pub async fn last_checkpoint(&self) -> Result<Option<u64>> {
+        let entries = self.read_all().await?;
+        Ok(entries
+            .iter()
+            .rev()
+            .find_map(|entry| match entry.operation {
+                WalOperation::Checkpoint { base_seq } => Some(base_seq),
+                _ => None,
+            }))
+    }
+
+    /// Number of segment files currently on disk.
+    pub fn segment_count(&self) -> Result<usize> {
+        Ok(self.list_segments()?.len())
     }
 
     /// Lists all segment files in chronological order.
     fn list_segments(&self) -> Result<Vec<PathBuf>> {
+        Self::segment_paths(&self.dir)
+    }
+
+    pub(crate) fn segment_paths(dir: &Path) -> Result<Vec<PathBuf>> {
         let mut segments = Vec::new();
 
-        for entry in fs::read_dir(&self.dir)? {
+        for entry in fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
 
@@ -387,6 +820,17 @@ impl WriteAheadLog {
     }
 }
 
+/// Marks an append as the group fsync leader; dropping it, even when the
+/// append is cancelled or fails, hands the role on and wakes the waiters.
+struct GroupLeader<'a>(&'a WriteAheadLog);
+
+impl Drop for GroupLeader<'_> {
+    fn drop(&mut self) {
+        self.0.group_leader.store(false, Ordering::SeqCst);
+        self.0.synced.send_modify(|_| {});
+    }
+}
+
 /// Statistics from WAL validation.
 #[derive(Debug, Default, Clone)]
 pub struct WalValidation {
@@ -395,6 +839,9 @@ pub struct WalValidation {
     pub corrupted_entries: u64,
     pub total_segments: u64,
     pub corrupted_segments: u64,
+    /// Bytes removed by [`RecoveryPolicy::TruncateToLastValid`] or
+    /// [`RecoveryPolicy::RepairTornTail`].
+    pub discarded_bytes: u64,
 }
 
 impl WalValidation {
@@ -435,8 +882,9 @@ impl WalValidation {
             "WAL integrity check failed: {} corrupted entr{}, {} corrupted segment{} \
              out of {} total entr{} across {} segment{}.\n\
              Recovery options:\n  \
-             1. Run `pluresdb-cli wal recover --path <wal-dir>` to replay only valid \
-                entries (recommended — minimises data loss).\n  \
+             1. Run `pluresdb wal verify --dir <wal-dir>` to list the damaged entries, and \
+                `pluresdb wal repair --dir <wal-dir>` to cut off a torn tail left by a \
+                crash (recommended — never drops a complete entry).\n  \
              2. Delete all .wal files in the WAL directory and restart from your last \
                 snapshot (safe, but may lose recent writes not yet in a snapshot).\n  \
              3. Delete only the corrupted segment files identified in the log output \
@@ -453,44 +901,87 @@ impl WalValidation {
     }
 }
 
+/// Result of walking a segment record by record.
+#[derive(Debug, Default)]
+struct SegmentScan {
+    /// Records with a complete payload, decodable or not.
+    entries: u64,
+    /// Records that failed to decode or failed their checksum.
+    corrupted_entries: u64,
+    /// The segment ends in a truncated or implausibly sized record.
+    damaged: bool,
+    /// Where a record cut short by the end of the file starts, if the
+    /// segment ends in one.
+    torn_at: Option<u64>,
+    /// Length of the prefix before the first bad record.
+    valid_len: u64,
+    /// Total length of the segment file.
+    len: u64,
+}
+
+impl SegmentScan {
+    fn is_clean(&self) -> bool {
+        self.valid_len == self.len
+    }
+}
+
 /// A single WAL segment file.
 #[derive(Debug)]
 struct WalSegment {
     path: PathBuf,
     file: File,
+    compression: WalCompression,
 }
 
 impl WalSegment {
-    /// Creates a new WAL segment.
-    fn create(dir: &Path, start_seq: u64) -> Result<Self> {
+    /// Creates a new WAL segment, writing a header for `compression` unless
+    /// the file already holds data, in which case its own codec is kept.
+    fn create(dir: &Path, start_seq: u64, compression: WalCompression) -> Result<Self> {
         let filename = format!("{:016x}.wal", start_seq);
         let path = dir.join(filename);
 
-        let file = OpenOptions::new()
+        let mut file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&path)
             .with_context(|| format!("failed to create WAL segment: {}", path.display()))?;
 
-        debug!(?path, "created WAL segment");
+        let compression = if file.metadata()?.len() > 0 {
+            read_segment_header(&path)?.0
+        } else {
+            if compression != WalCompression::None {
+                file.write_all(SEGMENT_MAGIC)?;
+                file.write_all(&[compression.id()])?;
+            }
+            compression
+        };
+
+        debug!(?path, ?compression, "created WAL segment");
 
-        Ok(Self { path, file })
+        Ok(Self {
+            path,
+            file,
+            compression,
+        })
     }
 
     /// Opens an existing WAL segment for reading.
     fn open_read(path: &Path) -> Result<Self> {
         let file = File::open(path)
             .with_context(|| format!("failed to open WAL segment: {}", path.display()))?;
+        let (compression, _) = read_segment_header(path)?;
 
         Ok(Self {
             path: path.to_path_buf(),
             file,
+            compression,
         })
     }
 
     /// Appends an entry to this segment.
     fn append(&mut self, entry: &WalEntry) -> Result<()> {
-        let bytes = serde_json::to_vec(entry).context("failed to serialize WAL entry")?;
+        let json = serde_json::to_vec(entry).context("failed to serialize WAL entry")?;
+        let bytes = self.compression.encode(json)?;
 
         // Write length prefix (u32) followed by entry bytes
         let len = bytes.len() as u32;
@@ -511,90 +1002,72 @@ impl WalSegment {
         Ok(self.file.metadata()?.len())
     }
 
-    /// Reads all entries from this segment.
-    ///
-    /// Returns `Err` if a partial write is detected (truncated length prefix,
-    /// implausible entry size, or truncated payload). This allows callers such as
-    /// [`WriteAheadLog::validate`] to count the segment as corrupted rather than
-    /// silently dropping its tail.
-    fn read_all(&self) -> Result<Vec<WalEntry>> {
-        // Open a new file handle for reading (the self.file is in append mode)
-        let read_file = File::open(&self.path).with_context(|| {
+    /// Walks every record, checking framing, decoding, and checksums, and
+    /// finds where the valid prefix ends.
+    fn scan(&self) -> Result<SegmentScan> {
+        let bytes = fs::read(&self.path).with_context(|| {
             format!(
-                "failed to open WAL segment for reading: {}",
+                "failed to read WAL segment for validation: {}",
                 self.path.display()
             )
         })?;
 
-        let mut reader = BufReader::new(read_file);
-        let mut entries = Vec::new();
-        let mut offset: u64 = 0;
-        let segment_name = self.path.display().to_string();
+        let (compression, header_len) = segment_header(&bytes)
+            .with_context(|| format!("in WAL segment: {}", self.path.display()))?;
 
-        loop {
-            // Read the first byte of the 4-byte length prefix via `read` (not
-            // `read_exact`) so we can distinguish a clean end-of-file (0 bytes
-            // returned at a record boundary) from a partial write that left fewer
-            // than 4 bytes in the file.
-            let mut len_buf = [0u8; 4];
-            match reader.read(&mut len_buf[..1])? {
-                0 => break, // clean EOF at a record boundary — normal end of segment
-                _ => {
-                    // We have the first byte; read the remaining 3.
-                    reader.read_exact(&mut len_buf[1..]).map_err(|e| {
-                        if e.kind() == io::ErrorKind::UnexpectedEof {
-                            anyhow::Error::from(WalError::TruncatedEntry {
-                                segment: segment_name.clone(),
-                                offset,
-                                expected_bytes: 4,
-                            })
-                        } else {
-                            anyhow::Error::from(e).context(WalError::TruncatedEntry {
-                                segment: segment_name.clone(),
-                                offset,
-                                expected_bytes: 4,
-                            })
-                        }
-                    })?;
+        let mut scan = SegmentScan {
+            len: bytes.len() as u64,
+            ..Default::default()
+        };
+        let mut first_bad = None;
+        let mut offset = header_len;
+        while offset < bytes.len() {
+            let Some(prefix) = bytes.get(offset..offset + 4) else {
+                scan.damaged = true;
+                scan.torn_at = Some(offset as u64);
+                break;
+            };
+            let len = u32::from_le_bytes(prefix.try_into().expect("4-byte prefix")) as usize;
+            let end = offset + 4 + len;
+            if len > MAX_ENTRY_SIZE || end > bytes.len() {
+                scan.damaged = true;
+                if len <= MAX_ENTRY_SIZE {
+                    scan.torn_at = Some(offset as u64);
                 }
+                break;
             }
 
-            let len = u32::from_le_bytes(len_buf) as usize;
-            let entry_offset = offset + 4;
-
-            // Reject implausibly large entries — the length prefix is likely corrupt.
-            if len > MAX_ENTRY_SIZE {
-                return Err(WalError::ImplausibleEntrySize {
-                    segment: segment_name,
-                    offset,
-                    claimed_size: len,
-                    max_size: MAX_ENTRY_SIZE,
-                }
-                .into());
+            scan.entries += 1;
+            let valid = compression
+                .decode_entry(&bytes[offset + 4..end])
+                .is_ok_and(|entry| entry.validate_checksum());
+            if !valid {
+                scan.corrupted_entries += 1;
+                first_bad.get_or_insert(offset);
             }
+            offset = end;
+        }
+        if scan.damaged {
+            first_bad.get_or_insert(offset);
+        }
+        scan.valid_len = first_bad.map_or(scan.len, |offset| offset as u64);
 
-            // Read the entry payload.
-            let mut entry_buf = vec![0u8; len];
-            reader.read_exact(&mut entry_buf).map_err(|e| {
-                if e.kind() == io::ErrorKind::UnexpectedEof {
-                    anyhow::Error::from(WalError::TruncatedEntry {
-                        segment: segment_name.clone(),
-                        offset: entry_offset,
-                        expected_bytes: len,
-                    })
-                } else {
-                    anyhow::Error::from(e).context(WalError::TruncatedEntry {
-                        segment: segment_name.clone(),
-                        offset: entry_offset,
-                        expected_bytes: len,
-                    })
-                }
-            })?;
+        Ok(scan)
+    }
 
-            offset += 4 + len as u64;
+    /// Reads all entries from this segment.
+    ///
+    /// Returns `Err` if a partial write is detected (truncated length prefix,
+    /// implausible entry size, or truncated payload). This allows callers such as
+    /// [`WriteAheadLog::validate`] to count the segment as corrupted rather than
+    /// silently dropping its tail.
+    fn read_all(&self) -> Result<Vec<WalEntry>> {
+        let mut reader = SegmentReader::open(&self.path)?;
+        let mut entries = Vec::new();
 
+        while let Some(entry_buf) = reader.next_record()? {
             // Deserialize entry
-            match serde_json::from_slice::<WalEntry>(&entry_buf) {
+            match reader.compression.decode_entry(&entry_buf) {
                 Ok(entry) => entries.push(entry),
                 Err(e) => {
                     warn!(error = ?e, "failed to deserialize WAL entry, skipping");
@@ -607,6 +1080,171 @@ impl WalSegment {
     }
 }
 
+/// Start sequence number encoded in a segment's file name.
+fn segment_start(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    u64::from_str_radix(stem, 16).ok()
+}
+
+/// Reads the length-prefixed records of one segment file, one at a time.
+struct SegmentReader {
+    reader: BufReader<File>,
+    segment: String,
+    offset: u64,
+    compression: WalCompression,
+}
+
+impl SegmentReader {
+    fn open(path: &Path) -> Result<Self> {
+        // Open a new file handle for reading (the segment's own is in append mode)
+        let mut read_file = File::open(path).with_context(|| {
+            format!("failed to open WAL segment for reading: {}", path.display())
+        })?;
+        let (compression, header_len) = read_segment_header(path)?;
+        read_file.seek(SeekFrom::Start(header_len as u64))?;
+
+        Ok(Self {
+            reader: BufReader::new(read_file),
+            segment: path.display().to_string(),
+            offset: header_len as u64,
+            compression,
+        })
+    }
+
+    /// Returns the next record's bytes, or `None` at a clean end of file.
+    ///
+    /// Returns `Err` if a partial write is detected (truncated length prefix,
+    /// implausible entry size, or truncated payload).
+    fn next_record(&mut self) -> Result<Option<Vec<u8>>> {
+        let offset = self.offset;
+        let segment_name = &self.segment;
+        let reader = &mut self.reader;
+
+        // Read the first byte of the 4-byte length prefix via `read` (not
+        // `read_exact`) so we can distinguish a clean end-of-file (0 bytes
+        // returned at a record boundary) from a partial write that left fewer
+        // than 4 bytes in the file.
+        let mut len_buf = [0u8; 4];
+        match reader.read(&mut len_buf[..1])? {
+            0 => return Ok(None), // clean EOF at a record boundary — normal end of segment
+            _ => {
+                // We have the first byte; read the remaining 3.
+                reader.read_exact(&mut len_buf[1..]).map_err(|e| {
+                    if e.kind() == io::ErrorKind::UnexpectedEof {
+                        anyhow::Error::from(WalError::TruncatedEntry {
+                            segment: segment_name.clone(),
+                            offset,
+                            expected_bytes: 4,
+                        })
+                    } else {
+                        anyhow::Error::from(e).context(WalError::TruncatedEntry {
+                            segment: segment_name.clone(),
+                            offset,
+                            expected_bytes: 4,
+                        })
+                    }
+                })?;
+            }
+        }
+
+        let len = u32::from_le_bytes(len_buf) as usize;
+        let entry_offset = offset + 4;
+
+        // Reject implausibly large entries — the length prefix is likely corrupt.
+        if len > MAX_ENTRY_SIZE {
+            return Err(WalError::ImplausibleEntrySize {
+                segment: segment_name.clone(),
+                offset,
+                claimed_size: len,
+                max_size: MAX_ENTRY_SIZE,
+            }
+            .into());
+        }
+
+        // Read the entry payload.
+        let mut entry_buf = vec![0u8; len];
+        reader.read_exact(&mut entry_buf).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                anyhow::Error::from(WalError::TruncatedEntry {
+                    segment: segment_name.clone(),
+                    offset: entry_offset,
+                    expected_bytes: len,
+                })
+            } else {
+                anyhow::Error::from(e).context(WalError::TruncatedEntry {
+                    segment: segment_name.clone(),
+                    offset: entry_offset,
+                    expected_bytes: len,
+                })
+            }
+        })?;
+
+        self.offset += 4 + len as u64;
+        Ok(Some(entry_buf))
+    }
+}
+
+/// Lazy iterator over WAL entries, returned by [`WriteAheadLog::read_from`].
+///
+/// Holds at most one segment open and one entry in memory at a time.
+pub struct WalCursor {
+    segments: std::vec::IntoIter<PathBuf>,
+    current: Option<SegmentReader>,
+    start_seq: u64,
+}
+
+impl Iterator for WalCursor {
+    type Item = Result<WalEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current.is_none() {
+                match SegmentReader::open(&self.segments.next()?) {
+                    Ok(reader) => self.current = Some(reader),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            let reader = self.current.as_mut().expect("segment reader is open");
+
+            let offset = reader.offset;
+            let bytes = match reader.next_record() {
+                Ok(Some(bytes)) => bytes,
+                Ok(None) => {
+                    self.current = None;
+                    continue;
+                }
+                Err(e) => {
+                    // Framing is lost; carry on with the next segment
+                    self.current = None;
+                    return Some(Err(e));
+                }
+            };
+
+            let entry = match reader.compression.decode_entry(&bytes) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    return Some(Err(e.context(format!(
+                        "failed to decode WAL entry at byte offset {offset} of segment '{}'",
+                        reader.segment
+                    ))));
+                }
+            };
+            if entry.seq < self.start_seq {
+                continue;
+            }
+            if !entry.validate_checksum() {
+                return Some(Err(WalError::ChecksumMismatch {
+                    segment: reader.segment.clone(),
+                    offset,
+                    seq: entry.seq,
+                }
+                .into()));
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -652,6 +1290,93 @@ mod tests {
         assert!(entries[1].validate_checksum());
     }
 
+    /// Appends `count` entries from concurrent tasks and returns their
+    /// sequence numbers.
+    async fn append_concurrently(wal: &Arc<WriteAheadLog>, count: u64) -> Vec<u64> {
+        let tasks: Vec<_> = (0..count)
+            .map(|i| {
+                let wal = Arc::clone(wal);
+                tokio::spawn(async move {
+                    wal.append(
+                        format!("actor-{i}"),
+                        WalOperation::Delete {
+                            id: format!("node-{i}"),
+                        },
+                    )
+                    .await
+                    .unwrap()
+                })
+            })
+            .collect();
+        let mut seqs = Vec::new();
+        for task in tasks {
+            seqs.push(task.await.unwrap());
+        }
+        seqs
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn group_commit_shares_fsyncs_between_concurrent_appends() {
+        const WRITERS: u64 = 32;
+
+        let temp_dir = TempDir::new().unwrap();
+        let individual = Arc::new(
+            WriteAheadLog::open_with_options(
+                temp_dir.path().join("individual"),
+                DurabilityLevel::Wal,
+                64 * 1024 * 1024,
+            )
+            .unwrap(),
+        );
+        append_concurrently(&individual, WRITERS).await;
+        assert_eq!(individual.fsync_count(), WRITERS);
+
+        let grouped = Arc::new(
+            WriteAheadLog::open_with_options(
+                temp_dir.path().join("grouped"),
+                DurabilityLevel::GroupCommit {
+                    window: Duration::from_millis(5),
+                },
+                64 * 1024 * 1024,
+            )
+            .unwrap(),
+        );
+        let mut seqs = append_concurrently(&grouped, WRITERS).await;
+        let fsyncs = grouped.fsync_count();
+        assert!(
+            fsyncs < WRITERS / 2,
+            "{fsyncs} fsyncs for {WRITERS} grouped appends"
+        );
+
+        seqs.sort_unstable();
+        seqs.dedup();
+        assert_eq!(seqs.len() as u64, WRITERS);
+        let entries = grouped.read_all().await.unwrap();
+        assert_eq!(entries.len() as u64, WRITERS);
+        assert!(entries.iter().all(WalEntry::validate_checksum));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn group_commit_keeps_entries_across_segment_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let window = Duration::from_millis(2);
+        let wal = Arc::new(
+            WriteAheadLog::open_with_options(
+                temp_dir.path(),
+                DurabilityLevel::GroupCommit { window },
+                256,
+            )
+            .unwrap(),
+        );
+        append_concurrently(&wal, 24).await;
+        drop(wal);
+
+        let reopened = WriteAheadLog::open(temp_dir.path()).unwrap();
+        assert!(reopened.segment_count().unwrap() > 1);
+        assert_eq!(reopened.read_all().await.unwrap().len(), 24);
+        assert_eq!(reopened.next_sequence(), 25);
+    }
+
     #[tokio::test]
     async fn test_wal_validation() {
         let temp_dir = TempDir::new().unwrap();
@@ -719,6 +1444,41 @@ mod tests {
         assert!(!entries.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_compact_to_last_checkpoint_removes_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        // Tiny segments so every couple of entries rotates to a new file
+        let wal =
+            WriteAheadLog::open_with_options(temp_dir.path(), DurabilityLevel::None, 256).unwrap();
+        assert_eq!(wal.last_checkpoint().await.unwrap(), None);
+
+        for i in 0..50 {
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Put {
+                    id: format!("node-{i}"),
+                    data: serde_json::json!({ "i": i }),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let base_seq = wal.next_sequence();
+        wal.append("actor-1".to_string(), WalOperation::Checkpoint { base_seq })
+            .await
+            .unwrap();
+
+        let before = wal.segment_count().unwrap();
+        let checkpoint = wal.last_checkpoint().await.unwrap().unwrap();
+        assert_eq!(checkpoint, base_seq);
+        let removed = wal.compact(checkpoint).await.unwrap();
+
+        assert!(removed > 0);
+        assert_eq!(wal.segment_count().unwrap(), before - removed);
+        let entries = wal.read_all().await.unwrap();
+        assert!(entries.iter().all(|entry| entry.seq >= base_seq));
+    }
+
     #[test]
     fn test_wal_entry_checksum() {
         let entry = WalEntry::new(
@@ -791,6 +1551,76 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn read_from_yields_exactly_the_tail_across_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let wal =
+            WriteAheadLog::open_with_options(temp_dir.path(), DurabilityLevel::Wal, 256).unwrap();
+        for i in 0..20 {
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Put {
+                    id: format!("node-{i}"),
+                    data: serde_json::json!({ "index": i }),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        assert!(wal.segment_count().unwrap() > 2);
+
+        let seqs = |start_seq| -> Vec<u64> {
+            wal.read_from(start_seq)
+                .unwrap()
+                .map(|entry| entry.unwrap().seq)
+                .collect()
+        };
+        assert_eq!(seqs(12), (12..=20).collect::<Vec<_>>());
+        assert_eq!(seqs(0), (1..=20).collect::<Vec<_>>());
+        assert_eq!(seqs(20), [20]);
+        assert!(seqs(21).is_empty());
+
+        // Segments wholly before the start are skipped without being opened
+        let first = wal.list_segments().unwrap().remove(0);
+        std::fs::write(&first, u32::MAX.to_le_bytes()).unwrap();
+        assert_eq!(seqs(12), (12..=20).collect::<Vec<_>>());
+        assert!(wal.read_from(1).unwrap().next().unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn read_from_reports_checksum_mismatches_and_keeps_going() {
+        let temp_dir = TempDir::new().unwrap();
+        let wal = WriteAheadLog::open(temp_dir.path()).unwrap();
+        for i in 0..3 {
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Delete {
+                    id: format!("node-{i}"),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let segment = wal.list_segments().unwrap().remove(0);
+        let raw = std::fs::read(&segment).unwrap();
+        let corrupted = corrupt_all_checksums_to_zero(&raw);
+        std::fs::write(&segment, &corrupted).unwrap();
+
+        let results: Vec<Result<WalEntry>> = wal.read_from(2).unwrap().collect();
+        assert_eq!(results.len(), 2);
+        for (result, seq) in results.iter().zip(2..) {
+            let err = result.as_ref().unwrap_err();
+            match err.downcast_ref::<WalError>() {
+                Some(wal_err @ WalError::ChecksumMismatch { seq: bad, .. }) => {
+                    assert_eq!(*bad, seq);
+                    assert_eq!(wal_err.code(), StorageErrorCode::WalChecksumMismatch);
+                }
+                other => panic!("expected a checksum mismatch, got {other:?}"),
+            }
+        }
+    }
+
     // ---------------------------------------------------------------------
     // Mutation-hardening tests (Level-0 #6).
     //
@@ -1027,6 +1857,179 @@ mod tests {
         assert!(!v.is_healthy(), "WAL with corruption must report unhealthy");
     }
 
+    /// Two good entries followed by an undecodable record and a good entry in
+    /// the first segment, plus a later segment holding one good entry.
+    async fn wal_with_corrupt_entry(dir: &Path) {
+        let wal = WriteAheadLog::open(dir).unwrap();
+        for i in 0..2 {
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Delete {
+                    id: format!("node-{i}"),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let first = wal.list_segments().unwrap().remove(0);
+        drop(wal);
+
+        let record = |entry: &WalEntry| {
+            let bytes = serde_json::to_vec(entry).unwrap();
+            let mut out = (bytes.len() as u32).to_le_bytes().to_vec();
+            out.extend(bytes);
+            out
+        };
+        let delete = |seq: u64| {
+            WalEntry::new(
+                seq,
+                "actor-1".to_string(),
+                WalOperation::Delete {
+                    id: format!("node-{seq}"),
+                },
+            )
+        };
+        let mut raw = std::fs::read(&first).unwrap();
+        raw.extend(7u32.to_le_bytes());
+        raw.extend(b"garbage");
+        raw.extend(record(&delete(3)));
+        std::fs::write(&first, raw).unwrap();
+        std::fs::write(dir.join(format!("{:016x}.wal", 100)), record(&delete(100))).unwrap();
+    }
+
+    #[tokio::test]
+    async fn open_with_recovery_strict_rejects_corruption() {
+        let temp_dir = TempDir::new().unwrap();
+        wal_with_corrupt_entry(temp_dir.path()).await;
+
+        let err = WriteAheadLog::open_with_recovery(temp_dir.path(), RecoveryPolicy::Strict)
+            .expect_err("strict open must fail on corruption");
+        let wal_err = err.downcast_ref::<WalError>().expect("typed WAL error");
+        assert_eq!(wal_err.code(), StorageErrorCode::WalCorruptionDetected);
+        assert!(err.to_string().contains("1 corrupted entry"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn open_with_recovery_skip_corrupt_keeps_everything() {
+        let temp_dir = TempDir::new().unwrap();
+        wal_with_corrupt_entry(temp_dir.path()).await;
+
+        let (wal, report) =
+            WriteAheadLog::open_with_recovery(temp_dir.path(), RecoveryPolicy::SkipCorrupt)
+                .unwrap();
+
+        assert_eq!(report.total_segments, 2);
+        assert_eq!(report.total_entries, 5);
+        assert_eq!(report.corrupted_entries, 1);
+        assert_eq!(report.discarded_bytes, 0);
+        let seqs: Vec<u64> = wal
+            .read_all()
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![1, 2, 3, 100]);
+    }
+
+    #[tokio::test]
+    async fn open_with_recovery_truncates_to_last_valid_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        wal_with_corrupt_entry(temp_dir.path()).await;
+
+        let (wal, report) =
+            WriteAheadLog::open_with_recovery(temp_dir.path(), RecoveryPolicy::TruncateToLastValid)
+                .unwrap();
+
+        assert_eq!(report.corrupted_entries, 1);
+        assert!(report.discarded_bytes > 0);
+        let seqs: Vec<u64> = wal
+            .read_all()
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.seq)
+            .collect();
+        assert_eq!(seqs, vec![1, 2]);
+        assert_eq!(wal.next_sequence(), 3);
+        assert_eq!(wal.list_segments().unwrap().len(), 1);
+        drop(wal);
+
+        let (_, report) =
+            WriteAheadLog::open_with_recovery(temp_dir.path(), RecoveryPolicy::Strict).unwrap();
+        assert!(report.is_healthy());
+        assert_eq!(report.valid_entries, 2);
+    }
+
+    #[tokio::test]
+    async fn open_with_recovery_cuts_off_only_a_torn_tail() {
+        // A partial length prefix, and a whole prefix promising more payload
+        // than was written
+        let torn_tails: [&[u8]; 2] = [&[0x01, 0x02], &[64, 0, 0, 0, b'{', b'"']];
+        for torn in torn_tails {
+            let temp_dir = TempDir::new().unwrap();
+            let wal = WriteAheadLog::open(temp_dir.path()).unwrap();
+            wal.append(
+                "actor-1".to_string(),
+                WalOperation::Delete {
+                    id: "node-1".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+            drop(wal);
+
+            let segment = WriteAheadLog::segment_paths(temp_dir.path())
+                .unwrap()
+                .remove(0);
+            let good_len = std::fs::metadata(&segment).unwrap().len();
+            let mut file = OpenOptions::new().append(true).open(&segment).unwrap();
+            file.write_all(torn).unwrap();
+            drop(file);
+
+            let (wal, report) =
+                WriteAheadLog::open_with_recovery(temp_dir.path(), RecoveryPolicy::RepairTornTail)
+                    .unwrap();
+            assert_eq!(report.corrupted_segments, 1);
+            assert_eq!(report.discarded_bytes, torn.len() as u64);
+            assert_eq!(std::fs::metadata(&segment).unwrap().len(), good_len);
+
+            // The log reads and appends cleanly again
+            assert!(wal.validate().await.unwrap().is_healthy());
+            let seq = wal
+                .append(
+                    "actor-1".to_string(),
+                    WalOperation::Delete {
+                        id: "node-2".to_string(),
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(seq, 2);
+            let seqs: Vec<u64> = wal
+                .read_all()
+                .await
+                .unwrap()
+                .iter()
+                .map(|e| e.seq)
+                .collect();
+            assert_eq!(seqs, vec![1, 2]);
+        }
+    }
+
+    #[tokio::test]
+    async fn repair_torn_tail_leaves_other_corruption_alone() {
+        let temp_dir = TempDir::new().unwrap();
+        wal_with_corrupt_entry(temp_dir.path()).await;
+
+        let (wal, report) =
+            WriteAheadLog::open_with_recovery(temp_dir.path(), RecoveryPolicy::RepairTornTail)
+                .unwrap();
+        assert_eq!(report.corrupted_entries, 1);
+        assert_eq!(report.discarded_bytes, 0);
+        assert_eq!(wal.validate().await.unwrap().corrupted_entries, 1);
+    }
+
     /// Rewrites a WAL segment's bytes, setting every entry's `checksum` field to
     /// 0 while keeping each record's length prefix correct. Helper for the
     /// validation-counting test above.
@@ -1060,7 +2063,7 @@ mod tests {
     /// segment(s) (entries strictly below the checkpoint) while preserving the
     /// segment holding the boundary entry.
     ///
-    /// - `Ok(())` body deletes nothing -> segment count unchanged.
+    /// - `Ok(0)` body deletes nothing -> segment count unchanged.
     /// - `<` -> `==` deletes only segments whose entries ALL equal the
     ///   checkpoint (none) -> nothing deleted.
     /// - `<` -> `>` deletes the wrong (later) segments / keeps the early one.
@@ -1100,7 +2103,7 @@ mod tests {
         // Checkpoint at seq 3: entries 0,1,2 are strictly below it (earlier
         // segments); entry 3 is the boundary and MUST survive.
         let checkpoint = seqs[3];
-        wal.compact(checkpoint).await.unwrap();
+        let removed = wal.compact(checkpoint).await.unwrap();
 
         assert!(
             !first_segment.exists(),
@@ -1113,6 +2116,11 @@ mod tests {
             segments_before.len(),
             segments_after.len()
         );
+        assert_eq!(
+            removed,
+            segments_before.len() - segments_after.len(),
+            "compact must report exactly the number of segments it deleted"
+        );
 
         let remaining = wal.read_all().await.unwrap();
         let remaining_seqs: Vec<u64> = remaining.iter().map(|e| e.seq).collect();
@@ -1127,4 +2135,80 @@ mod tests {
             remaining_seqs
         );
     }
+
+    #[tokio::test]
+    async fn zstd_segments_are_smaller_and_coexist_with_plain_ones() {
+        let plain_dir = TempDir::new().unwrap();
+        let zstd_dir = TempDir::new().unwrap();
+        let plain =
+            WriteAheadLog::open_with_options(plain_dir.path(), DurabilityLevel::None, u64::MAX)
+                .unwrap();
+        let zstd =
+            WriteAheadLog::open_with_options(zstd_dir.path(), DurabilityLevel::None, u64::MAX)
+                .unwrap()
+                .with_compression(WalCompression::Zstd);
+        for i in 0..50 {
+            let operation = WalOperation::Put {
+                id: format!("node-{i}"),
+                data: serde_json::json!({ "body": "lorem ipsum ".repeat(40), "n": i }),
+            };
+            plain
+                .append("actor-1".to_string(), operation.clone())
+                .await
+                .unwrap();
+            zstd.append("actor-1".to_string(), operation).await.unwrap();
+        }
+
+        let log_size = |wal: &WriteAheadLog| -> u64 {
+            wal.list_segments()
+                .unwrap()
+                .iter()
+                .map(|path| fs::metadata(path).unwrap().len())
+                .sum()
+        };
+        let (plain_size, zstd_size) = (log_size(&plain), log_size(&zstd));
+        assert!(
+            zstd_size * 2 < plain_size,
+            "zstd log is {zstd_size} bytes, plain log is {plain_size}"
+        );
+
+        let operations = |entries: Vec<WalEntry>| -> Vec<WalOperation> {
+            entries.into_iter().map(|e| e.operation).collect()
+        };
+        let expected = operations(plain.read_all().await.unwrap());
+        assert_eq!(operations(zstd.read_all().await.unwrap()), expected);
+        assert!(zstd.validate().await.unwrap().is_healthy());
+        drop(zstd);
+
+        // Reopened without compression, new entries land in a plain segment
+        // beside the compressed one and both read back
+        let (reopened, report) =
+            WriteAheadLog::open_with_recovery(zstd_dir.path(), RecoveryPolicy::Strict).unwrap();
+        assert!(report.is_healthy());
+        assert_eq!(report.total_entries, 50);
+        reopened
+            .append(
+                "actor-1".to_string(),
+                WalOperation::Delete {
+                    id: "node-0".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(reopened.segment_count().unwrap(), 2);
+        let seqs: Vec<u64> = reopened
+            .read_from(0)
+            .unwrap()
+            .map(|e| e.unwrap().seq)
+            .collect();
+        assert_eq!(seqs, (1..=51).collect::<Vec<_>>());
+        let mut recovered = operations(reopened.read_all().await.unwrap());
+        assert_eq!(
+            recovered.pop(),
+            Some(WalOperation::Delete {
+                id: "node-0".to_string()
+            })
+        );
+        assert_eq!(recovered, expected);
+    }
 }
@@ -642,7 +642,7 @@ async fn test_rebuild_fails_fast_on_corrupt_checksum() {
         msg
     );
     assert!(
-        msg.contains("pluresdb-cli wal recover") || msg.contains("wal recover"),
+        msg.contains("pluresdb wal verify --dir"),
         "error should mention the recovery CLI command, got: {}",
         msg
     );
@@ -743,8 +743,9 @@ async fn test_validation_recovery_guidance_content() {
         .recovery_guidance()
         .expect("should have recovery guidance");
     assert!(
-        guidance.contains("pluresdb-cli wal recover"),
-        "guidance should reference the recovery CLI command"
+        guidance.contains("pluresdb wal verify --dir")
+            && guidance.contains("pluresdb wal repair --dir"),
+        "guidance should reference the WAL CLI commands"
     );
     assert!(
         guidance.contains("Recovery options"),
@@ -0,0 +1,232 @@
+//! Forward local [`SyncEvent`]s to a byte sink and apply them on the far side.
+//!
+//! A [`SyncBridge`] pairs a [`SyncBroadcaster`] with the [`CrdtStore`] it
+//! describes.  [`forward_to`](SyncBridge::forward_to) turns each upsert or
+//! delete event into a self-contained [`BridgeMessage`] — the full node record
+//! including its vector clock — and writes it to any
+//! [`Sink<Vec<u8>>`](futures::Sink).  [`ingest_from`](SyncBridge::ingest_from)
+//! reads those messages from a stream and merges them into the local store
+//! with [`CrdtStore::merge_remote`].
+//!
+//! Messages that change nothing are not re-published, so two bridges wired to
+//! each other in both directions settle instead of echoing forever.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use pluresdb_core::{CrdtStore, NodeRecord};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+
+use crate::{SyncBroadcaster, SyncEvent};
+
+/// Wire format written by [`SyncBridge::forward_to`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BridgeMessage {
+    /// The current state of a node, clock included.
+    Upsert { record: NodeRecord },
+    /// A node was deleted.
+    Delete { id: String },
+    /// The forwarder fell behind and dropped events; a full snapshot of the
+    /// store follows as [`BridgeMessage::Upsert`]s.
+    Resync { missed: u64 },
+}
+
+impl BridgeMessage {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec(self).context("failed to encode bridge message")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).context("failed to decode bridge message")
+    }
+}
+
+/// Relays store changes between a [`SyncBroadcaster`] and a network transport.
+#[derive(Debug, Clone)]
+pub struct SyncBridge {
+    broadcaster: Arc<SyncBroadcaster>,
+    store: Arc<CrdtStore>,
+}
+
+impl SyncBridge {
+    /// Create a bridge for `store`, whose writes are announced on `broadcaster`.
+    pub fn new(broadcaster: Arc<SyncBroadcaster>, store: Arc<CrdtStore>) -> Self {
+        Self { broadcaster, store }
+    }
+
+    /// Forward every upsert and delete published after this call to `sink`.
+    ///
+    /// The broadcaster is subscribed before this returns, so no event is lost
+    /// between building the future and first polling it.  If the subscription
+    /// lags, a [`BridgeMessage::Resync`] marker is written followed by the
+    /// whole store, tombstones included so missed deletes still apply.  The
+    /// future completes when the broadcaster closes, and fails if the sink
+    /// rejects a message.
+    pub fn forward_to<S>(&self, mut sink: S) -> impl Future<Output = Result<()>> + Send + 'static
+    where
+        S: Sink<Vec<u8>> + Unpin + Send + 'static,
+        S::Error: std::fmt::Display,
+    {
+        let mut events = self.broadcaster.subscribe();
+        let store = Arc::clone(&self.store);
+        async move {
+            loop {
+                let messages = match events.recv().await {
+                    Ok(SyncEvent::NodeUpsert { id } | SyncEvent::NodeUpserted { id, .. }) => {
+                        match store.get(&id) {
+                            Some(record) => vec![BridgeMessage::Upsert { record }],
+                            // Deleted again before we got to it; the delete event follows.
+                            None => continue,
+                        }
+                    }
+                    Ok(SyncEvent::NodeDelete { id }) => vec![BridgeMessage::Delete { id }],
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("[SyncBridge] lagged by {} events; resyncing", missed);
+                        std::iter::once(BridgeMessage::Resync { missed })
+                            .chain(
+                                store
+                                    .list_including_tombstones()
+                                    .into_iter()
+                                    .map(|record| BridgeMessage::Upsert { record }),
+                            )
+                            .collect()
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+                for message in messages {
+                    sink.send(message.encode()?)
+                        .await
+                        .map_err(|e| anyhow!("bridge sink rejected message: {e}"))?;
+                }
+            }
+        }
+    }
+
+    /// Apply messages from `stream` to the store until the stream ends.
+    ///
+    /// Each message that changes the store is re-published on the
+    /// broadcaster, so local subscribers (and other bridges) see remote
+    /// writes like local ones.
+    pub async fn ingest_from<S>(&self, mut stream: S) -> Result<()>
+    where
+        S: Stream<Item = Vec<u8>> + Unpin,
+    {
+        while let Some(bytes) = stream.next().await {
+            let event = match BridgeMessage::decode(&bytes)? {
+                BridgeMessage::Upsert { record } => {
+                    let id = record.id.clone();
+                    self.store
+                        .merge_remote(record)
+                        .then_some(SyncEvent::NodeUpsert { id })
+                }
+                BridgeMessage::Delete { id } => self
+                    .store
+                    .delete(&id)
+                    .is_ok()
+                    .then_some(SyncEvent::NodeDelete { id }),
+                BridgeMessage::Resync { missed } => {
+                    debug!(
+                        "[SyncBridge] peer resyncing after missing {} events",
+                        missed
+                    );
+                    None
+                }
+            };
+            if let Some(event) = event {
+                self.broadcaster.publish(event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use serde_json::json;
+    use std::time::Duration;
+
+    struct Peer {
+        hub: Arc<SyncBroadcaster>,
+        store: Arc<CrdtStore>,
+        bridge: SyncBridge,
+    }
+
+    impl Peer {
+        fn new() -> Self {
+            let hub = Arc::new(SyncBroadcaster::default());
+            let store = Arc::new(CrdtStore::default());
+            let bridge = SyncBridge::new(Arc::clone(&hub), Arc::clone(&store));
+            Self { hub, store, bridge }
+        }
+
+        fn put(&self, id: &str, actor: &str, data: serde_json::Value) {
+            self.store.put(id, actor, data);
+            self.hub
+                .publish(SyncEvent::NodeUpsert { id: id.to_string() })
+                .unwrap();
+        }
+
+        fn snapshot(&self) -> Vec<(String, serde_json::Value)> {
+            let mut nodes: Vec<_> = self
+                .store
+                .list()
+                .into_iter()
+                .map(|record| (record.id, record.data))
+                .collect();
+            nodes.sort_by(|a, b| a.0.cmp(&b.0));
+            nodes
+        }
+    }
+
+    fn connect(from: &Peer, to: &Peer) {
+        let (tx, rx) = mpsc::unbounded();
+        tokio::spawn(from.bridge.forward_to(tx));
+        let bridge = to.bridge.clone();
+        tokio::spawn(async move { bridge.ingest_from(rx).await });
+    }
+
+    #[tokio::test]
+    async fn two_bridges_converge_over_a_channel() {
+        let a = Peer::new();
+        let b = Peer::new();
+        connect(&a, &b);
+        connect(&b, &a);
+
+        a.put("shared", "peer-a", json!({ "owner": "a" }));
+        a.put("only-a", "peer-a", json!({ "n": 1 }));
+        b.put("only-b", "peer-b", json!({ "n": 2 }));
+
+        let mut converged = false;
+        for _ in 0..100 {
+            if a.snapshot().len() == 3 && a.snapshot() == b.snapshot() {
+                converged = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(converged, "a: {:?}\nb: {:?}", a.snapshot(), b.snapshot());
+
+        b.store.delete("only-a").unwrap();
+        b.hub
+            .publish(SyncEvent::NodeDelete {
+                id: "only-a".to_string(),
+            })
+            .unwrap();
+        for _ in 0..100 {
+            if a.store.get("only-a").is_none() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(a.store.get("only-a").is_none());
+        assert_eq!(a.snapshot(), b.snapshot());
+    }
+}
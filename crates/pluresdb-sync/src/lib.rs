@@ -5,8 +5,10 @@
 //! broadcast hub with typed events that integrates with Tokio tasks.
 
 use anyhow::Result;
+use pluresdb_core::{CrdtStore, NodeData, NodeId, VectorClock};
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tracing::instrument;
 
 mod transport;
@@ -32,6 +34,15 @@ pub use gun_relay::GunRelayServer;
 mod replication;
 pub use replication::{MemConnection, Replicator};
 
+mod bridge;
+pub use bridge::{BridgeMessage, SyncBridge};
+
+mod peers;
+pub use peers::{MergeHook, PeerManager, PeerMessage, PeerStatus};
+
+mod orchestrator;
+pub use orchestrator::{MemTransport, PeerTransport, SyncOrchestrator};
+
 pub mod git_replication;
 
 /// Stable, documented error codes emitted by `pluresdb-sync`.
@@ -67,6 +78,16 @@ pub enum SyncEvent {
         /// Identifier of the node that was written.
         id: String,
     },
+    /// A node was inserted or updated, with enough state for a peer to merge
+    /// it without reading it back.  Build one with [`SyncEvent::upserted`].
+    NodeUpserted {
+        /// Identifier of the node that was written.
+        id: NodeId,
+        /// The node's data after the write.
+        data: NodeData,
+        /// The node's vector clock after the write.
+        clock: VectorClock,
+    },
     /// A node was deleted from the local store.
     NodeDelete {
         /// Identifier of the node that was removed.
@@ -82,6 +103,29 @@ pub enum SyncEvent {
         /// Stable identifier of the peer that disconnected.
         peer_id: String,
     },
+    /// A [`ReliableReceiver`] fell behind and `missed` events were dropped;
+    /// the consumer must re-read the whole store to catch up.  Never
+    /// published, only produced by [`ReliableReceiver::recv`].
+    ResyncRequired {
+        /// Number of events that were dropped.
+        missed: u64,
+    },
+}
+
+impl SyncEvent {
+    /// The event announcing that `id` was just written to `store`: a
+    /// [`SyncEvent::NodeUpserted`] carrying its current data and clock, or a
+    /// bare [`SyncEvent::NodeUpsert`] if the node is already gone again.
+    pub fn upserted(store: &CrdtStore, id: NodeId) -> Self {
+        match store.get(&id) {
+            Some(record) => Self::NodeUpserted {
+                id,
+                data: record.data,
+                clock: record.clock,
+            },
+            None => Self::NodeUpsert { id },
+        }
+    }
 }
 
 /// Tokio broadcast hub for [`SyncEvent`]s.
@@ -132,6 +176,24 @@ impl SyncBroadcaster {
         self.sender.subscribe()
     }
 
+    /// Subscribe to the event stream, reporting lag as an event.
+    ///
+    /// Unlike [`subscribe`](Self::subscribe), a receiver that falls more than
+    /// the channel capacity behind yields [`SyncEvent::ResyncRequired`]
+    /// instead of an error, so replication consumers cannot silently skip
+    /// writes.
+    pub fn subscribe_reliable(&self) -> ReliableReceiver {
+        ReliableReceiver {
+            inner: self.sender.subscribe(),
+        }
+    }
+
+    /// Number of receivers currently subscribed.  Producers can use this to
+    /// skip building events no one will read.
+    pub fn receiver_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
     /// Publish a [`SyncEvent`] to all current subscribers.
     ///
     /// Returns `Ok(())` on success.  Returns an [`anyhow::Error`] wrapping a
@@ -157,6 +219,27 @@ impl SyncBroadcaster {
     }
 }
 
+/// Receiver returned by [`SyncBroadcaster::subscribe_reliable`].
+#[derive(Debug)]
+pub struct ReliableReceiver {
+    inner: broadcast::Receiver<SyncEvent>,
+}
+
+impl ReliableReceiver {
+    /// Wait for the next event.
+    ///
+    /// Returns [`SyncEvent::ResyncRequired`] once after falling behind, then
+    /// continues with the oldest event still buffered.  Returns `None` when
+    /// the broadcaster has been dropped.
+    pub async fn recv(&mut self) -> Option<SyncEvent> {
+        match self.inner.recv().await {
+            Ok(event) => Some(event),
+            Err(RecvError::Lagged(missed)) => Some(SyncEvent::ResyncRequired { missed }),
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -179,6 +262,73 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn upserted_event_round_trips_data_and_clock() {
+        let hub = SyncBroadcaster::new(16);
+        let mut rx = hub.subscribe();
+        let store = CrdtStore::default();
+        let data = serde_json::json!({ "name": "plures", "tags": ["a", "b"] });
+        store.put("node-1", "peer-a", serde_json::json!({ "name": "draft" }));
+        let id = store.put("node-1", "peer-a", data.clone());
+        hub.publish(SyncEvent::upserted(&store, id)).unwrap();
+
+        let SyncEvent::NodeUpserted {
+            id,
+            data: received,
+            clock,
+        } = rx.recv().await.unwrap()
+        else {
+            panic!("expected a NodeUpserted event");
+        };
+        assert_eq!(id, "node-1");
+        assert_eq!(received, data);
+        assert_eq!(clock, store.get("node-1").unwrap().clock);
+        assert_eq!(clock.get("peer-a"), Some(&2));
+
+        store.delete("node-1").unwrap();
+        assert_eq!(
+            SyncEvent::upserted(&store, "node-1".to_string()),
+            SyncEvent::NodeUpsert {
+                id: "node-1".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reliable_receiver_reports_lag_as_resync_required() {
+        let hub = SyncBroadcaster::new(2);
+        assert_eq!(hub.receiver_count(), 0);
+        let mut rx = hub.subscribe_reliable();
+        assert_eq!(hub.receiver_count(), 1);
+
+        for i in 0..5 {
+            hub.publish(SyncEvent::NodeUpsert {
+                id: format!("node-{i}"),
+            })
+            .unwrap();
+        }
+
+        assert_eq!(
+            rx.recv().await,
+            Some(SyncEvent::ResyncRequired { missed: 3 })
+        );
+        assert_eq!(
+            rx.recv().await,
+            Some(SyncEvent::NodeUpsert {
+                id: "node-3".to_string()
+            })
+        );
+        assert_eq!(
+            rx.recv().await,
+            Some(SyncEvent::NodeUpsert {
+                id: "node-4".to_string()
+            })
+        );
+
+        drop(hub);
+        assert_eq!(rx.recv().await, None);
+    }
+
     #[test]
     fn sync_error_code_is_stable() {
         assert_eq!(
@@ -0,0 +1,191 @@
+//! Transport-independent anti-entropy sync driven by [`SyncBroadcaster`] events.
+//!
+//! A [`SyncOrchestrator`] runs one task per peer over any [`PeerTransport`].
+//! Each task speaks the [`PeerMessage`] protocol of [`PeerManager`](crate::PeerManager)
+//! without its WebSocket handshake:
+//!
+//! - every upsert or delete published on the local broadcaster is pushed to
+//!   the peer as it happens;
+//! - once at start and then every anti-entropy interval, the task sends
+//!   `SyncRequest { state_clock }`, and the peer answers with
+//!   `Records(diff_since(clock))`, repairing anything a push missed;
+//! - records the peer sends are merged with [`CrdtStore::merge_remote`], and
+//!   those that change the store are re-published, so other peers' tasks
+//!   forward them in turn.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use pluresdb_core::CrdtStore;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+use crate::{PeerMessage, SyncBroadcaster, SyncEvent};
+
+/// A message channel to one peer, such as a QUIC stream, a WebSocket or an
+/// in-process [`MemTransport`].
+#[async_trait]
+pub trait PeerTransport: Send {
+    /// Send `message` to the peer.
+    async fn send(&mut self, message: PeerMessage) -> Result<()>;
+
+    /// Wait for the peer's next message, or `None` once it has closed.
+    ///
+    /// Must be cancel-safe: the orchestrator drops a pending call whenever
+    /// a local write or a tick arrives first, and no message may be lost.
+    async fn receive(&mut self) -> Result<Option<PeerMessage>>;
+}
+
+/// In-process [`PeerTransport`] over unbounded channels, for tests and for
+/// syncing stores within one process.
+#[derive(Debug)]
+pub struct MemTransport {
+    tx: mpsc::UnboundedSender<PeerMessage>,
+    rx: mpsc::UnboundedReceiver<PeerMessage>,
+}
+
+impl MemTransport {
+    /// Two connected ends: what one sends, the other receives.
+    pub fn pair() -> (MemTransport, MemTransport) {
+        let (tx_a, rx_a) = mpsc::unbounded_channel();
+        let (tx_b, rx_b) = mpsc::unbounded_channel();
+        (
+            MemTransport { tx: tx_a, rx: rx_b },
+            MemTransport { tx: tx_b, rx: rx_a },
+        )
+    }
+}
+
+#[async_trait]
+impl PeerTransport for MemTransport {
+    async fn send(&mut self, message: PeerMessage) -> Result<()> {
+        self.tx
+            .send(message)
+            .context("MemTransport: send failed — peer dropped")
+    }
+
+    async fn receive(&mut self) -> Result<Option<PeerMessage>> {
+        Ok(self.rx.recv().await)
+    }
+}
+
+/// Keeps a store in sync with its peers; see the [module docs](self).
+#[derive(Debug)]
+pub struct SyncOrchestrator {
+    store: Arc<CrdtStore>,
+    broadcaster: Arc<SyncBroadcaster>,
+    anti_entropy_interval: Duration,
+}
+
+impl SyncOrchestrator {
+    const DEFAULT_ANTI_ENTROPY_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// An orchestrator for `store`.  Local writes must be published on
+    /// `broadcaster` to be pushed to peers.
+    pub fn new(store: Arc<CrdtStore>, broadcaster: Arc<SyncBroadcaster>) -> Self {
+        Self {
+            store,
+            broadcaster,
+            anti_entropy_interval: Self::DEFAULT_ANTI_ENTROPY_INTERVAL,
+        }
+    }
+
+    /// How often to ask each peer for what this store is missing
+    /// (default 30 s).
+    pub fn with_anti_entropy_interval(mut self, interval: Duration) -> Self {
+        self.anti_entropy_interval = interval;
+        self
+    }
+
+    /// Sync with the peer behind `transport` on a new task, until either
+    /// side closes.
+    pub fn spawn<T>(self: &Arc<Self>, transport: T) -> JoinHandle<Result<()>>
+    where
+        T: PeerTransport + 'static,
+    {
+        let orchestrator = Arc::clone(self);
+        tokio::spawn(async move { orchestrator.run(transport).await })
+    }
+
+    /// Sync with the peer behind `transport` until it closes or the
+    /// broadcaster is dropped.  Fails if the transport does.
+    pub async fn run<T: PeerTransport>(&self, mut transport: T) -> Result<()> {
+        // Subscribe before the first sync request so no write between the
+        // diff and the live stream is lost
+        let mut events = self.broadcaster.subscribe_reliable();
+        let mut ticks = tokio::time::interval(self.anti_entropy_interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let outgoing = tokio::select! {
+                message = transport.receive() => match message? {
+                    Some(message) => self.handle(message),
+                    None => return Ok(()),
+                },
+                event = events.recv() => match event {
+                    Some(SyncEvent::NodeUpsert { id } | SyncEvent::NodeUpserted { id, .. }) => {
+                        // Deleted again before we got to it; the delete event follows
+                        self.store
+                            .get(&id)
+                            .map(|record| PeerMessage::Records { records: vec![record] })
+                    }
+                    Some(SyncEvent::NodeDelete { id }) => Some(PeerMessage::Delete { id }),
+                    Some(SyncEvent::ResyncRequired { missed }) => {
+                        warn!("[SyncOrchestrator] lagged by {} events; resending every record", missed);
+                        Some(PeerMessage::Records {
+                            records: self.store.list_including_tombstones(),
+                        })
+                    }
+                    Some(_) => None,
+                    None => return Ok(()),
+                },
+                _ = ticks.tick() => Some(PeerMessage::SyncRequest {
+                    clock: self.store.state_clock(),
+                }),
+            };
+            if let Some(message) = outgoing {
+                transport.send(message).await?;
+            }
+        }
+    }
+
+    /// Apply one message from a peer, returning the reply if it needs one.
+    fn handle(&self, message: PeerMessage) -> Option<PeerMessage> {
+        match message {
+            PeerMessage::Hello { clock, .. } | PeerMessage::SyncRequest { clock } => {
+                Some(PeerMessage::Records {
+                    records: self.store.diff_since(&clock),
+                })
+            }
+            PeerMessage::Records { records } => {
+                for record in records {
+                    let id = record.id.clone();
+                    if self.store.merge_remote(record) {
+                        self.changed(id);
+                    }
+                }
+                None
+            }
+            PeerMessage::Delete { id } => {
+                if self.store.delete(&id).is_ok() {
+                    self.changed(id);
+                }
+                None
+            }
+        }
+    }
+
+    /// Tell local subscribers, other peers' tasks among them, about a
+    /// change a peer made.
+    fn changed(&self, id: String) {
+        let event = match self.store.get(&id) {
+            Some(_) => SyncEvent::NodeUpsert { id },
+            None => SyncEvent::NodeDelete { id },
+        };
+        debug!("[SyncOrchestrator] applied peer change: {:?}", event);
+        let _ = self.broadcaster.publish(event);
+    }
+}
@@ -0,0 +1,499 @@
+//! Anti-entropy sync between PluresDB instances over WebSocket.
+//!
+//! A [`PeerManager`] owns one instance's connections to its peers.  Either
+//! side can dial: [`PeerManager::connect`] opens a WebSocket to another
+//! instance's [`router`](PeerManager::router), which accepts it at `/sync`.
+//! Both ends then run the same protocol, one JSON [`PeerMessage`] per text
+//! frame:
+//!
+//! ```text
+//!  A                                           B
+//!  │ ── Hello { peer_id, state_clock } ──────► │
+//!  │ ◄────── Hello { peer_id, state_clock } ── │
+//!  │ ── Records(diff_since(B's clock)) ──────► │
+//!  │ ◄────── Records(diff_since(A's clock)) ── │
+//!  │ ◄─────── Records / Delete (live) ───────► │
+//! ```
+//!
+//! After the handshake, every upsert or delete published on the local
+//! [`SyncBroadcaster`] is forwarded to each peer.  Records a peer sends are
+//! merged with [`CrdtStore::merge_remote`], and those that change the store
+//! are re-published, so writes spread across a mesh and stop once every
+//! instance has them.  [`PeerManager::sync`] runs another anti-entropy round
+//! on demand.
+
+use std::fmt::Display;
+use std::future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::extract::ws::{Message as AxumMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use dashmap::DashMap;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use pluresdb_core::{CrdtStore, NodeRecord, VectorClock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
+use tracing::{debug, info, warn};
+
+use crate::{SyncBroadcaster, SyncEvent};
+
+/// How long a new connection may take to send its `Hello`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Wire format exchanged between [`PeerManager`]s.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PeerMessage {
+    /// First message on a connection: who the sender is and what it has.
+    Hello { peer_id: String, clock: VectorClock },
+    /// Ask for every record the sender may be missing, given its clock.
+    SyncRequest { clock: VectorClock },
+    /// Records for the receiver to merge: a diff or a live write.
+    Records { records: Vec<NodeRecord> },
+    /// A node was deleted.
+    Delete { id: String },
+}
+
+impl PeerMessage {
+    pub fn encode(&self) -> Result<String> {
+        serde_json::to_string(self).context("failed to encode peer message")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).context("failed to decode peer message")
+    }
+}
+
+/// A live connection, as reported by [`PeerManager::peers`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PeerStatus {
+    /// The id the peer announced in its `Hello`.
+    pub peer_id: String,
+    /// The URL dialled for outbound connections; `"inbound"` otherwise.
+    pub address: String,
+    /// Whether this instance opened the connection.
+    pub outbound: bool,
+    /// When the handshake completed, in milliseconds since the Unix epoch.
+    pub connected_at_ms: u64,
+}
+
+/// Called after a peer's message changes the store, with the node's id and
+/// its visible record, or `None` if the node is now deleted.
+pub type MergeHook = Arc<dyn Fn(&str, Option<&NodeRecord>) + Send + Sync>;
+
+enum Command {
+    Send(PeerMessage),
+    Close,
+}
+
+struct PeerHandle {
+    status: PeerStatus,
+    /// Distinguishes this connection from a later one to the same peer.
+    session: u64,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+/// Connections from one instance to its peers; see the [module docs](self).
+pub struct PeerManager {
+    peer_id: String,
+    store: Arc<CrdtStore>,
+    broadcaster: Arc<SyncBroadcaster>,
+    peers: DashMap<String, PeerHandle>,
+    next_session: AtomicU64,
+    merge_hook: Option<MergeHook>,
+}
+
+impl std::fmt::Debug for PeerManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeerManager")
+            .field("peer_id", &self.peer_id)
+            .field("peers", &self.peers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PeerManager {
+    /// A manager that syncs `store` as `peer_id`.  Local writes must be
+    /// published on `broadcaster` to reach peers.
+    pub fn new(
+        peer_id: impl Into<String>,
+        store: Arc<CrdtStore>,
+        broadcaster: Arc<SyncBroadcaster>,
+    ) -> Self {
+        Self {
+            peer_id: peer_id.into(),
+            store,
+            broadcaster,
+            peers: DashMap::new(),
+            next_session: AtomicU64::new(0),
+            merge_hook: None,
+        }
+    }
+
+    /// Call `hook` whenever a peer changes the store, for example to write
+    /// the change through to a storage engine the store does not own.
+    pub fn with_merge_hook(
+        mut self,
+        hook: impl Fn(&str, Option<&NodeRecord>) + Send + Sync + 'static,
+    ) -> Self {
+        self.merge_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// This instance's id, sent to peers in `Hello`.
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    /// Every live connection, ordered by peer id.
+    pub fn peers(&self) -> Vec<PeerStatus> {
+        let mut peers: Vec<PeerStatus> = self
+            .peers
+            .iter()
+            .map(|entry| entry.status.clone())
+            .collect();
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        peers
+    }
+
+    /// Ask `peer_id`, or every peer if `None`, for what this instance is
+    /// missing.  Returns how many peers were asked.
+    pub fn sync(&self, peer_id: Option<&str>) -> Result<usize> {
+        let clock = self.store.state_clock();
+        let request = |handle: &PeerHandle| {
+            let message = PeerMessage::SyncRequest {
+                clock: clock.clone(),
+            };
+            handle.commands.send(Command::Send(message)).is_ok()
+        };
+        match peer_id {
+            Some(id) => {
+                let handle = self
+                    .peers
+                    .get(id)
+                    .ok_or_else(|| anyhow!("not connected to peer '{}'", id))?;
+                Ok(usize::from(request(&handle)))
+            }
+            None => Ok(self.peers.iter().filter(|entry| request(entry)).count()),
+        }
+    }
+
+    /// Close the connection to `peer_id`.  Returns `false` if there was none.
+    pub fn disconnect(&self, peer_id: &str) -> bool {
+        match self.peers.remove(peer_id) {
+            Some((_, handle)) => {
+                let _ = handle.commands.send(Command::Close);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Open a WebSocket to the `/sync` endpoint at `url`, exchange diffs,
+    /// and keep streaming writes in the background.  Returns the peer's id
+    /// once the handshake is done.
+    pub async fn connect(self: &Arc<Self>, url: &str) -> Result<String> {
+        let (socket, _) = connect_async(url)
+            .await
+            .with_context(|| format!("failed to connect to peer at {}", url))?;
+        let (sink, stream) = socket.split();
+        let sink = sink.with(|text: String| {
+            future::ready(Ok::<_, tokio_tungstenite::tungstenite::Error>(
+                WsMessage::text(text),
+            ))
+        });
+        let stream = stream
+            .take_while(|frame| future::ready(frame.is_ok()))
+            .filter_map(|frame| {
+                future::ready(match frame {
+                    Ok(WsMessage::Text(text)) => Some(text.as_bytes().to_vec()),
+                    Ok(WsMessage::Binary(bytes)) => Some(bytes.to_vec()),
+                    _ => None,
+                })
+            });
+        self.start_session(url.to_string(), true, Box::pin(sink), Box::pin(stream))
+            .await
+    }
+
+    /// A router accepting peer connections at `/sync`, to be merged into
+    /// the instance's HTTP server.
+    pub fn router(self: &Arc<Self>) -> Router {
+        Router::new()
+            .route("/sync", get(sync_handler))
+            .with_state(Arc::clone(self))
+    }
+
+    /// Serve one accepted WebSocket until it closes.
+    pub async fn accept(self: Arc<Self>, socket: WebSocket) {
+        let (sink, stream) = socket.split();
+        let sink = sink.with(|text: String| {
+            future::ready(Ok::<_, axum::Error>(AxumMessage::Text(text.into())))
+        });
+        let stream = stream
+            .take_while(|frame| future::ready(frame.is_ok()))
+            .filter_map(|frame| {
+                future::ready(match frame {
+                    Ok(AxumMessage::Text(text)) => Some(text.as_bytes().to_vec()),
+                    Ok(AxumMessage::Binary(bytes)) => Some(bytes.to_vec()),
+                    _ => None,
+                })
+            });
+        if let Err(e) = self
+            .start_session(
+                "inbound".to_string(),
+                false,
+                Box::pin(sink),
+                Box::pin(stream),
+            )
+            .await
+        {
+            warn!("[PeerManager] rejected inbound peer: {:#}", e);
+        }
+    }
+
+    /// Run the handshake on a fresh connection, register the peer, and
+    /// spawn the task that serves it.
+    async fn start_session<S, R>(
+        self: &Arc<Self>,
+        address: String,
+        outbound: bool,
+        mut sink: S,
+        mut stream: R,
+    ) -> Result<String>
+    where
+        S: Sink<String> + Unpin + Send + 'static,
+        S::Error: Display,
+        R: Stream<Item = Vec<u8>> + Unpin + Send + 'static,
+    {
+        // Subscribe first so no write between the diff and the live stream
+        // is lost
+        let events = self.broadcaster.subscribe();
+        let hello = PeerMessage::Hello {
+            peer_id: self.peer_id.clone(),
+            clock: self.store.state_clock(),
+        };
+        send(&mut sink, &hello).await?;
+
+        let frame = tokio::time::timeout(HANDSHAKE_TIMEOUT, stream.next())
+            .await
+            .map_err(|_| anyhow!("peer did not say hello within {:?}", HANDSHAKE_TIMEOUT))?
+            .ok_or_else(|| anyhow!("peer closed the connection during the handshake"))?;
+        let PeerMessage::Hello { peer_id, clock } = PeerMessage::decode(&frame)? else {
+            bail!("peer did not start with hello");
+        };
+        if peer_id == self.peer_id {
+            bail!("refusing to connect to this instance itself");
+        }
+
+        let (commands, inbox) = mpsc::unbounded_channel();
+        let session = self.next_session.fetch_add(1, Ordering::Relaxed);
+        match self.peers.entry(peer_id.clone()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => {
+                bail!("already connected to peer '{}'", peer_id)
+            }
+            dashmap::mapref::entry::Entry::Vacant(slot) => {
+                slot.insert(PeerHandle {
+                    status: PeerStatus {
+                        peer_id: peer_id.clone(),
+                        address,
+                        outbound,
+                        connected_at_ms: now_ms(),
+                    },
+                    session,
+                    commands,
+                });
+            }
+        }
+        info!("[PeerManager] connected to peer {}", peer_id);
+        let _ = self.broadcaster.publish(SyncEvent::PeerConnected {
+            peer_id: peer_id.clone(),
+        });
+
+        let diff = PeerMessage::Records {
+            records: self.store.diff_since(&clock),
+        };
+        let manager = Arc::clone(self);
+        let remote = peer_id.clone();
+        tokio::spawn(async move {
+            let result = match send(&mut sink, &diff).await {
+                Ok(()) => {
+                    manager
+                        .serve(&remote, &mut sink, &mut stream, inbox, events)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("[PeerManager] connection to {} failed: {:#}", remote, e);
+            }
+            manager
+                .peers
+                .remove_if(&remote, |_, handle| handle.session == session);
+            let _ = sink.close().await;
+            info!("[PeerManager] disconnected from peer {}", remote);
+            let _ = manager
+                .broadcaster
+                .publish(SyncEvent::PeerDisconnected { peer_id: remote });
+        });
+        Ok(peer_id)
+    }
+
+    /// Relay frames, local writes, and commands until either side closes.
+    async fn serve<S, R>(
+        &self,
+        remote: &str,
+        sink: &mut S,
+        stream: &mut R,
+        mut inbox: mpsc::UnboundedReceiver<Command>,
+        mut events: tokio::sync::broadcast::Receiver<SyncEvent>,
+    ) -> Result<()>
+    where
+        S: Sink<String> + Unpin,
+        S::Error: Display,
+        R: Stream<Item = Vec<u8>> + Unpin,
+    {
+        loop {
+            let outgoing = tokio::select! {
+                frame = stream.next() => {
+                    let Some(frame) = frame else {
+                        return Ok(());
+                    };
+                    match PeerMessage::decode(&frame) {
+                        Ok(message) => self.handle(message),
+                        Err(e) => {
+                            warn!("[PeerManager] ignoring bad frame from {}: {:#}", remote, e);
+                            None
+                        }
+                    }
+                }
+                command = inbox.recv() => match command {
+                    Some(Command::Send(message)) => Some(message),
+                    Some(Command::Close) | None => return Ok(()),
+                },
+                event = events.recv() => match event {
+                    Ok(SyncEvent::NodeUpsert { id } | SyncEvent::NodeUpserted { id, .. }) => {
+                        // Deleted again before we got to it; the delete event follows
+                        self.store
+                            .get(&id)
+                            .map(|record| PeerMessage::Records { records: vec![record] })
+                    }
+                    Ok(SyncEvent::NodeDelete { id }) => Some(PeerMessage::Delete { id }),
+                    Ok(_) => None,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("[PeerManager] lagged by {} events; resending every record", missed);
+                        Some(PeerMessage::Records {
+                            records: self.store.list_including_tombstones(),
+                        })
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+            };
+            if let Some(message) = outgoing {
+                send(sink, &message).await?;
+            }
+        }
+    }
+
+    /// Apply one message from a peer, returning the reply if it needs one.
+    fn handle(&self, message: PeerMessage) -> Option<PeerMessage> {
+        match message {
+            PeerMessage::Hello { clock, .. } | PeerMessage::SyncRequest { clock } => {
+                Some(PeerMessage::Records {
+                    records: self.store.diff_since(&clock),
+                })
+            }
+            PeerMessage::Records { records } => {
+                for record in records {
+                    let id = record.id.clone();
+                    if self.store.merge_remote(record) {
+                        self.changed(&id);
+                    }
+                }
+                None
+            }
+            PeerMessage::Delete { id } => {
+                if self.store.delete(&id).is_ok() {
+                    self.changed(&id);
+                }
+                None
+            }
+        }
+    }
+
+    /// Report a change made by a peer to the hook and local subscribers.
+    fn changed(&self, id: &str) {
+        let record = self.store.get(id);
+        if let Some(hook) = &self.merge_hook {
+            hook(id, record.as_ref());
+        }
+        let id = id.to_string();
+        let event = match record {
+            Some(_) => SyncEvent::NodeUpsert { id },
+            None => SyncEvent::NodeDelete { id },
+        };
+        debug!("[PeerManager] applied peer change: {:?}", event);
+        let _ = self.broadcaster.publish(event);
+    }
+}
+
+async fn sync_handler(
+    ws: WebSocketUpgrade,
+    State(manager): State<Arc<PeerManager>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| manager.accept(socket))
+}
+
+async fn send<S>(sink: &mut S, message: &PeerMessage) -> Result<()>
+where
+    S: Sink<String> + Unpin,
+    S::Error: Display,
+{
+    sink.send(message.encode()?)
+        .await
+        .map_err(|e| anyhow!("failed to send to peer: {}", e))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn peer_messages_round_trip_as_tagged_json() {
+        let store = CrdtStore::default();
+        store.put("n1", "a", json!({ "v": 1 }));
+        let message = PeerMessage::Records {
+            records: store.list(),
+        };
+        let text = message.encode().unwrap();
+        assert!(text.starts_with(r#"{"type":"records""#), "{text}");
+        assert_eq!(PeerMessage::decode(text.as_bytes()).unwrap(), message);
+    }
+
+    #[tokio::test]
+    async fn sync_and_disconnect_report_unknown_peers() {
+        let manager = PeerManager::new(
+            "a",
+            Arc::new(CrdtStore::default()),
+            Arc::new(SyncBroadcaster::default()),
+        );
+        assert!(manager.peers().is_empty());
+        assert_eq!(manager.sync(None).unwrap(), 0);
+        assert!(manager.sync(Some("b")).is_err());
+        assert!(!manager.disconnect("b"));
+    }
+}
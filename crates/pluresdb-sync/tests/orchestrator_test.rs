@@ -0,0 +1,99 @@
+//! Two in-process stores kept in sync by [`SyncOrchestrator`]s over an
+//! in-memory [`MemTransport`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use pluresdb_core::CrdtStore;
+use pluresdb_sync::{MemTransport, SyncBroadcaster, SyncEvent, SyncOrchestrator};
+use serde_json::json;
+
+const TICK: Duration = Duration::from_millis(100);
+
+struct Replica {
+    store: Arc<CrdtStore>,
+    hub: Arc<SyncBroadcaster>,
+    orchestrator: Arc<SyncOrchestrator>,
+}
+
+impl Replica {
+    fn new() -> Self {
+        let store = Arc::new(CrdtStore::default());
+        let hub = Arc::new(SyncBroadcaster::default());
+        let orchestrator = Arc::new(
+            SyncOrchestrator::new(Arc::clone(&store), Arc::clone(&hub))
+                .with_anti_entropy_interval(TICK),
+        );
+        Self {
+            store,
+            hub,
+            orchestrator,
+        }
+    }
+
+    fn put(&self, id: &str, data: serde_json::Value) {
+        self.store.put(id, "local", data);
+        self.hub
+            .publish(SyncEvent::upserted(&self.store, id.to_string()))
+            .unwrap();
+    }
+}
+
+/// Two replicas syncing with each other.
+fn connected() -> (Replica, Replica) {
+    let (a, b) = (Replica::new(), Replica::new());
+    let (to_b, to_a) = MemTransport::pair();
+    a.orchestrator.spawn(to_b);
+    b.orchestrator.spawn(to_a);
+    (a, b)
+}
+
+/// Poll `condition` for up to one anti-entropy tick.
+async fn within_a_tick(condition: impl Fn() -> bool) -> bool {
+    let deadline = tokio::time::Instant::now() + TICK;
+    while tokio::time::Instant::now() < deadline {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    condition()
+}
+
+#[tokio::test]
+async fn local_put_reaches_the_peer_within_a_tick() {
+    let (a, b) = connected();
+    // Let both tasks subscribe and finish their first anti-entropy round
+    tokio::time::sleep(TICK / 5).await;
+
+    a.put("note", json!({ "text": "hello" }));
+    assert!(within_a_tick(|| b.store.get("note").is_some()).await);
+    assert_eq!(
+        b.store.get("note").unwrap().data,
+        json!({ "text": "hello" })
+    );
+
+    b.store.delete("note").unwrap();
+    b.hub
+        .publish(SyncEvent::NodeDelete {
+            id: "note".to_string(),
+        })
+        .unwrap();
+    assert!(within_a_tick(|| a.store.get("note").is_none()).await);
+}
+
+#[tokio::test]
+async fn anti_entropy_pulls_writes_that_were_never_published() {
+    let (a, b) = connected();
+    tokio::time::sleep(TICK / 5).await;
+
+    // Written without an event, so only the periodic diff can carry it
+    a.store.put("quiet", "local", json!({ "n": 1 }));
+    assert!(b.store.get("quiet").is_none());
+    tokio::time::sleep(TICK).await;
+    assert!(within_a_tick(|| b.store.get("quiet").is_some()).await);
+    assert_eq!(
+        b.store.get("quiet").unwrap().clock,
+        a.store.get("quiet").unwrap().clock
+    );
+}
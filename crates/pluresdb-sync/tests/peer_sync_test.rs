@@ -0,0 +1,115 @@
+//! Two in-process PluresDB instances syncing through [`PeerManager`] over
+//! real WebSockets on ephemeral ports.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use pluresdb_core::CrdtStore;
+use pluresdb_sync::{PeerManager, SyncBroadcaster, SyncEvent};
+use serde_json::json;
+
+struct Instance {
+    store: Arc<CrdtStore>,
+    hub: Arc<SyncBroadcaster>,
+    peers: Arc<PeerManager>,
+    url: String,
+}
+
+impl Instance {
+    /// Start an instance serving `/sync` on a random local port.
+    async fn start(peer_id: &str) -> Self {
+        let store = Arc::new(CrdtStore::default());
+        let hub = Arc::new(SyncBroadcaster::default());
+        let peers = Arc::new(PeerManager::new(
+            peer_id,
+            Arc::clone(&store),
+            Arc::clone(&hub),
+        ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/sync", listener.local_addr().unwrap());
+        let router = peers.router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Self {
+            store,
+            hub,
+            peers,
+            url,
+        }
+    }
+
+    fn put(&self, id: &str, data: serde_json::Value) {
+        self.store.put(id, self.peers.peer_id(), data);
+        self.hub
+            .publish(SyncEvent::NodeUpsert { id: id.to_string() })
+            .unwrap();
+    }
+
+    fn snapshot(&self) -> Vec<(String, serde_json::Value)> {
+        let mut nodes: Vec<_> = self
+            .store
+            .list()
+            .into_iter()
+            .map(|record| (record.id, record.data))
+            .collect();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+        nodes
+    }
+}
+
+/// Poll `condition` for up to two seconds.
+async fn eventually(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..200 {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn two_instances_exchange_diffs_then_stream_writes() {
+    let a = Instance::start("peer-a").await;
+    let b = Instance::start("peer-b").await;
+    a.put("only-a", json!({ "n": 1 }));
+    b.put("only-b", json!({ "n": 2 }));
+    b.put("doomed", json!({}));
+
+    assert_eq!(a.peers.connect(&b.url).await.unwrap(), "peer-b");
+    assert!(
+        eventually(|| a.snapshot().len() == 3 && a.snapshot() == b.snapshot()).await,
+        "a: {:?}\nb: {:?}",
+        a.snapshot(),
+        b.snapshot()
+    );
+    let connected = a.peers.peers();
+    assert_eq!(connected.len(), 1);
+    assert_eq!(connected[0].peer_id, "peer-b");
+    assert!(connected[0].outbound);
+    assert_eq!(b.peers.peers()[0].peer_id, "peer-a");
+    assert!(a.peers.connect(&b.url).await.is_err());
+
+    // Live writes and deletes on either side reach the other
+    a.put("live", json!({ "from": "a" }));
+    b.store.delete("doomed").unwrap();
+    b.hub
+        .publish(SyncEvent::NodeDelete {
+            id: "doomed".to_string(),
+        })
+        .unwrap();
+    assert!(
+        eventually(|| {
+            b.store
+                .get("live")
+                .is_some_and(|record| record.data == json!({ "from": "a" }))
+                && a.store.get("doomed").is_none()
+        })
+        .await
+    );
+    assert_eq!(a.snapshot(), b.snapshot());
+
+    assert_eq!(a.peers.sync(None).unwrap(), 1);
+    assert!(a.peers.disconnect("peer-b"));
+    assert!(a.peers.peers().is_empty());
+    assert!(eventually(|| b.peers.peers().is_empty()).await);
+}
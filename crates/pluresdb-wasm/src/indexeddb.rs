@@ -6,6 +6,7 @@
  */
 
 use js_sys::Promise;
+use pluresdb_core::NodeRecord;
 use serde_json::Value;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
@@ -173,6 +174,72 @@ impl IndexedDBStore {
         Ok(())
     }
 
+    /// Save `record` in full under its id, clock and timestamp included, so
+    /// causal history survives a reload
+    pub async fn put_record(&self, record: &NodeRecord) -> Result<(), JsValue> {
+        let value = serde_json::to_value(record)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize record: {}", e)))?;
+        self.put(&record.id, &value).await
+    }
+
+    /// Save every record in one readwrite transaction, so either all of them
+    /// are stored or, if any write fails, none are
+    pub async fn put_records(&self, records: &[NodeRecord]) -> Result<(), JsValue> {
+        let values = records
+            .iter()
+            .map(|record| {
+                let value = serde_json::to_value(record).map_err(|e| {
+                    JsValue::from_str(&format!("Failed to serialize record: {}", e))
+                })?;
+                serde_wasm_bindgen::to_value(&value)
+                    .map_err(|e| JsValue::from_str(&format!("Failed to serialize: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let transaction = self
+            .db
+            .transaction_with_str_and_mode("nodes", IdbTransactionMode::Readwrite)
+            .map_err(|e| JsValue::from_str(&format!("Failed to create transaction: {:?}", e)))?;
+
+        let object_store = transaction
+            .object_store("nodes")
+            .map_err(|e| JsValue::from_str(&format!("Failed to get object store: {:?}", e)))?;
+
+        // Queue every put before awaiting any, so the transaction stays
+        // active until the last one lands
+        let mut requests = Vec::with_capacity(records.len());
+        for (record, value) in records.iter().zip(&values) {
+            match object_store.put_with_key(value, &JsValue::from_str(&record.id)) {
+                Ok(request) => requests.push(request),
+                Err(e) => {
+                    let _ = transaction.abort();
+                    return Err(JsValue::from_str(&format!("Failed to put value: {:?}", e)));
+                }
+            }
+        }
+
+        // A failed request aborts the transaction, rolling back the others
+        for request in requests {
+            JsFuture::from(request_to_promise(request)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Load every saved record, ready for `CrdtStore::insert_record`
+    pub async fn load_records(&self) -> Result<Vec<NodeRecord>, JsValue> {
+        let mut records = Vec::new();
+        for key in self.get_all_keys().await? {
+            if let Some(value) = self.get(&key).await? {
+                let record = serde_json::from_value(value).map_err(|e| {
+                    JsValue::from_str(&format!("Failed to parse record {}: {}", key, e))
+                })?;
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
     /// Get all keys from IndexedDB
     pub async fn get_all_keys(&self) -> Result<Vec<String>, JsValue> {
         let transaction = self
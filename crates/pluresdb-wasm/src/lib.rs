@@ -15,11 +15,12 @@ use std::sync::Arc;
 
 use chrono::{DateTime, TimeZone, Utc};
 use js_sys::{Function, Object};
-use pluresdb_core::CrdtStore;
+use pluresdb_core::{ActorId, ActorIdExt, CrdtStore, MergeOutcome, NodeRecord};
 use pluresdb_procedures::agens::{AgensEvent, AgensRuntime, StateTable, TimerTable};
 use pluresdb_procedures::engine::ProcedureEngine;
 use pluresdb_procedures::ir::Step;
 use pluresdb_storage::MemoryStorage;
+use serde::Serialize;
 use serde_wasm_bindgen::{from_value, to_value};
 use wasm_bindgen::prelude::*;
 
@@ -45,11 +46,11 @@ impl PluresDBBrowser {
     /// Create a new in-memory PluresDB instance.
     ///
     /// `db_name` is reserved for future IndexedDB persistence.
-    /// `actor_id` defaults to `"browser"` when not supplied.
+    /// `actor_id` defaults to a random id unique to this instance.
     #[wasm_bindgen(constructor)]
     pub fn new(_db_name: &str, actor_id: Option<String>) -> Self {
         console_error_panic_hook::set_once();
-        let actor = actor_id.unwrap_or_else(|| "browser".to_string());
+        let actor = actor_id.unwrap_or_else(ActorId::new_random);
         let storage = Arc::new(MemoryStorage::default());
         let store = Arc::new(CrdtStore::default().with_persistence(storage));
         Self {
@@ -62,8 +63,30 @@ impl PluresDBBrowser {
     pub fn put(&self, id: &str, data: JsValue) -> Result<String, JsValue> {
         let json: serde_json::Value =
             from_value(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
-        let node_id = self.store.put(id, &self.actor_id, json);
-        Ok(node_id)
+        self.store
+            .try_put(id, &self.actor_id, json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Insert or update every `{ id, data }` entry of a JS array. Returns
+    /// how many were written.
+    ///
+    /// The whole array is checked before anything is written, so a malformed
+    /// entry rejects the batch and leaves the store untouched.
+    pub fn put_many(&self, entries: JsValue) -> Result<usize, JsValue> {
+        #[derive(serde::Deserialize)]
+        struct Entry {
+            id: String,
+            data: serde_json::Value,
+        }
+
+        let entries: Vec<Entry> =
+            from_value(entries).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let items = entries
+            .into_iter()
+            .map(|entry| (entry.id, entry.data))
+            .collect();
+        Ok(self.store.put_batch(&self.actor_id, items).len())
     }
 
     /// Insert or update a record with a pre-computed embedding vector.
@@ -128,10 +151,87 @@ impl PluresDBBrowser {
         Ok(arr.into())
     }
 
+    /// Every record, tombstones included, with its clock and timestamp, as a
+    /// JSON-compatible array that [`import`](Self::import) accepts.
+    pub fn export(&self) -> Result<JsValue, JsValue> {
+        let records = self.store.list_including_tombstones();
+        records
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Merge the records of an [`export`](Self::export) snapshot with the
+    /// same clock-aware rules peers use.  Returns how many changed the store.
+    ///
+    /// Records this store has already seen are ignored, so importing the
+    /// same snapshot twice leaves clocks unchanged.  The whole snapshot is
+    /// parsed before anything is merged.
+    pub fn import(&self, data: JsValue) -> Result<usize, JsValue> {
+        let records: Vec<NodeRecord> =
+            from_value(data).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(records
+            .into_iter()
+            .map(|record| self.store.merge_record(record))
+            .filter(|outcome| *outcome != MergeOutcome::Ignored)
+            .count())
+    }
+
     /// Number of records currently stored.
     pub fn node_count(&self) -> usize {
         self.store.list().len()
     }
+
+    /// Records whose `data.type` equals `node_type`, as a JS array of
+    /// `{ id, data, timestamp }` objects.
+    pub fn list_by_type(&self, node_type: String) -> Result<JsValue, JsValue> {
+        let summaries: Vec<serde_json::Value> = self
+            .store
+            .list_by_type(&node_type)
+            .into_iter()
+            .map(|record| {
+                serde_json::json!({
+                    "id": record.id,
+                    "data": record.data,
+                    "timestamp": record.timestamp.to_rfc3339(),
+                })
+            })
+            .collect();
+        to_value(&summaries).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Records whose data contains `query`, ignoring case, ranked by how
+    /// often it occurs.
+    ///
+    /// Returns at most `limit` (default 10) `{ id, data, timestamp, score }`
+    /// objects, where `score` is the number of occurrences.
+    pub fn search(&self, query: String, limit: Option<u32>) -> Result<JsValue, JsValue> {
+        let query = query.to_lowercase();
+        let mut matches: Vec<_> = self
+            .store
+            .list()
+            .into_iter()
+            .filter_map(|record| {
+                let text = serde_json::to_string(&record.data).ok()?.to_lowercase();
+                let score = text.matches(&query).count();
+                (score > 0).then_some((record, score))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1));
+        matches.truncate(limit.unwrap_or(10) as usize);
+
+        let results: Vec<serde_json::Value> = matches
+            .into_iter()
+            .map(|(record, score)| {
+                serde_json::json!({
+                    "id": record.id,
+                    "data": record.data,
+                    "timestamp": record.timestamp.to_rfc3339(),
+                    "score": score,
+                })
+            })
+            .collect();
+        to_value(&results).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
 }
 
 /// Shared CRDT store for wasm runtimes.
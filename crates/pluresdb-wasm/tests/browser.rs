@@ -0,0 +1,119 @@
+//! Exercises `PluresDBBrowser` queries; run with `wasm-pack test --node`.
+
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::JSON;
+use pluresdb_wasm::PluresDBBrowser;
+use serde_json::{json, Value};
+use serde_wasm_bindgen::{from_value, to_value};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn seeded() -> PluresDBBrowser {
+    let db = PluresDBBrowser::new("test", None);
+    for (id, data) in [
+        (
+            "alice",
+            json!({ "type": "Person", "name": "Alice", "bio": "likes rust and more rust" }),
+        ),
+        (
+            "bob",
+            json!({ "type": "Person", "name": "Bob", "bio": "likes go" }),
+        ),
+        (
+            "post",
+            json!({ "type": "Post", "title": "Rust in the browser" }),
+        ),
+    ] {
+        db.put(id, to_value(&data).unwrap()).unwrap();
+    }
+    db
+}
+
+fn ids(results: &[Value]) -> Vec<&str> {
+    results
+        .iter()
+        .map(|result| result["id"].as_str().unwrap())
+        .collect()
+}
+
+#[wasm_bindgen_test]
+fn list_by_type_filters_on_the_type_field() {
+    let db = seeded();
+
+    let people: Vec<Value> = from_value(db.list_by_type("Person".to_string()).unwrap()).unwrap();
+    let mut people_ids = ids(&people);
+    people_ids.sort_unstable();
+    assert_eq!(people_ids, ["alice", "bob"]);
+    assert!(people.iter().all(|person| person["timestamp"].is_string()));
+    assert_eq!(people[0]["data"]["type"], "Person");
+
+    let none: Vec<Value> = from_value(db.list_by_type("Comment".to_string()).unwrap()).unwrap();
+    assert!(none.is_empty());
+}
+
+#[wasm_bindgen_test]
+fn search_ranks_by_occurrences_and_honours_the_limit() {
+    let db = seeded();
+
+    let results: Vec<Value> = from_value(db.search("RUST".to_string(), None).unwrap()).unwrap();
+    assert_eq!(ids(&results), ["alice", "post"]);
+    assert_eq!(results[0]["score"], 2);
+    assert_eq!(results[1]["data"]["title"], "Rust in the browser");
+
+    let limited: Vec<Value> = from_value(db.search("likes".to_string(), Some(1)).unwrap()).unwrap();
+    assert_eq!(limited.len(), 1);
+    let missing: Vec<Value> = from_value(db.search("python".to_string(), None).unwrap()).unwrap();
+    assert!(missing.is_empty());
+}
+
+/// Build the array through `JSON.parse`, so its entries are plain objects
+/// as a browser caller would pass them.
+fn js_array(value: &Value) -> JsValue {
+    JSON::parse(&value.to_string()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn put_many_imports_a_batch_or_nothing() {
+    let db = PluresDBBrowser::new("test", None);
+    let batch: Value = (0..50)
+        .map(|i| json!({ "id": format!("item-{i}"), "data": { "type": "Item", "n": i } }))
+        .collect();
+    assert_eq!(db.put_many(js_array(&batch)).unwrap(), 50);
+    assert_eq!(db.node_count(), 50);
+    let item: Value = from_value(db.get("item-49").unwrap()).unwrap();
+    assert_eq!(item["data"]["n"], 49);
+
+    let bad = json!([
+        { "id": "fresh", "data": { "n": 1 } },
+        { "data": { "n": 2 } },
+    ]);
+    assert!(db.put_many(js_array(&bad)).is_err());
+    assert_eq!(db.node_count(), 50);
+    assert!(db.get("fresh").unwrap().is_null());
+}
+
+#[wasm_bindgen_test]
+fn export_then_import_into_an_empty_store_round_trips_records_and_clocks() {
+    let db = seeded();
+    db.delete("bob");
+    let snapshot: Value = from_value(db.export().unwrap()).unwrap();
+    assert_eq!(snapshot.as_array().unwrap().len(), 3);
+
+    let copy = PluresDBBrowser::new("copy", Some("other".to_string()));
+    assert_eq!(copy.import(js_array(&snapshot)).unwrap(), 3);
+    assert_eq!(copy.node_count(), 2);
+    assert!(copy.get("bob").unwrap().is_null());
+    let restored: Value = from_value(copy.export().unwrap()).unwrap();
+    let by_id = |records: &Value| {
+        let mut records = records.as_array().unwrap().clone();
+        records.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+        records
+    };
+    assert_eq!(by_id(&restored), by_id(&snapshot));
+
+    // Importing the same snapshot again changes nothing
+    assert_eq!(copy.import(js_array(&snapshot)).unwrap(), 0);
+    let again: Value = from_value(copy.export().unwrap()).unwrap();
+    assert_eq!(by_id(&again), by_id(&snapshot));
+}
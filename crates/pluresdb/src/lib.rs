@@ -35,34 +35,56 @@
 //! - `async`: Enables async/await support (included in default)
 
 // Re-export core types
+pub use pluresdb_core::{canonical_json, json_depth};
 pub use pluresdb_core::{
-    ActorId, CoreErrorCode, CrdtOperation, CrdtStore, EmbedText, NoOpPlugin, NodeData, NodeId,
-    NodeRecord, PluresLmPlugin, VectorClock, VectorIndex, VectorSearchResult,
+    ActorId, ActorIdExt, AuditEvent, AuditOp, AuditSink, CachedEmbedder, ClockOrdering,
+    ConflictOutcome, ConflictPreview, ConflictStrategy, CoreErrorCode, CrdtOperation, CrdtStore,
+    CrdtValue, Direction, DistanceMetric, EmbedText, EmbeddingCacheStats, EmbeddingConfig,
+    ErrorKind, FileAuditSink, GCounter, IdStrategy, JsonFilter, JsonPatch, MemoryAuditSink,
+    MergeOutcome, NoOpPlugin, NodeData, NodeId, NodeRecord, NodeWatch, PluresLmPlugin,
+    RetryingEmbedder, StoreMetrics, StoreSnapshot, TransientEmbedError, TraverseOpts, TypeRegistry,
+    ValidationError, VectorClock, VectorError, VectorIndex, VectorSearchResult, ACTOR_ID_FILE,
     DEFAULT_EMBEDDING_DIM,
 };
 
 #[cfg(feature = "sqlite-compat")]
-pub use pluresdb_core::{Database, DatabaseOptions, DatabasePath, QueryResult, SqlValue};
+pub use pluresdb_core::sql_params;
+#[cfg(feature = "sqlite-compat")]
+pub use pluresdb_core::{
+    BlobHandle, CheckpointMode, CheckpointResult, Database, DatabaseOptions, DatabasePath,
+    DatabasePool, PooledDatabase, QueryCacheStats, QueryResult, SqlValue, SqliteStorage,
+};
 
 #[cfg(feature = "embeddings")]
 pub use pluresdb_core::FastEmbedder;
 
 // Re-export storage types
 pub use pluresdb_storage::{
-    EncryptionConfig, EncryptionMetadata, MemoryStorage, ReplayStats, SledStorage, StorageEngine,
-    StorageErrorCode, StoredNode, WalEntry, WalOperation, WriteAheadLog,
+    CacheStats, CachingStorage, ConsistencyReport, EncryptionConfig, EncryptionMetadata,
+    FlushPolicy, MemoryStorage, RecoveryPolicy, ReplayStats, SledStorage, StorageChange,
+    StorageEngine, StorageErrorCode, StorageFormat, StorageMetrics, StoredNode, TieredStats,
+    TieredStorage, WalBackedStorage, WalCursor, WalEntry, WalOperation, WalValidation,
+    WriteAheadLog,
 };
 
 // Re-export sync types
-pub use pluresdb_sync::{GunRelayServer, SyncBroadcaster, SyncErrorCode, SyncEvent};
+pub use pluresdb_sync::{
+    GunRelayServer, PeerTransport, ReliableReceiver, SyncBridge, SyncBroadcaster, SyncErrorCode,
+    SyncEvent, SyncOrchestrator,
+};
 
 // Re-export commonly used error types
 #[cfg(feature = "sqlite-compat")]
 pub use pluresdb_core::DatabaseError;
 pub use pluresdb_core::StoreError as CoreError;
 
-// Re-export storage replay utilities
-pub use pluresdb_storage::{metadata_pruning, rebuild_from_wal, replay_wal};
+// Re-export replay utilities; the path-based map replays remain available
+// from `pluresdb_storage`
+pub use pluresdb_core::replay::{rebuild_from_wal, replay_wal};
+pub use pluresdb_storage::{metadata_pruning, verify_consistency};
+
+// Re-export backup utilities
+pub use pluresdb_core::backup::{restore_backup, write_backup, BackupHeader, RestoreStats};
 
 /// Convenience function to create a new in-memory database
 ///
@@ -20,7 +20,8 @@ export {
 export declare class PluresDatabase {
   /**
    * Create a new PluresDB instance.
-   * @param actorId - Actor ID for CRDT operations. Defaults to "node-actor".
+   * @param actorId - Actor ID for CRDT operations. Defaults to the id kept in
+   *   `dbPath`, or a random one per instance.
    * @param dbPath - Path to database file. Omit for in-memory.
    */
   constructor(actorId?: string, dbPath?: string);
@@ -28,7 +29,8 @@ export declare class PluresDatabase {
   /**
    * Create a PluresDB instance with automatic text embedding.
    * @param model - HuggingFace model ID such as "BAAI/bge-small-en-v1.5"
-   * @param actorId - Actor ID for CRDT operations. Defaults to "node-actor".
+   * @param actorId - Actor ID for CRDT operations. Defaults to the id kept in
+   *   `dbPath`, or a random one per instance.
    * @param dbPath - Path to database file. Omit for in-memory.
    */
   static newWithEmbeddings(model: string, actorId?: string, dbPath?: string): PluresDatabase;